@@ -0,0 +1,65 @@
+//! Throughput of the keyspace under contention: every thread runs a mix of
+//! `insert`, `get` and `delete` on its own keys, with one shard (the old
+//! single global lock) and with the default number of shards.
+//!
+//! Run with `cargo bench --bench keyspace`.
+use my_redis::clock::SystemClock;
+use my_redis::constants::SHARDS;
+use my_redis::storage::{Expiry, Storage};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const OPERATIONS_PER_THREAD: usize = 200_000;
+const KEYS_PER_THREAD: usize = 1_000;
+
+fn run(shards: usize, threads: usize) -> Duration {
+    let storage = Arc::new(Storage::new(1, shards, Arc::new(SystemClock::new()), 1));
+    let start = Instant::now();
+    let workers: Vec<_> = (0..threads)
+        .map(|thread| {
+            let storage = storage.clone();
+            thread::spawn(move || {
+                for i in 0..OPERATIONS_PER_THREAD {
+                    let key = format!("key:{}:{}", thread, i % KEYS_PER_THREAD);
+                    match i % 4 {
+                        0 | 1 => {
+                            storage.insert(0, key, String::from("value"), Expiry::INFINITE);
+                        }
+                        2 => {
+                            let _ = storage.get(0, &key);
+                        }
+                        _ => {
+                            storage.delete(0, vec![key]);
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("benchmark thread panicked");
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let cores = thread::available_parallelism().map_or(4, |cores| cores.get());
+    let mut threads = vec![1];
+    while threads[threads.len() - 1] < cores {
+        threads.push((threads[threads.len() - 1] * 2).min(cores));
+    }
+    println!("{:>8} {:>8} {:>14}", "shards", "threads", "ops/s");
+    for shards in [1, SHARDS] {
+        for &count in &threads {
+            let elapsed = run(shards, count);
+            let operations = (OPERATIONS_PER_THREAD * count) as f64;
+            println!(
+                "{:>8} {:>8} {:>14.0}",
+                shards,
+                count,
+                operations / elapsed.as_secs_f64()
+            );
+        }
+    }
+}
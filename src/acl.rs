@@ -0,0 +1,793 @@
+use crate::command::{CommandSpec, ACL_CATEGORIES, COMMAND_SPECS};
+use crate::pattern::glob_match;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_USER: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyPattern {
+    pattern: String,
+    read: bool,
+    write: bool,
+}
+impl KeyPattern {
+    fn describe(&self) -> String {
+        match (self.read, self.write) {
+            (true, true) => format!("~{}", self.pattern),
+            (true, false) => format!("%R~{}", self.pattern),
+            _ => format!("%W~{}", self.pattern),
+        }
+    }
+}
+
+/// Reason a command was refused by [`User::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denied {
+    Command(String),
+    Key(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    enabled: bool,
+    nopass: bool,
+    // SHA-256 hashes of the passwords, as lowercase hex
+    passwords: Vec<String>,
+    allowed_commands: HashSet<&'static str>,
+    // `cmd|sub` entries allowed while `cmd` itself is not allowed
+    allowed_subcommands: HashSet<String>,
+    // `cmd|sub` entries denied while `cmd` itself is allowed
+    denied_subcommands: HashSet<String>,
+    command_rules: Vec<String>,
+    keys: Vec<KeyPattern>,
+    // There are no Pub/Sub commands, so channels are either all allowed or
+    // none, only to keep ACL files written for Redis loadable.
+    all_channels: bool,
+}
+
+pub fn hash_password(password: &str) -> String {
+    Sha256::digest(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn category_commands(category: &str) -> Option<Vec<&'static CommandSpec>> {
+    if category == "all" {
+        return Some(COMMAND_SPECS.iter().collect());
+    }
+    if !ACL_CATEGORIES.contains(&category) {
+        return None;
+    }
+    Some(
+        COMMAND_SPECS
+            .iter()
+            .filter(|spec| spec.categories.contains(&category))
+            .collect(),
+    )
+}
+
+fn find_command(name: &str) -> Option<&'static str> {
+    COMMAND_SPECS
+        .iter()
+        .find(|spec| spec.name == name)
+        .map(|spec| spec.name)
+}
+
+fn validate_hash(hash: &str) -> Result<String, String> {
+    if hash.len() != 64 || !hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+        return Err(String::from(
+            "The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters",
+        ));
+    }
+    Ok(hash.to_owned())
+}
+
+impl User {
+    pub fn new(name: &str) -> Self {
+        User {
+            name: name.to_owned(),
+            enabled: false,
+            nopass: false,
+            passwords: Vec::new(),
+            allowed_commands: HashSet::new(),
+            allowed_subcommands: HashSet::new(),
+            denied_subcommands: HashSet::new(),
+            command_rules: vec![String::from("-@all")],
+            keys: Vec::new(),
+            all_channels: false,
+        }
+    }
+
+    fn default_user() -> Self {
+        let mut user = User::new(DEFAULT_USER);
+        for rule in ["on", "nopass", "~*", "&*", "+@all"] {
+            user.apply_rule(rule).expect("default user rules are valid");
+        }
+        user
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_nopass(&self) -> bool {
+        self.nopass
+    }
+
+    pub fn check_password(&self, password: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let hash = hash_password(password);
+        self.nopass || self.passwords.contains(&hash)
+    }
+
+    fn allow_command(&mut self, name: &'static str) {
+        self.allowed_commands.insert(name);
+        let prefix = format!("{}|", name);
+        self.allowed_subcommands.retain(|s| !s.starts_with(&prefix));
+        self.denied_subcommands.retain(|s| !s.starts_with(&prefix));
+    }
+
+    fn deny_command(&mut self, name: &'static str) {
+        self.allowed_commands.remove(name);
+        let prefix = format!("{}|", name);
+        self.allowed_subcommands.retain(|s| !s.starts_with(&prefix));
+        self.denied_subcommands.retain(|s| !s.starts_with(&prefix));
+    }
+
+    fn apply_command_rule(&mut self, rule: &str) -> Result<(), String> {
+        let (allow, target) = rule.split_at(1);
+        let allow = allow == "+";
+        let unknown = || String::from("Unknown command or category name in ACL");
+        if let Some(category) = target.strip_prefix('@') {
+            let commands = category_commands(&category.to_lowercase()).ok_or_else(unknown)?;
+            for spec in commands {
+                if allow {
+                    self.allow_command(spec.name);
+                } else {
+                    self.deny_command(spec.name);
+                }
+            }
+            if category.eq_ignore_ascii_case("all") {
+                self.command_rules.clear();
+            }
+        } else if let Some((command, subcommand)) = target.split_once('|') {
+            let command = find_command(&command.to_lowercase()).ok_or_else(unknown)?;
+            if subcommand.is_empty() || subcommand.contains('|') {
+                return Err(String::from("Syntax error"));
+            }
+            let full_name = format!("{}|{}", command, subcommand.to_lowercase());
+            match (allow, self.allowed_commands.contains(command)) {
+                (true, true) => {
+                    self.denied_subcommands.remove(&full_name);
+                }
+                (true, false) => {
+                    self.allowed_subcommands.insert(full_name);
+                }
+                (false, true) => {
+                    self.denied_subcommands.insert(full_name);
+                }
+                (false, false) => {
+                    self.allowed_subcommands.remove(&full_name);
+                }
+            }
+        } else {
+            let command = find_command(&target.to_lowercase()).ok_or_else(unknown)?;
+            if allow {
+                self.allow_command(command);
+            } else {
+                self.deny_command(command);
+            }
+        }
+        self.command_rules.push(rule.to_lowercase());
+        Ok(())
+    }
+
+    fn add_key_pattern(&mut self, pattern: &str, read: bool, write: bool) {
+        let key_pattern = KeyPattern {
+            pattern: pattern.to_owned(),
+            read,
+            write,
+        };
+        if !self.keys.contains(&key_pattern) {
+            self.keys.push(key_pattern);
+        }
+    }
+
+    fn add_password_hash(&mut self, hash: String) {
+        self.nopass = false;
+        if !self.passwords.contains(&hash) {
+            self.passwords.push(hash);
+        }
+    }
+
+    fn remove_password_hash(&mut self, hash: &str) -> Result<(), String> {
+        let before = self.passwords.len();
+        self.passwords.retain(|p| p != hash);
+        if before == self.passwords.len() {
+            return Err(String::from("no such password"));
+        }
+        Ok(())
+    }
+
+    /// Applies a single `ACL SETUSER` rule, returning the reason on failure.
+    pub fn apply_rule(&mut self, rule: &str) -> Result<(), String> {
+        match rule.to_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => {
+                self.keys.clear();
+                self.add_key_pattern("*", true, true);
+            }
+            "resetkeys" => self.keys.clear(),
+            "allchannels" | "&*" => self.all_channels = true,
+            "resetchannels" => self.all_channels = false,
+            "allcommands" => return self.apply_command_rule("+@all"),
+            "nocommands" => return self.apply_command_rule("-@all"),
+            "reset" => {
+                let name = self.name.clone();
+                *self = User::new(&name);
+                for rule in ["resetpass", "resetkeys", "resetchannels", "off", "-@all"] {
+                    self.apply_rule(rule)?;
+                }
+            }
+            _ => {
+                if let Some(password) = rule.strip_prefix('>') {
+                    self.add_password_hash(hash_password(password));
+                } else if let Some(password) = rule.strip_prefix('<') {
+                    self.remove_password_hash(&hash_password(password))?;
+                } else if let Some(hash) = rule.strip_prefix('#') {
+                    self.add_password_hash(validate_hash(hash)?);
+                } else if let Some(hash) = rule.strip_prefix('!') {
+                    self.remove_password_hash(&validate_hash(hash)?)?;
+                } else if let Some(pattern) = rule.strip_prefix('~') {
+                    self.add_key_pattern(pattern, true, true);
+                } else if let Some(rest) = rule.strip_prefix('%') {
+                    let (permissions, pattern) = rest
+                        .split_once('~')
+                        .ok_or_else(|| String::from("Syntax error"))?;
+                    let permissions = permissions.to_uppercase();
+                    if permissions.is_empty() || permissions.chars().any(|c| c != 'R' && c != 'W') {
+                        return Err(String::from("Syntax error"));
+                    }
+                    self.add_key_pattern(
+                        pattern,
+                        permissions.contains('R'),
+                        permissions.contains('W'),
+                    );
+                } else if rule.starts_with('&') {
+                    return Err(String::from(
+                        "Channel patterns other than '&*' are not supported, there are no Pub/Sub commands",
+                    ));
+                } else if rule.starts_with('+') || rule.starts_with('-') {
+                    return self.apply_command_rule(rule);
+                } else {
+                    return Err(String::from("Syntax error"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+        if self.nopass {
+            flags.push("nopass");
+        }
+        flags
+    }
+
+    pub fn passwords(&self) -> Vec<String> {
+        self.passwords.clone()
+    }
+
+    pub fn describe_commands(&self) -> String {
+        let mut description = if self.command_rules.first().map(String::as_str) == Some("+@all") {
+            vec![String::from("+@all")]
+        } else {
+            vec![String::from("-@all")]
+        };
+        description.extend(
+            self.command_rules
+                .iter()
+                .filter(|rule| *rule != "+@all" && *rule != "-@all")
+                .cloned(),
+        );
+        description.join(" ")
+    }
+
+    pub fn describe_keys(&self) -> String {
+        self.keys
+            .iter()
+            .map(KeyPattern::describe)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn describe_channels(&self) -> String {
+        String::from(if self.all_channels { "&*" } else { "" })
+    }
+
+    /// The user as a line of `ACL LIST`.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("user {}", self.name)];
+        parts.extend(self.flags().iter().map(|f| f.to_string()));
+        parts.extend(self.passwords.iter().map(|p| format!("#{}", p)));
+        parts.push(if self.keys.is_empty() {
+            String::from("resetkeys")
+        } else {
+            self.describe_keys()
+        });
+        parts.push(if self.all_channels {
+            self.describe_channels()
+        } else {
+            String::from("resetchannels")
+        });
+        parts.push(self.describe_commands());
+        parts.join(" ")
+    }
+
+    pub fn can_run(&self, spec: &CommandSpec, subcommand: Option<&str>) -> bool {
+        let full_name = subcommand.map(|sub| format!("{}|{}", spec.name, sub.to_lowercase()));
+        if self.allowed_commands.contains(spec.name) {
+            full_name.is_none_or(|name| !self.denied_subcommands.contains(&name))
+        } else {
+            full_name.is_some_and(|name| self.allowed_subcommands.contains(&name))
+        }
+    }
+
+    pub fn can_access_key(&self, key: &str, read: bool, write: bool) -> bool {
+        self.keys.iter().any(|pattern| {
+            (!read || pattern.read)
+                && (!write || pattern.write)
+                && glob_match(&pattern.pattern, key)
+        })
+    }
+
+    /// Checks whether the user may run the command with the given key arguments.
+    pub fn check(
+        &self,
+        spec: &CommandSpec,
+        subcommand: Option<&str>,
+        keys: &[&str],
+    ) -> Result<(), Denied> {
+        if !self.can_run(spec, subcommand) {
+            let name = match subcommand {
+                Some(sub) => format!("{}|{}", spec.name, sub.to_lowercase()),
+                None => spec.name.to_owned(),
+            };
+            return Err(Denied::Command(name));
+        }
+        for (index, key) in keys.iter().enumerate() {
+            let (read, write) = spec.key_access(index);
+            if !self.can_access_key(key, read, write) {
+                return Err(Denied::Key(key.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the content of an ACL file, one `user <name> <rules...>` per line.
+fn parse_acl_file(path: &str, content: &str) -> Result<BTreeMap<String, User>, String> {
+    let mut users = BTreeMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line_error = |message: String| format!("{}:{}: {}", path, index + 1, message);
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            None => continue,
+            Some("user") => {}
+            Some(_) => return Err(line_error(String::from("should start with user keyword"))),
+        }
+        let Some(name) = parts.next() else {
+            return Err(line_error(String::from("user name is missing")));
+        };
+        if users.contains_key(name) {
+            return Err(line_error(format!("Duplicate user '{}' found", name)));
+        }
+        let mut user = User::new(name);
+        for rule in parts {
+            user.apply_rule(rule).map_err(|reason| {
+                line_error(format!(
+                    "Error in applying operation '{}': {}",
+                    rule, reason
+                ))
+            })?;
+        }
+        users.insert(name.to_owned(), user);
+    }
+    users
+        .entry(DEFAULT_USER.to_owned())
+        .or_insert_with(User::default_user);
+    Ok(users)
+}
+
+const ACL_LOG_MAX_LEN: usize = 128;
+// Denials with the same reason, object and user within this window are grouped.
+const ACL_LOG_GROUPING_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogReason {
+    Command,
+    Key,
+    Auth,
+}
+impl LogReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogReason::Command => "command",
+            LogReason::Key => "key",
+            LogReason::Auth => "auth",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub count: u64,
+    pub reason: LogReason,
+    pub context: &'static str,
+    pub object: String,
+    pub username: String,
+    pub client_info: String,
+    pub entry_id: u64,
+    pub created: u128,
+    pub updated: u128,
+}
+
+struct AclLog {
+    entries: VecDeque<LogEntry>,
+    next_id: u64,
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// Milliseconds elapsed since `timestamp`, for the `age-seconds` field.
+pub fn age_millis(timestamp: u128) -> u128 {
+    now_millis().saturating_sub(timestamp)
+}
+
+/// ACL users, the ACL file location and the `ACL LOG` of one server.
+pub struct Acl {
+    users: Mutex<BTreeMap<String, User>>,
+    file: Mutex<Option<PathBuf>>,
+    log: Mutex<AclLog>,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        let mut users = BTreeMap::new();
+        users.insert(DEFAULT_USER.to_owned(), User::default_user());
+        Acl {
+            users: Mutex::new(users),
+            file: Mutex::new(None),
+            log: Mutex::new(AclLog {
+                entries: VecDeque::new(),
+                next_id: 0,
+            }),
+        }
+    }
+}
+
+impl Acl {
+    fn users(&self) -> MutexGuard<'_, BTreeMap<String, User>> {
+        self.users.lock().expect("Could not lock ACL users")
+    }
+
+    pub fn get_user(&self, name: &str) -> Option<User> {
+        self.users().get(name).cloned()
+    }
+
+    pub fn user_names(&self) -> Vec<String> {
+        self.users().keys().cloned().collect()
+    }
+
+    pub fn list_users(&self) -> Vec<User> {
+        self.users().values().cloned().collect()
+    }
+
+    /// Creates the user if needed and applies all rules atomically: if one rule
+    /// fails the user is left untouched. On failure returns the failing rule and reason.
+    pub fn set_user(&self, name: &str, rules: &[&str]) -> Result<(), (String, String)> {
+        let mut users = self.users();
+        let mut user = users.get(name).cloned().unwrap_or_else(|| User::new(name));
+        for rule in rules {
+            user.apply_rule(rule)
+                .map_err(|reason| (rule.to_string(), reason))?;
+        }
+        users.insert(name.to_owned(), user);
+        Ok(())
+    }
+
+    pub fn delete_user(&self, name: &str) -> bool {
+        self.users().remove(name).is_some()
+    }
+
+    /// Implements `requirepass`: the default user needs the password to authenticate.
+    pub fn set_requirepass(&self, password: &str) {
+        let rules: Vec<String> = if password.is_empty() {
+            vec![String::from("nopass")]
+        } else {
+            vec![String::from("resetpass"), format!(">{}", password)]
+        };
+        let rules: Vec<&str> = rules.iter().map(String::as_str).collect();
+        self.set_user(DEFAULT_USER, &rules)
+            .expect("requirepass rules are valid");
+    }
+
+    /// Returns the user if `password` authenticates it.
+    pub fn authenticate(&self, name: &str, password: &str) -> Option<User> {
+        self.get_user(name)
+            .filter(|user| user.check_password(password))
+    }
+
+    /// Configures the file used by `ACL LOAD` and `ACL SAVE`.
+    pub fn set_acl_file(&self, path: PathBuf) {
+        *self.file.lock().expect("Could not lock ACL file path") = Some(path);
+    }
+
+    fn acl_file(&self) -> Result<PathBuf, String> {
+        self.file
+            .lock()
+            .expect("Could not lock ACL file path")
+            .clone()
+            .ok_or_else(|| String::from("This Redis instance is not configured to use an ACL file. You may want to specify users via the ACL SETUSER command and then issue a CONFIG REWRITE (assuming you have a Redis configuration file set) in order to store users in the Redis configuration."))
+    }
+
+    /// Replaces all users with the content of the ACL file. Nothing changes if
+    /// the file has an error.
+    pub fn load(&self) -> Result<(), String> {
+        let path = self.acl_file()?;
+        let display = path.display().to_string();
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Error loading ACLs, opening file '{}': {}", display, e))?;
+        let loaded = parse_acl_file(&display, &content).map_err(|e| {
+            format!(
+                "{}. WARNING: ACL errors detected, no change to the previously active ACL rules was performed",
+                e
+            )
+        })?;
+        *self.users() = loaded;
+        Ok(())
+    }
+
+    /// Writes all users to the ACL file, replacing it atomically.
+    pub fn save(&self) -> Result<(), String> {
+        let path = self.acl_file()?;
+        let content: String = self
+            .list_users()
+            .iter()
+            .map(|user| format!("{}\n", user.describe()))
+            .collect();
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, content)
+            .and_then(|_| std::fs::rename(&temporary, &path))
+            .map_err(|e| format!("There was an error trying to save the ACLs. Please check the server logs for more information: {}", e))
+    }
+
+    /// Records a denied command, key, channel or authentication in `ACL LOG`.
+    pub fn log(&self, reason: LogReason, object: &str, username: &str, client_info: &str) {
+        let now = now_millis();
+        let mut log = self.log.lock().expect("Could not lock ACL log");
+        let similar = log.entries.iter().position(|entry| {
+            entry.reason == reason
+                && entry.object == object
+                && entry.username == username
+                && now - entry.updated < ACL_LOG_GROUPING_WINDOW.as_millis()
+        });
+        let entry = match similar.and_then(|index| log.entries.remove(index)) {
+            Some(mut entry) => {
+                entry.count += 1;
+                entry.updated = now;
+                entry.client_info = client_info.to_owned();
+                entry
+            }
+            None => {
+                log.next_id += 1;
+                LogEntry {
+                    count: 1,
+                    reason,
+                    context: "toplevel",
+                    object: object.to_owned(),
+                    username: username.to_owned(),
+                    client_info: client_info.to_owned(),
+                    entry_id: log.next_id - 1,
+                    created: now,
+                    updated: now,
+                }
+            }
+        };
+        log.entries.push_front(entry);
+        log.entries.truncate(ACL_LOG_MAX_LEN);
+    }
+
+    /// The most recent `count` log entries, newest first.
+    pub fn log_entries(&self, count: usize) -> Vec<LogEntry> {
+        let log = self.log.lock().expect("Could not lock ACL log");
+        log.entries.iter().take(count).cloned().collect()
+    }
+
+    pub fn reset_log(&self) {
+        self.log
+            .lock()
+            .expect("Could not lock ACL log")
+            .entries
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::command_spec;
+
+    fn user(rules: &[&str]) -> User {
+        let mut user = User::new("test");
+        for rule in rules {
+            user.apply_rule(rule).unwrap();
+        }
+        user
+    }
+
+    #[test]
+    fn test_command_rules() {
+        let get = command_spec("get").unwrap();
+        let client = command_spec("client").unwrap();
+        let user = user(&["+@read", "-get", "+client|id"]);
+        assert!(!user.can_run(get, None));
+        assert!(user.can_run(client, Some("id")));
+        assert!(!user.can_run(client, Some("kill")));
+        assert!(user.can_run(command_spec("dbsize").unwrap(), None));
+
+        let user = self::user(&["+@all", "-client|kill"]);
+        assert!(user.can_run(client, Some("list")));
+        assert!(!user.can_run(client, Some("kill")));
+        assert_eq!(user.describe_commands(), "+@all -client|kill");
+
+        assert!(User::new("test").apply_rule("+unknown").is_err());
+        assert!(User::new("test").apply_rule("+@unknown").is_err());
+    }
+
+    #[test]
+    fn test_key_rules() {
+        let get = command_spec("get").unwrap();
+        let set = command_spec("set").unwrap();
+        let user = user(&["+@all", "~cache:*", "%R~ro:*"]);
+        assert!(user.check(get, None, &["cache:1"]).is_ok());
+        assert!(user.check(set, None, &["cache:1"]).is_ok());
+        assert!(user.check(get, None, &["ro:1"]).is_ok());
+        assert_eq!(
+            user.check(set, None, &["ro:1"]),
+            Err(Denied::Key(String::from("ro:1")))
+        );
+        assert_eq!(
+            user.check(get, None, &["other"]),
+            Err(Denied::Key(String::from("other")))
+        );
+
+        // Sources are read and destinations written, whatever the command.
+        let zunionstore = command_spec("zunionstore").unwrap();
+        let lmove = command_spec("lmove").unwrap();
+        let user = self::user(&["+@all", "%R~src:*", "%W~dst:*"]);
+        assert!(user.check(zunionstore, None, &["dst:1", "src:1"]).is_ok());
+        assert_eq!(
+            user.check(zunionstore, None, &["src:1", "src:2"]),
+            Err(Denied::Key(String::from("src:1")))
+        );
+        assert_eq!(
+            user.check(lmove, None, &["src:1", "dst:1"]),
+            Err(Denied::Key(String::from("src:1")))
+        );
+        let user = self::user(&["+@all", "~src:*", "%W~dst:*"]);
+        assert!(user.check(lmove, None, &["src:1", "dst:1"]).is_ok());
+        assert!(user.check(lmove, None, &["dst:1", "src:1"]).is_err());
+    }
+
+    #[test]
+    fn test_channel_rules() {
+        let mut user = user(&["allchannels"]);
+        assert_eq!(user.describe_channels(), "&*");
+        user.apply_rule("resetchannels").unwrap();
+        assert!(user.describe().contains(" resetchannels "));
+        user.apply_rule("&*").unwrap();
+        assert_eq!(user.describe_channels(), "&*");
+        assert!(user.apply_rule("&news:*").is_err());
+    }
+
+    #[test]
+    fn test_acl_log() {
+        let acl = Acl::default();
+        acl.log(LogReason::Command, "get", "alice", "id=1");
+        acl.log(LogReason::Key, "secret", "alice", "id=1");
+        let entries = acl.log_entries(10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].reason, LogReason::Key);
+        assert_eq!(entries[0].object, "secret");
+        assert_eq!(entries[0].entry_id, 1);
+        assert_eq!(entries[1].reason, LogReason::Command);
+        assert_eq!(entries[1].username, "alice");
+        assert_eq!(entries[1].context, "toplevel");
+        assert_eq!(entries[1].count, 1);
+
+        // A repeated denial is counted on its entry, which moves to the front.
+        acl.log(LogReason::Command, "get", "alice", "id=2");
+        let entries = acl.log_entries(10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].entry_id, 0);
+        assert_eq!(entries[0].count, 2);
+        assert_eq!(entries[0].client_info, "id=2");
+        assert!(entries[0].updated >= entries[0].created);
+
+        // Another user, or the same denial after the grouping window, is a
+        // new entry.
+        acl.log(LogReason::Command, "get", "bob", "id=3");
+        acl.log.lock().unwrap().entries[1].updated -= ACL_LOG_GROUPING_WINDOW.as_millis();
+        acl.log(LogReason::Command, "get", "alice", "id=2");
+        let entries = acl.log_entries(10);
+        assert_eq!(entries.len(), 4);
+        assert_eq!((entries[0].entry_id, entries[0].count), (3, 1));
+        assert_eq!(entries[1].username, "bob");
+
+        let entries = acl.log_entries(1);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].entry_id, 3);
+        assert!(acl.log_entries(0).is_empty());
+
+        acl.reset_log();
+        assert!(acl.log_entries(10).is_empty());
+        acl.log(LogReason::Auth, "AUTH", "alice", "id=1");
+        assert_eq!(acl.log_entries(10)[0].entry_id, 4);
+
+        for i in 0..ACL_LOG_MAX_LEN + 10 {
+            acl.log(LogReason::Key, &i.to_string(), "alice", "id=1");
+        }
+        assert_eq!(acl.log_entries(usize::MAX).len(), ACL_LOG_MAX_LEN);
+    }
+
+    #[test]
+    fn test_acl_file_round_trip() {
+        let users = parse_acl_file(
+            "users.acl",
+            "user alice on >secret ~cache:* +@read\n\nuser bob off nopass\n",
+        )
+        .unwrap();
+        let alice = &users["alice"];
+        assert!(alice.check_password("secret"));
+        assert!(!alice.check_password("other"));
+        assert_eq!(alice.passwords(), vec![hash_password("secret")]);
+        assert!(users.contains_key(DEFAULT_USER));
+
+        let saved: String = users
+            .values()
+            .map(|user| format!("{}\n", user.describe()))
+            .collect();
+        let reloaded = parse_acl_file("users.acl", &saved).unwrap();
+        for (name, user) in &users {
+            assert_eq!(reloaded[name].describe(), user.describe());
+        }
+
+        assert!(parse_acl_file("users.acl", "user alice on\nuser alice off\n").is_err());
+        assert!(parse_acl_file("users.acl", "alice on\n").is_err());
+        assert!(parse_acl_file("users.acl", "user alice #nothex\n").is_err());
+    }
+}
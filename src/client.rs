@@ -0,0 +1,312 @@
+use crate::server::Server;
+use crate::storage::Blocked;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyMode {
+    On,
+    Off,
+    // Set by `CLIENT REPLY SKIP`, the reply of the command itself is dropped
+    // and the mode moves to `Skip` so the reply of the next command is dropped too.
+    SkipNext,
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientType {
+    Normal,
+    Master,
+    Replica,
+    PubSub,
+}
+impl TryFrom<&str> for ClientType {
+    type Error = ();
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "normal" => Ok(ClientType::Normal),
+            "master" => Ok(ClientType::Master),
+            "replica" | "slave" => Ok(ClientType::Replica),
+            "pubsub" => Ok(ClientType::PubSub),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClientState {
+    name: Option<String>,
+    last_interaction: Instant,
+    last_command: String,
+    query_buffer: usize,
+    output_buffer: usize,
+    reply: ReplyMode,
+    db: usize,
+    user: Option<String>,
+    /// RESP version negotiated with `HELLO`.
+    resp: u8,
+}
+
+/// A connected client as seen by the registry. The connection task and the
+/// registry share the same `Arc<Client>`, so commands issued from one
+/// connection can inspect or kill another one.
+pub struct Client {
+    pub id: u64,
+    pub addr: SocketAddr,
+    pub laddr: SocketAddr,
+    created: Instant,
+    state: Mutex<ClientState>,
+    /// Set by a blocking command that could not run yet, with its timeout.
+    blocked: Mutex<Option<(Blocked, Option<Duration>)>>,
+    kill: Notify,
+    server: Arc<Server>,
+}
+
+impl Client {
+    /// The server instance the client is connected to.
+    pub fn server(&self) -> &Arc<Server> {
+        &self.server
+    }
+    fn state(&self) -> MutexGuard<'_, ClientState> {
+        self.state.lock().expect("Could not lock client state")
+    }
+    pub fn client_type(&self) -> ClientType {
+        ClientType::Normal
+    }
+    pub fn name(&self) -> Option<String> {
+        self.state().name.clone()
+    }
+    pub fn set_name(&self, name: Option<String>) {
+        self.state().name = name;
+    }
+    pub fn age(&self) -> Duration {
+        self.created.elapsed()
+    }
+    pub fn idle(&self) -> Duration {
+        self.state().last_interaction.elapsed()
+    }
+    /// Index of the database selected with `SELECT`.
+    pub fn db(&self) -> usize {
+        self.state().db
+    }
+    pub fn select(&self, db: usize) {
+        self.state().db = db;
+    }
+    /// Name of the ACL user the connection is authenticated as, if any.
+    pub fn user(&self) -> Option<String> {
+        self.state().user.clone()
+    }
+    pub fn authenticate(&self, user: &str) {
+        self.state().user = Some(user.to_owned());
+    }
+    pub fn resp(&self) -> u8 {
+        self.state().resp
+    }
+    pub fn set_resp(&self, resp: u8) {
+        self.state().resp = resp;
+    }
+    pub fn set_reply_mode(&self, mode: ReplyMode) {
+        self.state().reply = mode;
+    }
+    /// Records the arrival of a new request of `size` bytes.
+    pub fn record_request(&self, size: usize) {
+        let mut state = self.state();
+        state.last_interaction = Instant::now();
+        state.query_buffer = size;
+    }
+    /// Records the command that was executed and the size of its reply.
+    pub fn record_command(&self, command: &str, reply_size: usize) {
+        let mut state = self.state();
+        state.last_command = command.to_owned();
+        state.query_buffer = 0;
+        state.output_buffer = reply_size;
+    }
+    /// Returns whether the reply of the command that just ran should be sent,
+    /// advancing the `CLIENT REPLY SKIP` state machine.
+    pub fn consume_reply(&self) -> bool {
+        let mut state = self.state();
+        match state.reply {
+            ReplyMode::On => true,
+            ReplyMode::Off => false,
+            ReplyMode::SkipNext => {
+                state.reply = ReplyMode::Skip;
+                false
+            }
+            ReplyMode::Skip => {
+                state.reply = ReplyMode::On;
+                false
+            }
+        }
+    }
+    /// Parks the client: the reply of the command that is running is the one
+    /// `blocked` eventually returns.
+    pub fn block(&self, blocked: Blocked, timeout: Option<Duration>) {
+        *self.blocked.lock().expect("Could not lock blocked state") = Some((blocked, timeout));
+    }
+    pub fn take_blocked(&self) -> Option<(Blocked, Option<Duration>)> {
+        self.blocked
+            .lock()
+            .expect("Could not lock blocked state")
+            .take()
+    }
+    /// Asks the connection task to close the connection.
+    pub fn kill(&self) {
+        self.kill.notify_one();
+    }
+    pub async fn killed(&self) {
+        self.kill.notified().await
+    }
+    /// One line of `CLIENT LIST` / `CLIENT INFO` output.
+    pub fn info(&self) -> String {
+        let state = self.state();
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} flags={} db={} qbuf={} obl={} cmd={} user={} resp={}\n",
+            self.id,
+            self.addr,
+            self.laddr,
+            state.name.clone().unwrap_or_default(),
+            self.created.elapsed().as_secs(),
+            state.last_interaction.elapsed().as_secs(),
+            if self.server.storage.is_blocked(self.id) {
+                "b"
+            } else {
+                "N"
+            },
+            state.db,
+            state.query_buffer,
+            state.output_buffer,
+            if state.last_command.is_empty() {
+                "NULL"
+            } else {
+                state.last_command.as_str()
+            },
+            state.user.as_deref().unwrap_or_default(),
+            state.resp,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMode {
+    Write,
+    All,
+}
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    until: Instant,
+    mode: PauseMode,
+}
+
+/// Registry of the connected clients of one server, plus the `CLIENT PAUSE` state.
+pub struct Clients {
+    next_id: AtomicU64,
+    clients: Mutex<BTreeMap<u64, Arc<Client>>>,
+    pause: Mutex<Option<Pause>>,
+    unpaused: Notify,
+}
+
+impl Default for Clients {
+    fn default() -> Self {
+        Clients {
+            next_id: AtomicU64::new(1),
+            clients: Mutex::new(BTreeMap::new()),
+            pause: Mutex::new(None),
+            unpaused: Notify::new(),
+        }
+    }
+}
+
+impl Clients {
+    fn registry(&self) -> MutexGuard<'_, BTreeMap<u64, Arc<Client>>> {
+        self.clients.lock().expect("Could not lock client registry")
+    }
+
+    pub fn register(
+        &self,
+        server: Arc<Server>,
+        addr: SocketAddr,
+        laddr: SocketAddr,
+    ) -> Arc<Client> {
+        let now = Instant::now();
+        let client = Arc::new(Client {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            addr,
+            laddr,
+            created: now,
+            state: Mutex::new(ClientState {
+                name: None,
+                last_interaction: now,
+                last_command: String::new(),
+                query_buffer: 0,
+                output_buffer: 0,
+                reply: ReplyMode::On,
+                db: 0,
+                user: None,
+                resp: 2,
+            }),
+            blocked: Mutex::new(None),
+            kill: Notify::new(),
+            server,
+        });
+        self.registry().insert(client.id, client.clone());
+        client
+    }
+
+    pub fn unregister(&self, id: u64) {
+        self.registry().remove(&id);
+    }
+
+    /// All connected clients, ordered by id.
+    pub fn clients(&self) -> Vec<Arc<Client>> {
+        self.registry().values().cloned().collect()
+    }
+
+    pub fn pause(&self, duration: Duration, mode: PauseMode) {
+        let until = Instant::now() + duration;
+        let mut pause = self.pause.lock().expect("Could not lock pause state");
+        // A new pause never shortens an existing one, and `ALL` wins over `WRITE`.
+        let pause_state = match *pause {
+            Some(current) => Pause {
+                until: current.until.max(until),
+                mode: if current.mode == PauseMode::All {
+                    PauseMode::All
+                } else {
+                    mode
+                },
+            },
+            None => Pause { until, mode },
+        };
+        *pause = Some(pause_state);
+    }
+
+    pub fn unpause(&self) {
+        *self.pause.lock().expect("Could not lock pause state") = None;
+        self.unpaused.notify_waiters();
+    }
+
+    /// Waits while the clients are paused for a command with the given write flag.
+    pub async fn wait_if_paused(&self, is_write: bool) {
+        loop {
+            // Created before checking the state so an `unpause` in between is not missed.
+            let unpaused = self.unpaused.notified();
+            let remaining = {
+                let mut pause = self.pause.lock().expect("Could not lock pause state");
+                match *pause {
+                    Some(Pause { until, .. }) if until <= Instant::now() => {
+                        *pause = None;
+                        return;
+                    }
+                    Some(Pause { until, mode }) if mode == PauseMode::All || is_write => {
+                        until - Instant::now()
+                    }
+                    _ => return,
+                }
+            };
+            let _ = tokio::time::timeout(remaining, unpaused).await;
+        }
+    }
+}
@@ -1,55 +1,3443 @@
+/// Unwraps a parsed argument or returns its error reply. Defined before the
+/// command modules so that they can all use it.
+macro_rules! parse {
+    ($parsed:expr) => {
+        match $parsed {
+            Ok(value) => value,
+            Err(reply) => return reply,
+        }
+    };
+}
+
+mod acl;
+mod auth;
+mod bitmap;
+mod blocking;
+mod client;
+mod dbsize;
 mod del;
 mod echo;
+mod expire;
+mod flush;
 mod get;
+mod hash;
+mod hash_expire;
+mod hello;
+mod hyperloglog;
+mod info;
+mod key_type;
+mod list;
+mod move_key;
 mod ping;
+mod scan;
+mod select;
 mod set;
+mod sets;
+mod sorted_set;
+mod stream;
+mod swapdb;
+mod ttl;
 
+use crate::client::Client;
 use crate::error::*;
 use crate::{
     error::RedisError,
-    value::{bulk_string::BulkString, serialize::Serialize},
+    value::{
+        array::Array,
+        bulk_string::BulkString,
+        integer::Integer,
+        map::Map,
+        nulls::Nulls,
+        serialize::Serialize,
+        simple_error::{ErrorType, SimpleError},
+        simple_string::SimpleString,
+        Value,
+    },
+};
+use acl::AclCommand;
+use auth::AuthCommand;
+use bitmap::{
+    BitCountCommand, BitFieldCommand, BitFieldRoCommand, BitOpCommand, BitPosCommand,
+    GetBitCommand, SetBitCommand,
 };
+use client::ClientCommand;
+use dbsize::DbSizeCommand;
 use del::DelCommand;
 use echo::EchoCommand;
+use expire::{ExpireAtCommand, ExpireCommand, PExpireAtCommand, PExpireCommand};
+use flush::{FlushAllCommand, FlushDbCommand};
 use get::GetCommand;
+use hash::{
+    HDelCommand, HExistsCommand, HGetAllCommand, HGetCommand, HIncrByCommand, HIncrByFloatCommand,
+    HKeysCommand, HLenCommand, HMGetCommand, HRandFieldCommand, HScanCommand, HSetCommand,
+    HSetNxCommand, HStrLenCommand, HValsCommand,
+};
+use hash_expire::{
+    HExpireAtCommand, HExpireCommand, HExpireTimeCommand, HGetExCommand, HPExpireAtCommand,
+    HPExpireCommand, HPExpireTimeCommand, HPTtlCommand, HPersistCommand, HSetExCommand,
+    HTtlCommand,
+};
+use hello::HelloCommand;
+use hyperloglog::{PfAddCommand, PfCountCommand, PfMergeCommand};
+use info::InfoCommand;
+use key_type::TypeCommand;
+use list::{
+    BLMPopCommand, BLMoveCommand, BLPopCommand, BRPopCommand, LIndexCommand, LInsertCommand,
+    LLenCommand, LMPopCommand, LMoveCommand, LPopCommand, LPosCommand, LPushCommand, LPushXCommand,
+    LRangeCommand, LRemCommand, LSetCommand, LTrimCommand, RPopCommand, RPushCommand,
+    RPushXCommand,
+};
+use move_key::MoveCommand;
 use ping::PingCommand;
+use select::SelectCommand;
 use set::SetCommand;
+use sets::{
+    SAddCommand, SCardCommand, SDiffCommand, SDiffStoreCommand, SInterCardCommand, SInterCommand,
+    SInterStoreCommand, SIsMemberCommand, SMIsMemberCommand, SMembersCommand, SMoveCommand,
+    SPopCommand, SRandMemberCommand, SRemCommand, SScanCommand, SUnionCommand, SUnionStoreCommand,
+};
+use sorted_set::{
+    BZPopMaxCommand, BZPopMinCommand, ZAddCommand, ZCardCommand, ZCountCommand, ZDiffCommand,
+    ZDiffStoreCommand, ZIncrByCommand, ZInterCardCommand, ZInterCommand, ZInterStoreCommand,
+    ZMScoreCommand, ZPopMaxCommand, ZPopMinCommand, ZRangeCommand, ZRangeStoreCommand,
+    ZRankCommand, ZRemCommand, ZRemRangeByLexCommand, ZRemRangeByRankCommand,
+    ZRemRangeByScoreCommand, ZRevRankCommand, ZScanCommand, ZScoreCommand, ZUnionCommand,
+    ZUnionStoreCommand,
+};
+use stream::{
+    XAckCommand, XAddCommand, XAutoClaimCommand, XClaimCommand, XDelCommand, XGroupCommand,
+    XInfoCommand, XLenCommand, XPendingCommand, XRangeCommand, XReadCommand, XReadGroupCommand,
+    XRevRangeCommand, XTrimCommand,
+};
+use swapdb::SwapDbCommand;
+use ttl::{ExpireTimeCommand, PExpireTimeCommand, PTtlCommand, PersistCommand, TtlCommand};
 pub enum Command {
     ECHO(EchoCommand),
     PING(PingCommand),
     GET(GetCommand),
     SET(SetCommand),
     DEL(DelCommand),
+    CLIENT(ClientCommand),
+    SELECT(SelectCommand),
+    MOVE(MoveCommand),
+    SWAPDB(SwapDbCommand),
+    DBSIZE(DbSizeCommand),
+    FLUSHDB(FlushDbCommand),
+    FLUSHALL(FlushAllCommand),
+    INFO(InfoCommand),
+    AUTH(AuthCommand),
+    ACL(AclCommand),
+    EXPIRE(ExpireCommand),
+    PEXPIRE(PExpireCommand),
+    EXPIREAT(ExpireAtCommand),
+    PEXPIREAT(PExpireAtCommand),
+    TTL(TtlCommand),
+    PTTL(PTtlCommand),
+    EXPIRETIME(ExpireTimeCommand),
+    PEXPIRETIME(PExpireTimeCommand),
+    PERSIST(PersistCommand),
+    TYPE(TypeCommand),
+    LPUSH(LPushCommand),
+    RPUSH(RPushCommand),
+    LPUSHX(LPushXCommand),
+    RPUSHX(RPushXCommand),
+    LPOP(LPopCommand),
+    RPOP(RPopCommand),
+    LLEN(LLenCommand),
+    LRANGE(LRangeCommand),
+    LINDEX(LIndexCommand),
+    LSET(LSetCommand),
+    LREM(LRemCommand),
+    LTRIM(LTrimCommand),
+    LINSERT(LInsertCommand),
+    LPOS(LPosCommand),
+    LMOVE(LMoveCommand),
+    LMPOP(LMPopCommand),
+    BLPOP(BLPopCommand),
+    BRPOP(BRPopCommand),
+    BLMOVE(BLMoveCommand),
+    BLMPOP(BLMPopCommand),
+    HELLO(HelloCommand),
+    HSET(HSetCommand),
+    HSETNX(HSetNxCommand),
+    HGET(HGetCommand),
+    HMGET(HMGetCommand),
+    HGETALL(HGetAllCommand),
+    HDEL(HDelCommand),
+    HEXISTS(HExistsCommand),
+    HLEN(HLenCommand),
+    HKEYS(HKeysCommand),
+    HVALS(HValsCommand),
+    HSTRLEN(HStrLenCommand),
+    HINCRBY(HIncrByCommand),
+    HINCRBYFLOAT(HIncrByFloatCommand),
+    HRANDFIELD(HRandFieldCommand),
+    HSCAN(HScanCommand),
+    HEXPIRE(HExpireCommand),
+    HPEXPIRE(HPExpireCommand),
+    HEXPIREAT(HExpireAtCommand),
+    HPEXPIREAT(HPExpireAtCommand),
+    HTTL(HTtlCommand),
+    HPTTL(HPTtlCommand),
+    HEXPIRETIME(HExpireTimeCommand),
+    HPEXPIRETIME(HPExpireTimeCommand),
+    HPERSIST(HPersistCommand),
+    HGETEX(HGetExCommand),
+    HSETEX(HSetExCommand),
+    SADD(SAddCommand),
+    SREM(SRemCommand),
+    SMEMBERS(SMembersCommand),
+    SISMEMBER(SIsMemberCommand),
+    SMISMEMBER(SMIsMemberCommand),
+    SCARD(SCardCommand),
+    SPOP(SPopCommand),
+    SRANDMEMBER(SRandMemberCommand),
+    SMOVE(SMoveCommand),
+    SINTER(SInterCommand),
+    SUNION(SUnionCommand),
+    SDIFF(SDiffCommand),
+    SINTERSTORE(SInterStoreCommand),
+    SUNIONSTORE(SUnionStoreCommand),
+    SDIFFSTORE(SDiffStoreCommand),
+    SINTERCARD(SInterCardCommand),
+    SSCAN(SScanCommand),
+    ZADD(ZAddCommand),
+    ZREM(ZRemCommand),
+    ZSCORE(ZScoreCommand),
+    ZMSCORE(ZMScoreCommand),
+    ZINCRBY(ZIncrByCommand),
+    ZCARD(ZCardCommand),
+    ZCOUNT(ZCountCommand),
+    ZRANK(ZRankCommand),
+    ZREVRANK(ZRevRankCommand),
+    ZRANGE(ZRangeCommand),
+    ZRANGESTORE(ZRangeStoreCommand),
+    ZPOPMIN(ZPopMinCommand),
+    ZPOPMAX(ZPopMaxCommand),
+    BZPOPMIN(BZPopMinCommand),
+    BZPOPMAX(BZPopMaxCommand),
+    ZREMRANGEBYRANK(ZRemRangeByRankCommand),
+    ZREMRANGEBYSCORE(ZRemRangeByScoreCommand),
+    ZREMRANGEBYLEX(ZRemRangeByLexCommand),
+    ZSCAN(ZScanCommand),
+    ZUNION(ZUnionCommand),
+    ZINTER(ZInterCommand),
+    ZDIFF(ZDiffCommand),
+    ZUNIONSTORE(ZUnionStoreCommand),
+    ZINTERSTORE(ZInterStoreCommand),
+    ZDIFFSTORE(ZDiffStoreCommand),
+    ZINTERCARD(ZInterCardCommand),
+    XADD(XAddCommand),
+    XRANGE(XRangeCommand),
+    XREVRANGE(XRevRangeCommand),
+    XLEN(XLenCommand),
+    XDEL(XDelCommand),
+    XTRIM(XTrimCommand),
+    XREAD(XReadCommand),
+    XGROUP(XGroupCommand),
+    XREADGROUP(XReadGroupCommand),
+    XACK(XAckCommand),
+    XPENDING(XPendingCommand),
+    XCLAIM(XClaimCommand),
+    XAUTOCLAIM(XAutoClaimCommand),
+    XINFO(XInfoCommand),
+    PFADD(PfAddCommand),
+    PFCOUNT(PfCountCommand),
+    PFMERGE(PfMergeCommand),
+    SETBIT(SetBitCommand),
+    GETBIT(GetBitCommand),
+    BITCOUNT(BitCountCommand),
+    BITPOS(BitPosCommand),
+    BITOP(BitOpCommand),
+    BITFIELD(BitFieldCommand),
+    BITFIELDRO(BitFieldRoCommand),
 }
 impl TryFrom<BulkString> for Command {
     type Error = RedisError;
     fn try_from(s: BulkString) -> Result<Self> {
-        match s {
-            BulkString(x) if x.to_lowercase() == String::from("echo") => {
-                Ok(Self::ECHO(EchoCommand))
+        match s.0.to_lowercase().as_str() {
+            "echo" => Ok(Self::ECHO(EchoCommand)),
+            "ping" => Ok(Self::PING(PingCommand)),
+            "get" => Ok(Self::GET(GetCommand)),
+            "set" => Ok(Self::SET(SetCommand)),
+            "del" => Ok(Self::DEL(DelCommand)),
+            "client" => Ok(Self::CLIENT(ClientCommand)),
+            "select" => Ok(Self::SELECT(SelectCommand)),
+            "move" => Ok(Self::MOVE(MoveCommand)),
+            "swapdb" => Ok(Self::SWAPDB(SwapDbCommand)),
+            "dbsize" => Ok(Self::DBSIZE(DbSizeCommand)),
+            "flushdb" => Ok(Self::FLUSHDB(FlushDbCommand)),
+            "flushall" => Ok(Self::FLUSHALL(FlushAllCommand)),
+            "info" => Ok(Self::INFO(InfoCommand)),
+            "auth" => Ok(Self::AUTH(AuthCommand)),
+            "acl" => Ok(Self::ACL(AclCommand)),
+            "expire" => Ok(Self::EXPIRE(ExpireCommand)),
+            "pexpire" => Ok(Self::PEXPIRE(PExpireCommand)),
+            "expireat" => Ok(Self::EXPIREAT(ExpireAtCommand)),
+            "pexpireat" => Ok(Self::PEXPIREAT(PExpireAtCommand)),
+            "ttl" => Ok(Self::TTL(TtlCommand)),
+            "pttl" => Ok(Self::PTTL(PTtlCommand)),
+            "expiretime" => Ok(Self::EXPIRETIME(ExpireTimeCommand)),
+            "pexpiretime" => Ok(Self::PEXPIRETIME(PExpireTimeCommand)),
+            "persist" => Ok(Self::PERSIST(PersistCommand)),
+            "type" => Ok(Self::TYPE(TypeCommand)),
+            "lpush" => Ok(Self::LPUSH(LPushCommand)),
+            "rpush" => Ok(Self::RPUSH(RPushCommand)),
+            "lpushx" => Ok(Self::LPUSHX(LPushXCommand)),
+            "rpushx" => Ok(Self::RPUSHX(RPushXCommand)),
+            "lpop" => Ok(Self::LPOP(LPopCommand)),
+            "rpop" => Ok(Self::RPOP(RPopCommand)),
+            "llen" => Ok(Self::LLEN(LLenCommand)),
+            "lrange" => Ok(Self::LRANGE(LRangeCommand)),
+            "lindex" => Ok(Self::LINDEX(LIndexCommand)),
+            "lset" => Ok(Self::LSET(LSetCommand)),
+            "lrem" => Ok(Self::LREM(LRemCommand)),
+            "ltrim" => Ok(Self::LTRIM(LTrimCommand)),
+            "linsert" => Ok(Self::LINSERT(LInsertCommand)),
+            "lpos" => Ok(Self::LPOS(LPosCommand)),
+            "lmove" => Ok(Self::LMOVE(LMoveCommand)),
+            "lmpop" => Ok(Self::LMPOP(LMPopCommand)),
+            "blpop" => Ok(Self::BLPOP(BLPopCommand)),
+            "brpop" => Ok(Self::BRPOP(BRPopCommand)),
+            "blmove" => Ok(Self::BLMOVE(BLMoveCommand)),
+            "blmpop" => Ok(Self::BLMPOP(BLMPopCommand)),
+            "hello" => Ok(Self::HELLO(HelloCommand)),
+            "hset" => Ok(Self::HSET(HSetCommand)),
+            "hsetnx" => Ok(Self::HSETNX(HSetNxCommand)),
+            "hget" => Ok(Self::HGET(HGetCommand)),
+            "hmget" => Ok(Self::HMGET(HMGetCommand)),
+            "hgetall" => Ok(Self::HGETALL(HGetAllCommand)),
+            "hdel" => Ok(Self::HDEL(HDelCommand)),
+            "hexists" => Ok(Self::HEXISTS(HExistsCommand)),
+            "hlen" => Ok(Self::HLEN(HLenCommand)),
+            "hkeys" => Ok(Self::HKEYS(HKeysCommand)),
+            "hvals" => Ok(Self::HVALS(HValsCommand)),
+            "hstrlen" => Ok(Self::HSTRLEN(HStrLenCommand)),
+            "hincrby" => Ok(Self::HINCRBY(HIncrByCommand)),
+            "hincrbyfloat" => Ok(Self::HINCRBYFLOAT(HIncrByFloatCommand)),
+            "hrandfield" => Ok(Self::HRANDFIELD(HRandFieldCommand)),
+            "hscan" => Ok(Self::HSCAN(HScanCommand)),
+            "hexpire" => Ok(Self::HEXPIRE(HExpireCommand)),
+            "hpexpire" => Ok(Self::HPEXPIRE(HPExpireCommand)),
+            "hexpireat" => Ok(Self::HEXPIREAT(HExpireAtCommand)),
+            "hpexpireat" => Ok(Self::HPEXPIREAT(HPExpireAtCommand)),
+            "httl" => Ok(Self::HTTL(HTtlCommand)),
+            "hpttl" => Ok(Self::HPTTL(HPTtlCommand)),
+            "hexpiretime" => Ok(Self::HEXPIRETIME(HExpireTimeCommand)),
+            "hpexpiretime" => Ok(Self::HPEXPIRETIME(HPExpireTimeCommand)),
+            "hpersist" => Ok(Self::HPERSIST(HPersistCommand)),
+            "hgetex" => Ok(Self::HGETEX(HGetExCommand)),
+            "hsetex" => Ok(Self::HSETEX(HSetExCommand)),
+            "sadd" => Ok(Self::SADD(SAddCommand)),
+            "srem" => Ok(Self::SREM(SRemCommand)),
+            "smembers" => Ok(Self::SMEMBERS(SMembersCommand)),
+            "sismember" => Ok(Self::SISMEMBER(SIsMemberCommand)),
+            "smismember" => Ok(Self::SMISMEMBER(SMIsMemberCommand)),
+            "scard" => Ok(Self::SCARD(SCardCommand)),
+            "spop" => Ok(Self::SPOP(SPopCommand)),
+            "srandmember" => Ok(Self::SRANDMEMBER(SRandMemberCommand)),
+            "smove" => Ok(Self::SMOVE(SMoveCommand)),
+            "sinter" => Ok(Self::SINTER(SInterCommand)),
+            "sunion" => Ok(Self::SUNION(SUnionCommand)),
+            "sdiff" => Ok(Self::SDIFF(SDiffCommand)),
+            "sinterstore" => Ok(Self::SINTERSTORE(SInterStoreCommand)),
+            "sunionstore" => Ok(Self::SUNIONSTORE(SUnionStoreCommand)),
+            "sdiffstore" => Ok(Self::SDIFFSTORE(SDiffStoreCommand)),
+            "sintercard" => Ok(Self::SINTERCARD(SInterCardCommand)),
+            "sscan" => Ok(Self::SSCAN(SScanCommand)),
+            "zadd" => Ok(Self::ZADD(ZAddCommand)),
+            "zrem" => Ok(Self::ZREM(ZRemCommand)),
+            "zscore" => Ok(Self::ZSCORE(ZScoreCommand)),
+            "zmscore" => Ok(Self::ZMSCORE(ZMScoreCommand)),
+            "zincrby" => Ok(Self::ZINCRBY(ZIncrByCommand)),
+            "zcard" => Ok(Self::ZCARD(ZCardCommand)),
+            "zcount" => Ok(Self::ZCOUNT(ZCountCommand)),
+            "zrank" => Ok(Self::ZRANK(ZRankCommand)),
+            "zrevrank" => Ok(Self::ZREVRANK(ZRevRankCommand)),
+            "zrange" => Ok(Self::ZRANGE(ZRangeCommand)),
+            "zrangestore" => Ok(Self::ZRANGESTORE(ZRangeStoreCommand)),
+            "zpopmin" => Ok(Self::ZPOPMIN(ZPopMinCommand)),
+            "zpopmax" => Ok(Self::ZPOPMAX(ZPopMaxCommand)),
+            "bzpopmin" => Ok(Self::BZPOPMIN(BZPopMinCommand)),
+            "bzpopmax" => Ok(Self::BZPOPMAX(BZPopMaxCommand)),
+            "zremrangebyrank" => Ok(Self::ZREMRANGEBYRANK(ZRemRangeByRankCommand)),
+            "zremrangebyscore" => Ok(Self::ZREMRANGEBYSCORE(ZRemRangeByScoreCommand)),
+            "zremrangebylex" => Ok(Self::ZREMRANGEBYLEX(ZRemRangeByLexCommand)),
+            "zscan" => Ok(Self::ZSCAN(ZScanCommand)),
+            "zunion" => Ok(Self::ZUNION(ZUnionCommand)),
+            "zinter" => Ok(Self::ZINTER(ZInterCommand)),
+            "zdiff" => Ok(Self::ZDIFF(ZDiffCommand)),
+            "zunionstore" => Ok(Self::ZUNIONSTORE(ZUnionStoreCommand)),
+            "zinterstore" => Ok(Self::ZINTERSTORE(ZInterStoreCommand)),
+            "zdiffstore" => Ok(Self::ZDIFFSTORE(ZDiffStoreCommand)),
+            "zintercard" => Ok(Self::ZINTERCARD(ZInterCardCommand)),
+            "xadd" => Ok(Self::XADD(XAddCommand)),
+            "xrange" => Ok(Self::XRANGE(XRangeCommand)),
+            "xrevrange" => Ok(Self::XREVRANGE(XRevRangeCommand)),
+            "xlen" => Ok(Self::XLEN(XLenCommand)),
+            "xdel" => Ok(Self::XDEL(XDelCommand)),
+            "xtrim" => Ok(Self::XTRIM(XTrimCommand)),
+            "xread" => Ok(Self::XREAD(XReadCommand)),
+            "xgroup" => Ok(Self::XGROUP(XGroupCommand)),
+            "xreadgroup" => Ok(Self::XREADGROUP(XReadGroupCommand)),
+            "xack" => Ok(Self::XACK(XAckCommand)),
+            "xpending" => Ok(Self::XPENDING(XPendingCommand)),
+            "xclaim" => Ok(Self::XCLAIM(XClaimCommand)),
+            "xautoclaim" => Ok(Self::XAUTOCLAIM(XAutoClaimCommand)),
+            "xinfo" => Ok(Self::XINFO(XInfoCommand)),
+            "pfadd" => Ok(Self::PFADD(PfAddCommand)),
+            "pfcount" => Ok(Self::PFCOUNT(PfCountCommand)),
+            "pfmerge" => Ok(Self::PFMERGE(PfMergeCommand)),
+            "setbit" => Ok(Self::SETBIT(SetBitCommand)),
+            "getbit" => Ok(Self::GETBIT(GetBitCommand)),
+            "bitcount" => Ok(Self::BITCOUNT(BitCountCommand)),
+            "bitpos" => Ok(Self::BITPOS(BitPosCommand)),
+            "bitop" => Ok(Self::BITOP(BitOpCommand)),
+            "bitfield" => Ok(Self::BITFIELD(BitFieldCommand)),
+            "bitfield_ro" => Ok(Self::BITFIELDRO(BitFieldRoCommand)),
+            _ => Err(RedisError::UnknownCommand),
+        }
+    }
+}
+/// Static description of a command used for ACL checks and client pausing.
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// ACL categories, without the leading `@`.
+    pub categories: &'static [&'static str],
+    /// Positions of the key arguments.
+    pub keys: Option<KeySpec>,
+}
+/// Where the key arguments of a command are, counted from the command name.
+#[derive(Debug, Clone, Copy)]
+pub enum KeySpec {
+    /// Keys at `(first, last, step)`. A negative `last` counts from the end of
+    /// the arguments.
+    Range(usize, isize, usize),
+    /// The argument at the given position is a number of keys that follow it.
+    NumKeys(usize),
+    /// A destination key, then a number of keys that follow it, like the
+    /// `STORE` variants of `ZUNION` and co.
+    DestinationNumKeys,
+    /// The first half of the arguments after `STREAMS`, like in `XREAD`.
+    Streams,
+}
+impl CommandSpec {
+    pub fn is_write(&self) -> bool {
+        self.categories.contains(&"write")
+    }
+    pub fn is_read(&self) -> bool {
+        self.categories.contains(&"read")
+    }
+    /// Whether the key at `index` among `keys` is read and written, for the
+    /// `%R~`/`%W~` ACL key rules. The keys of most commands are all used
+    /// alike, by the `read` and `write` categories, but some read sources
+    /// and write a destination, like in Redis key specs.
+    pub fn key_access(&self, index: usize) -> (bool, bool) {
+        const STORES: [&str; 9] = [
+            "sinterstore",
+            "sunionstore",
+            "sdiffstore",
+            "zunionstore",
+            "zinterstore",
+            "zdiffstore",
+            "zrangestore",
+            "bitop",
+            "pfmerge",
+        ];
+        const MOVES: [&str; 3] = ["lmove", "blmove", "smove"];
+        if STORES.contains(&self.name) {
+            // The destination, then the sources. PFMERGE also reads the
+            // destination, merged into the result.
+            (index > 0 || self.name == "pfmerge", index == 0)
+        } else if MOVES.contains(&self.name) {
+            // Popped from the source, pushed to the destination.
+            (index == 0, true)
+        } else {
+            (self.is_read(), self.is_write())
+        }
+    }
+    /// Extracts the key arguments from `options` (the arguments after the command name).
+    pub fn keys<'a>(&self, options: &'a [BulkString]) -> Vec<&'a str> {
+        let (first, last, step) = match self.keys {
+            None => return Vec::new(),
+            Some(KeySpec::Range(first, last, step)) => (first, last, step),
+            Some(KeySpec::Streams) => {
+                let Some(streams) = options
+                    .iter()
+                    .position(|option| option.0.eq_ignore_ascii_case("streams"))
+                else {
+                    return Vec::new();
+                };
+                let streams = &options[streams + 1..];
+                return streams[..streams.len() / 2]
+                    .iter()
+                    .map(|key| key.0.as_str())
+                    .collect();
             }
-            BulkString(x) if x.to_lowercase() == String::from("ping") => {
-                Ok(Self::PING(PingCommand))
+            Some(KeySpec::DestinationNumKeys) => {
+                let sources = Self {
+                    keys: Some(KeySpec::NumKeys(2)),
+                    ..*self
+                };
+                let destination = options.first().map(|key| key.0.as_str());
+                return destination
+                    .into_iter()
+                    .chain(sources.keys(options))
+                    .collect();
             }
-            BulkString(x) if x.to_lowercase() == String::from("get") => Ok(Self::GET(GetCommand)),
-            BulkString(x) if x.to_lowercase() == String::from("set") => Ok(Self::SET(SetCommand)),
-            BulkString(x) if x.to_lowercase() == String::from("del") => Ok(Self::DEL(DelCommand)),
-            _ => Err(RedisError::UnknownCommand),
+            Some(KeySpec::NumKeys(position)) => {
+                let numkeys = options
+                    .get(position - 1)
+                    .and_then(|numkeys| numkeys.0.parse::<isize>().ok())
+                    .unwrap_or(0);
+                // The client can claim any number of keys, the range is
+                // clamped to the arguments below.
+                let last = (position as isize)
+                    .checked_add(numkeys.max(0))
+                    .unwrap_or(isize::MAX);
+                (position + 1, last, 1)
+            }
+        };
+        let last = if last < 0 {
+            options.len() as isize + last + 1
+        } else {
+            last
+        };
+        // Never past the arguments, however many keys were claimed.
+        let last = last.clamp(0, options.len() as isize) as usize;
+        (first..=last)
+            .step_by(step)
+            .filter_map(|position| options.get(position - 1))
+            .map(|key| key.0.as_str())
+            .collect()
+    }
+}
+
+pub const ACL_CATEGORIES: [&str; 19] = [
+    "keyspace",
+    "read",
+    "write",
+    "string",
+    "list",
+    "hash",
+    "set",
+    "sortedset",
+    "stream",
+    "hyperloglog",
+    "bitmap",
+    "fast",
+    "slow",
+    "blocking",
+    "admin",
+    "dangerous",
+    "connection",
+    "pubsub",
+    "server",
+];
+
+pub static COMMAND_SPECS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "echo",
+        categories: &["fast", "connection"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "ping",
+        categories: &["fast", "connection"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "get",
+        categories: &["read", "string", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "set",
+        categories: &["write", "string", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "del",
+        categories: &["keyspace", "write", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "client",
+        categories: &["admin", "slow", "dangerous", "connection"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "select",
+        categories: &["fast", "connection"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "move",
+        categories: &["keyspace", "write", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "swapdb",
+        categories: &["keyspace", "write", "fast", "dangerous"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "dbsize",
+        categories: &["keyspace", "read", "fast"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "flushdb",
+        categories: &["keyspace", "write", "slow", "dangerous"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "flushall",
+        categories: &["keyspace", "write", "slow", "dangerous"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "info",
+        categories: &["slow", "dangerous", "server"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "auth",
+        categories: &["fast", "connection"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "acl",
+        categories: &["admin", "slow", "dangerous", "server"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "expire",
+        categories: &["keyspace", "write", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "pexpire",
+        categories: &["keyspace", "write", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "expireat",
+        categories: &["keyspace", "write", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "pexpireat",
+        categories: &["keyspace", "write", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "ttl",
+        categories: &["keyspace", "read", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "pttl",
+        categories: &["keyspace", "read", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "expiretime",
+        categories: &["keyspace", "read", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "pexpiretime",
+        categories: &["keyspace", "read", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "persist",
+        categories: &["keyspace", "write", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "type",
+        categories: &["keyspace", "read", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lpush",
+        categories: &["write", "list", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "rpush",
+        categories: &["write", "list", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lpushx",
+        categories: &["write", "list", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "rpushx",
+        categories: &["write", "list", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lpop",
+        categories: &["write", "list", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "rpop",
+        categories: &["write", "list", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "llen",
+        categories: &["read", "list", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lrange",
+        categories: &["read", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lindex",
+        categories: &["read", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lset",
+        categories: &["write", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lrem",
+        categories: &["write", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "ltrim",
+        categories: &["write", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "linsert",
+        categories: &["write", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lpos",
+        categories: &["read", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "lmove",
+        categories: &["write", "list", "slow"],
+        keys: Some(KeySpec::Range(1, 2, 1)),
+    },
+    CommandSpec {
+        name: "lmpop",
+        categories: &["write", "list", "slow"],
+        keys: Some(KeySpec::NumKeys(1)),
+    },
+    CommandSpec {
+        name: "blpop",
+        categories: &["write", "list", "slow", "blocking"],
+        keys: Some(KeySpec::Range(1, -2, 1)),
+    },
+    CommandSpec {
+        name: "brpop",
+        categories: &["write", "list", "slow", "blocking"],
+        keys: Some(KeySpec::Range(1, -2, 1)),
+    },
+    CommandSpec {
+        name: "blmove",
+        categories: &["write", "list", "slow", "blocking"],
+        keys: Some(KeySpec::Range(1, 2, 1)),
+    },
+    CommandSpec {
+        name: "blmpop",
+        categories: &["write", "list", "slow", "blocking"],
+        keys: Some(KeySpec::NumKeys(2)),
+    },
+    CommandSpec {
+        name: "hello",
+        categories: &["fast", "connection"],
+        keys: None,
+    },
+    CommandSpec {
+        name: "hset",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hsetnx",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hget",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hmget",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hgetall",
+        categories: &["read", "hash", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hdel",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hexists",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hlen",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hkeys",
+        categories: &["read", "hash", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hvals",
+        categories: &["read", "hash", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hstrlen",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hincrby",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hincrbyfloat",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hrandfield",
+        categories: &["read", "hash", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hscan",
+        categories: &["read", "hash", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hexpire",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hpexpire",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hexpireat",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hpexpireat",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "httl",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hpttl",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hexpiretime",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hpexpiretime",
+        categories: &["read", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hpersist",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hgetex",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "hsetex",
+        categories: &["write", "hash", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "sadd",
+        categories: &["write", "set", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "srem",
+        categories: &["write", "set", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "smembers",
+        categories: &["read", "set", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "sismember",
+        categories: &["read", "set", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "smismember",
+        categories: &["read", "set", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "scard",
+        categories: &["read", "set", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "spop",
+        categories: &["write", "set", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "srandmember",
+        categories: &["read", "set", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "smove",
+        categories: &["write", "set", "fast"],
+        keys: Some(KeySpec::Range(1, 2, 1)),
+    },
+    CommandSpec {
+        name: "sinter",
+        categories: &["read", "set", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "sunion",
+        categories: &["read", "set", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "sdiff",
+        categories: &["read", "set", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "sinterstore",
+        categories: &["write", "set", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "sunionstore",
+        categories: &["write", "set", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "sdiffstore",
+        categories: &["write", "set", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "sintercard",
+        categories: &["read", "set", "slow"],
+        keys: Some(KeySpec::NumKeys(1)),
+    },
+    CommandSpec {
+        name: "sscan",
+        categories: &["read", "set", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zadd",
+        categories: &["write", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zrem",
+        categories: &["write", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zscore",
+        categories: &["read", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zmscore",
+        categories: &["read", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zincrby",
+        categories: &["write", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zcard",
+        categories: &["read", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zcount",
+        categories: &["read", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zrank",
+        categories: &["read", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zrevrank",
+        categories: &["read", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zrange",
+        categories: &["read", "sortedset", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zrangestore",
+        categories: &["write", "sortedset", "slow"],
+        keys: Some(KeySpec::Range(1, 2, 1)),
+    },
+    CommandSpec {
+        name: "zpopmin",
+        categories: &["write", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zpopmax",
+        categories: &["write", "sortedset", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "bzpopmin",
+        categories: &["write", "sortedset", "fast", "blocking"],
+        keys: Some(KeySpec::Range(1, -2, 1)),
+    },
+    CommandSpec {
+        name: "bzpopmax",
+        categories: &["write", "sortedset", "fast", "blocking"],
+        keys: Some(KeySpec::Range(1, -2, 1)),
+    },
+    CommandSpec {
+        name: "zremrangebyrank",
+        categories: &["write", "sortedset", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zremrangebyscore",
+        categories: &["write", "sortedset", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zremrangebylex",
+        categories: &["write", "sortedset", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zscan",
+        categories: &["read", "sortedset", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "zunion",
+        categories: &["read", "sortedset", "slow"],
+        keys: Some(KeySpec::NumKeys(1)),
+    },
+    CommandSpec {
+        name: "zinter",
+        categories: &["read", "sortedset", "slow"],
+        keys: Some(KeySpec::NumKeys(1)),
+    },
+    CommandSpec {
+        name: "zdiff",
+        categories: &["read", "sortedset", "slow"],
+        keys: Some(KeySpec::NumKeys(1)),
+    },
+    CommandSpec {
+        name: "zunionstore",
+        categories: &["write", "sortedset", "slow"],
+        keys: Some(KeySpec::DestinationNumKeys),
+    },
+    CommandSpec {
+        name: "zinterstore",
+        categories: &["write", "sortedset", "slow"],
+        keys: Some(KeySpec::DestinationNumKeys),
+    },
+    CommandSpec {
+        name: "zdiffstore",
+        categories: &["write", "sortedset", "slow"],
+        keys: Some(KeySpec::DestinationNumKeys),
+    },
+    CommandSpec {
+        name: "zintercard",
+        categories: &["read", "sortedset", "slow"],
+        keys: Some(KeySpec::NumKeys(1)),
+    },
+    CommandSpec {
+        name: "xadd",
+        categories: &["write", "stream", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xrange",
+        categories: &["read", "stream", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xrevrange",
+        categories: &["read", "stream", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xlen",
+        categories: &["read", "stream", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xdel",
+        categories: &["write", "stream", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xtrim",
+        categories: &["write", "stream", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xread",
+        categories: &["read", "stream", "slow", "blocking"],
+        keys: Some(KeySpec::Streams),
+    },
+    CommandSpec {
+        name: "xgroup",
+        categories: &["write", "stream", "slow"],
+        keys: Some(KeySpec::Range(2, 2, 1)),
+    },
+    CommandSpec {
+        name: "xreadgroup",
+        categories: &["write", "stream", "slow", "blocking"],
+        keys: Some(KeySpec::Streams),
+    },
+    CommandSpec {
+        name: "xack",
+        categories: &["write", "stream", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xpending",
+        categories: &["read", "stream", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xclaim",
+        categories: &["write", "stream", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xautoclaim",
+        categories: &["write", "stream", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "xinfo",
+        categories: &["read", "stream", "slow"],
+        keys: Some(KeySpec::Range(2, 2, 1)),
+    },
+    CommandSpec {
+        name: "pfadd",
+        categories: &["write", "hyperloglog", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "pfcount",
+        categories: &["read", "hyperloglog", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "pfmerge",
+        categories: &["write", "hyperloglog", "slow"],
+        keys: Some(KeySpec::Range(1, -1, 1)),
+    },
+    CommandSpec {
+        name: "setbit",
+        categories: &["write", "bitmap", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "getbit",
+        categories: &["read", "bitmap", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "bitcount",
+        categories: &["read", "bitmap", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "bitpos",
+        categories: &["read", "bitmap", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "bitop",
+        categories: &["write", "bitmap", "slow"],
+        keys: Some(KeySpec::Range(2, -1, 1)),
+    },
+    CommandSpec {
+        name: "bitfield",
+        categories: &["write", "bitmap", "slow"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+    CommandSpec {
+        name: "bitfield_ro",
+        categories: &["read", "bitmap", "fast"],
+        keys: Some(KeySpec::Range(1, 1, 1)),
+    },
+];
+
+pub fn command_spec(name: &str) -> Option<&'static CommandSpec> {
+    COMMAND_SPECS.iter().find(|spec| spec.name == name)
+}
+
+impl Command {
+    pub fn spec(&self) -> &'static CommandSpec {
+        command_spec(self.name()).expect("every command has a spec")
+    }
+    /// Whether the command has subcommands, like `CLIENT LIST`.
+    pub fn is_container(&self) -> bool {
+        matches!(self, Self::CLIENT(_) | Self::ACL(_))
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ECHO(_) => "echo",
+            Self::PING(_) => "ping",
+            Self::GET(_) => "get",
+            Self::SET(_) => "set",
+            Self::DEL(_) => "del",
+            Self::CLIENT(_) => "client",
+            Self::SELECT(_) => "select",
+            Self::MOVE(_) => "move",
+            Self::SWAPDB(_) => "swapdb",
+            Self::DBSIZE(_) => "dbsize",
+            Self::FLUSHDB(_) => "flushdb",
+            Self::FLUSHALL(_) => "flushall",
+            Self::INFO(_) => "info",
+            Self::AUTH(_) => "auth",
+            Self::ACL(_) => "acl",
+            Self::EXPIRE(_) => "expire",
+            Self::PEXPIRE(_) => "pexpire",
+            Self::EXPIREAT(_) => "expireat",
+            Self::PEXPIREAT(_) => "pexpireat",
+            Self::TTL(_) => "ttl",
+            Self::PTTL(_) => "pttl",
+            Self::EXPIRETIME(_) => "expiretime",
+            Self::PEXPIRETIME(_) => "pexpiretime",
+            Self::PERSIST(_) => "persist",
+            Self::TYPE(_) => "type",
+            Self::LPUSH(_) => "lpush",
+            Self::RPUSH(_) => "rpush",
+            Self::LPUSHX(_) => "lpushx",
+            Self::RPUSHX(_) => "rpushx",
+            Self::LPOP(_) => "lpop",
+            Self::RPOP(_) => "rpop",
+            Self::LLEN(_) => "llen",
+            Self::LRANGE(_) => "lrange",
+            Self::LINDEX(_) => "lindex",
+            Self::LSET(_) => "lset",
+            Self::LREM(_) => "lrem",
+            Self::LTRIM(_) => "ltrim",
+            Self::LINSERT(_) => "linsert",
+            Self::LPOS(_) => "lpos",
+            Self::LMOVE(_) => "lmove",
+            Self::LMPOP(_) => "lmpop",
+            Self::BLPOP(_) => "blpop",
+            Self::BRPOP(_) => "brpop",
+            Self::BLMOVE(_) => "blmove",
+            Self::BLMPOP(_) => "blmpop",
+            Self::HELLO(_) => "hello",
+            Self::HSET(_) => "hset",
+            Self::HSETNX(_) => "hsetnx",
+            Self::HGET(_) => "hget",
+            Self::HMGET(_) => "hmget",
+            Self::HGETALL(_) => "hgetall",
+            Self::HDEL(_) => "hdel",
+            Self::HEXISTS(_) => "hexists",
+            Self::HLEN(_) => "hlen",
+            Self::HKEYS(_) => "hkeys",
+            Self::HVALS(_) => "hvals",
+            Self::HSTRLEN(_) => "hstrlen",
+            Self::HINCRBY(_) => "hincrby",
+            Self::HINCRBYFLOAT(_) => "hincrbyfloat",
+            Self::HRANDFIELD(_) => "hrandfield",
+            Self::HSCAN(_) => "hscan",
+            Self::HEXPIRE(_) => "hexpire",
+            Self::HPEXPIRE(_) => "hpexpire",
+            Self::HEXPIREAT(_) => "hexpireat",
+            Self::HPEXPIREAT(_) => "hpexpireat",
+            Self::HTTL(_) => "httl",
+            Self::HPTTL(_) => "hpttl",
+            Self::HEXPIRETIME(_) => "hexpiretime",
+            Self::HPEXPIRETIME(_) => "hpexpiretime",
+            Self::HPERSIST(_) => "hpersist",
+            Self::HGETEX(_) => "hgetex",
+            Self::HSETEX(_) => "hsetex",
+            Self::SADD(_) => "sadd",
+            Self::SREM(_) => "srem",
+            Self::SMEMBERS(_) => "smembers",
+            Self::SISMEMBER(_) => "sismember",
+            Self::SMISMEMBER(_) => "smismember",
+            Self::SCARD(_) => "scard",
+            Self::SPOP(_) => "spop",
+            Self::SRANDMEMBER(_) => "srandmember",
+            Self::SMOVE(_) => "smove",
+            Self::SINTER(_) => "sinter",
+            Self::SUNION(_) => "sunion",
+            Self::SDIFF(_) => "sdiff",
+            Self::SINTERSTORE(_) => "sinterstore",
+            Self::SUNIONSTORE(_) => "sunionstore",
+            Self::SDIFFSTORE(_) => "sdiffstore",
+            Self::SINTERCARD(_) => "sintercard",
+            Self::SSCAN(_) => "sscan",
+            Self::ZADD(_) => "zadd",
+            Self::ZREM(_) => "zrem",
+            Self::ZSCORE(_) => "zscore",
+            Self::ZMSCORE(_) => "zmscore",
+            Self::ZINCRBY(_) => "zincrby",
+            Self::ZCARD(_) => "zcard",
+            Self::ZCOUNT(_) => "zcount",
+            Self::ZRANK(_) => "zrank",
+            Self::ZREVRANK(_) => "zrevrank",
+            Self::ZRANGE(_) => "zrange",
+            Self::ZRANGESTORE(_) => "zrangestore",
+            Self::ZPOPMIN(_) => "zpopmin",
+            Self::ZPOPMAX(_) => "zpopmax",
+            Self::BZPOPMIN(_) => "bzpopmin",
+            Self::BZPOPMAX(_) => "bzpopmax",
+            Self::ZREMRANGEBYRANK(_) => "zremrangebyrank",
+            Self::ZREMRANGEBYSCORE(_) => "zremrangebyscore",
+            Self::ZREMRANGEBYLEX(_) => "zremrangebylex",
+            Self::ZSCAN(_) => "zscan",
+            Self::ZUNION(_) => "zunion",
+            Self::ZINTER(_) => "zinter",
+            Self::ZDIFF(_) => "zdiff",
+            Self::ZUNIONSTORE(_) => "zunionstore",
+            Self::ZINTERSTORE(_) => "zinterstore",
+            Self::ZDIFFSTORE(_) => "zdiffstore",
+            Self::ZINTERCARD(_) => "zintercard",
+            Self::XADD(_) => "xadd",
+            Self::XRANGE(_) => "xrange",
+            Self::XREVRANGE(_) => "xrevrange",
+            Self::XLEN(_) => "xlen",
+            Self::XDEL(_) => "xdel",
+            Self::XTRIM(_) => "xtrim",
+            Self::XREAD(_) => "xread",
+            Self::XGROUP(_) => "xgroup",
+            Self::XREADGROUP(_) => "xreadgroup",
+            Self::XACK(_) => "xack",
+            Self::XPENDING(_) => "xpending",
+            Self::XCLAIM(_) => "xclaim",
+            Self::XAUTOCLAIM(_) => "xautoclaim",
+            Self::XINFO(_) => "xinfo",
+            Self::PFADD(_) => "pfadd",
+            Self::PFCOUNT(_) => "pfcount",
+            Self::PFMERGE(_) => "pfmerge",
+            Self::SETBIT(_) => "setbit",
+            Self::GETBIT(_) => "getbit",
+            Self::BITCOUNT(_) => "bitcount",
+            Self::BITPOS(_) => "bitpos",
+            Self::BITOP(_) => "bitop",
+            Self::BITFIELD(_) => "bitfield",
+            Self::BITFIELDRO(_) => "bitfield_ro",
         }
     }
 }
+pub(crate) fn error(message: &str) -> Box<dyn Serialize> {
+    Box::new(Value::SimpleError(SimpleError {
+        error_type: ErrorType::ERR,
+        message: message.to_owned(),
+    }))
+}
+pub(crate) fn ok() -> Box<dyn Serialize> {
+    Box::new(Value::SimpleString(SimpleString(String::from("OK"))))
+}
+pub(crate) fn wrong_type() -> Box<dyn Serialize> {
+    Box::new(Value::SimpleError(SimpleError {
+        error_type: ErrorType::WRONGTYPE,
+        message: String::from("Operation against a key holding the wrong kind of value"),
+    }))
+}
+/// Reply for the errors of typed storage operations.
+pub(crate) fn storage_error(e: RedisError) -> Box<dyn Serialize> {
+    match e {
+        RedisError::WrongType => wrong_type(),
+        RedisError::KeyDoesNotExist => error("no such key"),
+        RedisError::IndexOutOfBoundError => error("index out of range"),
+        RedisError::NoGroup { .. } => Box::new(Value::SimpleError(SimpleError {
+            error_type: ErrorType::NOGROUP,
+            message: e.to_string(),
+        })),
+        RedisError::BusyGroup => Box::new(Value::SimpleError(SimpleError {
+            error_type: ErrorType::BUSYGROUP,
+            message: e.to_string(),
+        })),
+        RedisError::NotHyperLogLog => Box::new(Value::SimpleError(SimpleError {
+            error_type: ErrorType::WRONGTYPE,
+            message: e.to_string(),
+        })),
+        RedisError::CorruptedHll => Box::new(Value::SimpleError(SimpleError {
+            error_type: ErrorType::INVALIDOBJ,
+            message: e.to_string(),
+        })),
+        e => error(&e.to_string()),
+    }
+}
+pub(crate) fn integer(value: i64) -> Box<dyn Serialize> {
+    Box::new(Value::Integer(Integer(value)))
+}
+/// A bulk string, or a null reply for `None`.
+pub(crate) fn bulk_or_null(value: Option<String>) -> Box<dyn Serialize> {
+    match value {
+        Some(value) => Box::new(Value::BulkString(BulkString(value))),
+        None => Box::new(Value::Nulls(Nulls)),
+    }
+}
+/// A map for the clients that negotiated RESP3, a flat array of keys and
+/// values otherwise.
+pub(crate) fn map_reply(client: &Client, entries: Vec<(Value, Value)>) -> Value {
+    if client.resp() >= 3 {
+        return Value::Map(Map(entries));
+    }
+    Value::Array(Array(
+        entries
+            .into_iter()
+            .flat_map(|(key, value)| [key, value])
+            .collect(),
+    ))
+}
+pub(crate) fn bulk_array(values: Vec<String>) -> Value {
+    Value::Array(Array(
+        values
+            .into_iter()
+            .map(|value| Value::BulkString(BulkString(value)))
+            .collect(),
+    ))
+}
+/// Parses an integer argument, with the error reply Redis uses.
+pub(crate) fn parse_integer(value: &BulkString) -> std::result::Result<i64, Box<dyn Serialize>> {
+    value
+        .0
+        .parse()
+        .map_err(|_| error("value is not an integer or out of range"))
+}
+pub(crate) fn wrong_number_of_arguments(command: &str) -> Box<dyn Serialize> {
+    error(&format!(
+        "wrong number of arguments for '{}' command",
+        command
+    ))
+}
 pub trait Execute {
-    fn execute(self, options: Vec<BulkString>) -> Box<dyn Serialize>;
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize>;
 }
 
 impl Execute for Command {
-    fn execute(self, options: Vec<BulkString>) -> Box<dyn Serialize> {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
         match self {
-            Self::ECHO(echo_command) => echo_command.execute(options),
-            Self::PING(ping_command) => ping_command.execute(options),
-            Self::GET(get_command) => get_command.execute(options),
-            Self::SET(set_command) => set_command.execute(options),
-            Self::DEL(del_command) => del_command.execute(options),
+            Self::ECHO(echo_command) => echo_command.execute(options, client),
+            Self::PING(ping_command) => ping_command.execute(options, client),
+            Self::GET(get_command) => get_command.execute(options, client),
+            Self::SET(set_command) => set_command.execute(options, client),
+            Self::DEL(del_command) => del_command.execute(options, client),
+            Self::CLIENT(client_command) => client_command.execute(options, client),
+            Self::SELECT(select_command) => select_command.execute(options, client),
+            Self::MOVE(move_command) => move_command.execute(options, client),
+            Self::SWAPDB(swapdb_command) => swapdb_command.execute(options, client),
+            Self::DBSIZE(dbsize_command) => dbsize_command.execute(options, client),
+            Self::FLUSHDB(flushdb_command) => flushdb_command.execute(options, client),
+            Self::FLUSHALL(flushall_command) => flushall_command.execute(options, client),
+            Self::INFO(info_command) => info_command.execute(options, client),
+            Self::AUTH(auth_command) => auth_command.execute(options, client),
+            Self::ACL(acl_command) => acl_command.execute(options, client),
+            Self::EXPIRE(expire_command) => expire_command.execute(options, client),
+            Self::PEXPIRE(pexpire_command) => pexpire_command.execute(options, client),
+            Self::EXPIREAT(expireat_command) => expireat_command.execute(options, client),
+            Self::PEXPIREAT(pexpireat_command) => pexpireat_command.execute(options, client),
+            Self::TTL(ttl_command) => ttl_command.execute(options, client),
+            Self::PTTL(pttl_command) => pttl_command.execute(options, client),
+            Self::EXPIRETIME(expiretime_command) => expiretime_command.execute(options, client),
+            Self::PEXPIRETIME(pexpiretime_command) => pexpiretime_command.execute(options, client),
+            Self::PERSIST(persist_command) => persist_command.execute(options, client),
+            Self::TYPE(type_command) => type_command.execute(options, client),
+            Self::LPUSH(lpush_command) => lpush_command.execute(options, client),
+            Self::RPUSH(rpush_command) => rpush_command.execute(options, client),
+            Self::LPUSHX(lpushx_command) => lpushx_command.execute(options, client),
+            Self::RPUSHX(rpushx_command) => rpushx_command.execute(options, client),
+            Self::LPOP(lpop_command) => lpop_command.execute(options, client),
+            Self::RPOP(rpop_command) => rpop_command.execute(options, client),
+            Self::LLEN(llen_command) => llen_command.execute(options, client),
+            Self::LRANGE(lrange_command) => lrange_command.execute(options, client),
+            Self::LINDEX(lindex_command) => lindex_command.execute(options, client),
+            Self::LSET(lset_command) => lset_command.execute(options, client),
+            Self::LREM(lrem_command) => lrem_command.execute(options, client),
+            Self::LTRIM(ltrim_command) => ltrim_command.execute(options, client),
+            Self::LINSERT(linsert_command) => linsert_command.execute(options, client),
+            Self::LPOS(lpos_command) => lpos_command.execute(options, client),
+            Self::LMOVE(lmove_command) => lmove_command.execute(options, client),
+            Self::LMPOP(lmpop_command) => lmpop_command.execute(options, client),
+            Self::BLPOP(blpop_command) => blpop_command.execute(options, client),
+            Self::BRPOP(brpop_command) => brpop_command.execute(options, client),
+            Self::BLMOVE(blmove_command) => blmove_command.execute(options, client),
+            Self::BLMPOP(blmpop_command) => blmpop_command.execute(options, client),
+            Self::HELLO(hello_command) => hello_command.execute(options, client),
+            Self::HSET(hset_command) => hset_command.execute(options, client),
+            Self::HSETNX(hsetnx_command) => hsetnx_command.execute(options, client),
+            Self::HGET(hget_command) => hget_command.execute(options, client),
+            Self::HMGET(hmget_command) => hmget_command.execute(options, client),
+            Self::HGETALL(hgetall_command) => hgetall_command.execute(options, client),
+            Self::HDEL(hdel_command) => hdel_command.execute(options, client),
+            Self::HEXISTS(hexists_command) => hexists_command.execute(options, client),
+            Self::HLEN(hlen_command) => hlen_command.execute(options, client),
+            Self::HKEYS(hkeys_command) => hkeys_command.execute(options, client),
+            Self::HVALS(hvals_command) => hvals_command.execute(options, client),
+            Self::HSTRLEN(hstrlen_command) => hstrlen_command.execute(options, client),
+            Self::HINCRBY(hincrby_command) => hincrby_command.execute(options, client),
+            Self::HINCRBYFLOAT(hincrbyfloat_command) => {
+                hincrbyfloat_command.execute(options, client)
+            }
+            Self::HRANDFIELD(hrandfield_command) => hrandfield_command.execute(options, client),
+            Self::HSCAN(hscan_command) => hscan_command.execute(options, client),
+            Self::HEXPIRE(hexpire_command) => hexpire_command.execute(options, client),
+            Self::HPEXPIRE(hpexpire_command) => hpexpire_command.execute(options, client),
+            Self::HEXPIREAT(hexpireat_command) => hexpireat_command.execute(options, client),
+            Self::HPEXPIREAT(hpexpireat_command) => hpexpireat_command.execute(options, client),
+            Self::HTTL(httl_command) => httl_command.execute(options, client),
+            Self::HPTTL(hpttl_command) => hpttl_command.execute(options, client),
+            Self::HEXPIRETIME(hexpiretime_command) => hexpiretime_command.execute(options, client),
+            Self::HPEXPIRETIME(hpexpiretime_command) => {
+                hpexpiretime_command.execute(options, client)
+            }
+            Self::HPERSIST(hpersist_command) => hpersist_command.execute(options, client),
+            Self::HGETEX(hgetex_command) => hgetex_command.execute(options, client),
+            Self::HSETEX(hsetex_command) => hsetex_command.execute(options, client),
+            Self::SADD(sadd_command) => sadd_command.execute(options, client),
+            Self::SREM(srem_command) => srem_command.execute(options, client),
+            Self::SMEMBERS(smembers_command) => smembers_command.execute(options, client),
+            Self::SISMEMBER(sismember_command) => sismember_command.execute(options, client),
+            Self::SMISMEMBER(smismember_command) => smismember_command.execute(options, client),
+            Self::SCARD(scard_command) => scard_command.execute(options, client),
+            Self::SPOP(spop_command) => spop_command.execute(options, client),
+            Self::SRANDMEMBER(srandmember_command) => srandmember_command.execute(options, client),
+            Self::SMOVE(smove_command) => smove_command.execute(options, client),
+            Self::SINTER(sinter_command) => sinter_command.execute(options, client),
+            Self::SUNION(sunion_command) => sunion_command.execute(options, client),
+            Self::SDIFF(sdiff_command) => sdiff_command.execute(options, client),
+            Self::SINTERSTORE(sinterstore_command) => sinterstore_command.execute(options, client),
+            Self::SUNIONSTORE(sunionstore_command) => sunionstore_command.execute(options, client),
+            Self::SDIFFSTORE(sdiffstore_command) => sdiffstore_command.execute(options, client),
+            Self::SINTERCARD(sintercard_command) => sintercard_command.execute(options, client),
+            Self::SSCAN(sscan_command) => sscan_command.execute(options, client),
+            Self::ZADD(zadd_command) => zadd_command.execute(options, client),
+            Self::ZREM(zrem_command) => zrem_command.execute(options, client),
+            Self::ZSCORE(zscore_command) => zscore_command.execute(options, client),
+            Self::ZMSCORE(zmscore_command) => zmscore_command.execute(options, client),
+            Self::ZINCRBY(zincrby_command) => zincrby_command.execute(options, client),
+            Self::ZCARD(zcard_command) => zcard_command.execute(options, client),
+            Self::ZCOUNT(zcount_command) => zcount_command.execute(options, client),
+            Self::ZRANK(zrank_command) => zrank_command.execute(options, client),
+            Self::ZREVRANK(zrevrank_command) => zrevrank_command.execute(options, client),
+            Self::ZRANGE(zrange_command) => zrange_command.execute(options, client),
+            Self::ZRANGESTORE(zrangestore_command) => zrangestore_command.execute(options, client),
+            Self::ZPOPMIN(zpopmin_command) => zpopmin_command.execute(options, client),
+            Self::ZPOPMAX(zpopmax_command) => zpopmax_command.execute(options, client),
+            Self::BZPOPMIN(bzpopmin_command) => bzpopmin_command.execute(options, client),
+            Self::BZPOPMAX(bzpopmax_command) => bzpopmax_command.execute(options, client),
+            Self::ZREMRANGEBYRANK(zremrangebyrank_command) => {
+                zremrangebyrank_command.execute(options, client)
+            }
+            Self::ZREMRANGEBYSCORE(zremrangebyscore_command) => {
+                zremrangebyscore_command.execute(options, client)
+            }
+            Self::ZREMRANGEBYLEX(zremrangebylex_command) => {
+                zremrangebylex_command.execute(options, client)
+            }
+            Self::ZSCAN(zscan_command) => zscan_command.execute(options, client),
+            Self::ZUNION(zunion_command) => zunion_command.execute(options, client),
+            Self::ZINTER(zinter_command) => zinter_command.execute(options, client),
+            Self::ZDIFF(zdiff_command) => zdiff_command.execute(options, client),
+            Self::ZUNIONSTORE(zunionstore_command) => zunionstore_command.execute(options, client),
+            Self::ZINTERSTORE(zinterstore_command) => zinterstore_command.execute(options, client),
+            Self::ZDIFFSTORE(zdiffstore_command) => zdiffstore_command.execute(options, client),
+            Self::ZINTERCARD(zintercard_command) => zintercard_command.execute(options, client),
+            Self::XADD(xadd_command) => xadd_command.execute(options, client),
+            Self::XRANGE(xrange_command) => xrange_command.execute(options, client),
+            Self::XREVRANGE(xrevrange_command) => xrevrange_command.execute(options, client),
+            Self::XLEN(xlen_command) => xlen_command.execute(options, client),
+            Self::XDEL(xdel_command) => xdel_command.execute(options, client),
+            Self::XTRIM(xtrim_command) => xtrim_command.execute(options, client),
+            Self::XREAD(xread_command) => xread_command.execute(options, client),
+            Self::XGROUP(xgroup_command) => xgroup_command.execute(options, client),
+            Self::XREADGROUP(xreadgroup_command) => xreadgroup_command.execute(options, client),
+            Self::XACK(xack_command) => xack_command.execute(options, client),
+            Self::XPENDING(xpending_command) => xpending_command.execute(options, client),
+            Self::XCLAIM(xclaim_command) => xclaim_command.execute(options, client),
+            Self::XAUTOCLAIM(xautoclaim_command) => xautoclaim_command.execute(options, client),
+            Self::XINFO(xinfo_command) => xinfo_command.execute(options, client),
+            Self::PFADD(pfadd_command) => pfadd_command.execute(options, client),
+            Self::PFCOUNT(pfcount_command) => pfcount_command.execute(options, client),
+            Self::PFMERGE(pfmerge_command) => pfmerge_command.execute(options, client),
+            Self::SETBIT(setbit_command) => setbit_command.execute(options, client),
+            Self::GETBIT(getbit_command) => getbit_command.execute(options, client),
+            Self::BITCOUNT(bitcount_command) => bitcount_command.execute(options, client),
+            Self::BITPOS(bitpos_command) => bitpos_command.execute(options, client),
+            Self::BITOP(bitop_command) => bitop_command.execute(options, client),
+            Self::BITFIELD(bitfield_command) => bitfield_command.execute(options, client),
+            Self::BITFIELDRO(bitfield_ro_command) => bitfield_ro_command.execute(options, client),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acl::LogReason;
+    use crate::clock::MockClock;
+    use crate::config::Config;
+    use crate::db::SetOptions;
+    use crate::object::RedisObject;
+    use crate::server::Server;
+    use crate::storage::{Expiry, Update};
+    use crate::value::deserialize::Deserialize;
+    use std::collections::VecDeque;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    pub(crate) fn test_client() -> Arc<Client> {
+        test_client_with(Config::default())
+    }
+
+    pub(crate) fn test_client_with(config: Config) -> Arc<Client> {
+        let server = Server::open(config).unwrap();
+        let addr = "127.0.0.1:6378".parse().unwrap();
+        server.clients.register(server.clone(), addr, addr)
+    }
+
+    /// Runs one command and returns its serialized reply.
+    pub(crate) fn run(client: &Client, args: &[&str]) -> String {
+        String::from_utf8(run_bytes(client, args)).expect("a UTF-8 reply")
+    }
+
+    /// Like `run`, for replies that may carry binary strings.
+    pub(crate) fn run_bytes(client: &Client, args: &[&str]) -> Vec<u8> {
+        let command = Command::try_from(BulkString(args[0].to_owned())).unwrap();
+        let options = args[1..]
+            .iter()
+            .map(|arg| BulkString(arg.to_string()))
+            .collect();
+        command.execute(options, client).serialize()
+    }
+
+    /// Writes `key` in the selected database with a timeout that already elapsed.
+    fn set_expired(client: &Client, key: &str) {
+        let expired = SetOptions::default().expire_at(UNIX_EPOCH + Duration::from_secs(1));
+        client
+            .server()
+            .db(client.db())
+            .unwrap()
+            .set_with(key, "old", expired);
+    }
+
+    #[test]
+    fn test_commands_treat_expired_keys_as_absent() {
+        let client = test_client();
+
+        set_expired(&client, "key");
+        assert_eq!(run(&client, &["GET", "key"]), "_\r\n");
+
+        set_expired(&client, "key");
+        assert_eq!(run(&client, &["SET", "key", "new", "XX"]), "_\r\n");
+        assert_eq!(run(&client, &["SET", "key", "new", "NX"]), "+Ok\r\n");
+        assert_eq!(run(&client, &["GET", "key"]), "$3\r\nnew\r\n");
+
+        set_expired(&client, "key");
+        assert_eq!(run(&client, &["SET", "key", "new", "GET"]), "_\r\n");
+
+        set_expired(&client, "key");
+        assert_eq!(run(&client, &["DEL", "key"]), ":0\r\n");
+
+        set_expired(&client, "key");
+        assert_eq!(run(&client, &["MOVE", "key", "1"]), ":0\r\n");
+        assert_eq!(client.server().storage.size(1), 0);
+
+        run(&client, &["SET", "key", "value"]);
+        client.select(1);
+        set_expired(&client, "key");
+        client.select(0);
+        assert_eq!(run(&client, &["MOVE", "key", "1"]), ":1\r\n");
+
+        let db = client.server().db(0).unwrap();
+        set_expired(&client, "key");
+        assert!(!db.exists("key"));
+        set_expired(&client, "key");
+        assert!(!db.expire("key", Duration::from_secs(10)));
+        set_expired(&client, "key");
+        assert_eq!(db.ttl("key"), None);
+        set_expired(&client, "key");
+        assert!(db.compare_and_swap("key", None, "new"));
+    }
+
+    #[test]
+    fn test_select_and_move() {
+        let client = test_client();
+        let databases = Config::default().databases.to_string();
+        assert_eq!(
+            run(&client, &["SELECT", &databases]),
+            "-ERR DB index is out of range\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SELECT", "-1"]),
+            "-ERR DB index is out of range\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SELECT", "one"]),
+            "-ERR value is not an integer or out of range\r\n"
+        );
+        assert_eq!(client.db(), 0);
+
+        run(&client, &["SET", "key", "a"]);
+        assert_eq!(run(&client, &["SELECT", "1"]), "+OK\r\n");
+        assert_eq!(run(&client, &["GET", "key"]), "_\r\n");
+        run(&client, &["SET", "key", "b"]);
+
+        // A key already in the destination is left alone on both sides.
+        run(&client, &["SELECT", "0"]);
+        assert_eq!(run(&client, &["MOVE", "key", "1"]), ":0\r\n");
+        assert_eq!(run(&client, &["GET", "key"]), "$1\r\na\r\n");
+        run(&client, &["SELECT", "1"]);
+        assert_eq!(run(&client, &["GET", "key"]), "$1\r\nb\r\n");
+
+        assert_eq!(run(&client, &["MOVE", "key", "2"]), ":1\r\n");
+        assert_eq!(run(&client, &["GET", "key"]), "_\r\n");
+        assert_eq!(
+            run(&client, &["MOVE", "key", "1"]),
+            "-ERR source and destination objects are the same\r\n"
+        );
+        assert_eq!(
+            run(&client, &["MOVE", "key", &databases]),
+            "-ERR DB index is out of range\r\n"
+        );
+    }
+
+    #[test]
+    fn test_swapdb_is_seen_by_every_client() {
+        let client = test_client();
+        let other = connect(&client, 6380);
+        run(&client, &["SET", "key", "zero"]);
+        run(&other, &["SELECT", "1"]);
+        run(&other, &["SET", "key", "one"]);
+        run(&other, &["SET", "other", "one"]);
+
+        assert_eq!(run(&client, &["SWAPDB", "0", "1"]), "+OK\r\n");
+        assert_eq!(run(&client, &["GET", "key"]), "$3\r\none\r\n");
+        assert_eq!(run(&client, &["DBSIZE"]), ":2\r\n");
+        assert_eq!(run(&other, &["GET", "key"]), "$4\r\nzero\r\n");
+        assert_eq!(run(&other, &["DBSIZE"]), ":1\r\n");
+
+        assert_eq!(
+            run(&client, &["SWAPDB", "0", "-1"]),
+            "-ERR invalid second DB index\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SWAPDB", "100", "0"]),
+            "-ERR invalid first DB index\r\n"
+        );
+    }
+
+    #[test]
+    fn test_flush_and_keyspace_info() {
+        let client = test_client();
+        run(&client, &["SET", "a", "1"]);
+        run(&client, &["SET", "b", "1"]);
+        run(&client, &["EXPIRE", "b", "100"]);
+        run(&client, &["SELECT", "1"]);
+        run(&client, &["SET", "c", "1"]);
+        run(&client, &["SELECT", "0"]);
+        assert_eq!(run(&client, &["DBSIZE"]), ":2\r\n");
+        assert!(run(&client, &["DBSIZE", "extra"]).starts_with("-ERR wrong number"));
+
+        let info = run(&client, &["INFO", "keyspace"]);
+        assert!(info.contains("# Keyspace\r\n"));
+        assert!(info.contains("db0:keys=2,expires=1,"));
+        assert!(info.contains("db1:keys=1,expires=0,avg_ttl=0\r\n"));
+        assert!(!info.contains("db2:"));
+
+        // FLUSHDB only clears the selected database.
+        assert_eq!(run(&client, &["FLUSHDB"]), "+OK\r\n");
+        assert_eq!(run(&client, &["DBSIZE"]), ":0\r\n");
+        assert_eq!(client.server().storage.size(1), 1);
+        let info = run(&client, &["INFO", "keyspace"]);
+        assert!(!info.contains("db0:"));
+        assert!(info.contains("db1:keys=1,"));
+
+        assert_eq!(run(&client, &["FLUSHDB", "later"]), "-ERR syntax error\r\n");
+        assert_eq!(run(&client, &["FLUSHALL", "SYNC"]), "+OK\r\n");
+        assert_eq!(client.server().storage.size(1), 0);
+    }
+
+    #[test]
+    fn test_ttl_commands() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let client = test_client_with(Config {
+            clock: clock.clone(),
+            ..Config::default()
+        });
+        run(&client, &["SET", "key", "value"]);
+        assert_eq!(run(&client, &["TTL", "missing"]), ":-2\r\n");
+        assert_eq!(run(&client, &["TTL", "key"]), ":-1\r\n");
+        assert_eq!(run(&client, &["EXPIRETIME", "key"]), ":-1\r\n");
+        assert_eq!(run(&client, &["EXPIRE", "missing", "10"]), ":0\r\n");
+
+        assert_eq!(run(&client, &["EXPIRE", "key", "100", "XX"]), ":0\r\n");
+        assert_eq!(run(&client, &["EXPIRE", "key", "100", "GT"]), ":0\r\n");
+        assert_eq!(run(&client, &["EXPIRE", "key", "100", "NX"]), ":1\r\n");
+        assert_eq!(run(&client, &["EXPIRE", "key", "200", "NX"]), ":0\r\n");
+        assert_eq!(run(&client, &["TTL", "key"]), ":100\r\n");
+        assert_eq!(run(&client, &["PTTL", "key"]), ":100000\r\n");
+        assert_eq!(run(&client, &["EXPIRETIME", "key"]), ":1100\r\n");
+        assert_eq!(run(&client, &["PEXPIRETIME", "key"]), ":1100000\r\n");
+
+        assert_eq!(run(&client, &["PEXPIRE", "key", "50000", "GT"]), ":0\r\n");
+        assert_eq!(run(&client, &["PEXPIRE", "key", "50000", "LT"]), ":1\r\n");
+        assert_eq!(
+            run(&client, &["EXPIREAT", "key", "2000", "XX", "GT"]),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["PEXPIREAT", "key", "1500000", "LT"]),
+            ":1\r\n"
+        );
+        clock.advance(Duration::from_millis(499_500));
+        assert_eq!(run(&client, &["TTL", "key"]), ":1\r\n");
+        assert_eq!(run(&client, &["PTTL", "key"]), ":500\r\n");
+
+        assert_eq!(run(&client, &["PERSIST", "key"]), ":1\r\n");
+        assert_eq!(run(&client, &["PERSIST", "key"]), ":0\r\n");
+        assert_eq!(run(&client, &["TTL", "key"]), ":-1\r\n");
+
+        assert_eq!(run(&client, &["EXPIRE", "key", "-1"]), ":1\r\n");
+        assert_eq!(run(&client, &["GET", "key"]), "_\r\n");
+
+        run(&client, &["SET", "key", "value"]);
+        assert_eq!(
+            run(&client, &["EXPIRE", "key", "10", "NX", "GT"]),
+            "-ERR NX and XX, GT or LT options at the same time are not compatible\r\n"
+        );
+        assert_eq!(
+            run(&client, &["EXPIRE", "key", "10", "GT", "LT"]),
+            "-ERR GT and LT options at the same time are not compatible\r\n"
+        );
+        assert_eq!(
+            run(&client, &["EXPIRE", "key", "10", "FOO"]),
+            "-ERR Unsupported option FOO\r\n"
+        );
+        assert_eq!(
+            run(&client, &["EXPIRE", "key", "ten"]),
+            "-ERR value is not an integer or out of range\r\n"
+        );
+        assert_eq!(
+            run(&client, &["EXPIRE", "key", "9223372036854775807"]),
+            "-ERR invalid expire time in 'expire' command\r\n"
+        );
+        assert_eq!(run(&client, &["TTL", "key"]), ":-1\r\n");
+    }
+
+    #[test]
+    fn test_type_and_wrongtype() {
+        let client = test_client();
+        let db = client.server().db(0).unwrap();
+        let list = RedisObject::List(VecDeque::from([String::from("a")]));
+        db.update("list", |_| (Update::Set(list, Expiry::INFINITE), ()));
+        run(&client, &["SET", "string", "value"]);
+
+        assert_eq!(run(&client, &["TYPE", "string"]), "+string\r\n");
+        assert_eq!(run(&client, &["TYPE", "list"]), "+list\r\n");
+        assert_eq!(run(&client, &["TYPE", "missing"]), "+none\r\n");
+
+        let wrongtype = "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+        assert_eq!(run(&client, &["GET", "list"]), wrongtype);
+        assert_eq!(run(&client, &["SET", "list", "value", "GET"]), wrongtype);
+        assert_eq!(run(&client, &["TYPE", "list"]), "+list\r\n");
+        assert!(!db.compare_and_swap("list", None, "value"));
+
+        // Commands that don't read the value work on any type.
+        assert_eq!(run(&client, &["EXPIRE", "list", "100"]), ":1\r\n");
+        assert_eq!(run(&client, &["SET", "list", "value"]), "+Ok\r\n");
+        assert_eq!(run(&client, &["TYPE", "list"]), "+string\r\n");
+
+        // A collection emptied in place is removed.
+        let list = RedisObject::List(VecDeque::from([String::from("a")]));
+        db.update("list", |_| (Update::Set(list, Expiry::INFINITE), ()));
+        db.update("list", |value| {
+            if let Some(RedisObject::List(list)) = value {
+                list.pop_front();
+            }
+            (Update::Modified, ())
+        });
+        assert!(!db.exists("list"));
+    }
+
+    #[test]
+    fn test_list_commands() {
+        let client = test_client();
+        assert_eq!(run(&client, &["RPUSH", "list", "a", "b", "c"]), ":3\r\n");
+        assert_eq!(run(&client, &["LPUSH", "list", "y", "z"]), ":5\r\n");
+        assert_eq!(
+            run(&client, &["LRANGE", "list", "0", "-1"]),
+            "*5\r\n$1\r\nz\r\n$1\r\ny\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n"
+        );
+        assert_eq!(run(&client, &["LRANGE", "list", "5", "10"]), "*0\r\n");
+        assert_eq!(run(&client, &["LINDEX", "list", "-1"]), "$1\r\nc\r\n");
+        assert_eq!(run(&client, &["LINDEX", "list", "5"]), "_\r\n");
+        assert_eq!(run(&client, &["LPUSHX", "missing", "a"]), ":0\r\n");
+        assert!(!client.server().db(0).unwrap().exists("missing"));
+
+        assert_eq!(run(&client, &["LSET", "list", "0", "x"]), "+OK\r\n");
+        assert_eq!(
+            run(&client, &["LSET", "list", "9", "x"]),
+            "-ERR index out of range\r\n"
+        );
+        assert_eq!(
+            run(&client, &["LSET", "missing", "0", "x"]),
+            "-ERR no such key\r\n"
+        );
+        assert_eq!(
+            run(&client, &["LINSERT", "list", "BEFORE", "a", "a"]),
+            ":6\r\n"
+        );
+        assert_eq!(
+            run(&client, &["LINSERT", "list", "AFTER", "?", "a"]),
+            ":-1\r\n"
+        );
+        // x y a a b c
+        assert_eq!(run(&client, &["LPOS", "list", "a"]), ":2\r\n");
+        assert_eq!(run(&client, &["LPOS", "list", "a", "RANK", "-1"]), ":3\r\n");
+        assert_eq!(
+            run(&client, &["LPOS", "list", "a", "COUNT", "0"]),
+            "*2\r\n:2\r\n:3\r\n"
+        );
+        assert_eq!(run(&client, &["LPOS", "list", "a", "MAXLEN", "2"]), "_\r\n");
+        assert_eq!(run(&client, &["LREM", "list", "-1", "a"]), ":1\r\n");
+        assert_eq!(run(&client, &["LTRIM", "list", "1", "-2"]), "+OK\r\n");
+        assert_eq!(
+            run(&client, &["LRANGE", "list", "0", "-1"]),
+            "*3\r\n$1\r\ny\r\n$1\r\na\r\n$1\r\nb\r\n"
+        );
+
+        assert_eq!(run(&client, &["LPOP", "list"]), "$1\r\ny\r\n");
+        assert_eq!(
+            run(&client, &["RPOP", "list", "5"]),
+            "*2\r\n$1\r\nb\r\n$1\r\na\r\n"
+        );
+        assert_eq!(run(&client, &["LLEN", "list"]), ":0\r\n");
+        assert_eq!(run(&client, &["LPOP", "list"]), "_\r\n");
+        assert!(!client.server().db(0).unwrap().exists("list"));
+
+        run(&client, &["SET", "string", "value"]);
+        assert!(run(&client, &["LPUSH", "string", "a"]).starts_with("-WRONGTYPE"));
+        assert!(run(&client, &["LRANGE", "string", "0", "1"]).starts_with("-WRONGTYPE"));
+        assert_eq!(
+            run(&client, &["LPOP", "string", "-1"]),
+            "-ERR value is out of range, must be positive\r\n"
+        );
+    }
+
+    #[test]
+    fn test_list_move_and_multi_pop() {
+        let client = test_client();
+        run(&client, &["RPUSH", "source", "a", "b", "c"]);
+        assert_eq!(
+            run(
+                &client,
+                &["LMOVE", "source", "destination", "LEFT", "RIGHT"]
+            ),
+            "$1\r\na\r\n"
+        );
+        assert_eq!(
+            run(&client, &["LMOVE", "source", "source", "RIGHT", "LEFT"]),
+            "$1\r\nc\r\n"
+        );
+        assert_eq!(
+            run(&client, &["LRANGE", "source", "0", "-1"]),
+            "*2\r\n$1\r\nc\r\n$1\r\nb\r\n"
+        );
+        assert_eq!(
+            run(&client, &["LMOVE", "missing", "source", "LEFT", "LEFT"]),
+            "_\r\n"
+        );
+
+        // Nothing is popped when the destination has the wrong type.
+        run(&client, &["SET", "string", "value"]);
+        assert!(
+            run(&client, &["LMOVE", "source", "string", "LEFT", "LEFT"]).starts_with("-WRONGTYPE")
+        );
+        assert_eq!(run(&client, &["LLEN", "source"]), ":2\r\n");
+
+        assert_eq!(
+            run(
+                &client,
+                &[
+                    "LMPOP",
+                    "3",
+                    "missing",
+                    "source",
+                    "destination",
+                    "LEFT",
+                    "COUNT",
+                    "5"
+                ]
+            ),
+            "*2\r\n$6\r\nsource\r\n*2\r\n$1\r\nc\r\n$1\r\nb\r\n"
+        );
+        assert_eq!(run(&client, &["LMPOP", "1", "source", "LEFT"]), "_\r\n");
+        assert_eq!(
+            run(&client, &["LMPOP", "0", "source", "LEFT"]),
+            "-ERR numkeys should be greater than 0\r\n"
+        );
+
+        let spec = COMMAND_SPECS
+            .iter()
+            .find(|spec| spec.name == "lmpop")
+            .unwrap();
+        let options: Vec<BulkString> = ["2", "a", "b", "LEFT"]
+            .iter()
+            .map(|arg| BulkString(arg.to_string()))
+            .collect();
+        assert_eq!(spec.keys(&options), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_numkeys_out_of_range() {
+        let cases: [(&str, [&str; 3], &[&str]); 5] = [
+            (
+                "lmpop",
+                ["9223372036854775807", "a", "LEFT"],
+                &["a", "LEFT"],
+            ),
+            ("lmpop", ["3000000000", "a", "LEFT"], &["a", "LEFT"]),
+            // Not a valid count at all, so no keys.
+            ("lmpop", ["18446744073709551615", "a", "LEFT"], &[]),
+            ("blmpop", ["0", "9223372036854775807", "a"], &["a"]),
+            ("zintercard", ["9223372036854775807", "a", "b"], &["a", "b"]),
+        ];
+        for (name, args, keys) in cases {
+            let spec = command_spec(name).unwrap();
+            let options: Vec<BulkString> =
+                args.iter().map(|arg| BulkString(arg.to_string())).collect();
+            assert_eq!(spec.keys(&options), keys);
+        }
+        let client = test_client();
+        let too_many = "-ERR Number of keys can't be greater than number of args\r\n";
+        for numkeys in ["3000000000", "18446744073709551615"] {
+            assert_eq!(run(&client, &["LMPOP", numkeys, "a", "LEFT"]), too_many);
+            assert_eq!(
+                run(&client, &["BLMPOP", "0", numkeys, "a", "LEFT"]),
+                too_many
+            );
+        }
+        assert_eq!(
+            run(&client, &["LMPOP", "2", "a", "b"]),
+            "-ERR syntax error\r\n"
+        );
+    }
+
+    #[test]
+    fn test_blocking_timeouts() {
+        let client = test_client();
+        run(&client, &["RPUSH", "l", "a", "b"]);
+        run(&client, &["ZADD", "z", "1", "a"]);
+        for timeout in ["1e300", "inf", "nan"] {
+            for args in [&["BLPOP", "l", timeout][..], &["BZPOPMIN", "z", timeout]] {
+                assert_eq!(run(&client, args), "-ERR timeout is out of range\r\n");
+            }
+        }
+        assert_eq!(
+            run(&client, &["BLPOP", "l", "-1"]),
+            "-ERR timeout is negative\r\n"
+        );
+        assert_eq!(
+            run(&client, &["BLPOP", "l", "soon"]),
+            "-ERR timeout is not a float or out of range\r\n"
+        );
+        // Large timeouts that fit are fine, the key is ready anyway.
+        assert_eq!(
+            run(&client, &["BLPOP", "l", "1e15"]),
+            "*2\r\n$1\r\nl\r\n$1\r\na\r\n"
+        );
+        assert_eq!(
+            run(&client, &["BZPOPMIN", "z", "0.5"]),
+            "*3\r\n$1\r\nz\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_hash_commands() {
+        let client = test_client();
+        assert_eq!(run(&client, &["HSET", "h", "a", "1", "b", "2"]), ":2\r\n");
+        assert_eq!(run(&client, &["HSET", "h", "a", "10", "c", "3"]), ":1\r\n");
+        assert_eq!(
+            run(&client, &["HSET", "h", "a"]),
+            "-ERR wrong number of arguments for 'hset' command\r\n"
+        );
+        assert_eq!(run(&client, &["HGET", "h", "a"]), "$2\r\n10\r\n");
+        assert_eq!(run(&client, &["HGET", "h", "x"]), "_\r\n");
+        assert_eq!(
+            run(&client, &["HMGET", "h", "b", "x"]),
+            "*2\r\n$1\r\n2\r\n_\r\n"
+        );
+        assert_eq!(run(&client, &["HSETNX", "h", "a", "0"]), ":0\r\n");
+        assert_eq!(run(&client, &["HSETNX", "h", "d", "4"]), ":1\r\n");
+        assert_eq!(run(&client, &["HLEN", "h"]), ":4\r\n");
+        assert_eq!(run(&client, &["HEXISTS", "h", "d"]), ":1\r\n");
+        assert_eq!(run(&client, &["HSTRLEN", "h", "a"]), ":2\r\n");
+        assert_eq!(run(&client, &["HINCRBY", "h", "a", "-15"]), ":-5\r\n");
+        assert_eq!(run(&client, &["HINCRBY", "h", "new", "2"]), ":2\r\n");
+        assert_eq!(
+            run(&client, &["HINCRBYFLOAT", "h", "b", "0.5"]),
+            "$3\r\n2.5\r\n"
+        );
+        assert_eq!(run(&client, &["HSET", "h", "text", "abc"]), ":1\r\n");
+        assert_eq!(
+            run(&client, &["HINCRBY", "h", "text", "1"]),
+            "-ERR hash value is not an integer\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HINCRBYFLOAT", "h", "text", "1"]),
+            "-ERR hash value is not a float\r\n"
+        );
+        run(&client, &["HSET", "h", "max", &i64::MAX.to_string()]);
+        assert_eq!(
+            run(&client, &["HINCRBY", "h", "max", "1"]),
+            "-ERR increment or decrement would overflow\r\n"
+        );
+        assert_eq!(run(&client, &["HDEL", "h", "text", "max", "x"]), ":2\r\n");
+
+        let db = client.server().db(0).unwrap();
+        let all = db.hgetall("h").unwrap();
+        assert_eq!(all.len(), 5);
+        assert_eq!(all["a"], "-5");
+        assert_eq!(run(&client, &["HKEYS", "h"]).matches("\r\n$").count(), 5);
+        assert!(run(&client, &["HGETALL", "h"]).starts_with("*10\r\n"));
+        assert!(run(&client, &["HRANDFIELD", "h"]).starts_with("$"));
+        assert!(run(&client, &["HRANDFIELD", "h", "10"]).starts_with("*5\r\n"));
+        assert!(run(&client, &["HRANDFIELD", "h", "-10"]).starts_with("*10\r\n"));
+        assert!(run(&client, &["HRANDFIELD", "h", "2", "WITHVALUES"]).starts_with("*4\r\n"));
+        assert_eq!(run(&client, &["HRANDFIELD", "missing"]), "_\r\n");
+        assert_eq!(run(&client, &["HRANDFIELD", "missing", "2"]), "*0\r\n");
+        let min = i64::MIN.to_string();
+        let half = (i64::MIN / 2).to_string();
+        for args in [
+            &["HRANDFIELD", "h", &min][..],
+            &["HRANDFIELD", "h", &half, "WITHVALUES"],
+            &["HRANDFIELD", "h", &min, "WITHVALUES"],
+        ] {
+            assert_eq!(run(&client, args), "-ERR value is out of range\r\n");
+        }
+        // The shard of the key is still usable.
+        assert_eq!(run(&client, &["HLEN", "h"]), ":5\r\n");
+
+        // The last field removed removes the key.
+        assert_eq!(
+            run(&client, &["HDEL", "h", "a", "b", "c", "d", "new"]),
+            ":5\r\n"
+        );
+        assert!(!db.exists("h"));
+        run(&client, &["SET", "string", "value"]);
+        assert!(run(&client, &["HGET", "string", "a"]).starts_with("-WRONGTYPE"));
+        assert!(run(&client, &["HSET", "string", "a", "1"]).starts_with("-WRONGTYPE"));
+    }
+
+    #[test]
+    fn test_hash_scan() {
+        let client = test_client();
+        let db = client.server().db(0).unwrap();
+        let pairs: Vec<(String, String)> = (0..50)
+            .map(|i| (format!("field:{}", i), i.to_string()))
+            .collect();
+        let pairs: Vec<(&str, &str)> = pairs
+            .iter()
+            .map(|(f, v)| (f.as_str(), v.as_str()))
+            .collect();
+        db.hset("h", &pairs).unwrap();
+
+        let mut cursor = String::from("0");
+        let mut fields = 0;
+        loop {
+            let reply = Value::deserialize(
+                run(&client, &["HSCAN", "h", &cursor, "COUNT", "7", "NOVALUES"]).as_bytes(),
+            )
+            .unwrap()
+            .value;
+            let Value::Array(Array(reply)) = reply else {
+                panic!("expected an array");
+            };
+            let [Value::BulkString(BulkString(next)), Value::Array(Array(elements))] = &reply[..]
+            else {
+                panic!("unexpected reply");
+            };
+            fields += elements.len();
+            cursor = next.clone();
+            if cursor == "0" {
+                break;
+            }
+        }
+        assert_eq!(fields, 50);
+        assert_eq!(
+            run(
+                &client,
+                &["HSCAN", "h", "0", "MATCH", "field:7", "COUNT", "100"]
+            ),
+            "*2\r\n$1\r\n0\r\n*2\r\n$7\r\nfield:7\r\n$1\r\n7\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HSCAN", "h", "x"]),
+            "-ERR invalid cursor\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HSCAN", "h", "0", "COUNT", "0"]),
+            "-ERR syntax error\r\n"
+        );
+    }
+
+    #[test]
+    fn test_hash_field_expiration() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let client = test_client_with(Config {
+            clock: clock.clone(),
+            ..Config::default()
+        });
+        run(&client, &["HSET", "h", "a", "1", "b", "2", "c", "3"]);
+        assert_eq!(
+            run(
+                &client,
+                &["HEXPIRE", "h", "10", "FIELDS", "2", "a", "missing"]
+            ),
+            "*2\r\n:1\r\n:-2\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HEXPIRE", "h", "20", "NX", "FIELDS", "1", "a"]),
+            "*1\r\n:0\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["HPEXPIRE", "h", "20000", "GT", "FIELDS", "1", "a"]
+            ),
+            "*1\r\n:1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HTTL", "h", "FIELDS", "3", "a", "b", "missing"]),
+            "*3\r\n:20\r\n:-1\r\n:-2\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HPEXPIRETIME", "h", "FIELDS", "1", "a"]),
+            "*1\r\n:1020000\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HPERSIST", "h", "FIELDS", "2", "a", "b"]),
+            "*2\r\n:1\r\n:-1\r\n"
+        );
+        // A deadline in the past deletes the field.
+        assert_eq!(
+            run(&client, &["HEXPIREAT", "h", "1", "FIELDS", "1", "c"]),
+            "*1\r\n:2\r\n"
+        );
+        assert_eq!(run(&client, &["HLEN", "h"]), ":2\r\n");
+
+        // Incrementing keeps the timeout of a field, overwriting clears it.
+        run(&client, &["HEXPIRE", "h", "10", "FIELDS", "2", "a", "b"]);
+        run(&client, &["HINCRBY", "h", "a", "1"]);
+        run(&client, &["HSET", "h", "b", "5"]);
+        assert_eq!(
+            run(&client, &["HTTL", "h", "FIELDS", "2", "a", "b"]),
+            "*2\r\n:10\r\n:-1\r\n"
+        );
+
+        // Expired fields are dropped on access, and the key with the last one.
+        clock.advance(Duration::from_secs(11));
+        assert_eq!(
+            run(&client, &["HGETALL", "h"]),
+            "*2\r\n$1\r\nb\r\n$1\r\n5\r\n"
+        );
+        run(&client, &["HPEXPIRE", "h", "100", "FIELDS", "1", "b"]);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(run(&client, &["TYPE", "h"]), "+none\r\n");
+
+        assert_eq!(
+            run(
+                &client,
+                &["HSETEX", "h", "FNX", "EX", "10", "FIELDS", "2", "x", "1", "y", "2"]
+            ),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HSETEX", "h", "FNX", "FIELDS", "1", "x", "3"]),
+            ":0\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["HSETEX", "h", "FXX", "KEEPTTL", "FIELDS", "1", "x", "3"]
+            ),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["HGETEX", "h", "PERSIST", "FIELDS", "2", "x", "missing"]
+            ),
+            "*2\r\n$1\r\n3\r\n_\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HTTL", "h", "FIELDS", "2", "x", "y"]),
+            "*2\r\n:-1\r\n:10\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HGETEX", "h", "EXAT", "1", "FIELDS", "1", "y"]),
+            "*1\r\n$1\r\n2\r\n"
+        );
+        assert_eq!(run(&client, &["HEXISTS", "h", "y"]), ":0\r\n");
+
+        assert_eq!(
+            run(&client, &["HGETEX", "h", "PX", "0", "FIELDS", "1", "x"]),
+            "-ERR invalid expire time in 'hgetex' command\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HTTL", "h", "FIELDS", "2", "x"]),
+            "-ERR The `numfields` parameter must match the number of arguments\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HTTL", "h", "FIELDS", "0", "x"]),
+            "-ERR Parameter `numFields` should be greater than 0\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HPERSIST", "h", "x", "FIELDS", "1"]),
+            "-ERR Mandatory argument FIELDS is missing or not at the right position\r\n"
+        );
+
+        // The active expiry cycle drops fields that are never accessed.
+        run(&client, &["HSET", "k", "f", "v", "g", "w"]);
+        run(&client, &["HPEXPIRE", "k", "100", "FIELDS", "2", "f", "g"]);
+        clock.advance(Duration::from_secs(1));
+        let storage = &client.server().storage;
+        storage.expire_cycle(Duration::from_secs(1));
+        assert_eq!(storage.size(0), 1);
+        assert_eq!(
+            storage.expire_stats.expired_fields.load(Ordering::Relaxed),
+            4
+        );
+    }
+
+    #[test]
+    fn test_set_commands() {
+        let client = test_client();
+        assert_eq!(run(&client, &["SADD", "s", "3", "1", "2", "1"]), ":3\r\n");
+        // Sets of integers are kept sorted.
+        assert_eq!(
+            run(&client, &["SMEMBERS", "s"]),
+            "*3\r\n$1\r\n1\r\n$1\r\n2\r\n$1\r\n3\r\n"
+        );
+        assert_eq!(run(&client, &["TYPE", "s"]), "+set\r\n");
+        assert_eq!(run(&client, &["SCARD", "s"]), ":3\r\n");
+        assert_eq!(run(&client, &["SISMEMBER", "s", "2"]), ":1\r\n");
+        assert_eq!(
+            run(&client, &["SMISMEMBER", "s", "2", "x"]),
+            "*2\r\n:1\r\n:0\r\n"
+        );
+        assert_eq!(run(&client, &["SREM", "s", "2", "x"]), ":1\r\n");
+        assert_eq!(run(&client, &["SMOVE", "s", "t", "3"]), ":1\r\n");
+        assert_eq!(run(&client, &["SMOVE", "s", "t", "3"]), ":0\r\n");
+        assert_eq!(run(&client, &["SMEMBERS", "t"]), "*1\r\n$1\r\n3\r\n");
+        assert_eq!(run(&client, &["SPOP", "s"]), "$1\r\n1\r\n");
+        assert_eq!(run(&client, &["TYPE", "s"]), "+none\r\n");
+        assert_eq!(run(&client, &["SPOP", "s"]), "_\r\n");
+        assert_eq!(run(&client, &["SPOP", "s", "2"]), "*0\r\n");
+        assert_eq!(
+            run(&client, &["SRANDMEMBER", "t", "-2"]),
+            "*2\r\n$1\r\n3\r\n$1\r\n3\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SRANDMEMBER", "t", "5"]),
+            "*1\r\n$1\r\n3\r\n"
+        );
+        for count in [i64::MIN, i64::MIN / 2, i64::MAX / 2 + 1] {
+            assert_eq!(
+                run(&client, &["SRANDMEMBER", "t", &count.to_string()]),
+                "-ERR value is out of range\r\n"
+            );
         }
+        // The shard of the key is still usable.
+        assert_eq!(run(&client, &["SCARD", "t"]), ":1\r\n");
+
+        run(&client, &["SADD", "a", "1", "2", "3", "4"]);
+        run(&client, &["SADD", "b", "2", "3", "5"]);
+        run(&client, &["SADD", "c", "3"]);
+        assert_eq!(
+            run(&client, &["SINTER", "a", "b", "c"]),
+            "*1\r\n$1\r\n3\r\n"
+        );
+        assert_eq!(run(&client, &["SINTER", "a", "missing"]), "*0\r\n");
+        assert_eq!(
+            run(&client, &["SDIFFSTORE", "d", "a", "b", "missing"]),
+            ":2\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SMEMBERS", "d"]),
+            "*2\r\n$1\r\n1\r\n$1\r\n4\r\n"
+        );
+        assert_eq!(run(&client, &["SUNIONSTORE", "d", "a", "b"]), ":5\r\n");
+        assert_eq!(
+            run(&client, &["SUNION", "c", "missing"]),
+            "*1\r\n$1\r\n3\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SINTERSTORE", "d", "a", "missing"]),
+            ":0\r\n"
+        );
+        assert_eq!(run(&client, &["TYPE", "d"]), "+none\r\n");
+        assert_eq!(run(&client, &["SINTERCARD", "2", "a", "b"]), ":2\r\n");
+        assert_eq!(
+            run(&client, &["SINTERCARD", "2", "a", "b", "LIMIT", "1"]),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SINTERCARD", "3", "a", "b"]),
+            "-ERR Number of keys can't be greater than number of args\r\n"
+        );
+
+        run(&client, &["SET", "string", "value"]);
+        assert_eq!(
+            run(&client, &["SINTER", "a", "string"]),
+            "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SMOVE", "a", "string", "1"]),
+            "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+
+        run(&client, &["SADD", "big", "x", "y", "z"]);
+        let reply =
+            Value::deserialize(run(&client, &["SSCAN", "big", "0", "MATCH", "[xy]"]).as_bytes())
+                .unwrap()
+                .value;
+        let Value::Array(Array(reply)) = reply else {
+            panic!("expected an array");
+        };
+        let [Value::BulkString(BulkString(cursor)), Value::Array(Array(members))] = &reply[..]
+        else {
+            panic!("unexpected reply");
+        };
+        assert_eq!(cursor, "0");
+        assert_eq!(members.len(), 2);
+    }
+
+    #[test]
+    fn test_sorted_set_commands() {
+        let client = test_client();
+        assert_eq!(
+            run(&client, &["ZADD", "z", "1", "a", "2", "b", "3", "c"]),
+            ":3\r\n"
+        );
+        assert_eq!(run(&client, &["TYPE", "z"]), "+zset\r\n");
+        assert_eq!(
+            run(&client, &["ZADD", "z", "NX", "5", "a", "4", "d"]),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZADD", "z", "XX", "CH", "5", "a", "9", "e"]),
+            ":1\r\n"
+        );
+        assert_eq!(run(&client, &["ZADD", "z", "GT", "CH", "1", "a"]), ":0\r\n");
+        assert_eq!(
+            run(&client, &["ZADD", "z", "LT", "INCR", "1", "a"]),
+            "_\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZADD", "z", "INCR", "0.5", "a"]),
+            "$3\r\n5.5\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZINCRBY", "z", "-inf", "b"]),
+            "$4\r\n-inf\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZINCRBY", "z", "+inf", "b"]),
+            "-ERR resulting score is not a number (NaN)\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZADD", "z", "NX", "GT", "1", "a"]),
+            "-ERR GT, LT, and/or NX options at the same time are not compatible\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZADD", "z", "nan", "a"]),
+            "-ERR value is not a valid float\r\n"
+        );
+        // b -inf, c 3, d 4, a 5.5
+        assert_eq!(run(&client, &["ZCARD", "z"]), ":4\r\n");
+        assert_eq!(run(&client, &["ZSCORE", "z", "c"]), "$1\r\n3\r\n");
+        assert_eq!(
+            run(&client, &["ZMSCORE", "z", "d", "missing"]),
+            "*2\r\n$1\r\n4\r\n_\r\n"
+        );
+        assert_eq!(run(&client, &["ZCOUNT", "z", "(3", "+inf"]), ":2\r\n");
+        assert_eq!(run(&client, &["ZCOUNT", "z", "-inf", "3"]), ":2\r\n");
+        assert_eq!(run(&client, &["ZRANK", "z", "d"]), ":2\r\n");
+        assert_eq!(
+            run(&client, &["ZREVRANK", "z", "d", "WITHSCORE"]),
+            "*2\r\n:1\r\n$1\r\n4\r\n"
+        );
+        assert_eq!(run(&client, &["ZRANK", "z", "missing"]), "_\r\n");
+        assert_eq!(run(&client, &["ZREM", "z", "b", "missing"]), ":1\r\n");
+
+        assert_eq!(
+            run(&client, &["ZPOPMIN", "z"]),
+            "*2\r\n$1\r\nc\r\n$1\r\n3\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZPOPMAX", "z", "5"]),
+            "*4\r\n$1\r\na\r\n$3\r\n5.5\r\n$1\r\nd\r\n$1\r\n4\r\n"
+        );
+        assert_eq!(run(&client, &["TYPE", "z"]), "+none\r\n");
+        assert_eq!(run(&client, &["ZPOPMIN", "z"]), "*0\r\n");
+    }
+
+    #[test]
+    fn test_zrange() {
+        let client = test_client();
+        run(
+            &client,
+            &[
+                "ZADD", "z", "1", "a", "2", "b", "3", "c", "4", "d", "5", "e",
+            ],
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "z", "1", "-3"]),
+            "*2\r\n$1\r\nb\r\n$1\r\nc\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "z", "0", "1", "REV", "WITHSCORES"]),
+            "*4\r\n$1\r\ne\r\n$1\r\n5\r\n$1\r\nd\r\n$1\r\n4\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "z", "(1", "3", "BYSCORE"]),
+            "*2\r\n$1\r\nb\r\n$1\r\nc\r\n"
+        );
+        // With REV the bounds are max then min.
+        assert_eq!(
+            run(
+                &client,
+                &["ZRANGE", "z", "+inf", "2", "BYSCORE", "REV", "LIMIT", "1", "2"]
+            ),
+            "*2\r\n$1\r\nd\r\n$1\r\nc\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["ZRANGE", "z", "-inf", "+inf", "BYSCORE", "LIMIT", "3", "-1"]
+            ),
+            "*2\r\n$1\r\nd\r\n$1\r\ne\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "z", "0", "-1", "LIMIT", "0", "1"]),
+            "-ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX\r\n"
+        );
+
+        run(
+            &client,
+            &["ZADD", "lex", "0", "a", "0", "b", "0", "c", "0", "d"],
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "lex", "(a", "[c", "BYLEX"]),
+            "*2\r\n$1\r\nb\r\n$1\r\nc\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "lex", "+", "(b", "BYLEX", "REV"]),
+            "*2\r\n$1\r\nd\r\n$1\r\nc\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "lex", "+", "-", "BYLEX"]),
+            "*0\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "lex", "a", "c", "BYLEX"]),
+            "-ERR min or max not valid string range item\r\n"
+        );
+
+        assert_eq!(
+            run(&client, &["ZRANGESTORE", "dst", "z", "2", "4", "BYSCORE"]),
+            ":3\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "dst", "0", "-1"]),
+            "*3\r\n$1\r\nb\r\n$1\r\nc\r\n$1\r\nd\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZREMRANGEBYRANK", "dst", "0", "0"]),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZREMRANGEBYSCORE", "dst", "(3", "4"]),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZREMRANGEBYLEX", "lex", "-", "[b"]),
+            ":2\r\n"
+        );
+        assert_eq!(run(&client, &["ZCARD", "lex"]), ":2\r\n");
+        assert_eq!(
+            run(&client, &["ZRANGESTORE", "dst", "z", "10", "20"]),
+            ":0\r\n"
+        );
+        assert_eq!(run(&client, &["TYPE", "dst"]), "+none\r\n");
+
+        client.authenticate("default");
+        run(&client, &["HELLO", "3"]);
+        assert_eq!(
+            run(&client, &["ZRANGE", "z", "0", "0", "WITHSCORES"]),
+            "*1\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZSCAN", "z", "0", "MATCH", "a"]),
+            "*2\r\n$1\r\n0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_sorted_set_combinations() {
+        let client = test_client();
+        // Flat RESP2 reply of members with their scores.
+        let scored = |pairs: &[(&str, &str)]| {
+            let mut reply = format!("*{}\r\n", pairs.len() * 2);
+            for (member, score) in pairs {
+                for value in [member, score] {
+                    reply += &format!("${}\r\n{}\r\n", value.len(), value);
+                }
+            }
+            reply
+        };
+        run(&client, &["ZADD", "a", "1", "x", "2", "y", "3", "z"]);
+        run(&client, &["ZADD", "b", "10", "y", "20", "z", "5", "w"]);
+        run(&client, &["SADD", "s", "x", "w"]);
+
+        assert_eq!(
+            run(&client, &["ZUNION", "2", "a", "b", "WITHSCORES"]),
+            scored(&[("x", "1"), ("w", "5"), ("y", "12"), ("z", "23")])
+        );
+        // Plain sets count as members with a score of 1.
+        assert_eq!(
+            run(&client, &["ZUNION", "3", "a", "b", "s", "WITHSCORES"]),
+            scored(&[("x", "2"), ("w", "6"), ("y", "12"), ("z", "23")])
+        );
+        assert_eq!(
+            run(
+                &client,
+                &[
+                    "ZINTER",
+                    "2",
+                    "a",
+                    "b",
+                    "WEIGHTS",
+                    "2",
+                    "3",
+                    "AGGREGATE",
+                    "MAX",
+                    "WITHSCORES"
+                ]
+            ),
+            scored(&[("y", "30"), ("z", "60")])
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["ZINTER", "2", "a", "b", "AGGREGATE", "min", "WITHSCORES"]
+            ),
+            scored(&[("y", "2"), ("z", "3")])
+        );
+        assert_eq!(run(&client, &["ZINTER", "2", "a", "missing"]), "*0\r\n");
+        assert_eq!(run(&client, &["ZDIFF", "2", "a", "b"]), "*1\r\n$1\r\nx\r\n");
+        assert_eq!(
+            run(&client, &["ZDIFF", "2", "a", "s", "WITHSCORES"]),
+            scored(&[("y", "2"), ("z", "3")])
+        );
+
+        assert_eq!(
+            run(&client, &["ZINTERSTORE", "out", "2", "a", "s"]),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "out", "0", "-1", "WITHSCORES"]),
+            scored(&[("x", "2")])
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["ZUNIONSTORE", "out", "2", "a", "s", "WEIGHTS", "1", "-1"]
+            ),
+            ":4\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZRANGE", "out", "0", "-1", "WITHSCORES"]),
+            scored(&[("w", "-1"), ("x", "0"), ("y", "2"), ("z", "3")])
+        );
+        assert_eq!(
+            run(&client, &["ZDIFFSTORE", "out", "2", "a", "a"]),
+            ":0\r\n"
+        );
+        assert_eq!(run(&client, &["TYPE", "out"]), "+none\r\n");
+
+        assert_eq!(run(&client, &["ZINTERCARD", "2", "a", "b"]), ":2\r\n");
+        assert_eq!(
+            run(&client, &["ZINTERCARD", "2", "a", "b", "LIMIT", "1"]),
+            ":1\r\n"
+        );
+        assert_eq!(run(&client, &["ZINTERCARD", "2", "a", "s"]), ":1\r\n");
+
+        // inf * 0 and inf + -inf are 0 rather than NaN.
+        run(&client, &["ZADD", "inf", "inf", "m"]);
+        run(&client, &["ZADD", "-inf", "-inf", "m"]);
+        assert_eq!(
+            run(
+                &client,
+                &["ZUNION", "1", "inf", "WEIGHTS", "0", "WITHSCORES"]
+            ),
+            scored(&[("m", "0")])
+        );
+        assert_eq!(
+            run(&client, &["ZUNION", "2", "inf", "-inf", "WITHSCORES"]),
+            scored(&[("m", "0")])
+        );
+
+        assert_eq!(
+            run(&client, &["ZUNION", "0", "a"]),
+            "-ERR at least 1 input key is needed for 'zunion' command\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZUNION", "2", "a", "b", "WEIGHTS", "1"]),
+            "-ERR syntax error\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZINTER", "2", "a", "b", "WEIGHTS", "1", "x"]),
+            "-ERR weight value is not a float\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZDIFF", "2", "a", "b", "WEIGHTS", "1", "1"]),
+            "-ERR syntax error\r\n"
+        );
+        assert_eq!(
+            run(&client, &["ZUNIONSTORE", "out", "1", "a", "WITHSCORES"]),
+            "-ERR syntax error\r\n"
+        );
+        run(&client, &["SET", "string", "v"]);
+        assert!(run(&client, &["ZUNION", "2", "a", "string"]).starts_with("-WRONGTYPE"));
+
+        let spec = COMMAND_SPECS
+            .iter()
+            .find(|spec| spec.name == "zunionstore")
+            .unwrap();
+        let options: Vec<BulkString> = ["out", "2", "a", "b", "WEIGHTS", "1", "2"]
+            .iter()
+            .map(|arg| BulkString(arg.to_string()))
+            .collect();
+        assert_eq!(spec.keys(&options), vec!["out", "a", "b"]);
+
+        for name in ["zunionstore", "zinterstore", "zdiffstore"] {
+            let spec = command_spec(name).unwrap();
+            let options: Vec<BulkString> = ["d", "9223372036854775807", "a"]
+                .iter()
+                .map(|arg| BulkString(arg.to_string()))
+                .collect();
+            assert_eq!(spec.keys(&options), vec!["d", "a"]);
+        }
+        assert_eq!(
+            run(&client, &["ZUNIONSTORE", "d", "9223372036854775807", "a"]),
+            "-ERR syntax error\r\n"
+        );
+    }
+
+    #[test]
+    fn test_stream_commands() {
+        let client = test_client();
+        // RESP2 reply of entries, each with a single field.
+        let entries = |entries: &[(&str, &str, &str)]| {
+            let mut reply = format!("*{}\r\n", entries.len());
+            for (id, field, value) in entries {
+                reply += &format!("*2\r\n${}\r\n{}\r\n*2\r\n", id.len(), id);
+                for value in [field, value] {
+                    reply += &format!("${}\r\n{}\r\n", value.len(), value);
+                }
+            }
+            reply
+        };
+        assert_eq!(
+            run(&client, &["XADD", "s", "1-1", "a", "1"]),
+            "$3\r\n1-1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XADD", "s", "1-*", "b", "2"]),
+            "$3\r\n1-2\r\n"
+        );
+        assert_eq!(run(&client, &["XADD", "s", "2", "c", "3"]), "$3\r\n2-0\r\n");
+        assert_eq!(run(&client, &["TYPE", "s"]), "+stream\r\n");
+        assert_eq!(
+            run(&client, &["XADD", "s", "1-5", "d", "4"]),
+            "-ERR The ID specified in XADD is equal or smaller than the target stream top item\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XADD", "other", "0-0", "d", "4"]),
+            "-ERR The ID specified in XADD must be greater than 0-0\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XADD", "s", "3-0", "d"]),
+            "-ERR wrong number of arguments for 'xadd' command\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XADD", "other", "NOMKSTREAM", "*", "d", "4"]),
+            "_\r\n"
+        );
+        assert_eq!(run(&client, &["XLEN", "s"]), ":3\r\n");
+        assert_eq!(run(&client, &["XLEN", "other"]), ":0\r\n");
+
+        assert_eq!(
+            run(&client, &["XRANGE", "s", "-", "+"]),
+            entries(&[("1-1", "a", "1"), ("1-2", "b", "2"), ("2-0", "c", "3")])
+        );
+        // An end without a sequence number includes all of them.
+        assert_eq!(
+            run(&client, &["XRANGE", "s", "(1-1", "2"]),
+            entries(&[("1-2", "b", "2"), ("2-0", "c", "3")])
+        );
+        assert_eq!(
+            run(&client, &["XREVRANGE", "s", "+", "-", "COUNT", "2"]),
+            entries(&[("2-0", "c", "3"), ("1-2", "b", "2")])
+        );
+        assert_eq!(
+            run(&client, &["XRANGE", "s", "-", "+", "COUNT", "0"]),
+            "*0\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XRANGE", "s", "x", "+"]),
+            "-ERR Invalid stream ID specified as stream command argument\r\n"
+        );
+
+        assert_eq!(run(&client, &["XDEL", "s", "1-2", "9-9"]), ":1\r\n");
+        assert_eq!(run(&client, &["XTRIM", "s", "MAXLEN", "1"]), ":1\r\n");
+        assert_eq!(
+            run(&client, &["XRANGE", "s", "-", "+"]),
+            entries(&[("2-0", "c", "3")])
+        );
+        assert_eq!(
+            run(&client, &["XADD", "s", "MAXLEN", "=", "1", "3-0", "d", "4"]),
+            "$3\r\n3-0\r\n"
+        );
+        assert_eq!(run(&client, &["XLEN", "s"]), ":1\r\n");
+        assert_eq!(
+            run(&client, &["XTRIM", "s", "MAXLEN", "1", "LIMIT", "10"]),
+            "-ERR syntax error, LIMIT cannot be used without the special ~ option\r\n"
+        );
+        // A stream left without entries still exists.
+        assert_eq!(run(&client, &["XTRIM", "s", "MINID", "4"]), ":1\r\n");
+        assert_eq!(run(&client, &["TYPE", "s"]), "+stream\r\n");
+        assert!(run(&client, &["XADD", "s", "*", "e", "5"]).starts_with('$'));
+        assert_eq!(run(&client, &["XLEN", "s"]), ":1\r\n");
+
+        run(&client, &["XADD", "r", "1-0", "a", "1"]);
+        run(&client, &["XADD", "r", "2-0", "b", "2"]);
+        assert_eq!(
+            run(
+                &client,
+                &["XREAD", "COUNT", "1", "STREAMS", "r", "missing", "0", "0"]
+            ),
+            format!("*1\r\n*2\r\n$1\r\nr\r\n{}", entries(&[("1-0", "a", "1")]))
+        );
+        assert_eq!(
+            run(&client, &["XREAD", "STREAMS", "r", "1"]),
+            format!("*1\r\n*2\r\n$1\r\nr\r\n{}", entries(&[("2-0", "b", "2")]))
+        );
+        assert_eq!(run(&client, &["XREAD", "STREAMS", "r", "$"]), "_\r\n");
+        assert_eq!(
+            run(&client, &["XREAD", "STREAMS", "r", "s", "0"]),
+            "-ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XREAD", "BLOCK", "-1", "STREAMS", "r", "0"]),
+            "-ERR timeout is negative\r\n"
+        );
+        run(&client, &["SET", "string", "v"]);
+        assert!(run(&client, &["XADD", "string", "*", "a", "1"]).starts_with("-WRONGTYPE"));
+
+        let spec = COMMAND_SPECS
+            .iter()
+            .find(|spec| spec.name == "xread")
+            .unwrap();
+        let options: Vec<BulkString> = ["COUNT", "1", "STREAMS", "a", "b", "0", "$"]
+            .iter()
+            .map(|arg| BulkString(arg.to_string()))
+            .collect();
+        assert_eq!(spec.keys(&options), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_stream_consumer_groups() {
+        let client = test_client();
+        for id in ["1-0", "2-0", "3-0"] {
+            run(&client, &["XADD", "s", id, "f", "v"]);
+        }
+        let entry = |id: &str| format!("*2\r\n$3\r\n{}\r\n*2\r\n$1\r\nf\r\n$1\r\nv\r\n", id);
+        // RESP2 reply of XREADGROUP for stream `s`.
+        let read = |ids: &[&str]| {
+            let entries: String = ids.iter().map(|id| entry(id)).collect();
+            format!("*1\r\n*2\r\n$1\r\ns\r\n*{}\r\n{}", ids.len(), entries)
+        };
+        assert_eq!(
+            run(&client, &["XGROUP", "CREATE", "missing", "g", "$"]),
+            "-ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XGROUP", "CREATE", "s", "g", "0"]),
+            "+OK\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XGROUP", "CREATE", "s", "g", "$"]),
+            "-BUSYGROUP Consumer Group name already exists\r\n"
+        );
+
+        assert_eq!(
+            run(
+                &client,
+                &[
+                    "XREADGROUP",
+                    "GROUP",
+                    "g",
+                    "alice",
+                    "COUNT",
+                    "2",
+                    "STREAMS",
+                    "s",
+                    ">"
+                ]
+            ),
+            read(&["1-0", "2-0"])
+        );
+        // Any other ID than `>` reads the pending entries of the consumer.
+        assert_eq!(
+            run(
+                &client,
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", "1-0"]
+            ),
+            read(&["2-0"])
+        );
+        assert_eq!(
+            run(&client, &["XPENDING", "s", "g"]),
+            "*4\r\n:2\r\n$3\r\n1-0\r\n$3\r\n2-0\r\n*1\r\n*2\r\n$5\r\nalice\r\n$1\r\n2\r\n"
+        );
+        assert_eq!(run(&client, &["XACK", "s", "g", "1-0", "9-0"]), ":1\r\n");
+        assert_eq!(run(&client, &["XACK", "s", "nope", "2-0"]), ":0\r\n");
+
+        assert_eq!(
+            run(
+                &client,
+                &[
+                    "XCLAIM",
+                    "s",
+                    "g",
+                    "bob",
+                    "0",
+                    "2-0",
+                    "RETRYCOUNT",
+                    "5",
+                    "JUSTID"
+                ]
+            ),
+            "*1\r\n$3\r\n2-0\r\n"
+        );
+        let pending = run(
+            &client,
+            &["XPENDING", "s", "g", "IDLE", "0", "-", "+", "10", "bob"],
+        );
+        assert!(pending.starts_with("*1\r\n*4\r\n$3\r\n2-0\r\n$3\r\nbob\r\n:"));
+        assert!(pending.ends_with(":5\r\n"));
+        assert_eq!(
+            run(&client, &["XPENDING", "s", "g", "-", "+", "10", "alice"]),
+            "*0\r\n"
+        );
+
+        // A deleted entry stays pending until claimed or acknowledged.
+        run(&client, &["XDEL", "s", "2-0"]);
+        assert_eq!(
+            run(
+                &client,
+                &["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", "0"]
+            ),
+            "*1\r\n*2\r\n$1\r\ns\r\n*1\r\n*2\r\n$3\r\n2-0\r\n_\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XAUTOCLAIM", "s", "g", "carol", "0", "-"]),
+            "*3\r\n$3\r\n0-0\r\n*0\r\n*1\r\n$3\r\n2-0\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XPENDING", "s", "g"]),
+            "*4\r\n:0\r\n_\r\n_\r\n_\r\n"
+        );
+
+        assert_eq!(
+            run(
+                &client,
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"]
+            ),
+            read(&["3-0"])
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"]
+            ),
+            "_\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["XREADGROUP", "GROUP", "nope", "alice", "STREAMS", "s", ">"]
+            ),
+            "-NOGROUP No such key 's' or consumer group 'nope' in XREADGROUP with GROUP option\r\n"
+        );
+
+        let groups = run(&client, &["XINFO", "GROUPS", "s"]);
+        assert!(groups.contains("$17\r\nlast-delivered-id\r\n$3\r\n3-0\r\n"));
+        assert!(
+            run(&client, &["XINFO", "STREAM", "s"]).starts_with("*20\r\n$6\r\nlength\r\n:2\r\n")
+        );
+        assert_eq!(
+            run(&client, &["XINFO", "CONSUMERS", "s", "nope"]),
+            "-NOGROUP No such consumer group 'nope' for key name 's'\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XINFO", "CONSUMERS", "missing", "g"]),
+            "-ERR no such key\r\n"
+        );
+
+        assert_eq!(
+            run(&client, &["XGROUP", "CREATECONSUMER", "s", "g", "dave"]),
+            ":1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XGROUP", "CREATECONSUMER", "s", "g", "dave"]),
+            ":0\r\n"
+        );
+        assert_eq!(
+            run(&client, &["XGROUP", "DELCONSUMER", "s", "g", "alice"]),
+            ":1\r\n"
+        );
+        assert_eq!(run(&client, &["XGROUP", "SETID", "s", "g", "0"]), "+OK\r\n");
+        assert_eq!(
+            run(
+                &client,
+                &["XREADGROUP", "GROUP", "g", "dave", "STREAMS", "s", ">"]
+            ),
+            read(&["1-0", "3-0"])
+        );
+        assert_eq!(run(&client, &["XGROUP", "DESTROY", "s", "g"]), ":1\r\n");
+        assert_eq!(run(&client, &["XGROUP", "DESTROY", "s", "g"]), ":0\r\n");
+    }
+
+    #[test]
+    fn test_hyperloglog_commands() {
+        let client = test_client();
+        assert_eq!(run(&client, &["PFADD", "a", "x", "y", "z"]), ":1\r\n");
+        assert_eq!(run(&client, &["PFADD", "a", "x"]), ":0\r\n");
+        assert_eq!(run(&client, &["PFADD", "empty"]), ":1\r\n");
+        assert_eq!(run(&client, &["PFADD", "empty"]), ":0\r\n");
+        assert_eq!(run(&client, &["TYPE", "a"]), "+string\r\n");
+        assert_eq!(run(&client, &["PFCOUNT", "a"]), ":3\r\n");
+        assert_eq!(run(&client, &["PFCOUNT", "missing"]), ":0\r\n");
+
+        run(&client, &["PFADD", "b", "z", "w"]);
+        assert_eq!(run(&client, &["PFCOUNT", "a", "b", "missing"]), ":4\r\n");
+        assert_eq!(run(&client, &["PFMERGE", "c", "a", "b"]), "+OK\r\n");
+        assert_eq!(run(&client, &["PFCOUNT", "c"]), ":4\r\n");
+        assert_eq!(run(&client, &["PFMERGE", "a", "b"]), "+OK\r\n");
+        assert_eq!(run(&client, &["PFCOUNT", "a"]), ":4\r\n");
+
+        run(&client, &["SET", "s", "not an hll"]);
+        assert_eq!(
+            run(&client, &["PFADD", "s", "x"]),
+            "-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n"
+        );
+        assert_eq!(
+            run(&client, &["PFMERGE", "c", "s"]),
+            "-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n"
+        );
+        run(&client, &["RPUSH", "l", "x"]);
+        assert_eq!(
+            run(&client, &["PFCOUNT", "l"]),
+            "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+    }
+
+    #[test]
+    fn test_binary_strings_are_replied_verbatim() {
+        let client = test_client();
+        run(&client, &["PFADD", "hll", "a", "b", "c"]);
+        let stored = client
+            .server()
+            .storage
+            .read(client.db(), "hll", |value| match value {
+                Some(RedisObject::String(hll)) => hll.clone(),
+                _ => panic!("expected a string"),
+            });
+        // Sparse encoding, with the cached cardinality invalidated.
+        assert_eq!(stored[..5], *b"HYLL\x01");
+        assert!(stored[15] & 0x80 != 0);
+        let mut expected = format!("${}\r\n", stored.len()).into_bytes();
+        expected.extend_from_slice(&stored);
+        expected.extend_from_slice(b"\r\n");
+        assert_eq!(run_bytes(&client, &["GET", "hll"]), expected);
+
+        run(&client, &["BITFIELD", "b", "SET", "u8", "0", "255"]);
+        assert_eq!(run_bytes(&client, &["GET", "b"]), b"$1\r\n\xff\r\n");
+    }
+
+    #[test]
+    fn test_bitmap_commands() {
+        let client = test_client();
+        assert_eq!(run(&client, &["SETBIT", "k", "7", "1"]), ":0\r\n");
+        assert_eq!(run(&client, &["SETBIT", "k", "7", "0"]), ":1\r\n");
+        assert_eq!(run(&client, &["GETBIT", "k", "7"]), ":0\r\n");
+        assert_eq!(run(&client, &["GETBIT", "missing", "100"]), ":0\r\n");
+        assert_eq!(
+            run(&client, &["SETBIT", "k", "4294967296", "1"]),
+            "-ERR bit offset is not an integer or out of range\r\n"
+        );
+        assert_eq!(
+            run(&client, &["SETBIT", "k", "0", "2"]),
+            "-ERR bit is not an integer or out of range\r\n"
+        );
+
+        run(&client, &["SET", "s", "foobar"]);
+        assert_eq!(run(&client, &["BITCOUNT", "s"]), ":26\r\n");
+        assert_eq!(run(&client, &["BITCOUNT", "s", "1", "1"]), ":6\r\n");
+        assert_eq!(
+            run(&client, &["BITCOUNT", "s", "-2", "-1", "BYTE"]),
+            ":7\r\n"
+        );
+        assert_eq!(
+            run(&client, &["BITCOUNT", "s", "5", "30", "BIT"]),
+            ":17\r\n"
+        );
+        assert_eq!(run(&client, &["BITCOUNT", "s", "-1", "-100"]), ":0\r\n");
+        assert_eq!(
+            run(&client, &["BITCOUNT", "s", "1"]),
+            "-ERR syntax error\r\n"
+        );
+
+        // The bytes 0xff 0xf0.
+        run(&client, &["BITFIELD", "b", "SET", "u12", "0", "4095"]);
+        assert_eq!(run(&client, &["BITPOS", "b", "0"]), ":12\r\n");
+        assert_eq!(
+            run(&client, &["BITPOS", "b", "1", "7", "15", "BIT"]),
+            ":7\r\n"
+        );
+        assert_eq!(run(&client, &["BITPOS", "b", "0", "0", "0"]), ":-1\r\n");
+        assert_eq!(run(&client, &["BITPOS", "b", "1", "2"]), ":-1\r\n");
+        run(&client, &["SETBIT", "f", "7", "1"]);
+        run(&client, &["BITOP", "NOT", "f", "f"]);
+        run(&client, &["BITOP", "NOT", "f", "f"]);
+        assert_eq!(run(&client, &["BITPOS", "f", "1"]), ":7\r\n");
+        assert_eq!(run(&client, &["BITPOS", "missing", "0"]), ":0\r\n");
+        assert_eq!(run(&client, &["BITPOS", "missing", "1"]), ":-1\r\n");
+        assert_eq!(
+            run(&client, &["BITPOS", "b", "2"]),
+            "-ERR The bit argument must be 1 or 0.\r\n"
+        );
+
+        run(&client, &["SET", "a", "abcdef"]);
+        assert_eq!(run(&client, &["BITOP", "AND", "d", "s", "a"]), ":6\r\n");
+        assert_eq!(run(&client, &["GET", "d"]), "$6\r\n`bc`ab\r\n");
+        assert_eq!(run(&client, &["BITOP", "OR", "d", "missing"]), ":0\r\n");
+        assert_eq!(run(&client, &["TYPE", "d"]), "+none\r\n");
+        assert_eq!(
+            run(&client, &["BITOP", "NOT", "d", "s", "a"]),
+            "-ERR BITOP NOT must be called with a single source key.\r\n"
+        );
+
+        assert_eq!(
+            run(
+                &client,
+                &["BITFIELD", "bf", "SET", "i8", "#1", "-100", "GET", "u8", "8"]
+            ),
+            "*2\r\n:0\r\n:156\r\n"
+        );
+        assert_eq!(
+            run(&client, &["BITFIELD", "bf", "INCRBY", "i8", "8", "-100"]),
+            "*1\r\n:56\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &[
+                    "BITFIELD", "bf", "OVERFLOW", "SAT", "INCRBY", "i8", "8", "100", "OVERFLOW",
+                    "FAIL", "INCRBY", "i8", "8", "1"
+                ]
+            ),
+            "*2\r\n:127\r\n_\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["BITFIELD", "bf", "OVERFLOW", "SAT", "SET", "u4", "0", "-1"]
+            ),
+            "*1\r\n:0\r\n"
+        );
+        assert_eq!(
+            run(
+                &client,
+                &["BITFIELD_RO", "bf", "GET", "u4", "0", "GET", "i8", "8"]
+            ),
+            "*2\r\n:15\r\n:127\r\n"
+        );
+        assert_eq!(
+            run(&client, &["BITFIELD_RO", "bf", "INCRBY", "i8", "8", "1"]),
+            "-ERR BITFIELD_RO only supports the GET subcommand\r\n"
+        );
+        assert_eq!(
+            run(&client, &["BITFIELD", "bf", "GET", "u64", "0"]),
+            "-ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.\r\n"
+        );
+        assert_eq!(
+            run(&client, &["BITFIELD", "missing", "GET", "u8", "0"]),
+            "*1\r\n:0\r\n"
+        );
+        assert_eq!(run(&client, &["TYPE", "missing"]), "+none\r\n");
+
+        run(&client, &["RPUSH", "l", "x"]);
+        assert_eq!(
+            run(&client, &["SETBIT", "l", "0", "1"]),
+            "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+    }
+
+    /// Registers another client on the server of `client`.
+    fn connect(client: &Client, port: u16) -> Arc<Client> {
+        let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+        let server = client.server().clone();
+        server.clients.register(server.clone(), addr, addr)
+    }
+
+    /// Whether the connection task of `client` was asked to close.
+    async fn is_killed(client: &Client) -> bool {
+        tokio::time::timeout(Duration::ZERO, client.killed())
+            .await
+            .is_ok()
+    }
+
+    #[test]
+    fn test_client_setname_and_getname() {
+        let client = test_client();
+        assert_eq!(run(&client, &["CLIENT", "GETNAME"]), "_\r\n");
+        assert_eq!(run(&client, &["CLIENT", "SETNAME", "worker-1"]), "+OK\r\n");
+        assert_eq!(run(&client, &["CLIENT", "GETNAME"]), "$8\r\nworker-1\r\n");
+
+        let invalid =
+            "-ERR Client names cannot contain spaces, newlines or special characters.\r\n";
+        assert_eq!(run(&client, &["CLIENT", "SETNAME", "a b"]), invalid);
+        assert_eq!(run(&client, &["CLIENT", "SETNAME", "a\nb"]), invalid);
+        assert_eq!(run(&client, &["CLIENT", "SETNAME", "é"]), invalid);
+        assert_eq!(run(&client, &["CLIENT", "GETNAME"]), "$8\r\nworker-1\r\n");
+
+        assert_eq!(run(&client, &["CLIENT", "SETNAME", ""]), "+OK\r\n");
+        assert_eq!(run(&client, &["CLIENT", "GETNAME"]), "_\r\n");
+        assert!(run(&client, &["CLIENT", "SETNAME"]).starts_with("-ERR wrong number"));
+    }
+
+    #[test]
+    fn test_client_list_fields() {
+        let client = test_client();
+        let other = connect(&client, 6380);
+        run(&client, &["CLIENT", "SETNAME", "me"]);
+        run(&client, &["SELECT", "2"]);
+
+        let info = run(&client, &["CLIENT", "INFO"]);
+        let line = info.split("\r\n").nth(1).unwrap();
+        let fields: Vec<&str> = line
+            .split(' ')
+            .map(|f| f.split('=').next().unwrap())
+            .collect();
+        let expected = [
+            "id", "addr", "laddr", "name", "age", "idle", "flags", "db", "qbuf", "obl", "cmd",
+            "user", "resp",
+        ];
+        assert_eq!(fields, expected);
+        assert!(line.starts_with(&format!("id={} addr=127.0.0.1:6378 ", client.id)));
+        assert!(line.contains(" name=me "));
+        assert!(line.contains(" db=2 "));
+
+        let list = run(&client, &["CLIENT", "LIST"]);
+        assert!(list.contains(&format!("id={} addr=127.0.0.1:6378 ", client.id)));
+        assert!(list.contains(&format!(
+            "id={} addr=127.0.0.1:6380 laddr=127.0.0.1:6380 name= ",
+            other.id
+        )));
+
+        let id = other.id.to_string();
+        let list = run(&client, &["CLIENT", "LIST", "ID", &id]);
+        assert!(list.contains("addr=127.0.0.1:6380 "));
+        assert!(!list.contains("addr=127.0.0.1:6378 "));
+        assert_eq!(
+            run(&client, &["CLIENT", "LIST", "ID", "0"]),
+            "-ERR Invalid client ID '0'\r\n"
+        );
+        assert_eq!(
+            run(&client, &["CLIENT", "LIST", "TYPE", "nope"]),
+            "-ERR Unknown client type 'nope'\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_kill_by_id_and_addr() {
+        let client = test_client();
+        let first = connect(&client, 6380);
+        let second = connect(&client, 6381);
+
+        let id = first.id.to_string();
+        assert_eq!(run(&client, &["CLIENT", "KILL", "ID", &id]), ":1\r\n");
+        assert!(is_killed(&first).await);
+        assert!(!is_killed(&second).await);
+
+        assert_eq!(
+            run(&client, &["CLIENT", "KILL", "ADDR", "127.0.0.1:6381"]),
+            ":1\r\n"
+        );
+        assert!(is_killed(&second).await);
+        assert_eq!(
+            run(&client, &["CLIENT", "KILL", "ADDR", "127.0.0.1:9999"]),
+            ":0\r\n"
+        );
+
+        // The old form replies OK or an error instead of a count.
+        assert_eq!(
+            run(&client, &["CLIENT", "KILL", "127.0.0.1:6380"]),
+            "+OK\r\n"
+        );
+        assert!(is_killed(&first).await);
+        assert_eq!(
+            run(&client, &["CLIENT", "KILL", "127.0.0.1:9999"]),
+            "-ERR No such client\r\n"
+        );
+
+        // The current client is skipped unless SKIPME is off.
+        let me = client.id.to_string();
+        assert_eq!(run(&client, &["CLIENT", "KILL", "ID", &me]), ":0\r\n");
+        assert!(!is_killed(&client).await);
+        assert_eq!(
+            run(&client, &["CLIENT", "KILL", "ID", &me, "SKIPME", "no"]),
+            ":1\r\n"
+        );
+        assert!(is_killed(&client).await);
+        assert_eq!(
+            run(&client, &["CLIENT", "KILL", "ID", "0"]),
+            "-ERR client-id should be greater than 0\r\n"
+        );
+    }
+
+    #[test]
+    fn test_acl_log_command() {
+        let client = test_client();
+        let acl = &client.server().acl;
+        acl.log(LogReason::Command, "get", "alice", "id=1");
+        acl.log(LogReason::Key, "secret", "alice", "id=1");
+        acl.log(LogReason::Key, "secret", "alice", "id=1");
+
+        let log = run(&client, &["ACL", "LOG"]);
+        assert!(log.starts_with("*2\r\n*20\r\n$5\r\ncount\r\n:2\r\n$6\r\nreason\r\n$3\r\nkey\r\n"));
+        assert!(log.contains("$6\r\nobject\r\n$3\r\nget\r\n"));
+        let log = run(&client, &["ACL", "LOG", "1"]);
+        assert!(log.starts_with("*1\r\n"));
+        assert!(log.contains("$6\r\nobject\r\n$6\r\nsecret\r\n"));
+        assert_eq!(run(&client, &["ACL", "LOG", "0"]), "*0\r\n");
+        assert_eq!(
+            run(&client, &["ACL", "LOG", "-1"]),
+            "-ERR value is out of range, must be positive\r\n"
+        );
+        assert!(run(&client, &["ACL", "LOG", "1", "2"]).starts_with("-ERR wrong number"));
+
+        assert_eq!(run(&client, &["ACL", "LOG", "RESET"]), "+OK\r\n");
+        assert_eq!(run(&client, &["ACL", "LOG"]), "*0\r\n");
+    }
+
+    #[test]
+    fn test_hello_negotiates_resp3_maps() {
+        let client = test_client();
+        client.authenticate("default");
+        run(&client, &["HSET", "h", "a", "1"]);
+        assert_eq!(
+            run(&client, &["HGETALL", "h"]),
+            "*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HELLO", "4"]),
+            "-NOPROTO unsupported protocol version\r\n"
+        );
+        let hello = run(&client, &["HELLO", "3", "SETNAME", "worker"]);
+        assert!(hello.starts_with("%7\r\n$6\r\nserver\r\n$5\r\nredis\r\n"));
+        assert!(hello.contains("$5\r\nproto\r\n:3\r\n"));
+        assert_eq!(client.name().as_deref(), Some("worker"));
+        assert_eq!(
+            run(&client, &["HGETALL", "h"]),
+            "%1\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+        assert_eq!(
+            run(&client, &["HRANDFIELD", "h", "1", "WITHVALUES"]),
+            "*1\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+        assert!(run(&client, &["HELLO", "2"]).starts_with("*14\r\n"));
+        assert_eq!(
+            run(&client, &["HGETALL", "h"]),
+            "*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
     }
 }
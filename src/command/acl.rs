@@ -0,0 +1,203 @@
+use crate::acl::{self, DEFAULT_USER};
+use crate::client::Client;
+use crate::command::{
+    error, ok, wrong_number_of_arguments, Execute, ACL_CATEGORIES, COMMAND_SPECS,
+};
+use crate::server::Server;
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct AclCommand;
+
+fn bulk(value: &str) -> Value {
+    Value::BulkString(BulkString(value.to_owned()))
+}
+fn wrong_arguments(subcommand: &str) -> Box<dyn Serialize> {
+    wrong_number_of_arguments(&format!("acl|{}", subcommand))
+}
+
+fn set_user(server: &Server, args: &[BulkString]) -> Box<dyn Serialize> {
+    let Some((BulkString(name), rules)) = args.split_first() else {
+        return wrong_arguments("setuser");
+    };
+    let rules: Vec<&str> = rules.iter().map(|rule| rule.0.as_str()).collect();
+    match server.acl.set_user(name, &rules) {
+        Ok(()) => ok(),
+        Err((rule, reason)) => error(&format!(
+            "Error in ACL SETUSER modifier '{}': {}",
+            rule, reason
+        )),
+    }
+}
+
+fn get_user(server: &Server, name: &str) -> Box<dyn Serialize> {
+    let Some(user) = server.acl.get_user(name) else {
+        return Box::new(Value::Nulls(Nulls));
+    };
+    Box::new(Value::Array(Array(vec![
+        bulk("flags"),
+        Value::Array(Array(user.flags().iter().map(|f| bulk(f)).collect())),
+        bulk("passwords"),
+        Value::Array(Array(user.passwords().iter().map(|p| bulk(p)).collect())),
+        bulk("commands"),
+        bulk(&user.describe_commands()),
+        bulk("keys"),
+        bulk(&user.describe_keys()),
+        bulk("channels"),
+        bulk(&user.describe_channels()),
+        bulk("selectors"),
+        Value::Array(Array(vec![])),
+    ])))
+}
+
+fn delete_users(server: &Server, names: &[BulkString]) -> Box<dyn Serialize> {
+    if names.iter().any(|name| name.0 == DEFAULT_USER) {
+        return error("The 'default' user cannot be removed");
+    }
+    let mut deleted = 0;
+    for BulkString(name) in names {
+        if server.acl.delete_user(name) {
+            deleted += 1;
+            // Connections authenticated as a removed user are closed.
+            for target in server.clients.clients() {
+                if target.user().as_ref() == Some(name) {
+                    target.kill();
+                }
+            }
+        }
+    }
+    Box::new(Value::Integer(Integer(deleted)))
+}
+
+fn load(server: &Server) -> Box<dyn Serialize> {
+    if let Err(e) = server.acl.load() {
+        return error(&e);
+    }
+    // Connections authenticated as a user that is no longer defined are closed.
+    for target in server.clients.clients() {
+        if target
+            .user()
+            .is_some_and(|user| server.acl.get_user(&user).is_none())
+        {
+            target.kill();
+        }
+    }
+    ok()
+}
+
+fn log(server: &Server, args: &[BulkString]) -> Box<dyn Serialize> {
+    let count = match args.first() {
+        None => 10,
+        Some(BulkString(arg)) if arg.eq_ignore_ascii_case("reset") => {
+            server.acl.reset_log();
+            return ok();
+        }
+        Some(BulkString(arg)) => match arg.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => return error("value is out of range, must be positive"),
+        },
+    };
+    let entries = server
+        .acl
+        .log_entries(count)
+        .into_iter()
+        .map(|entry| {
+            Value::Array(Array(vec![
+                bulk("count"),
+                Value::Integer(Integer(entry.count as i64)),
+                bulk("reason"),
+                bulk(entry.reason.as_str()),
+                bulk("context"),
+                bulk(entry.context),
+                bulk("object"),
+                bulk(&entry.object),
+                bulk("username"),
+                bulk(&entry.username),
+                bulk("age-seconds"),
+                bulk(&format!(
+                    "{:.3}",
+                    acl::age_millis(entry.created) as f64 / 1000.0
+                )),
+                bulk("client-info"),
+                bulk(&entry.client_info),
+                bulk("entry-id"),
+                Value::Integer(Integer(entry.entry_id as i64)),
+                bulk("timestamp-created"),
+                Value::Integer(Integer(entry.created as i64)),
+                bulk("timestamp-last-updated"),
+                Value::Integer(Integer(entry.updated as i64)),
+            ]))
+        })
+        .collect();
+    Box::new(Value::Array(Array(entries)))
+}
+
+fn categories(category: Option<&str>) -> Box<dyn Serialize> {
+    let names: Vec<Value> = match category {
+        None => ACL_CATEGORIES.iter().map(|c| bulk(c)).collect(),
+        Some(category) => {
+            let category = category.to_lowercase();
+            if !ACL_CATEGORIES.contains(&category.as_str()) {
+                return error(&format!("Unknown category '{}'", category));
+            }
+            COMMAND_SPECS
+                .iter()
+                .filter(|spec| spec.categories.contains(&category.as_str()))
+                .map(|spec| bulk(spec.name))
+                .collect()
+        }
+    };
+    Box::new(Value::Array(Array(names)))
+}
+
+impl Execute for AclCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        let Some(BulkString(subcommand)) = options.first() else {
+            return wrong_number_of_arguments("acl");
+        };
+        let subcommand = subcommand.to_lowercase();
+        let args = &options[1..];
+        let server = client.server();
+        match subcommand.as_str() {
+            "setuser" => set_user(server, args),
+            "getuser" if args.len() == 1 => get_user(server, &args[0].0),
+            "deluser" if !args.is_empty() => delete_users(server, args),
+            "list" if args.is_empty() => Box::new(Value::Array(Array(
+                server
+                    .acl
+                    .list_users()
+                    .iter()
+                    .map(|user| bulk(&user.describe()))
+                    .collect(),
+            ))),
+            "users" if args.is_empty() => Box::new(Value::Array(Array(
+                server
+                    .acl
+                    .user_names()
+                    .iter()
+                    .map(|name| bulk(name))
+                    .collect(),
+            ))),
+            "whoami" if args.is_empty() => match client.user() {
+                Some(user) => Box::new(bulk(&user)),
+                None => Box::new(Value::Nulls(Nulls)),
+            },
+            "load" if args.is_empty() => load(server),
+            "save" if args.is_empty() => match server.acl.save() {
+                Ok(()) => ok(),
+                Err(e) => error(&e),
+            },
+            "log" if args.len() <= 1 => log(server, args),
+            "cat" if args.len() <= 1 => categories(args.first().map(|c| c.0.as_str())),
+            "getuser" | "deluser" | "list" | "users" | "whoami" | "cat" | "load" | "save"
+            | "log" => wrong_arguments(&subcommand),
+            _ => error(&format!(
+                "unknown subcommand '{}'. Try ACL HELP.",
+                options[0].0
+            )),
+        }
+    }
+}
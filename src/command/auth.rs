@@ -0,0 +1,40 @@
+use crate::acl::{LogReason, DEFAULT_USER};
+use crate::client::Client;
+use crate::command::{error, ok, wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+use crate::value::simple_error::{ErrorType, SimpleError};
+use crate::value::Value;
+pub struct AuthCommand;
+impl Execute for AuthCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        let (user, password) = match options.as_slice() {
+            [BulkString(password)] => {
+                let acl = &client.server().acl;
+                if acl
+                    .get_user(DEFAULT_USER)
+                    .is_some_and(|user| user.is_nopass())
+                {
+                    return error("AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?");
+                }
+                (DEFAULT_USER, password)
+            }
+            [BulkString(user), BulkString(password)] => (user.as_str(), password),
+            _ => return wrong_number_of_arguments("auth"),
+        };
+        let acl = &client.server().acl;
+        match acl.authenticate(user, password) {
+            Some(user) => {
+                client.authenticate(&user.name);
+                ok()
+            }
+            None => {
+                acl.log(LogReason::Auth, "AUTH", user, client.info().trim_end());
+                Box::new(Value::SimpleError(SimpleError {
+                    error_type: ErrorType::WRONGPASS,
+                    message: String::from("invalid username-password pair or user is disabled."),
+                }))
+            }
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use crate::client::Client;
+use crate::command::{
+    error, integer, parse_integer, storage_error, wrong_number_of_arguments, Execute,
+};
+use crate::object::bitmap::{BitField, BitOp, Overflow};
+use crate::storage::{BitFieldOp, BitRange};
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct SetBitCommand;
+pub struct GetBitCommand;
+pub struct BitCountCommand;
+pub struct BitPosCommand;
+pub struct BitOpCommand;
+pub struct BitFieldCommand;
+pub struct BitFieldRoCommand;
+
+/// Strings are limited to 512MB like in Redis, so bit offsets to 2^32.
+const MAX_BIT_OFFSET: u64 = (1 << 32) - 1;
+
+fn offset_error() -> Box<dyn Serialize> {
+    error("bit offset is not an integer or out of range")
+}
+
+fn parse_offset(option: &BulkString) -> Result<u64, Box<dyn Serialize>> {
+    match option.0.parse() {
+        Ok(offset) if offset <= MAX_BIT_OFFSET => Ok(offset),
+        _ => Err(offset_error()),
+    }
+}
+
+/// `start [end [BYTE|BIT]]` of `BITCOUNT` and `BITPOS`.
+fn parse_range(options: &[BulkString]) -> Result<Option<BitRange>, Box<dyn Serialize>> {
+    let Some(start) = options.first() else {
+        return Ok(None);
+    };
+    let start = parse_integer(start)?;
+    let end = options.get(1).map(parse_integer).transpose()?;
+    let bits = match options
+        .get(2)
+        .map(|unit| unit.0.to_ascii_uppercase())
+        .as_deref()
+    {
+        None | Some("BYTE") => false,
+        Some("BIT") => true,
+        Some(_) => return Err(error("syntax error")),
+    };
+    if options.len() > 3 {
+        return Err(error("syntax error"));
+    }
+    Ok(Some(BitRange { start, end, bits }))
+}
+
+/// Types like `i16` or `u8`.
+fn parse_field(option: &BulkString) -> Result<BitField, Box<dyn Serialize>> {
+    let field = match (option.0.strip_prefix('i'), option.0.strip_prefix('u')) {
+        (Some(bits), _) => Some((true, bits, 64)),
+        (_, Some(bits)) => Some((false, bits, 63)),
+        _ => None,
+    };
+    match field.and_then(|(signed, bits, max)| Some((signed, bits.parse().ok()?, max))) {
+        Some((signed, bits, max)) if (1..=max).contains(&bits) => Ok(BitField { signed, bits }),
+        _ => Err(error(
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.",
+        )),
+    }
+}
+
+/// A bit offset, or with a `#` prefix an index of fields of the type.
+fn parse_field_offset(option: &BulkString, field: BitField) -> Result<u64, Box<dyn Serialize>> {
+    let offset = match option.0.strip_prefix('#') {
+        Some(index) => index
+            .parse::<u64>()
+            .ok()
+            .and_then(|index| index.checked_mul(field.bits as u64)),
+        None => option.0.parse().ok(),
+    };
+    match offset {
+        Some(offset) if offset <= MAX_BIT_OFFSET => Ok(offset),
+        _ => Err(offset_error()),
+    }
+}
+
+fn parse_bitfield(
+    options: &[BulkString],
+    command: &str,
+) -> Result<Vec<BitFieldOp>, Box<dyn Serialize>> {
+    let read_only = command == "BITFIELD_RO";
+    let mut ops = Vec::new();
+    let mut overflow = Overflow::default();
+    let mut i = 0;
+    while i < options.len() {
+        let remaining = options.len() - i - 1;
+        match options[i].0.to_ascii_uppercase().as_str() {
+            "OVERFLOW" if remaining >= 1 => {
+                overflow = match options[i + 1].0.to_ascii_uppercase().as_str() {
+                    "WRAP" => Overflow::Wrap,
+                    "SAT" => Overflow::Sat,
+                    "FAIL" => Overflow::Fail,
+                    _ => return Err(error("Invalid OVERFLOW type specified")),
+                };
+                i += 2;
+            }
+            "GET" if remaining >= 2 => {
+                let field = parse_field(&options[i + 1])?;
+                let offset = parse_field_offset(&options[i + 2], field)?;
+                ops.push(BitFieldOp::Get(field, offset));
+                i += 3;
+            }
+            op @ ("SET" | "INCRBY") if remaining >= 3 => {
+                let field = parse_field(&options[i + 1])?;
+                let offset = parse_field_offset(&options[i + 2], field)?;
+                if read_only {
+                    return Err(error(&format!(
+                        "{} only supports the GET subcommand",
+                        command
+                    )));
+                }
+                let value = parse_integer(&options[i + 3])?;
+                ops.push(match op {
+                    "SET" => BitFieldOp::Set(field, offset, value, overflow),
+                    _ => BitFieldOp::IncrBy(field, offset, value, overflow),
+                });
+                i += 4;
+            }
+            _ => return Err(error("syntax error")),
+        }
+    }
+    Ok(ops)
+}
+
+fn bitfield(options: Vec<BulkString>, client: &Client, command: &str) -> Box<dyn Serialize> {
+    if options.is_empty() {
+        return wrong_number_of_arguments(&command.to_ascii_lowercase());
+    }
+    let ops = parse!(parse_bitfield(&options[1..], command));
+    let storage = &client.server().storage;
+    match storage.bit_field(client.db(), &options[0].0, &ops) {
+        Ok(values) => Box::new(Value::Array(Array(
+            values
+                .into_iter()
+                .map(|value| match value {
+                    Some(value) => Value::Integer(Integer(value)),
+                    None => Value::Nulls(Nulls),
+                })
+                .collect(),
+        ))),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for SetBitCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("setbit");
+        }
+        let offset = parse!(parse_offset(&options[1]));
+        let bit = match options[2].0.as_str() {
+            "0" => false,
+            "1" => true,
+            _ => return error("bit is not an integer or out of range"),
+        };
+        let storage = &client.server().storage;
+        match storage.set_bit(client.db(), &options[0].0, offset, bit) {
+            Ok(previous) => integer(previous as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for GetBitCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("getbit");
+        }
+        let offset = parse!(parse_offset(&options[1]));
+        let storage = &client.server().storage;
+        match storage.get_bit(client.db(), &options[0].0, offset) {
+            Ok(bit) => integer(bit as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for BitCountCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.is_empty() {
+            return wrong_number_of_arguments("bitcount");
+        }
+        let range = parse!(parse_range(&options[1..]));
+        // Unlike BITPOS the end is required.
+        if range.is_some_and(|range| range.end.is_none()) {
+            return error("syntax error");
+        }
+        let storage = &client.server().storage;
+        match storage.bit_count(client.db(), &options[0].0, range) {
+            Ok(count) => integer(count as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for BitPosCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("bitpos");
+        }
+        let bit = match parse!(parse_integer(&options[1])) {
+            0 => false,
+            1 => true,
+            _ => return error("The bit argument must be 1 or 0."),
+        };
+        let range = parse!(parse_range(&options[2..]));
+        let storage = &client.server().storage;
+        match storage.bit_pos(client.db(), &options[0].0, bit, range) {
+            Ok(position) => integer(position),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for BitOpCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 {
+            return wrong_number_of_arguments("bitop");
+        }
+        let op = match options[0].0.to_ascii_uppercase().as_str() {
+            "AND" => BitOp::And,
+            "OR" => BitOp::Or,
+            "XOR" => BitOp::Xor,
+            "NOT" => BitOp::Not,
+            _ => return error("syntax error"),
+        };
+        if op == BitOp::Not && options.len() != 3 {
+            return error("BITOP NOT must be called with a single source key.");
+        }
+        let sources: Vec<String> = options[2..].iter().map(|key| key.0.clone()).collect();
+        let storage = &client.server().storage;
+        match storage.bit_op(client.db(), op, &options[1].0, &sources) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for BitFieldCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        bitfield(options, client, "BITFIELD")
+    }
+}
+impl Execute for BitFieldRoCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        bitfield(options, client, "BITFIELD_RO")
+    }
+}
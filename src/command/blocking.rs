@@ -0,0 +1,44 @@
+use crate::client::Client;
+use crate::command::{error, storage_error};
+use crate::storage::{Attempt, BlockResult};
+use crate::value::bulk_string::BulkString;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+use std::time::Duration;
+
+/// Parses the timeout of a blocking command, in seconds. `None` for 0, which
+/// blocks forever.
+pub(crate) fn parse_timeout(timeout: &BulkString) -> Result<Option<Duration>, Box<dyn Serialize>> {
+    match timeout.0.parse::<f64>() {
+        Ok(timeout) if timeout < 0.0 => Err(error("timeout is negative")),
+        Ok(0.0) => Ok(None),
+        // Infinite or too large for a `Duration`.
+        Ok(timeout) => match Duration::try_from_secs_f64(timeout) {
+            Ok(timeout) => Ok(Some(timeout)),
+            Err(_) => Err(error("timeout is out of range")),
+        },
+        Err(_) => Err(error("timeout is not a float or out of range")),
+    }
+}
+
+/// Runs `attempt` right away, or parks the client on `keys` until it succeeds
+/// or `timeout` elapses. The connection task sends the reply of a parked
+/// client once it is unblocked.
+pub(crate) fn block_on(
+    client: &Client,
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+    attempt: Attempt,
+) -> Box<dyn Serialize> {
+    let storage = &client.server().storage;
+    match storage.block(client.id, client.db(), keys, attempt) {
+        Ok(BlockResult::Ready(reply)) => Box::new(reply),
+        Ok(BlockResult::Blocked(blocked)) => {
+            client.block(blocked, timeout);
+            // Replaced by the reply the client gets once unblocked.
+            Box::new(Value::Nulls(Nulls))
+        }
+        Err(e) => storage_error(e),
+    }
+}
@@ -0,0 +1,258 @@
+use crate::client::{Client, ClientType, PauseMode, ReplyMode};
+use crate::command::{error, ok, wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+use std::time::Duration;
+pub struct ClientCommand;
+
+fn wrong_arguments(subcommand: &str) -> Box<dyn Serialize> {
+    wrong_number_of_arguments(&format!("client|{}", subcommand))
+}
+
+#[derive(Default)]
+struct KillFilter {
+    id: Option<u64>,
+    addr: Option<String>,
+    laddr: Option<String>,
+    client_type: Option<ClientType>,
+    max_age: Option<u64>,
+    user: Option<String>,
+    skip_me: bool,
+}
+impl KillFilter {
+    fn matches(&self, target: &Client, current: &Client) -> bool {
+        if self.skip_me && target.id == current.id {
+            return false;
+        }
+        self.id.is_none_or(|id| id == target.id)
+            && self
+                .addr
+                .as_ref()
+                .is_none_or(|addr| *addr == target.addr.to_string())
+            && self
+                .laddr
+                .as_ref()
+                .is_none_or(|laddr| *laddr == target.laddr.to_string())
+            && self
+                .client_type
+                .is_none_or(|client_type| client_type == target.client_type())
+            && self
+                .max_age
+                .is_none_or(|max_age| target.age().as_secs() >= max_age)
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|user| target.user().as_ref() == Some(user))
+    }
+}
+
+/// Sets the name of the client, an empty name removes it.
+pub(crate) fn set_name(client: &Client, name: &str) -> Result<(), Box<dyn Serialize>> {
+    if name.chars().any(|c| c <= ' ' || c > '~') {
+        return Err(error(
+            "Client names cannot contain spaces, newlines or special characters.",
+        ));
+    }
+    client.set_name(if name.is_empty() {
+        None
+    } else {
+        Some(name.to_owned())
+    });
+    Ok(())
+}
+
+fn list(options: &[BulkString], client: &Client) -> Box<dyn Serialize> {
+    let mut client_type: Option<ClientType> = None;
+    let mut ids: Option<Vec<u64>> = None;
+    let mut options_iter = options.iter();
+    while let Some(BulkString(option)) = options_iter.next() {
+        match option.to_lowercase().as_str() {
+            "type" => {
+                let Some(BulkString(value)) = options_iter.next() else {
+                    return error("syntax error");
+                };
+                match ClientType::try_from(value.as_str()) {
+                    Ok(t) => client_type = Some(t),
+                    Err(_) => return error(&format!("Unknown client type '{}'", value)),
+                }
+            }
+            "id" => {
+                let mut parsed = Vec::new();
+                for BulkString(id) in options_iter.by_ref() {
+                    match id.parse::<u64>() {
+                        Ok(id) if id > 0 => parsed.push(id),
+                        _ => return error(&format!("Invalid client ID '{}'", id)),
+                    }
+                }
+                if parsed.is_empty() {
+                    return error("syntax error");
+                }
+                ids = Some(parsed);
+            }
+            _ => return error("syntax error"),
+        }
+    }
+    let mut output = String::new();
+    for target in client.server().clients.clients() {
+        if client_type.is_some_and(|t| t != target.client_type()) {
+            continue;
+        }
+        if ids.as_ref().is_some_and(|ids| !ids.contains(&target.id)) {
+            continue;
+        }
+        output.push_str(&target.info());
+    }
+    Box::new(Value::BulkString(BulkString(output)))
+}
+
+fn kill(options: &[BulkString], current: &Client) -> Box<dyn Serialize> {
+    // Old form: CLIENT KILL addr:port
+    if options.len() == 1 {
+        let addr = &options[0].0;
+        return match current
+            .server()
+            .clients
+            .clients()
+            .into_iter()
+            .find(|target| target.addr.to_string() == *addr)
+        {
+            Some(target) => {
+                target.kill();
+                ok()
+            }
+            None => error("No such client"),
+        };
+    }
+    if options.is_empty() || !options.len().is_multiple_of(2) {
+        return error("syntax error");
+    }
+    let mut filter = KillFilter {
+        skip_me: true,
+        ..Default::default()
+    };
+    for pair in options.chunks(2) {
+        let value = &pair[1].0;
+        match pair[0].0.to_lowercase().as_str() {
+            "id" => match value.parse::<u64>() {
+                Ok(id) if id > 0 => filter.id = Some(id),
+                _ => return error("client-id should be greater than 0"),
+            },
+            "addr" => filter.addr = Some(value.to_owned()),
+            "laddr" => filter.laddr = Some(value.to_owned()),
+            "user" => match current.server().acl.get_user(value) {
+                Some(_) => filter.user = Some(value.to_owned()),
+                None => return error(&format!("No such user '{}'", value)),
+            },
+            "type" => match ClientType::try_from(value.as_str()) {
+                Ok(t) => filter.client_type = Some(t),
+                Err(_) => return error(&format!("Unknown client type '{}'", value)),
+            },
+            "skipme" => match value.to_lowercase().as_str() {
+                "yes" => filter.skip_me = true,
+                "no" => filter.skip_me = false,
+                _ => return error("syntax error"),
+            },
+            "maxage" => match value.parse::<u64>() {
+                Ok(max_age) => filter.max_age = Some(max_age),
+                Err(_) => return error("value is not an integer or out of range"),
+            },
+            _ => return error("syntax error"),
+        }
+    }
+    let mut killed = 0;
+    for target in current.server().clients.clients() {
+        if filter.matches(&target, current) {
+            target.kill();
+            killed += 1;
+        }
+    }
+    Box::new(Value::Integer(Integer(killed)))
+}
+
+fn pause(options: &[BulkString], client: &Client) -> Box<dyn Serialize> {
+    if options.is_empty() || options.len() > 2 {
+        return wrong_arguments("pause");
+    }
+    let timeout = match options[0].0.parse::<i64>() {
+        Ok(timeout) if timeout >= 0 => timeout as u64,
+        Ok(_) => return error("timeout is negative"),
+        Err(_) => return error("timeout is not an integer or out of range"),
+    };
+    let mode = match options.get(1).map(|mode| mode.0.to_lowercase()) {
+        None => PauseMode::All,
+        Some(mode) if mode == "all" => PauseMode::All,
+        Some(mode) if mode == "write" => PauseMode::Write,
+        Some(_) => return error("syntax error"),
+    };
+    client
+        .server()
+        .clients
+        .pause(Duration::from_millis(timeout), mode);
+    ok()
+}
+
+fn unblock(options: &[BulkString], client: &Client) -> Box<dyn Serialize> {
+    if options.is_empty() || options.len() > 2 {
+        return wrong_arguments("unblock");
+    }
+    let Ok(id) = options[0].0.parse::<u64>() else {
+        return error("value is not an integer or out of range");
+    };
+    let error_reply = match options.get(1).map(|reason| reason.0.to_lowercase()) {
+        None => false,
+        Some(reason) if reason == "timeout" => false,
+        Some(reason) if reason == "error" => true,
+        Some(_) => return error("CLIENT UNBLOCK reason should be TIMEOUT or ERROR"),
+    };
+    let unblocked = client.server().storage.unblock(id, error_reply);
+    Box::new(Value::Integer(Integer(unblocked as i64)))
+}
+
+impl Execute for ClientCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        let Some(BulkString(subcommand)) = options.first() else {
+            return wrong_number_of_arguments("client");
+        };
+        let subcommand = subcommand.to_lowercase();
+        let args = &options[1..];
+        match subcommand.as_str() {
+            "id" if args.is_empty() => Box::new(Value::Integer(Integer(client.id as i64))),
+            "info" if args.is_empty() => Box::new(Value::BulkString(BulkString(client.info()))),
+            "list" => list(args, client),
+            "getname" if args.is_empty() => match client.name() {
+                Some(name) => Box::new(Value::BulkString(BulkString(name))),
+                None => Box::new(Value::Nulls(Nulls)),
+            },
+            "setname" if args.len() == 1 => match set_name(client, &args[0].0) {
+                Ok(()) => ok(),
+                Err(e) => e,
+            },
+            "kill" => kill(args, client),
+            "pause" => pause(args, client),
+            "unblock" => unblock(args, client),
+            "unpause" if args.is_empty() => {
+                client.server().clients.unpause();
+                ok()
+            }
+            "reply" if args.len() == 1 => {
+                match args[0].0.to_lowercase().as_str() {
+                    "on" => client.set_reply_mode(ReplyMode::On),
+                    "off" => client.set_reply_mode(ReplyMode::Off),
+                    "skip" => client.set_reply_mode(ReplyMode::SkipNext),
+                    _ => return error("syntax error"),
+                }
+                ok()
+            }
+            "id" | "info" | "getname" | "setname" | "unpause" | "reply" => {
+                wrong_arguments(&subcommand)
+            }
+            _ => error(&format!(
+                "unknown subcommand '{}'. Try CLIENT HELP.",
+                options[0].0
+            )),
+        }
+    }
+}
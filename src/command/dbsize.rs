@@ -0,0 +1,17 @@
+use crate::client::Client;
+use crate::command::{wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct DbSizeCommand;
+impl Execute for DbSizeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if !options.is_empty() {
+            return wrong_number_of_arguments("dbsize");
+        }
+        Box::new(Value::Integer(Integer(
+            client.server().storage.size(client.db()) as i64,
+        )))
+    }
+}
@@ -1,3 +1,4 @@
+use crate::client::Client;
 use crate::command::Execute;
 use crate::value::bulk_string::BulkString;
 use crate::value::serialize::Serialize;
@@ -6,7 +7,7 @@ use crate::value::Value;
 use core::panic;
 pub struct EchoCommand;
 impl Execute for EchoCommand {
-    fn execute(self, options: Vec<BulkString>) -> Box<dyn Serialize> {
+    fn execute(self, options: Vec<BulkString>, _client: &Client) -> Box<dyn Serialize> {
         if options.len() != 1 {
             return Box::new(Value::SimpleError(SimpleError {
                 error_type: ErrorType::try_from("ERR").unwrap(),
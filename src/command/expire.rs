@@ -0,0 +1,85 @@
+use crate::client::Client;
+use crate::command::{error, wrong_number_of_arguments, Execute};
+use crate::storage::Expiry;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct ExpireCommand;
+pub struct PExpireCommand;
+pub struct ExpireAtCommand;
+pub struct PExpireAtCommand;
+
+/// Shared implementation of the `EXPIRE` family. The time argument is in
+/// units of `unit_ms` milliseconds, relative to now unless `absolute`.
+fn expire(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    unit_ms: i128,
+    absolute: bool,
+) -> Box<dyn Serialize> {
+    if options.len() < 2 {
+        return wrong_number_of_arguments(name);
+    }
+    let Ok(time) = options[1].0.parse::<i64>() else {
+        return error("value is not an integer or out of range");
+    };
+    let (mut nx, mut xx, mut gt, mut lt) = (false, false, false, false);
+    for BulkString(option) in &options[2..] {
+        match option.to_lowercase().as_str() {
+            "nx" => nx = true,
+            "xx" => xx = true,
+            "gt" => gt = true,
+            "lt" => lt = true,
+            _ => return error(&format!("Unsupported option {}", option)),
+        }
+    }
+    if nx && (xx || gt || lt) {
+        return error("NX and XX, GT or LT options at the same time are not compatible");
+    }
+    if gt && lt {
+        return error("GT and LT options at the same time are not compatible");
+    }
+    let storage = &client.server().storage;
+    let base = if absolute {
+        0
+    } else {
+        storage.now_ms() as i128
+    };
+    let deadline = time as i128 * unit_ms + base;
+    if deadline > i64::MAX as i128 || deadline < i64::MIN as i128 {
+        return error(&format!("invalid expire time in '{}' command", name));
+    }
+    // A deadline before the epoch is simply in the past: the key is deleted.
+    let expiry = Expiry::EPOCH(deadline.max(0) as u128);
+    let set = storage.expire_if(client.db(), &options[0].0, expiry, |current, new| {
+        let new = new.expect("an epoch expiry has a deadline");
+        (!nx || current.is_none())
+            && (!xx || current.is_some())
+            && (!gt || current.is_some_and(|current| new > current))
+            && (!lt || current.is_none_or(|current| new < current))
+    });
+    Box::new(Value::Integer(Integer(set as i64)))
+}
+
+impl Execute for ExpireCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("expire", &options, client, 1000, false)
+    }
+}
+impl Execute for PExpireCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("pexpire", &options, client, 1, false)
+    }
+}
+impl Execute for ExpireAtCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("expireat", &options, client, 1000, true)
+    }
+}
+impl Execute for PExpireAtCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("pexpireat", &options, client, 1, true)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::client::Client;
+use crate::command::{error, ok, wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+pub struct FlushDbCommand;
+pub struct FlushAllCommand;
+
+/// Validates the optional `ASYNC|SYNC` modifier. Flushing is always done
+/// synchronously, so both are accepted and behave the same.
+fn check_mode(name: &str, options: &[BulkString]) -> Option<Box<dyn Serialize>> {
+    match options {
+        [] => None,
+        [BulkString(mode)] if ["async", "sync"].contains(&mode.to_lowercase().as_str()) => None,
+        [_] => Some(error("syntax error")),
+        _ => Some(wrong_number_of_arguments(name)),
+    }
+}
+
+impl Execute for FlushDbCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if let Some(e) = check_mode("flushdb", &options) {
+            return e;
+        }
+        client.server().storage.flush(client.db());
+        ok()
+    }
+}
+impl Execute for FlushAllCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if let Some(e) = check_mode("flushall", &options) {
+            return e;
+        }
+        client.server().storage.flush_all();
+        ok()
+    }
+}
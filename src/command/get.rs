@@ -1,6 +1,6 @@
-use crate::command::Execute;
-use crate::storage::get;
-use crate::value::bulk_string::BulkString;
+use crate::client::Client;
+use crate::command::{wrong_type, Execute};
+use crate::value::bulk_string::{BulkBytes, BulkString};
 use crate::value::nulls::Nulls;
 use crate::value::serialize::Serialize;
 use crate::value::simple_error::{ErrorType, SimpleError};
@@ -8,7 +8,7 @@ use crate::value::Value;
 use core::panic;
 pub struct GetCommand;
 impl Execute for GetCommand {
-    fn execute(self, options: Vec<BulkString>) -> Box<dyn Serialize> {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
         if options.len() != 1 {
             return Box::new(Value::SimpleError(SimpleError {
                 error_type: ErrorType::try_from("ERR").unwrap(),
@@ -16,10 +16,10 @@ impl Execute for GetCommand {
             }));
         }
         if let Some(val) = options.get(0).cloned() {
-            if let Ok(va) = get(val.0) {
-                Box::new(Value::BulkString(BulkString(va)))
-            } else {
-                Box::new(Value::Nulls(Nulls))
+            match client.server().storage.get(client.db(), &val.0) {
+                Ok(Some(va)) => Box::new(Value::BulkBytes(BulkBytes(va))),
+                Ok(None) => Box::new(Value::Nulls(Nulls)),
+                Err(_) => wrong_type(),
             }
         } else {
             panic!("invalid arguments");
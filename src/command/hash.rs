@@ -0,0 +1,307 @@
+use crate::client::Client;
+use crate::command::scan::{parse_scan_options, scan_reply};
+use crate::command::{
+    bulk_array, bulk_or_null, error, integer, map_reply, parse_integer, storage_error,
+    wrong_number_of_arguments, Execute,
+};
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct HSetCommand;
+pub struct HSetNxCommand;
+pub struct HGetCommand;
+pub struct HMGetCommand;
+pub struct HGetAllCommand;
+pub struct HDelCommand;
+pub struct HExistsCommand;
+pub struct HLenCommand;
+pub struct HKeysCommand;
+pub struct HValsCommand;
+pub struct HStrLenCommand;
+pub struct HIncrByCommand;
+pub struct HIncrByFloatCommand;
+pub struct HRandFieldCommand;
+pub struct HScanCommand;
+
+fn bulk(value: String) -> Value {
+    Value::BulkString(BulkString(value))
+}
+
+fn strings(options: &[BulkString]) -> Vec<String> {
+    options.iter().map(|option| option.0.clone()).collect()
+}
+
+impl Execute for HSetCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 || options.len().is_multiple_of(2) {
+            return wrong_number_of_arguments("hset");
+        }
+        let pairs = options[1..]
+            .chunks(2)
+            .map(|pair| (pair[0].0.clone(), pair[1].0.clone()))
+            .collect();
+        match client
+            .server()
+            .storage
+            .hash_set(client.db(), &options[0].0, pairs)
+        {
+            Ok(added) => integer(added as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HSetNxCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("hsetnx");
+        }
+        let storage = &client.server().storage;
+        let (key, field, value) = (&options[0].0, &options[1].0, options[2].0.clone());
+        match storage.hash_set_if_absent(client.db(), key, field, value) {
+            Ok(set) => integer(set as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HGetCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("hget");
+        }
+        let storage = &client.server().storage;
+        match storage.hash_get(client.db(), &options[0].0, &options[1].0) {
+            Ok(value) => bulk_or_null(value),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HMGetCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("hmget");
+        }
+        let storage = &client.server().storage;
+        match storage.hash_get_many(client.db(), &options[0].0, &strings(&options[1..])) {
+            Ok(values) => Box::new(Value::Array(Array(
+                values
+                    .into_iter()
+                    .map(|value| value.map_or(Value::Nulls(Nulls), bulk))
+                    .collect(),
+            ))),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HGetAllCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("hgetall");
+        }
+        match client
+            .server()
+            .storage
+            .hash_get_all(client.db(), &options[0].0)
+        {
+            Ok(pairs) => Box::new(map_reply(
+                client,
+                pairs
+                    .into_iter()
+                    .map(|(field, value)| (bulk(field), bulk(value)))
+                    .collect(),
+            )),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HDelCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("hdel");
+        }
+        let storage = &client.server().storage;
+        match storage.hash_delete(client.db(), &options[0].0, &strings(&options[1..])) {
+            Ok(removed) => integer(removed as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HExistsCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("hexists");
+        }
+        let storage = &client.server().storage;
+        match storage.hash_exists(client.db(), &options[0].0, &options[1].0) {
+            Ok(exists) => integer(exists as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HLenCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("hlen");
+        }
+        match client.server().storage.hash_len(client.db(), &options[0].0) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HKeysCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("hkeys");
+        }
+        match client
+            .server()
+            .storage
+            .hash_fields(client.db(), &options[0].0)
+        {
+            Ok(fields) => Box::new(bulk_array(fields)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HValsCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("hvals");
+        }
+        match client
+            .server()
+            .storage
+            .hash_values(client.db(), &options[0].0)
+        {
+            Ok(values) => Box::new(bulk_array(values)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HStrLenCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("hstrlen");
+        }
+        let storage = &client.server().storage;
+        match storage.hash_value_len(client.db(), &options[0].0, &options[1].0) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HIncrByCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("hincrby");
+        }
+        let increment = match parse_integer(&options[2]) {
+            Ok(increment) => increment,
+            Err(e) => return e,
+        };
+        let storage = &client.server().storage;
+        match storage.hash_incr_by(client.db(), &options[0].0, &options[1].0, increment) {
+            Ok(value) => integer(value),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HIncrByFloatCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("hincrbyfloat");
+        }
+        let Ok(increment) = options[2].0.parse::<f64>() else {
+            return error("value is not a valid float");
+        };
+        let storage = &client.server().storage;
+        match storage.hash_incr_by_float(client.db(), &options[0].0, &options[1].0, increment) {
+            Ok(value) => Box::new(bulk(value)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HRandFieldCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.is_empty() || options.len() > 3 {
+            return wrong_number_of_arguments("hrandfield");
+        }
+        let with_values = match options.get(2) {
+            Some(option) if option.0.eq_ignore_ascii_case("withvalues") => true,
+            Some(_) => return error("syntax error"),
+            None => false,
+        };
+        // Like Redis, counts that could not be replied are refused, halved
+        // with values as each field takes two elements.
+        let max = if with_values { i64::MAX / 2 } else { i64::MAX };
+        let count = match options.get(1).map(parse_integer) {
+            Some(Ok(count)) if !(-max..=max).contains(&count) => {
+                return error("value is out of range")
+            }
+            Some(Ok(count)) => Some(count),
+            Some(Err(e)) => return e,
+            None => None,
+        };
+        let storage = &client.server().storage;
+        let pairs = match storage.hash_random_fields(client.db(), &options[0].0, count.unwrap_or(1))
+        {
+            Ok(pairs) => pairs,
+            Err(e) => return storage_error(e),
+        };
+        if count.is_none() {
+            return bulk_or_null(pairs.into_iter().next().map(|(field, _)| field));
+        }
+        if !with_values {
+            return Box::new(bulk_array(
+                pairs.into_iter().map(|(field, _)| field).collect(),
+            ));
+        }
+        // RESP3 clients get one array per field and value, like Redis does.
+        let pairs = pairs
+            .into_iter()
+            .map(|(field, value)| [bulk(field), bulk(value)]);
+        Box::new(Value::Array(Array(if client.resp() >= 3 {
+            pairs
+                .map(|pair| Value::Array(Array(pair.to_vec())))
+                .collect()
+        } else {
+            pairs.flatten().collect()
+        })))
+    }
+}
+impl Execute for HScanCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("hscan");
+        }
+        let scan_options = match parse_scan_options(&options[1..], true) {
+            Ok(scan_options) => scan_options,
+            Err(e) => return e,
+        };
+        let storage = &client.server().storage;
+        match storage.hash_scan(
+            client.db(),
+            &options[0].0,
+            scan_options.cursor,
+            scan_options.count,
+            scan_options.pattern.as_deref(),
+        ) {
+            Ok((cursor, pairs)) => {
+                let elements = pairs
+                    .into_iter()
+                    .flat_map(|(field, value)| {
+                        if scan_options.no_values {
+                            vec![field]
+                        } else {
+                            vec![field, value]
+                        }
+                    })
+                    .collect();
+                scan_reply(cursor, elements)
+            }
+            Err(e) => storage_error(e),
+        }
+    }
+}
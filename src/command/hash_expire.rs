@@ -0,0 +1,350 @@
+use crate::client::Client;
+use crate::command::{
+    error, integer, parse_integer, storage_error, wrong_number_of_arguments, Execute,
+};
+use crate::db::SetCondition;
+use crate::storage::{Expiry, FieldExpire};
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct HExpireCommand;
+pub struct HPExpireCommand;
+pub struct HExpireAtCommand;
+pub struct HPExpireAtCommand;
+pub struct HTtlCommand;
+pub struct HPTtlCommand;
+pub struct HExpireTimeCommand;
+pub struct HPExpireTimeCommand;
+pub struct HPersistCommand;
+pub struct HGetExCommand;
+pub struct HSetExCommand;
+
+fn strings(options: &[BulkString]) -> Vec<String> {
+    options.iter().map(|option| option.0.clone()).collect()
+}
+
+fn integers(values: impl IntoIterator<Item = i64>) -> Box<dyn Serialize> {
+    Box::new(Value::Array(Array(
+        values
+            .into_iter()
+            .map(|value| Value::Integer(Integer(value)))
+            .collect(),
+    )))
+}
+
+/// Parses `FIELDS numfields` followed by `width` arguments per field, and
+/// returns these arguments.
+fn parse_fields(options: &[BulkString], width: usize) -> Result<&[BulkString], Box<dyn Serialize>> {
+    let missing = || error("Mandatory argument FIELDS is missing or not at the right position");
+    match options.first() {
+        Some(BulkString(keyword)) if keyword.eq_ignore_ascii_case("fields") => {}
+        _ => return Err(missing()),
+    }
+    let count = parse_integer(options.get(1).ok_or_else(missing)?)?;
+    if count <= 0 {
+        return Err(error("Parameter `numFields` should be greater than 0"));
+    }
+    let fields = &options[2..];
+    if (count as u64).checked_mul(width as u64) != Some(fields.len() as u64) {
+        return Err(error(
+            "The `numfields` parameter must match the number of arguments",
+        ));
+    }
+    Ok(fields)
+}
+
+/// Parses the time of an `EX`, `PX`, `EXAT` or `PXAT` option, which must be
+/// positive.
+fn parse_expiry(name: &str, unit: &str, time: &BulkString) -> Result<Expiry, Box<dyn Serialize>> {
+    let time = parse_integer(time)?;
+    let invalid = || error(&format!("invalid expire time in '{}' command", name));
+    if time <= 0 {
+        return Err(invalid());
+    }
+    let time = time as u64;
+    Ok(match unit {
+        "ex" => Expiry::DURATION(time.checked_mul(1000).ok_or_else(invalid)?),
+        "px" => Expiry::DURATION(time),
+        "exat" => Expiry::EPOCH(time as u128 * 1000),
+        _ => Expiry::EPOCH(time as u128),
+    })
+}
+
+/// Shared implementation of the `HEXPIRE` family. The time argument is in
+/// units of `unit_ms` milliseconds, relative to now unless `absolute`. Each
+/// field replies -2 if it does not exist, 0 if the condition was not met, 1
+/// if its timeout was set and 2 if it was deleted by a deadline in the past.
+fn expire(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    unit_ms: i128,
+    absolute: bool,
+) -> Box<dyn Serialize> {
+    if options.len() < 5 {
+        return wrong_number_of_arguments(name);
+    }
+    let time = match parse_integer(&options[1]) {
+        Ok(time) => time,
+        Err(e) => return e,
+    };
+    let condition = options[2].0.to_lowercase();
+    let (condition, rest) = match condition.as_str() {
+        "nx" | "xx" | "gt" | "lt" => (Some(condition), &options[3..]),
+        _ => (None, &options[2..]),
+    };
+    let fields = match parse_fields(rest, 1) {
+        Ok(fields) => strings(fields),
+        Err(e) => return e,
+    };
+    let storage = &client.server().storage;
+    let base = if absolute {
+        0
+    } else {
+        storage.now_ms() as i128
+    };
+    let deadline = time as i128 * unit_ms + base;
+    if deadline > i64::MAX as i128 || deadline < i64::MIN as i128 {
+        return error(&format!("invalid expire time in '{}' command", name));
+    }
+    // A deadline before the epoch is simply in the past: the fields are deleted.
+    let deadline = deadline.max(0) as u128;
+    let results = storage.hash_expire_fields(
+        client.db(),
+        &options[0].0,
+        &fields,
+        deadline,
+        |current, new| match condition.as_deref() {
+            Some("nx") => current.is_none(),
+            Some("xx") => current.is_some(),
+            Some("gt") => current.is_some_and(|current| new > current),
+            Some("lt") => current.is_none_or(|current| new < current),
+            _ => true,
+        },
+    );
+    match results {
+        Ok(results) => integers(results.into_iter().map(|result| match result {
+            FieldExpire::NoField => -2,
+            FieldExpire::NotSet => 0,
+            FieldExpire::Set => 1,
+            FieldExpire::Deleted => 2,
+        })),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for HExpireCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("hexpire", &options, client, 1000, false)
+    }
+}
+impl Execute for HPExpireCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("hpexpire", &options, client, 1, false)
+    }
+}
+impl Execute for HExpireAtCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("hexpireat", &options, client, 1000, true)
+    }
+}
+impl Execute for HPExpireAtCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        expire("hpexpireat", &options, client, 1, true)
+    }
+}
+
+/// Shared implementation of `HTTL`, `HPTTL`, `HEXPIRETIME` and
+/// `HPEXPIRETIME`, per field like `TTL` and co are per key.
+fn ttl(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    unit_ms: u128,
+    absolute: bool,
+) -> Box<dyn Serialize> {
+    if options.len() < 4 {
+        return wrong_number_of_arguments(name);
+    }
+    let fields = match parse_fields(&options[1..], 1) {
+        Ok(fields) => strings(fields),
+        Err(e) => return e,
+    };
+    let storage = &client.server().storage;
+    let deadlines = match storage.hash_field_deadlines(client.db(), &options[0].0, &fields) {
+        Ok(deadlines) => deadlines,
+        Err(e) => return storage_error(e),
+    };
+    let now = storage.now_ms();
+    integers(deadlines.into_iter().map(|deadline| match deadline {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(deadline)) => {
+            let time = if absolute {
+                deadline
+            } else {
+                deadline.saturating_sub(now)
+            };
+            ((time + unit_ms / 2) / unit_ms) as i64
+        }
+    }))
+}
+
+impl Execute for HTtlCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("httl", &options, client, 1000, false)
+    }
+}
+impl Execute for HPTtlCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("hpttl", &options, client, 1, false)
+    }
+}
+impl Execute for HExpireTimeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("hexpiretime", &options, client, 1000, true)
+    }
+}
+impl Execute for HPExpireTimeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("hpexpiretime", &options, client, 1, true)
+    }
+}
+impl Execute for HPersistCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 4 {
+            return wrong_number_of_arguments("hpersist");
+        }
+        let fields = match parse_fields(&options[1..], 1) {
+            Ok(fields) => strings(fields),
+            Err(e) => return e,
+        };
+        let storage = &client.server().storage;
+        match storage.hash_persist_fields(client.db(), &options[0].0, &fields) {
+            Ok(results) => integers(results.into_iter().map(|result| match result {
+                None => -2,
+                Some(false) => -1,
+                Some(true) => 1,
+            })),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HGetExCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 4 {
+            return wrong_number_of_arguments("hgetex");
+        }
+        // `None` keeps the timeouts, `PERSIST` is an infinite expiry.
+        let mut expiry: Option<Expiry> = None;
+        let mut index = 1;
+        while let Some(BulkString(option)) = options.get(index) {
+            let option = option.to_lowercase();
+            match option.as_str() {
+                "ex" | "px" | "exat" | "pxat" | "persist" => {
+                    if expiry.is_some() {
+                        return error(
+                            "Only one of EX, PX, EXAT, PXAT or PERSIST arguments can be specified",
+                        );
+                    }
+                    if option == "persist" {
+                        expiry = Some(Expiry::INFINITE);
+                        index += 1;
+                        continue;
+                    }
+                    let Some(time) = options.get(index + 1) else {
+                        return error("syntax error");
+                    };
+                    match parse_expiry("hgetex", &option, time) {
+                        Ok(parsed) => expiry = Some(parsed),
+                        Err(e) => return e,
+                    }
+                    index += 2;
+                }
+                _ => break,
+            }
+        }
+        let fields = match parse_fields(&options[index..], 1) {
+            Ok(fields) => strings(fields),
+            Err(e) => return e,
+        };
+        let storage = &client.server().storage;
+        match storage.hash_get_and_expire(client.db(), &options[0].0, &fields, expiry) {
+            Ok(values) => Box::new(Value::Array(Array(
+                values
+                    .into_iter()
+                    .map(|value| {
+                        value.map_or(Value::Nulls(Nulls), |value| {
+                            Value::BulkString(BulkString(value))
+                        })
+                    })
+                    .collect(),
+            ))),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for HSetExCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 5 {
+            return wrong_number_of_arguments("hsetex");
+        }
+        let mut condition: Option<SetCondition> = None;
+        // Without an option the fields lose their timeout, like with `HSET`.
+        let mut expiry: Option<Option<Expiry>> = None;
+        let mut index = 1;
+        while let Some(BulkString(option)) = options.get(index) {
+            let option = option.to_lowercase();
+            match option.as_str() {
+                "fnx" | "fxx" => {
+                    if condition.is_some() {
+                        return error("Only one of FXX or FNX arguments can be specified");
+                    }
+                    condition = Some(if option == "fnx" {
+                        SetCondition::NX
+                    } else {
+                        SetCondition::XX
+                    });
+                    index += 1;
+                }
+                "ex" | "px" | "exat" | "pxat" | "keepttl" => {
+                    if expiry.is_some() {
+                        return error(
+                            "Only one of EX, PX, EXAT, PXAT or KEEPTTL arguments can be specified",
+                        );
+                    }
+                    if option == "keepttl" {
+                        expiry = Some(None);
+                        index += 1;
+                        continue;
+                    }
+                    let Some(time) = options.get(index + 1) else {
+                        return error("syntax error");
+                    };
+                    match parse_expiry("hsetex", &option, time) {
+                        Ok(parsed) => expiry = Some(Some(parsed)),
+                        Err(e) => return e,
+                    }
+                    index += 2;
+                }
+                _ => break,
+            }
+        }
+        let pairs = match parse_fields(&options[index..], 2) {
+            Ok(fields) => fields
+                .chunks(2)
+                .map(|pair| (pair[0].0.clone(), pair[1].0.clone()))
+                .collect(),
+            Err(e) => return e,
+        };
+        let expiry = expiry.unwrap_or(Some(Expiry::INFINITE));
+        let storage = &client.server().storage;
+        match storage.hash_set_and_expire(client.db(), &options[0].0, pairs, condition, expiry) {
+            Ok(set) => integer(set as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::acl::LogReason;
+use crate::client::Client;
+use crate::command::client::set_name;
+use crate::command::{error, map_reply, Execute};
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::serialize::Serialize;
+use crate::value::simple_error::{ErrorType, SimpleError};
+use crate::value::Value;
+pub struct HelloCommand;
+
+fn bulk(value: &str) -> Value {
+    Value::BulkString(BulkString(value.to_owned()))
+}
+
+fn simple_error(error_type: ErrorType, message: &str) -> Box<dyn Serialize> {
+    Box::new(Value::SimpleError(SimpleError {
+        error_type,
+        message: message.to_owned(),
+    }))
+}
+
+impl Execute for HelloCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        let mut resp = client.resp();
+        let mut auth: Option<(&str, &str)> = None;
+        let mut name: Option<&str> = None;
+        if let Some((BulkString(protover), args)) = options.split_first() {
+            resp = match protover.parse::<i64>() {
+                Ok(protover) if protover == 2 || protover == 3 => protover as u8,
+                Ok(_) => return simple_error(ErrorType::NOPROTO, "unsupported protocol version"),
+                Err(_) => return error("Protocol version is not an integer or out of range"),
+            };
+            let mut args = args.iter();
+            while let Some(BulkString(option)) = args.next() {
+                match (
+                    option.to_lowercase().as_str(),
+                    args.next(),
+                    args.clone().next(),
+                ) {
+                    ("auth", Some(user), Some(password)) => {
+                        args.next();
+                        auth = Some((&user.0, &password.0));
+                    }
+                    ("setname", Some(client_name), _) => name = Some(&client_name.0),
+                    _ => return error(&format!("Syntax error in HELLO option '{}'", option)),
+                }
+            }
+        }
+        if let Some((user, password)) = auth {
+            let acl = &client.server().acl;
+            match acl.authenticate(user, password) {
+                Some(user) => client.authenticate(&user.name),
+                None => {
+                    acl.log(LogReason::Auth, "HELLO", user, client.info().trim_end());
+                    return simple_error(
+                        ErrorType::WRONGPASS,
+                        "invalid username-password pair or user is disabled.",
+                    );
+                }
+            }
+        } else if client.user().is_none() {
+            return simple_error(
+                ErrorType::NOAUTH,
+                "HELLO must be called with the client already authenticated, otherwise the \
+                 HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client \
+                 and select the RESP protocol version at the same time",
+            );
+        }
+        if let Some(name) = name {
+            if let Err(e) = set_name(client, name) {
+                return e;
+            }
+        }
+        client.set_resp(resp);
+        Box::new(map_reply(
+            client,
+            vec![
+                (bulk("server"), bulk("redis")),
+                (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+                (bulk("proto"), Value::Integer(Integer(resp as i64))),
+                (bulk("id"), Value::Integer(Integer(client.id as i64))),
+                (bulk("mode"), bulk("standalone")),
+                (bulk("role"), bulk("master")),
+                (bulk("modules"), Value::Array(Array(Vec::new()))),
+            ],
+        ))
+    }
+}
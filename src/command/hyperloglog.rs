@@ -0,0 +1,53 @@
+use crate::client::Client;
+use crate::command::{integer, ok, storage_error, wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+pub struct PfAddCommand;
+pub struct PfCountCommand;
+pub struct PfMergeCommand;
+
+fn strings(options: &[BulkString]) -> Vec<String> {
+    options.iter().map(|option| option.0.clone()).collect()
+}
+
+impl Execute for PfAddCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.is_empty() {
+            return wrong_number_of_arguments("pfadd");
+        }
+        let elements = strings(&options[1..]);
+        let storage = &client.server().storage;
+        match storage.pf_add(client.db(), &options[0].0, &elements) {
+            Ok(updated) => integer(updated as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for PfCountCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.is_empty() {
+            return wrong_number_of_arguments("pfcount");
+        }
+        match client
+            .server()
+            .storage
+            .pf_count(client.db(), &strings(&options))
+        {
+            Ok(count) => integer(count as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for PfMergeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.is_empty() {
+            return wrong_number_of_arguments("pfmerge");
+        }
+        let sources = strings(&options[1..]);
+        let storage = &client.server().storage;
+        match storage.pf_merge(client.db(), &options[0].0, &sources) {
+            Ok(()) => ok(),
+            Err(e) => storage_error(e),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::client::Client;
+use crate::command::Execute;
+use crate::server::Server;
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+use std::sync::atomic::Ordering;
+pub struct InfoCommand;
+
+const SECTIONS: [&str; 3] = ["clients", "stats", "keyspace"];
+
+fn section(server: &Server, name: &str) -> String {
+    let mut output = String::new();
+    match name {
+        "clients" => {
+            output.push_str("# Clients\r\n");
+            output.push_str(&format!(
+                "connected_clients:{}\r\n",
+                server.clients.clients().len()
+            ));
+            output.push_str(&format!(
+                "blocked_clients:{}\r\n",
+                server.storage.blocked_clients()
+            ));
+        }
+        "stats" => {
+            let stats = &server.storage.expire_stats;
+            output.push_str("# Stats\r\n");
+            output.push_str(&format!(
+                "expired_keys:{}\r\n",
+                stats.expired_keys.load(Ordering::Relaxed)
+            ));
+            output.push_str(&format!(
+                "expired_subkeys:{}\r\n",
+                stats.expired_fields.load(Ordering::Relaxed)
+            ));
+            output.push_str(&format!(
+                "expired_time_cap_reached_count:{}\r\n",
+                stats.time_cap_reached.load(Ordering::Relaxed)
+            ));
+            output.push_str(&format!(
+                "expire_cycle_cpu_milliseconds:{}\r\n",
+                stats.cycle_time_us.load(Ordering::Relaxed) / 1000
+            ));
+        }
+        "keyspace" => {
+            output.push_str("# Keyspace\r\n");
+            for db in 0..server.storage.databases() {
+                if let Some(stats) = server.storage.keyspace_stats(db) {
+                    output.push_str(&format!(
+                        "db{}:keys={},expires={},avg_ttl={}\r\n",
+                        db, stats.keys, stats.expires, stats.avg_ttl
+                    ));
+                }
+            }
+        }
+        _ => {}
+    }
+    output
+}
+
+impl Execute for InfoCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        let requested: Vec<String> = options.iter().map(|o| o.0.to_lowercase()).collect();
+        let all = requested.is_empty()
+            || requested
+                .iter()
+                .any(|r| r == "all" || r == "default" || r == "everything");
+        let sections: Vec<String> = SECTIONS
+            .iter()
+            .filter(|s| all || requested.iter().any(|r| r == *s))
+            .map(|s| section(client.server(), s))
+            .collect();
+        Box::new(Value::BulkString(BulkString(sections.join("\r\n"))))
+    }
+}
@@ -0,0 +1,20 @@
+use crate::client::Client;
+use crate::command::{wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+use crate::value::simple_string::SimpleString;
+use crate::value::Value;
+pub struct TypeCommand;
+impl Execute for TypeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("type");
+        }
+        let type_name = client
+            .server()
+            .storage
+            .type_of(client.db(), &options[0].0)
+            .unwrap_or("none");
+        Box::new(Value::SimpleString(SimpleString(type_name.to_owned())))
+    }
+}
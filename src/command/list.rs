@@ -0,0 +1,422 @@
+use crate::client::Client;
+use crate::command::blocking::{block_on, parse_timeout};
+use crate::command::{
+    bulk_array, bulk_or_null, error, integer, ok, parse_integer, storage_error,
+    wrong_number_of_arguments, Execute,
+};
+use crate::storage::ListEnd;
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct LPushCommand;
+pub struct RPushCommand;
+pub struct LPushXCommand;
+pub struct RPushXCommand;
+pub struct LPopCommand;
+pub struct RPopCommand;
+pub struct LLenCommand;
+pub struct LRangeCommand;
+pub struct LIndexCommand;
+pub struct LSetCommand;
+pub struct LRemCommand;
+pub struct LTrimCommand;
+pub struct LInsertCommand;
+pub struct LPosCommand;
+pub struct LMoveCommand;
+pub struct LMPopCommand;
+pub struct BLPopCommand;
+pub struct BRPopCommand;
+pub struct BLMoveCommand;
+pub struct BLMPopCommand;
+
+fn parse_end(value: &BulkString) -> Result<ListEnd, Box<dyn Serialize>> {
+    ListEnd::try_from(value.0.as_str()).map_err(|_| error("syntax error"))
+}
+
+/// Parses a count that must be positive, with the given error message.
+fn parse_count(value: &BulkString, message: &str) -> Result<usize, Box<dyn Serialize>> {
+    match parse_integer(value)? {
+        count if count < 0 => Err(error(message)),
+        count => Ok(count as usize),
+    }
+}
+
+/// Shared implementation of `LPUSH`, `RPUSH`, `LPUSHX` and `RPUSHX`.
+fn push(
+    name: &str,
+    options: Vec<BulkString>,
+    client: &Client,
+    end: ListEnd,
+    only_if_exists: bool,
+) -> Box<dyn Serialize> {
+    if options.len() < 2 {
+        return wrong_number_of_arguments(name);
+    }
+    let mut options = options.into_iter().map(|option| option.0);
+    let key = options.next().unwrap_or_default();
+    let storage = &client.server().storage;
+    match storage.list_push(client.db(), &key, end, options.collect(), only_if_exists) {
+        Ok(len) => integer(len as i64),
+        Err(e) => storage_error(e),
+    }
+}
+
+/// Shared implementation of `LPOP` and `RPOP`: a single element without a
+/// count, an array with one.
+fn pop(name: &str, options: &[BulkString], client: &Client, end: ListEnd) -> Box<dyn Serialize> {
+    if options.is_empty() || options.len() > 2 {
+        return wrong_number_of_arguments(name);
+    }
+    let count = match options.get(1) {
+        Some(count) => Some(parse!(parse_count(
+            count,
+            "value is out of range, must be positive"
+        ))),
+        None => None,
+    };
+    let storage = &client.server().storage;
+    match storage.list_pop(client.db(), &options[0].0, end, count.unwrap_or(1)) {
+        Ok(None) => Box::new(Value::Nulls(Nulls)),
+        Ok(Some(elements)) if count.is_some() => Box::new(bulk_array(elements)),
+        Ok(Some(elements)) => bulk_or_null(elements.into_iter().next()),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for LPushCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        push("lpush", options, client, ListEnd::Left, false)
+    }
+}
+impl Execute for RPushCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        push("rpush", options, client, ListEnd::Right, false)
+    }
+}
+impl Execute for LPushXCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        push("lpushx", options, client, ListEnd::Left, true)
+    }
+}
+impl Execute for RPushXCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        push("rpushx", options, client, ListEnd::Right, true)
+    }
+}
+impl Execute for LPopCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        pop("lpop", &options, client, ListEnd::Left)
+    }
+}
+impl Execute for RPopCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        pop("rpop", &options, client, ListEnd::Right)
+    }
+}
+impl Execute for LLenCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("llen");
+        }
+        match client.server().storage.list_len(client.db(), &options[0].0) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for LRangeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("lrange");
+        }
+        let start = parse!(parse_integer(&options[1]));
+        let stop = parse!(parse_integer(&options[2]));
+        let storage = &client.server().storage;
+        match storage.list_range(client.db(), &options[0].0, start, stop) {
+            Ok(elements) => Box::new(bulk_array(elements)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for LIndexCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("lindex");
+        }
+        let index = parse!(parse_integer(&options[1]));
+        match client
+            .server()
+            .storage
+            .list_index(client.db(), &options[0].0, index)
+        {
+            Ok(element) => bulk_or_null(element),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for LSetCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("lset");
+        }
+        let index = parse!(parse_integer(&options[1]));
+        let storage = &client.server().storage;
+        match storage.list_set(client.db(), &options[0].0, index, options[2].0.clone()) {
+            Ok(()) => ok(),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for LRemCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("lrem");
+        }
+        let count = parse!(parse_integer(&options[1]));
+        let storage = &client.server().storage;
+        match storage.list_remove(client.db(), &options[0].0, count, &options[2].0) {
+            Ok(removed) => integer(removed as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for LTrimCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("ltrim");
+        }
+        let start = parse!(parse_integer(&options[1]));
+        let stop = parse!(parse_integer(&options[2]));
+        match client
+            .server()
+            .storage
+            .list_trim(client.db(), &options[0].0, start, stop)
+        {
+            Ok(()) => ok(),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for LInsertCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 4 {
+            return wrong_number_of_arguments("linsert");
+        }
+        let before = match options[1].0.to_lowercase().as_str() {
+            "before" => true,
+            "after" => false,
+            _ => return error("syntax error"),
+        };
+        let storage = &client.server().storage;
+        let (key, pivot, element) = (&options[0].0, &options[2].0, options[3].0.clone());
+        match storage.list_insert(client.db(), key, before, pivot, element) {
+            Ok(len) => integer(len),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for LPosCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("lpos");
+        }
+        let (mut rank, mut count, mut max_len) = (1, None, 0);
+        let mut options_iter = options[2..].iter();
+        while let Some(BulkString(option)) = options_iter.next() {
+            let Some(value) = options_iter.next() else {
+                return error("syntax error");
+            };
+            match option.to_lowercase().as_str() {
+                "rank" => {
+                    rank = parse!(parse_integer(value));
+                    if rank == 0 {
+                        return error(
+                            "RANK can't be zero: use 1 to start from the first match, \
+                             2 from the second ... or use negative to start from the end of the list",
+                        );
+                    }
+                }
+                "count" => count = Some(parse!(parse_count(value, "COUNT can't be negative"))),
+                "maxlen" => max_len = parse!(parse_count(value, "MAXLEN can't be negative")),
+                _ => return error("syntax error"),
+            }
+        }
+        let storage = &client.server().storage;
+        let positions = match storage.list_positions(
+            client.db(),
+            &options[0].0,
+            &options[1].0,
+            rank,
+            count.unwrap_or(1),
+            max_len,
+        ) {
+            Ok(positions) => positions,
+            Err(e) => return storage_error(e),
+        };
+        match (count, positions.first()) {
+            (Some(_), _) => Box::new(Value::Array(Array(
+                positions
+                    .into_iter()
+                    .map(|position| Value::Integer(Integer(position as i64)))
+                    .collect(),
+            ))),
+            (None, Some(position)) => integer(*position as i64),
+            (None, None) => Box::new(Value::Nulls(Nulls)),
+        }
+    }
+}
+impl Execute for LMoveCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 4 {
+            return wrong_number_of_arguments("lmove");
+        }
+        let from = parse!(parse_end(&options[2]));
+        let to = parse!(parse_end(&options[3]));
+        let storage = &client.server().storage;
+        match storage.list_move(client.db(), &options[0].0, &options[1].0, from, to) {
+            Ok(element) => bulk_or_null(element),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+/// Parses the arguments of `LMPOP` and `BLMPOP` from `numkeys` on.
+fn parse_multi_pop(
+    options: &[BulkString],
+) -> Result<(Vec<String>, ListEnd, usize), Box<dyn Serialize>> {
+    let numkeys = match options[0].0.parse::<usize>() {
+        Ok(numkeys) if numkeys > 0 => numkeys,
+        _ => return Err(error("numkeys should be greater than 0")),
+    };
+    if numkeys > options.len() - 1 {
+        return Err(error("Number of keys can't be greater than number of args"));
+    }
+    let Some(end) = options.get(numkeys + 1) else {
+        return Err(error("syntax error"));
+    };
+    let end = parse_end(end)?;
+    let count = match &options[numkeys + 2..] {
+        [] => 1,
+        [BulkString(option), count] if option.eq_ignore_ascii_case("count") => {
+            match count.0.parse::<usize>() {
+                Ok(count) if count > 0 => count,
+                _ => return Err(error("count should be greater than 0")),
+            }
+        }
+        _ => return Err(error("syntax error")),
+    };
+    let keys = options[1..=numkeys]
+        .iter()
+        .map(|key| key.0.clone())
+        .collect();
+    Ok((keys, end, count))
+}
+
+/// Reply of `LMPOP` and `BLMPOP`: the key and the popped elements.
+fn multi_pop_reply(key: String, elements: Vec<String>) -> Value {
+    Value::Array(Array(vec![
+        Value::BulkString(BulkString(key)),
+        bulk_array(elements),
+    ]))
+}
+
+impl Execute for LMPopCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 {
+            return wrong_number_of_arguments("lmpop");
+        }
+        let (keys, end, count) = parse!(parse_multi_pop(&options));
+        match client
+            .server()
+            .storage
+            .list_multi_pop(client.db(), &keys, end, count)
+        {
+            Ok(Some((key, elements))) => Box::new(multi_pop_reply(key, elements)),
+            Ok(None) => Box::new(Value::Nulls(Nulls)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// Shared implementation of `BLPOP` and `BRPOP`.
+fn blocking_pop(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    end: ListEnd,
+) -> Box<dyn Serialize> {
+    let Some((timeout, keys)) = options.split_last().filter(|(_, keys)| !keys.is_empty()) else {
+        return wrong_number_of_arguments(name);
+    };
+    let timeout = parse!(parse_timeout(timeout));
+    let keys: Vec<String> = keys.iter().map(|key| key.0.clone()).collect();
+    let db = client.db();
+    let popped = keys.clone();
+    block_on(
+        client,
+        keys,
+        timeout,
+        Box::new(move |storage| {
+            let popped = storage.list_multi_pop(db, &popped, end, 1)?;
+            Ok(popped.map(|(key, elements)| {
+                let mut reply = vec![key];
+                reply.extend(elements);
+                bulk_array(reply)
+            }))
+        }),
+    )
+}
+
+impl Execute for BLPopCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        blocking_pop("blpop", &options, client, ListEnd::Left)
+    }
+}
+impl Execute for BRPopCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        blocking_pop("brpop", &options, client, ListEnd::Right)
+    }
+}
+impl Execute for BLMoveCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 5 {
+            return wrong_number_of_arguments("blmove");
+        }
+        let from = parse!(parse_end(&options[2]));
+        let to = parse!(parse_end(&options[3]));
+        let timeout = parse!(parse_timeout(&options[4]));
+        let (source, destination) = (options[0].0.clone(), options[1].0.clone());
+        let db = client.db();
+        block_on(
+            client,
+            vec![source.clone()],
+            timeout,
+            Box::new(move |storage| {
+                let moved = storage.list_move(db, &source, &destination, from, to)?;
+                Ok(moved.map(|element| Value::BulkString(BulkString(element))))
+            }),
+        )
+    }
+}
+impl Execute for BLMPopCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 4 {
+            return wrong_number_of_arguments("blmpop");
+        }
+        let timeout = parse!(parse_timeout(&options[0]));
+        let (keys, end, count) = parse!(parse_multi_pop(&options[1..]));
+        let db = client.db();
+        let popped = keys.clone();
+        block_on(
+            client,
+            keys,
+            timeout,
+            Box::new(move |storage| {
+                let popped = storage.list_multi_pop(db, &popped, end, count)?;
+                Ok(popped.map(|(key, elements)| multi_pop_reply(key, elements)))
+            }),
+        )
+    }
+}
@@ -0,0 +1,25 @@
+use crate::client::Client;
+use crate::command::select::parse_db_index;
+use crate::command::{error, wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct MoveCommand;
+impl Execute for MoveCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("move");
+        }
+        let dst = match parse_db_index(client, &options[1].0, "DB index is out of range") {
+            Ok(db) => db,
+            Err(e) => return e,
+        };
+        let src = client.db();
+        if src == dst {
+            return error("source and destination objects are the same");
+        }
+        let moved = client.server().storage.move_key(src, dst, &options[0].0);
+        Box::new(Value::Integer(Integer(moved as i64)))
+    }
+}
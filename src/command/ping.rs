@@ -1,3 +1,4 @@
+use crate::client::Client;
 use crate::command::Execute;
 use crate::value::bulk_string::BulkString;
 use crate::value::serialize::Serialize;
@@ -6,7 +7,7 @@ use crate::value::simple_string::SimpleString;
 use crate::value::Value;
 pub struct PingCommand;
 impl Execute for PingCommand {
-    fn execute(self, options: Vec<BulkString>) -> Box<dyn Serialize> {
+    fn execute(self, options: Vec<BulkString>, _client: &Client) -> Box<dyn Serialize> {
         let options_count = options.len();
         if options_count > 1 {
             return Box::new(Value::SimpleError(SimpleError {
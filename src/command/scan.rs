@@ -0,0 +1,65 @@
+use crate::command::{bulk_array, error};
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+
+/// Arguments of `HSCAN`, `SSCAN` and `ZSCAN` after the key.
+pub(crate) struct ScanOptions {
+    pub cursor: u64,
+    pub count: usize,
+    pub pattern: Option<String>,
+    /// `NOVALUES`, only accepted by `HSCAN`.
+    pub no_values: bool,
+}
+
+pub(crate) fn parse_scan_options(
+    options: &[BulkString],
+    allow_no_values: bool,
+) -> Result<ScanOptions, Box<dyn Serialize>> {
+    let Some((BulkString(cursor), options)) = options.split_first() else {
+        return Err(error("syntax error"));
+    };
+    let Ok(cursor) = cursor.parse::<u64>() else {
+        return Err(error("invalid cursor"));
+    };
+    let mut scan_options = ScanOptions {
+        cursor,
+        count: 10,
+        pattern: None,
+        no_values: false,
+    };
+    let mut options_iter = options.iter();
+    while let Some(BulkString(option)) = options_iter.next() {
+        match option.to_lowercase().as_str() {
+            "match" => {
+                let Some(BulkString(pattern)) = options_iter.next() else {
+                    return Err(error("syntax error"));
+                };
+                // `*` matches everything, no need to compare.
+                scan_options.pattern = (pattern != "*").then(|| pattern.clone());
+            }
+            "count" => {
+                let Some(BulkString(count)) = options_iter.next() else {
+                    return Err(error("syntax error"));
+                };
+                match count.parse::<i64>() {
+                    Ok(count) if count >= 1 => scan_options.count = count as usize,
+                    Ok(_) => return Err(error("syntax error")),
+                    Err(_) => return Err(error("value is not an integer or out of range")),
+                }
+            }
+            "novalues" if allow_no_values => scan_options.no_values = true,
+            _ => return Err(error("syntax error")),
+        }
+    }
+    Ok(scan_options)
+}
+
+/// Reply of the scan commands: the next cursor and the elements.
+pub(crate) fn scan_reply(cursor: u64, elements: Vec<String>) -> Box<dyn Serialize> {
+    Box::new(Value::Array(Array(vec![
+        Value::BulkString(BulkString(cursor.to_string())),
+        bulk_array(elements),
+    ])))
+}
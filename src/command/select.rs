@@ -0,0 +1,34 @@
+use crate::client::Client;
+use crate::command::{error, ok, wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+pub struct SelectCommand;
+
+/// Parses a database index argument, returning the error reply on failure.
+pub fn parse_db_index(
+    client: &Client,
+    value: &str,
+    message: &str,
+) -> Result<usize, Box<dyn Serialize>> {
+    let databases = client.server().storage.databases();
+    match value.parse::<i64>() {
+        Ok(index) if index >= 0 && (index as usize) < databases => Ok(index as usize),
+        Ok(_) => Err(error(message)),
+        Err(_) => Err(error("value is not an integer or out of range")),
+    }
+}
+
+impl Execute for SelectCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("select");
+        }
+        match parse_db_index(client, &options[0].0, "DB index is out of range") {
+            Ok(db) => {
+                client.select(db);
+                ok()
+            }
+            Err(e) => e,
+        }
+    }
+}
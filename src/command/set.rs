@@ -1,339 +1,86 @@
-use crate::command::Execute;
+use crate::client::Client;
+use crate::command::{error, storage_error, wrong_number_of_arguments, wrong_type, Execute};
+use crate::db::{SetCondition, SetOptions};
 use crate::storage::Expiry;
-use crate::storage::{contains_key, insert};
-use crate::value::bulk_string::BulkString;
+use crate::value::bulk_string::{BulkBytes, BulkString};
 use crate::value::nulls::Nulls;
 use crate::value::serialize::Serialize;
-use crate::value::simple_error::{ErrorType, SimpleError};
 use crate::value::simple_string::SimpleString;
 use crate::value::Value;
 pub struct SetCommand;
-#[derive(Debug)]
-enum SetCondition {
-    NX,
-    XX,
-}
-type Seconds = u64;
-type MilliSeconds = u64;
-type EpochSeconds = u128;
-type EpochMilliSeconds = u128;
-#[derive(Debug)]
-enum ExpiryTime {
-    EX(Seconds),
-    PX(MilliSeconds),
-    EXAT(EpochSeconds),
-    PXAT(EpochMilliSeconds),
-}
-impl Execute for SetCommand {
-    fn execute(self, options: Vec<BulkString>) -> Box<dyn Serialize> {
-        let options_count = options.len();
-        let mut set_condition: Option<SetCondition> = None;
-        let mut expiry_time: Option<ExpiryTime> = None;
-        let key = options.get(0).cloned().unwrap().0;
-        let value = options.get(1).cloned().unwrap().0;
-        match options_count {
-            0 | 1 => {
-                return Box::new(Value::SimpleError(SimpleError {
-                    error_type: ErrorType::try_from("ERR").unwrap(),
-                    message: String::from("wrong number of arguments for 'echo' command"),
-                }));
-            }
-            2 => {
-                let _ = insert(key, value, Expiry::INFINITE);
-                return Box::new(Value::SimpleString(SimpleString(String::from("Ok"))));
-            }
-            3..=6 => {
-                let mut options_iter = options.iter();
-                options_iter.next();
-                options_iter.next();
-                while let Some(bs) = options_iter.next() {
-                    match bs {
-                        BulkString(x) if x.to_lowercase() == String::from("nx") => {
-                            match set_condition {
-                                Some(SetCondition::NX) => continue,
-                                Some(SetCondition::XX) => {
-                                    return Box::new(Value::SimpleError(SimpleError {
-                                        error_type: ErrorType::try_from("ERR").unwrap(),
-                                        message: String::from("syntax error"),
-                                    }));
-                                }
-                                None => set_condition = Some(SetCondition::NX),
-                            }
-                        }
-                        BulkString(x) if x.to_lowercase() == String::from("xx") => {
-                            match set_condition {
-                                Some(SetCondition::XX) => continue,
-                                Some(SetCondition::NX) => {
-                                    return Box::new(Value::SimpleError(SimpleError {
-                                        error_type: ErrorType::try_from("ERR").unwrap(),
-                                        message: String::from("syntax error"),
-                                    }));
-                                }
-                                None => set_condition = Some(SetCondition::XX),
-                            }
-                        }
-                        BulkString(x) if x.to_lowercase() == String::from("ex") => {
-                            if expiry_time.is_some() {
-                                return Box::new(Value::SimpleError(SimpleError {
-                                    error_type: ErrorType::try_from("ERR").unwrap(),
-                                    message: String::from("syntax error"),
-                                }));
-                            } else {
-                                let val = options_iter.next().cloned();
-                                if val.is_none() {
-                                    return Box::new(Value::SimpleError(SimpleError {
-                                        error_type: ErrorType::try_from("ERR").unwrap(),
-                                        message: String::from("syntax error"),
-                                    }));
-                                } else {
-                                    if let Ok(time_value) = val.unwrap().0.parse::<u64>() {
-                                        expiry_time = Some(ExpiryTime::EX(time_value));
-                                    } else {
-                                        return Box::new(Value::SimpleError(SimpleError {
-                                            error_type: ErrorType::try_from("ERR").unwrap(),
-                                            message: String::from(
-                                                "value is not an integer or out of range",
-                                            ),
-                                        }));
-                                    }
-                                }
-                            }
-                        }
-                        BulkString(x) if x.to_lowercase() == String::from("px") => {
-                            if expiry_time.is_some() {
-                                return Box::new(Value::SimpleError(SimpleError {
-                                    error_type: ErrorType::try_from("ERR").unwrap(),
-                                    message: String::from("syntax error"),
-                                }));
-                            } else {
-                                {
-                                    let val = options_iter.next().cloned();
-                                    if val.is_none() {
-                                        return Box::new(Value::SimpleError(SimpleError {
-                                            error_type: ErrorType::try_from("ERR").unwrap(),
-                                            message: String::from("syntax error"),
-                                        }));
-                                    } else {
-                                        if let Ok(time_value) = val.unwrap().0.parse::<u64>() {
-                                            expiry_time = Some(ExpiryTime::PX(time_value));
-                                        } else {
-                                            return Box::new(Value::SimpleError(SimpleError {
-                                                error_type: ErrorType::try_from("ERR").unwrap(),
-                                                message: String::from(
-                                                    "value is not an integer or out of range",
-                                                ),
-                                            }));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        BulkString(x) if x.to_lowercase() == String::from("exat") => {
-                            if expiry_time.is_some() {
-                                return Box::new(Value::SimpleError(SimpleError {
-                                    error_type: ErrorType::try_from("ERR").unwrap(),
-                                    message: String::from("syntax error"),
-                                }));
-                            } else {
-                                {
-                                    let val = options_iter.next().cloned();
-                                    if val.is_none() {
-                                        return Box::new(Value::SimpleError(SimpleError {
-                                            error_type: ErrorType::try_from("ERR").unwrap(),
-                                            message: String::from("syntax error"),
-                                        }));
-                                    } else {
-                                        if let Ok(time_value) = val.unwrap().0.parse::<u128>() {
-                                            expiry_time = Some(ExpiryTime::EXAT(time_value));
-                                        } else {
-                                            return Box::new(Value::SimpleError(SimpleError {
-                                                error_type: ErrorType::try_from("ERR").unwrap(),
-                                                message: String::from(
-                                                    "value is not an integer or out of range",
-                                                ),
-                                            }));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        BulkString(x) if x.to_lowercase() == String::from("pxat") => {
-                            if expiry_time.is_some() {
-                                return Box::new(Value::SimpleError(SimpleError {
-                                    error_type: ErrorType::try_from("ERR").unwrap(),
-                                    message: String::from("syntax error"),
-                                }));
-                            } else {
-                                {
-                                    let val = options_iter.next().cloned();
-                                    if val.is_none() {
-                                        return Box::new(Value::SimpleError(SimpleError {
-                                            error_type: ErrorType::try_from("ERR").unwrap(),
-                                            message: String::from("syntax error"),
-                                        }));
-                                    } else {
-                                        if let Ok(time_value) = val.unwrap().0.parse::<u128>() {
-                                            expiry_time = Some(ExpiryTime::PXAT(time_value));
-                                        } else {
-                                            return Box::new(Value::SimpleError(SimpleError {
-                                                error_type: ErrorType::try_from("ERR").unwrap(),
-                                                message: String::from(
-                                                    "value is not an integer or out of range",
-                                                ),
-                                            }));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        _ => {
-                            return Box::new(Value::SimpleError(SimpleError {
-                                error_type: ErrorType::try_from("ERR").unwrap(),
-                                message: String::from("syntax error"),
-                            }));
-                        }
-                    }
-                }
 
-                match expiry_time {
-                    None => match set_condition {
-                        Some(SetCondition::NX) => {
-                            if contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::INFINITE);
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        Some(SetCondition::XX) => {
-                            if !contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::INFINITE);
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        None => {
-                            let _ = insert(key, value, Expiry::INFINITE);
-                            return Box::new(Value::SimpleString(SimpleString(String::from("Ok"))));
-                        }
-                    },
-                    Some(ExpiryTime::EX(ex)) => match set_condition {
-                        Some(SetCondition::NX) => {
-                            if contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::DURATION(ex * 1000));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        Some(SetCondition::XX) => {
-                            if !contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::DURATION(ex * 1000));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        None => {
-                            let _ = insert(key, value, Expiry::DURATION(ex * 1000));
-                            return Box::new(Value::SimpleString(SimpleString(String::from("Ok"))));
-                        }
-                    },
-                    Some(ExpiryTime::PX(px)) => match set_condition {
-                        Some(SetCondition::NX) => {
-                            if contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::DURATION(px));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        Some(SetCondition::XX) => {
-                            if !contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::DURATION(px));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        None => {
-                            let _ = insert(key, value, Expiry::DURATION(px));
-                            return Box::new(Value::SimpleString(SimpleString(String::from("Ok"))));
-                        }
-                    },
-                    Some(ExpiryTime::EXAT(exat)) => match set_condition {
-                        Some(SetCondition::NX) => {
-                            if contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::EPOCH(exat * 1000));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        Some(SetCondition::XX) => {
-                            if !contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::EPOCH(exat * 1000));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        None => {
-                            let _ = insert(key, value, Expiry::EPOCH(exat * 1000));
-                            return Box::new(Value::SimpleString(SimpleString(String::from("Ok"))));
-                        }
-                    },
-                    Some(ExpiryTime::PXAT(pxat)) => match set_condition {
-                        Some(SetCondition::NX) => {
-                            if contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::EPOCH(pxat));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        Some(SetCondition::XX) => {
-                            if !contains_key(&key) {
-                                return Box::new(Value::Nulls(Nulls));
-                            } else {
-                                let _ = insert(key, value, Expiry::EPOCH(pxat));
-                                return Box::new(Value::SimpleString(SimpleString(String::from(
-                                    "Ok",
-                                ))));
-                            }
-                        }
-                        None => {
-                            let _ = insert(key, value, Expiry::EPOCH(pxat));
-                            return Box::new(Value::SimpleString(SimpleString(String::from("Ok"))));
-                        }
-                    },
+/// Parses the options following the key and the value, plus whether `GET` was given.
+fn parse_options(options: &[BulkString]) -> Result<(SetOptions, bool), Box<dyn Serialize>> {
+    let mut set_condition: Option<SetCondition> = None;
+    let mut expiry: Option<Expiry> = None;
+    let mut get = false;
+    let mut options_iter = options.iter();
+    while let Some(BulkString(option)) = options_iter.next() {
+        let option = option.to_lowercase();
+        match option.as_str() {
+            "nx" | "xx" => {
+                let condition = if option == "nx" {
+                    SetCondition::NX
+                } else {
+                    SetCondition::XX
+                };
+                if set_condition.is_some_and(|current| current != condition) {
+                    return Err(error("syntax error"));
                 }
+                set_condition = Some(condition);
             }
-            _ => {
-                return Box::new(Value::SimpleError(SimpleError {
-                    error_type: ErrorType::try_from("ERR").unwrap(),
-                    message: String::from("syntax error"),
-                }));
+            "get" => get = true,
+            "ex" | "px" | "exat" | "pxat" => {
+                if expiry.is_some() {
+                    return Err(error("syntax error"));
+                }
+                let Some(BulkString(time_value)) = options_iter.next() else {
+                    return Err(error("syntax error"));
+                };
+                let Ok(time_value) = time_value.parse::<u64>() else {
+                    return Err(error("value is not an integer or out of range"));
+                };
+                expiry = Some(match option.as_str() {
+                    "ex" => Expiry::DURATION(time_value * 1000),
+                    "px" => Expiry::DURATION(time_value),
+                    "exat" => Expiry::EPOCH(time_value as u128 * 1000),
+                    _ => Expiry::EPOCH(time_value as u128),
+                });
             }
+            _ => return Err(error("syntax error")),
+        }
+    }
+    let set_options = SetOptions {
+        condition: set_condition,
+        expiry: expiry.unwrap_or(Expiry::INFINITE),
+    };
+    Ok((set_options, get))
+}
+
+impl Execute for SetCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("set");
+        }
+        let (set_options, get) = match parse_options(&options[2..]) {
+            Ok(parsed) => parsed,
+            Err(e) => return e,
+        };
+        let db = match client.server().db(client.db()) {
+            Ok(db) => db,
+            Err(e) => return storage_error(e),
+        };
+        if get {
+            return match db.set_get(&options[0].0, &options[1].0, set_options) {
+                Ok(Some(previous)) => Box::new(Value::BulkBytes(BulkBytes(previous))),
+                Ok(None) => Box::new(Value::Nulls(Nulls)),
+                Err(_) => wrong_type(),
+            };
+        }
+        if db.set_with(&options[0].0, &options[1].0, set_options) {
+            Box::new(Value::SimpleString(SimpleString(String::from("Ok"))))
+        } else {
+            Box::new(Value::Nulls(Nulls))
         }
     }
 }
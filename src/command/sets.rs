@@ -0,0 +1,298 @@
+use crate::client::Client;
+use crate::command::scan::{parse_scan_options, scan_reply};
+use crate::command::{
+    bulk_array, bulk_or_null, error, integer, parse_integer, storage_error,
+    wrong_number_of_arguments, Execute,
+};
+use crate::storage::SetOperation;
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct SAddCommand;
+pub struct SRemCommand;
+pub struct SMembersCommand;
+pub struct SIsMemberCommand;
+pub struct SMIsMemberCommand;
+pub struct SCardCommand;
+pub struct SPopCommand;
+pub struct SRandMemberCommand;
+pub struct SMoveCommand;
+pub struct SInterCommand;
+pub struct SUnionCommand;
+pub struct SDiffCommand;
+pub struct SInterStoreCommand;
+pub struct SUnionStoreCommand;
+pub struct SDiffStoreCommand;
+pub struct SInterCardCommand;
+pub struct SScanCommand;
+
+fn strings(options: &[BulkString]) -> Vec<String> {
+    options.iter().map(|option| option.0.clone()).collect()
+}
+
+impl Execute for SAddCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("sadd");
+        }
+        let storage = &client.server().storage;
+        match storage.set_add(client.db(), &options[0].0, strings(&options[1..])) {
+            Ok(added) => integer(added as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SRemCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("srem");
+        }
+        let storage = &client.server().storage;
+        match storage.set_remove(client.db(), &options[0].0, &strings(&options[1..])) {
+            Ok(removed) => integer(removed as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SMembersCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("smembers");
+        }
+        match client
+            .server()
+            .storage
+            .set_members(client.db(), &options[0].0)
+        {
+            Ok(members) => Box::new(bulk_array(members)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SIsMemberCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("sismember");
+        }
+        let storage = &client.server().storage;
+        match storage.set_contains(client.db(), &options[0].0, &strings(&options[1..])) {
+            Ok(found) => integer(found[0] as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SMIsMemberCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("smismember");
+        }
+        let storage = &client.server().storage;
+        match storage.set_contains(client.db(), &options[0].0, &strings(&options[1..])) {
+            Ok(found) => Box::new(Value::Array(Array(
+                found
+                    .into_iter()
+                    .map(|found| Value::Integer(Integer(found as i64)))
+                    .collect(),
+            ))),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SCardCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("scard");
+        }
+        match client.server().storage.set_len(client.db(), &options[0].0) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SPopCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.is_empty() || options.len() > 2 {
+            return wrong_number_of_arguments("spop");
+        }
+        let count = match options.get(1).map(parse_integer) {
+            Some(Ok(count)) if count < 0 => {
+                return error("value is out of range, must be positive")
+            }
+            Some(Ok(count)) => Some(count as usize),
+            Some(Err(e)) => return e,
+            None => None,
+        };
+        let storage = &client.server().storage;
+        match storage.set_pop(client.db(), &options[0].0, count.unwrap_or(1)) {
+            Ok(popped) if count.is_none() => {
+                bulk_or_null(popped.and_then(|mut popped| popped.pop()))
+            }
+            Ok(popped) => Box::new(bulk_array(popped.unwrap_or_default())),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SRandMemberCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.is_empty() || options.len() > 2 {
+            return wrong_number_of_arguments("srandmember");
+        }
+        let count = match options.get(1).map(parse_integer) {
+            // Like Redis, counts that could not be replied are refused.
+            Some(Ok(count)) if !(-i64::MAX / 2..=i64::MAX / 2).contains(&count) => {
+                return error("value is out of range")
+            }
+            Some(Ok(count)) => Some(count),
+            Some(Err(e)) => return e,
+            None => None,
+        };
+        let storage = &client.server().storage;
+        match storage.set_random_members(client.db(), &options[0].0, count.unwrap_or(1)) {
+            Ok(members) if count.is_none() => bulk_or_null(members.into_iter().next()),
+            Ok(members) => Box::new(bulk_array(members)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SMoveCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("smove");
+        }
+        let storage = &client.server().storage;
+        let (source, destination, member) = (&options[0].0, &options[1].0, &options[2].0);
+        match storage.set_move(client.db(), source, destination, member) {
+            Ok(moved) => integer(moved as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// Shared implementation of `SINTER`, `SUNION` and `SDIFF`.
+fn combine(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    operation: SetOperation,
+) -> Box<dyn Serialize> {
+    if options.is_empty() {
+        return wrong_number_of_arguments(name);
+    }
+    let storage = &client.server().storage;
+    match storage.set_combine(client.db(), &strings(options), operation) {
+        Ok(members) => Box::new(bulk_array(members)),
+        Err(e) => storage_error(e),
+    }
+}
+
+/// Shared implementation of `SINTERSTORE`, `SUNIONSTORE` and `SDIFFSTORE`.
+fn combine_store(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    operation: SetOperation,
+) -> Box<dyn Serialize> {
+    if options.len() < 2 {
+        return wrong_number_of_arguments(name);
+    }
+    let storage = &client.server().storage;
+    let keys = strings(&options[1..]);
+    match storage.set_combine_store(client.db(), &options[0].0, &keys, operation) {
+        Ok(len) => integer(len as i64),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for SInterCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine("sinter", &options, client, SetOperation::Inter)
+    }
+}
+impl Execute for SUnionCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine("sunion", &options, client, SetOperation::Union)
+    }
+}
+impl Execute for SDiffCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine("sdiff", &options, client, SetOperation::Diff)
+    }
+}
+impl Execute for SInterStoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine_store("sinterstore", &options, client, SetOperation::Inter)
+    }
+}
+impl Execute for SUnionStoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine_store("sunionstore", &options, client, SetOperation::Union)
+    }
+}
+impl Execute for SDiffStoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine_store("sdiffstore", &options, client, SetOperation::Diff)
+    }
+}
+/// Parses the arguments of `SINTERCARD` and `ZINTERCARD`: `numkeys key
+/// [key ...] [LIMIT limit]`. Returns the keys and the limit, 0 for none.
+pub(crate) fn parse_intercard(
+    options: &[BulkString],
+) -> Result<(Vec<String>, usize), Box<dyn Serialize>> {
+    let numkeys = match options[0].0.parse::<usize>() {
+        Ok(numkeys) if numkeys > 0 => numkeys,
+        _ => return Err(error("numkeys should be greater than 0")),
+    };
+    if numkeys > options.len() - 1 {
+        return Err(error("Number of keys can't be greater than number of args"));
+    }
+    let limit = match &options[numkeys + 1..] {
+        [] => 0,
+        [BulkString(option), limit] if option.eq_ignore_ascii_case("limit") => limit
+            .0
+            .parse::<usize>()
+            .map_err(|_| error("LIMIT can't be negative"))?,
+        _ => return Err(error("syntax error")),
+    };
+    Ok((strings(&options[1..=numkeys]), limit))
+}
+
+impl Execute for SInterCardCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("sintercard");
+        }
+        let (keys, limit) = match parse_intercard(&options) {
+            Ok(parsed) => parsed,
+            Err(e) => return e,
+        };
+        let storage = &client.server().storage;
+        match storage.set_intersection_len(client.db(), &keys, limit) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for SScanCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("sscan");
+        }
+        let scan_options = match parse_scan_options(&options[1..], false) {
+            Ok(scan_options) => scan_options,
+            Err(e) => return e,
+        };
+        let storage = &client.server().storage;
+        match storage.set_scan(
+            client.db(),
+            &options[0].0,
+            scan_options.cursor,
+            scan_options.count,
+            scan_options.pattern.as_deref(),
+        ) {
+            Ok((cursor, members)) => scan_reply(cursor, members),
+            Err(e) => storage_error(e),
+        }
+    }
+}
@@ -0,0 +1,679 @@
+use crate::client::Client;
+use crate::command::blocking::{block_on, parse_timeout};
+use crate::command::scan::{parse_scan_options, scan_reply};
+use crate::command::sets::parse_intercard;
+use crate::command::{
+    bulk_or_null, error, integer, parse_integer, storage_error, wrong_number_of_arguments, Execute,
+};
+use crate::db::SetCondition;
+use crate::storage::{Aggregate, RangeBy, SetOperation, ZAddOptions, ZCombine, ZRange};
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+use std::ops::Bound;
+pub struct ZAddCommand;
+pub struct ZRemCommand;
+pub struct ZScoreCommand;
+pub struct ZMScoreCommand;
+pub struct ZIncrByCommand;
+pub struct ZCardCommand;
+pub struct ZCountCommand;
+pub struct ZRankCommand;
+pub struct ZRevRankCommand;
+pub struct ZRangeCommand;
+pub struct ZRangeStoreCommand;
+pub struct ZPopMinCommand;
+pub struct ZPopMaxCommand;
+pub struct BZPopMinCommand;
+pub struct BZPopMaxCommand;
+pub struct ZRemRangeByRankCommand;
+pub struct ZRemRangeByScoreCommand;
+pub struct ZRemRangeByLexCommand;
+pub struct ZScanCommand;
+pub struct ZUnionCommand;
+pub struct ZInterCommand;
+pub struct ZDiffCommand;
+pub struct ZUnionStoreCommand;
+pub struct ZInterStoreCommand;
+pub struct ZDiffStoreCommand;
+pub struct ZInterCardCommand;
+
+fn bulk(value: String) -> Value {
+    Value::BulkString(BulkString(value))
+}
+
+/// Formats a score for a reply: `inf`, `-inf`, or the shortest decimal that
+/// reads back as the same number.
+pub(crate) fn format_score(score: f64) -> String {
+    score.to_string()
+}
+
+pub(crate) fn parse_score(value: &BulkString) -> Result<f64, Box<dyn Serialize>> {
+    value
+        .0
+        .parse::<f64>()
+        .ok()
+        .filter(|score| !score.is_nan())
+        .ok_or_else(|| error("value is not a valid float"))
+}
+
+/// Parses a score bound of `ZCOUNT` or `ZRANGE ... BYSCORE`: a score,
+/// excluded if it starts with `(`.
+pub(crate) fn parse_score_bound(value: &BulkString) -> Result<Bound<f64>, Box<dyn Serialize>> {
+    let (score, excluded) = match value.0.strip_prefix('(') {
+        Some(score) => (score, true),
+        None => (value.0.as_str(), false),
+    };
+    match score.parse::<f64>() {
+        Ok(score) if score.is_nan() => Err(error("min or max is not a float")),
+        Ok(score) if excluded => Ok(Bound::Excluded(score)),
+        Ok(score) => Ok(Bound::Included(score)),
+        Err(_) => Err(error("min or max is not a float")),
+    }
+}
+
+/// Parses the bounds of `ZRANGE ... BYLEX`: `[member` or `(member`, or `-`
+/// and `+` for the lowest and highest possible members.
+fn parse_lex_range(min: &BulkString, max: &BulkString) -> Result<RangeBy, Box<dyn Serialize>> {
+    let parse = |value: &str| match value.split_at_checked(1) {
+        Some(("-", "")) | Some(("+", "")) => Ok(Bound::Unbounded),
+        Some(("[", member)) => Ok(Bound::Included(member.to_owned())),
+        Some(("(", member)) => Ok(Bound::Excluded(member.to_owned())),
+        _ => Err(error("min or max not valid string range item")),
+    };
+    let (lower, upper) = (parse(&min.0)?, parse(&max.0)?);
+    if min.0 == "+" || max.0 == "-" {
+        // Nothing is above `+` or below `-`.
+        return Ok(RangeBy::Rank(1, 0));
+    }
+    Ok(RangeBy::Lex(lower, upper))
+}
+
+/// Members with their scores: a flat array for RESP2, one pair per member
+/// for RESP3 clients, like Redis does.
+fn scored_reply(client: &Client, members: Vec<(String, f64)>) -> Value {
+    let pairs = members
+        .into_iter()
+        .map(|(member, score)| [bulk(member), bulk(format_score(score))]);
+    Value::Array(Array(if client.resp() >= 3 {
+        pairs
+            .map(|pair| Value::Array(Array(pair.to_vec())))
+            .collect()
+    } else {
+        pairs.flatten().collect()
+    }))
+}
+
+fn members_reply(members: Vec<(String, f64)>) -> Value {
+    Value::Array(Array(
+        members
+            .into_iter()
+            .map(|(member, _)| bulk(member))
+            .collect(),
+    ))
+}
+
+impl Execute for ZAddCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 {
+            return wrong_number_of_arguments("zadd");
+        }
+        let mut zadd_options = ZAddOptions::default();
+        let (mut nx, mut xx, mut incr) = (false, false, false);
+        let mut index = 1;
+        while let Some(BulkString(option)) = options.get(index) {
+            match option.to_lowercase().as_str() {
+                "nx" => nx = true,
+                "xx" => xx = true,
+                "gt" => zadd_options.gt = true,
+                "lt" => zadd_options.lt = true,
+                "ch" => zadd_options.changed = true,
+                "incr" => incr = true,
+                _ => break,
+            }
+            index += 1;
+        }
+        if nx && xx {
+            return error("XX and NX options at the same time are not compatible");
+        }
+        if (zadd_options.gt && zadd_options.lt) || (nx && (zadd_options.gt || zadd_options.lt)) {
+            return error("GT, LT, and/or NX options at the same time are not compatible");
+        }
+        zadd_options.condition = match (nx, xx) {
+            (true, _) => Some(SetCondition::NX),
+            (_, true) => Some(SetCondition::XX),
+            _ => None,
+        };
+        let pairs = &options[index..];
+        if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+            return error("syntax error");
+        }
+        if incr && pairs.len() != 2 {
+            return error("INCR option supports a single increment-element pair");
+        }
+        let mut scored = Vec::with_capacity(pairs.len() / 2);
+        for pair in pairs.chunks(2) {
+            scored.push((parse!(parse_score(&pair[0])), pair[1].0.clone()));
+        }
+        let storage = &client.server().storage;
+        let key = &options[0].0;
+        if incr {
+            let (increment, member) = &scored[0];
+            return match storage.sorted_set_incr_by(
+                client.db(),
+                key,
+                member,
+                *increment,
+                zadd_options,
+            ) {
+                Ok(score) => bulk_or_null(score.map(format_score)),
+                Err(e) => storage_error(e),
+            };
+        }
+        match storage.sorted_set_add(client.db(), key, scored, zadd_options) {
+            Ok(count) => integer(count as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for ZRemCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("zrem");
+        }
+        let members: Vec<String> = options[1..].iter().map(|member| member.0.clone()).collect();
+        let storage = &client.server().storage;
+        match storage.sorted_set_remove(client.db(), &options[0].0, &members) {
+            Ok(removed) => integer(removed as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for ZScoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("zscore");
+        }
+        let storage = &client.server().storage;
+        match storage.sorted_set_scores(client.db(), &options[0].0, &[options[1].0.clone()]) {
+            Ok(scores) => bulk_or_null(scores[0].map(format_score)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for ZMScoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("zmscore");
+        }
+        let members: Vec<String> = options[1..].iter().map(|member| member.0.clone()).collect();
+        let storage = &client.server().storage;
+        match storage.sorted_set_scores(client.db(), &options[0].0, &members) {
+            Ok(scores) => Box::new(Value::Array(Array(
+                scores
+                    .into_iter()
+                    .map(|score| {
+                        score.map_or(Value::Nulls(Nulls), |score| bulk(format_score(score)))
+                    })
+                    .collect(),
+            ))),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for ZIncrByCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("zincrby");
+        }
+        let increment = parse!(parse_score(&options[1]));
+        let storage = &client.server().storage;
+        match storage.sorted_set_incr_by(
+            client.db(),
+            &options[0].0,
+            &options[2].0,
+            increment,
+            ZAddOptions::default(),
+        ) {
+            Ok(score) => bulk_or_null(score.map(format_score)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for ZCardCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("zcard");
+        }
+        match client
+            .server()
+            .storage
+            .sorted_set_len(client.db(), &options[0].0)
+        {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for ZCountCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 3 {
+            return wrong_number_of_arguments("zcount");
+        }
+        let min = parse!(parse_score_bound(&options[1]));
+        let max = parse!(parse_score_bound(&options[2]));
+        let storage = &client.server().storage;
+        match storage.sorted_set_count(client.db(), &options[0].0, min, max) {
+            Ok(count) => integer(count as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// Shared implementation of `ZRANK` and `ZREVRANK`.
+fn rank(name: &str, options: &[BulkString], client: &Client, rev: bool) -> Box<dyn Serialize> {
+    let with_score = match options {
+        [_, _] => false,
+        [_, _, option] if option.0.eq_ignore_ascii_case("withscore") => true,
+        [_, _, _] => return error("syntax error"),
+        _ => return wrong_number_of_arguments(name),
+    };
+    let storage = &client.server().storage;
+    match storage.sorted_set_rank(client.db(), &options[0].0, &options[1].0, rev) {
+        Ok(None) => Box::new(Value::Nulls(Nulls)),
+        Ok(Some((rank, score))) if with_score => Box::new(Value::Array(Array(vec![
+            Value::Integer(Integer(rank as i64)),
+            bulk(format_score(score)),
+        ]))),
+        Ok(Some((rank, _))) => integer(rank as i64),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for ZRankCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        rank("zrank", &options, client, false)
+    }
+}
+impl Execute for ZRevRankCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        rank("zrevrank", &options, client, true)
+    }
+}
+
+/// Parses `start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count]
+/// [WITHSCORES]`, the arguments of `ZRANGE` after the key. `WITHSCORES` is
+/// only accepted if `with_scores_allowed`. Returns the range and whether
+/// `WITHSCORES` was given.
+fn parse_range(
+    options: &[BulkString],
+    with_scores_allowed: bool,
+) -> Result<(ZRange, bool), Box<dyn Serialize>> {
+    let (mut by_score, mut by_lex, mut rev, mut with_scores) = (false, false, false, false);
+    let mut limit = None;
+    let mut index = 2;
+    while let Some(BulkString(option)) = options.get(index) {
+        match option.to_lowercase().as_str() {
+            "byscore" => by_score = true,
+            "bylex" => by_lex = true,
+            "rev" => rev = true,
+            "withscores" if with_scores_allowed => with_scores = true,
+            "limit" => {
+                let (Some(offset), Some(count)) = (options.get(index + 1), options.get(index + 2))
+                else {
+                    return Err(error("syntax error"));
+                };
+                limit = Some((parse_integer(offset)?, parse_integer(count)?));
+                index += 2;
+            }
+            _ => return Err(error("syntax error")),
+        }
+        index += 1;
+    }
+    if by_score && by_lex {
+        return Err(error("syntax error"));
+    }
+    if limit.is_some() && !by_score && !by_lex {
+        return Err(error(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX",
+        ));
+    }
+    if with_scores && by_lex {
+        return Err(error(
+            "syntax error, WITHSCORES not supported in combination with BYLEX",
+        ));
+    }
+    // With REV, the score and lexicographical bounds come as max then min.
+    let (min, max) = if rev && (by_score || by_lex) {
+        (&options[1], &options[0])
+    } else {
+        (&options[0], &options[1])
+    };
+    let by = if by_score {
+        RangeBy::Score(parse_score_bound(min)?, parse_score_bound(max)?)
+    } else if by_lex {
+        parse_lex_range(min, max)?
+    } else {
+        RangeBy::Rank(parse_integer(min)?, parse_integer(max)?)
+    };
+    Ok((ZRange { by, rev, limit }, with_scores))
+}
+
+impl Execute for ZRangeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 {
+            return wrong_number_of_arguments("zrange");
+        }
+        let (range, with_scores) = parse!(parse_range(&options[1..], true));
+        let storage = &client.server().storage;
+        match storage.sorted_set_range(client.db(), &options[0].0, &range) {
+            Ok(members) if with_scores => Box::new(scored_reply(client, members)),
+            Ok(members) => Box::new(members_reply(members)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for ZRangeStoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 4 {
+            return wrong_number_of_arguments("zrangestore");
+        }
+        let (range, _) = parse!(parse_range(&options[2..], false));
+        let storage = &client.server().storage;
+        match storage.sorted_set_range_store(client.db(), &options[0].0, &options[1].0, &range) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// Shared implementation of `ZPOPMIN` and `ZPOPMAX`.
+fn pop(name: &str, options: &[BulkString], client: &Client, max: bool) -> Box<dyn Serialize> {
+    if options.is_empty() || options.len() > 2 {
+        return wrong_number_of_arguments(name);
+    }
+    let count = match options.get(1).map(parse_integer) {
+        Some(Ok(count)) if count < 0 => return error("value is out of range, must be positive"),
+        Some(Ok(count)) => Some(count as usize),
+        Some(Err(e)) => return e,
+        None => None,
+    };
+    let storage = &client.server().storage;
+    match storage.sorted_set_pop(client.db(), &options[0].0, count.unwrap_or(1), max) {
+        Ok(members) if count.is_some() => {
+            Box::new(scored_reply(client, members.unwrap_or_default()))
+        }
+        // A single member is a flat pair, whatever the protocol.
+        Ok(members) => Box::new(Value::Array(Array(
+            members
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|(member, score)| [bulk(member), bulk(format_score(score))])
+                .collect(),
+        ))),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for ZPopMinCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        pop("zpopmin", &options, client, false)
+    }
+}
+impl Execute for ZPopMaxCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        pop("zpopmax", &options, client, true)
+    }
+}
+
+/// Shared implementation of `BZPOPMIN` and `BZPOPMAX`: replies with the key,
+/// the member and its score.
+fn blocking_pop(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    max: bool,
+) -> Box<dyn Serialize> {
+    let Some((timeout, keys)) = options.split_last().filter(|(_, keys)| !keys.is_empty()) else {
+        return wrong_number_of_arguments(name);
+    };
+    let timeout = parse!(parse_timeout(timeout));
+    let keys: Vec<String> = keys.iter().map(|key| key.0.clone()).collect();
+    let db = client.db();
+    let popped = keys.clone();
+    block_on(
+        client,
+        keys,
+        timeout,
+        Box::new(move |storage| {
+            let popped = storage.sorted_set_multi_pop(db, &popped, 1, max)?;
+            Ok(popped.map(|(key, members)| {
+                let mut reply = vec![bulk(key)];
+                for (member, score) in members {
+                    reply.extend([bulk(member), bulk(format_score(score))]);
+                }
+                Value::Array(Array(reply))
+            }))
+        }),
+    )
+}
+
+impl Execute for BZPopMinCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        blocking_pop("bzpopmin", &options, client, false)
+    }
+}
+impl Execute for BZPopMaxCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        blocking_pop("bzpopmax", &options, client, true)
+    }
+}
+
+/// Shared implementation of the `ZREMRANGEBY*` commands.
+fn remove_range(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    by: impl FnOnce(&BulkString, &BulkString) -> Result<RangeBy, Box<dyn Serialize>>,
+) -> Box<dyn Serialize> {
+    if options.len() != 3 {
+        return wrong_number_of_arguments(name);
+    }
+    let by = parse!(by(&options[1], &options[2]));
+    let storage = &client.server().storage;
+    match storage.sorted_set_remove_range(client.db(), &options[0].0, by) {
+        Ok(removed) => integer(removed as i64),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for ZRemRangeByRankCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        remove_range("zremrangebyrank", &options, client, |start, stop| {
+            Ok(RangeBy::Rank(parse_integer(start)?, parse_integer(stop)?))
+        })
+    }
+}
+impl Execute for ZRemRangeByScoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        remove_range("zremrangebyscore", &options, client, |min, max| {
+            Ok(RangeBy::Score(
+                parse_score_bound(min)?,
+                parse_score_bound(max)?,
+            ))
+        })
+    }
+}
+impl Execute for ZRemRangeByLexCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        remove_range("zremrangebylex", &options, client, parse_lex_range)
+    }
+}
+impl Execute for ZScanCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("zscan");
+        }
+        let scan_options = parse!(parse_scan_options(&options[1..], false));
+        let storage = &client.server().storage;
+        match storage.sorted_set_scan(
+            client.db(),
+            &options[0].0,
+            scan_options.cursor,
+            scan_options.count,
+            scan_options.pattern.as_deref(),
+        ) {
+            Ok((cursor, members)) => scan_reply(
+                cursor,
+                members
+                    .into_iter()
+                    .flat_map(|(member, score)| [member, format_score(score)])
+                    .collect(),
+            ),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// Parses `numkeys key [key ...]` followed by `WEIGHTS` and `AGGREGATE`,
+/// which `ZDIFF` does not accept, and `WITHSCORES` unless `store`. Returns
+/// the keys, how to combine them and whether to reply with the scores.
+fn parse_combine(
+    name: &str,
+    options: &[BulkString],
+    operation: SetOperation,
+    store: bool,
+) -> Result<(Vec<String>, ZCombine, bool), Box<dyn Serialize>> {
+    let numkeys = parse_integer(&options[0])?;
+    if numkeys < 1 {
+        return Err(error(&format!(
+            "at least 1 input key is needed for '{}' command",
+            name
+        )));
+    }
+    let numkeys = numkeys as usize;
+    if numkeys > options.len() - 1 {
+        return Err(error("syntax error"));
+    }
+    let keys = options[1..=numkeys]
+        .iter()
+        .map(|key| key.0.clone())
+        .collect();
+    let mut combine = ZCombine::new(operation);
+    let mut with_scores = false;
+    let mut index = numkeys + 1;
+    while let Some(BulkString(option)) = options.get(index) {
+        match option.to_lowercase().as_str() {
+            "weights" if operation != SetOperation::Diff => {
+                let Some(weights) = options.get(index + 1..index + 1 + numkeys) else {
+                    return Err(error("syntax error"));
+                };
+                combine.weights = weights
+                    .iter()
+                    .map(|weight| {
+                        parse_score(weight).map_err(|_| error("weight value is not a float"))
+                    })
+                    .collect::<Result<_, _>>()?;
+                index += numkeys;
+            }
+            "aggregate" if operation != SetOperation::Diff => {
+                let aggregate = options.get(index + 1).map(|value| value.0.to_lowercase());
+                combine.aggregate = match aggregate.as_deref() {
+                    Some("sum") => Aggregate::Sum,
+                    Some("min") => Aggregate::Min,
+                    Some("max") => Aggregate::Max,
+                    _ => return Err(error("syntax error")),
+                };
+                index += 1;
+            }
+            "withscores" if !store => with_scores = true,
+            _ => return Err(error("syntax error")),
+        }
+        index += 1;
+    }
+    Ok((keys, combine, with_scores))
+}
+
+/// Shared implementation of `ZUNION`, `ZINTER` and `ZDIFF`.
+fn combine(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    operation: SetOperation,
+) -> Box<dyn Serialize> {
+    if options.len() < 2 {
+        return wrong_number_of_arguments(name);
+    }
+    let (keys, combine, with_scores) = parse!(parse_combine(name, options, operation, false));
+    let storage = &client.server().storage;
+    match storage.sorted_set_combine(client.db(), &keys, &combine) {
+        Ok(members) if with_scores => Box::new(scored_reply(client, members)),
+        Ok(members) => Box::new(members_reply(members)),
+        Err(e) => storage_error(e),
+    }
+}
+
+/// Shared implementation of `ZUNIONSTORE`, `ZINTERSTORE` and `ZDIFFSTORE`.
+fn combine_store(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    operation: SetOperation,
+) -> Box<dyn Serialize> {
+    if options.len() < 3 {
+        return wrong_number_of_arguments(name);
+    }
+    let (keys, combine, _) = parse!(parse_combine(name, &options[1..], operation, true));
+    let storage = &client.server().storage;
+    match storage.sorted_set_combine_store(client.db(), &options[0].0, &keys, &combine) {
+        Ok(len) => integer(len as i64),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for ZUnionCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine("zunion", &options, client, SetOperation::Union)
+    }
+}
+impl Execute for ZInterCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine("zinter", &options, client, SetOperation::Inter)
+    }
+}
+impl Execute for ZDiffCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine("zdiff", &options, client, SetOperation::Diff)
+    }
+}
+impl Execute for ZUnionStoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine_store("zunionstore", &options, client, SetOperation::Union)
+    }
+}
+impl Execute for ZInterStoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine_store("zinterstore", &options, client, SetOperation::Inter)
+    }
+}
+impl Execute for ZDiffStoreCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        combine_store("zdiffstore", &options, client, SetOperation::Diff)
+    }
+}
+impl Execute for ZInterCardCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("zintercard");
+        }
+        let (keys, limit) = parse!(parse_intercard(&options));
+        let storage = &client.server().storage;
+        match storage.sorted_set_intersection_len(client.db(), &keys, limit) {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
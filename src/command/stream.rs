@@ -0,0 +1,965 @@
+use crate::client::Client;
+use crate::command::blocking::block_on;
+use crate::command::{
+    error, integer, map_reply, ok, parse_integer, storage_error, wrong_number_of_arguments, Execute,
+};
+use crate::error::RedisError;
+use crate::object::{
+    Claim, ConsumerGroup, Stream, StreamEntry, StreamId, StreamTrim, TrimStrategy, NODE_MAX_ENTRIES,
+};
+use crate::storage::{GroupEntries, PendingFilter, Storage, XAddId};
+use crate::value::array::Array;
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::map::Map;
+use crate::value::nulls::Nulls;
+use crate::value::serialize::Serialize;
+use crate::value::simple_error::{ErrorType, SimpleError};
+use crate::value::Value;
+use std::time::Duration;
+pub struct XAddCommand;
+pub struct XRangeCommand;
+pub struct XRevRangeCommand;
+pub struct XLenCommand;
+pub struct XDelCommand;
+pub struct XTrimCommand;
+pub struct XReadCommand;
+pub struct XGroupCommand;
+pub struct XReadGroupCommand;
+pub struct XAckCommand;
+pub struct XPendingCommand;
+pub struct XClaimCommand;
+pub struct XAutoClaimCommand;
+pub struct XInfoCommand;
+
+const INVALID_ID: &str = "Invalid stream ID specified as stream command argument";
+
+fn bulk(value: String) -> Value {
+    Value::BulkString(BulkString(value))
+}
+
+/// Parses `ms-seq`, or `ms` alone with `missing_seq` as sequence number.
+fn parse_id(value: &str, missing_seq: u64) -> Result<StreamId, Box<dyn Serialize>> {
+    let parse = |part: &str| part.parse::<u64>().map_err(|_| error(INVALID_ID));
+    match value.split_once('-') {
+        Some((ms, seq)) => Ok(StreamId::new(parse(ms)?, parse(seq)?)),
+        None => Ok(StreamId::new(parse(value)?, missing_seq)),
+    }
+}
+
+/// Parses a bound of an `XRANGE` interval: `-` or `+`, or an ID excluded if
+/// it starts with `(`. An ID without a sequence number covers all of them.
+fn parse_range_bound(value: &BulkString, start: bool) -> Result<StreamId, Box<dyn Serialize>> {
+    match value.0.as_str() {
+        "-" => return Ok(StreamId::MIN),
+        "+" => return Ok(StreamId::MAX),
+        _ => {}
+    }
+    let missing_seq = if start { 0 } else { u64::MAX };
+    let Some(id) = value.0.strip_prefix('(') else {
+        return parse_id(&value.0, missing_seq);
+    };
+    let id = parse_id(id, missing_seq)?;
+    match start {
+        true => id
+            .next()
+            .ok_or_else(|| error("invalid start ID for the interval")),
+        false => id
+            .prev()
+            .ok_or_else(|| error("invalid end ID for the interval")),
+    }
+}
+
+/// Parses `MAXLEN|MINID [=|~] threshold` at the start of `options`. Returns
+/// the trim, without a limit, and the number of arguments read.
+fn parse_trim(options: &[BulkString]) -> Result<(StreamTrim, usize), Box<dyn Serialize>> {
+    let approximate = match options.get(1).map(|option| option.0.as_str()) {
+        Some("~") => Some(true),
+        Some("=") => Some(false),
+        _ => None,
+    };
+    let read = if approximate.is_some() { 3 } else { 2 };
+    let Some(threshold) = options.get(read - 1) else {
+        return Err(error("syntax error"));
+    };
+    let strategy = if options[0].0.eq_ignore_ascii_case("maxlen") {
+        match parse_integer(threshold)? {
+            max if max < 0 => return Err(error("The MAXLEN argument must be >= 0.")),
+            max => TrimStrategy::MaxLen(max as usize),
+        }
+    } else {
+        TrimStrategy::MinId(parse_id(&threshold.0, 0)?)
+    };
+    let trim = StreamTrim {
+        strategy,
+        approximate: approximate.unwrap_or(false),
+        limit: 0,
+    };
+    Ok((trim, read))
+}
+
+fn parse_limit(limit: Option<&BulkString>) -> Result<usize, Box<dyn Serialize>> {
+    match parse_integer(limit.ok_or_else(|| error("syntax error"))?)? {
+        limit if limit < 0 => Err(error("The LIMIT argument must be >= 0.")),
+        limit => Ok(limit as usize),
+    }
+}
+
+/// Adds `LIMIT` to a trim, which only approximate trimming accepts. Like in
+/// Redis, approximate trimming removes at most 100 nodes by default.
+fn with_limit(
+    trim: Option<StreamTrim>,
+    limit: Option<usize>,
+) -> Result<Option<StreamTrim>, Box<dyn Serialize>> {
+    match (trim, limit) {
+        (None, Some(_)) => Err(error(
+            "syntax error, LIMIT cannot be used without specifying a trimming strategy",
+        )),
+        (Some(trim), Some(_)) if !trim.approximate => Err(error(
+            "syntax error, LIMIT cannot be used without the special ~ option",
+        )),
+        (Some(trim), limit) if trim.approximate => Ok(Some(StreamTrim {
+            limit: limit.unwrap_or(100 * NODE_MAX_ENTRIES),
+            ..trim
+        })),
+        (trim, _) => Ok(trim),
+    }
+}
+
+/// An entry as an array of its ID and a flat array of its fields and values.
+fn entry_reply(entry: StreamEntry) -> Value {
+    let fields = entry
+        .fields
+        .into_iter()
+        .flat_map(|(field, value)| [bulk(field), bulk(value)])
+        .collect();
+    Value::Array(Array(vec![
+        bulk(entry.id.to_string()),
+        Value::Array(Array(fields)),
+    ]))
+}
+
+fn entries_reply(entries: Vec<StreamEntry>) -> Value {
+    Value::Array(Array(entries.into_iter().map(entry_reply).collect()))
+}
+
+/// Entries read by a consumer, with a null in place of the deleted ones.
+fn group_entries_reply(entries: GroupEntries) -> Value {
+    let entries = entries.into_iter().map(|(id, entry)| match entry {
+        Some(entry) => entry_reply(entry),
+        None => Value::Array(Array(vec![bulk(id.to_string()), Value::Nulls(Nulls)])),
+    });
+    Value::Array(Array(entries.collect()))
+}
+
+fn ids_reply(ids: impl IntoIterator<Item = StreamId>) -> Value {
+    Value::Array(Array(
+        ids.into_iter().map(|id| bulk(id.to_string())).collect(),
+    ))
+}
+
+/// The entries read from each stream: a map for RESP3 clients, an array of
+/// key and entries pairs otherwise.
+fn streams_reply(resp3: bool, streams: Vec<(String, Value)>) -> Value {
+    let streams = streams
+        .into_iter()
+        .map(|(key, entries)| (bulk(key), entries));
+    if resp3 {
+        return Value::Map(Map(streams.collect()));
+    }
+    Value::Array(Array(
+        streams
+            .map(|(key, entries)| Value::Array(Array(vec![key, entries])))
+            .collect(),
+    ))
+}
+
+fn read_reply(read: Vec<(String, Vec<StreamEntry>)>) -> Vec<(String, Value)> {
+    read.into_iter()
+        .map(|(key, entries)| (key, entries_reply(entries)))
+        .collect()
+}
+
+fn nogroup(message: String) -> Value {
+    Value::SimpleError(SimpleError {
+        error_type: ErrorType::NOGROUP,
+        message,
+    })
+}
+
+/// The error of `XGROUP` and `XINFO` for a missing group.
+fn no_such_group(key: &str, group: &str) -> Box<dyn Serialize> {
+    Box::new(nogroup(format!(
+        "No such consumer group '{}' for key name '{}'",
+        group, key
+    )))
+}
+
+/// The value of the option at `index`.
+fn option_value(options: &[BulkString], index: usize) -> Result<&BulkString, Box<dyn Serialize>> {
+    options.get(index + 1).ok_or_else(|| error("syntax error"))
+}
+
+/// Parses the timeout of `BLOCK`, `None` to block forever.
+fn parse_block(value: &BulkString) -> Result<Option<Duration>, Box<dyn Serialize>> {
+    match parse_integer(value)? {
+        timeout if timeout < 0 => Err(error("timeout is negative")),
+        0 => Ok(None),
+        timeout => Ok(Some(Duration::from_millis(timeout as u64))),
+    }
+}
+
+/// Parses a duration or time in milliseconds, replying `invalid` if negative.
+fn parse_ms(value: &BulkString, invalid: &str) -> Result<u128, Box<dyn Serialize>> {
+    match parse_integer(value)? {
+        ms if ms < 0 => Err(error(invalid)),
+        ms => Ok(ms as u128),
+    }
+}
+
+fn int(value: i64) -> Value {
+    Value::Integer(Integer(value))
+}
+
+impl Execute for XAddCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 4 {
+            return wrong_number_of_arguments("xadd");
+        }
+        let mut no_create = false;
+        let (mut trim, mut limit) = (None, None);
+        let mut index = 1;
+        while let Some(BulkString(option)) = options.get(index) {
+            match option.to_lowercase().as_str() {
+                "nomkstream" => {
+                    no_create = true;
+                    index += 1;
+                }
+                "maxlen" | "minid" => {
+                    let (parsed, read) = parse!(parse_trim(&options[index..]));
+                    trim = Some(parsed);
+                    index += read;
+                }
+                "limit" => {
+                    limit = Some(parse!(parse_limit(options.get(index + 1))));
+                    index += 2;
+                }
+                _ => break,
+            }
+        }
+        let trim = parse!(with_limit(trim, limit));
+        let (Some(id), fields) = (
+            options.get(index),
+            options.get(index + 1..).unwrap_or_default(),
+        ) else {
+            return wrong_number_of_arguments("xadd");
+        };
+        if fields.is_empty() || !fields.len().is_multiple_of(2) {
+            return wrong_number_of_arguments("xadd");
+        }
+        let id = match id.0.as_str() {
+            "*" => XAddId::Auto,
+            id => match id.strip_suffix("-*") {
+                Some(ms) => XAddId::AutoSeq(parse!(ms.parse().map_err(|_| error(INVALID_ID)))),
+                None => XAddId::Explicit(parse!(parse_id(id, 0))),
+            },
+        };
+        let fields = fields
+            .chunks(2)
+            .map(|pair| (pair[0].0.clone(), pair[1].0.clone()))
+            .collect();
+        let storage = &client.server().storage;
+        match storage.stream_add(client.db(), &options[0].0, id, fields, no_create, trim) {
+            Ok(Some(id)) => Box::new(bulk(id.to_string())),
+            Ok(None) => Box::new(Value::Nulls(Nulls)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// Shared implementation of `XRANGE` and `XREVRANGE`, which takes the end
+/// of the interval first.
+fn range(name: &str, options: &[BulkString], client: &Client, rev: bool) -> Box<dyn Serialize> {
+    if options.len() < 3 {
+        return wrong_number_of_arguments(name);
+    }
+    let count = match &options[3..] {
+        [] => None,
+        [BulkString(option), count] if option.eq_ignore_ascii_case("count") => {
+            Some(parse!(parse_integer(count)).max(0) as usize)
+        }
+        _ => return error("syntax error"),
+    };
+    let (start, end) = if rev {
+        (&options[2], &options[1])
+    } else {
+        (&options[1], &options[2])
+    };
+    let start = parse!(parse_range_bound(start, true));
+    let end = parse!(parse_range_bound(end, false));
+    if count == Some(0) {
+        return Box::new(Value::Array(Array(Vec::new())));
+    }
+    let storage = &client.server().storage;
+    match storage.stream_range(client.db(), &options[0].0, start..=end, count, rev) {
+        Ok(entries) => Box::new(entries_reply(entries)),
+        Err(e) => storage_error(e),
+    }
+}
+
+impl Execute for XRangeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        range("xrange", &options, client, false)
+    }
+}
+impl Execute for XRevRangeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        range("xrevrange", &options, client, true)
+    }
+}
+impl Execute for XLenCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("xlen");
+        }
+        match client
+            .server()
+            .storage
+            .stream_len(client.db(), &options[0].0)
+        {
+            Ok(len) => integer(len as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for XDelCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("xdel");
+        }
+        let ids = parse!(options[1..]
+            .iter()
+            .map(|id| parse_id(&id.0, 0))
+            .collect::<Result<Vec<_>, _>>());
+        let storage = &client.server().storage;
+        match storage.stream_delete(client.db(), &options[0].0, &ids) {
+            Ok(removed) => integer(removed as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for XTrimCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 {
+            return wrong_number_of_arguments("xtrim");
+        }
+        let strategy = options[1].0.to_lowercase();
+        if strategy != "maxlen" && strategy != "minid" {
+            return error("syntax error");
+        }
+        let (trim, read) = parse!(parse_trim(&options[1..]));
+        let limit = match &options[1 + read..] {
+            [] => None,
+            [BulkString(option), limit] if option.eq_ignore_ascii_case("limit") => {
+                Some(parse!(parse_limit(Some(limit))))
+            }
+            _ => return error("syntax error"),
+        };
+        let trim = parse!(with_limit(Some(trim), limit)).expect("a strategy was given");
+        let storage = &client.server().storage;
+        match storage.stream_trim(client.db(), &options[0].0, trim) {
+            Ok(removed) => integer(removed as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+impl Execute for XReadCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 {
+            return wrong_number_of_arguments("xread");
+        }
+        let (mut count, mut block) = (None, None);
+        let mut streams = None;
+        let mut index = 0;
+        while let Some(BulkString(option)) = options.get(index) {
+            match option.to_lowercase().as_str() {
+                "count" => {
+                    let Some(value) = options.get(index + 1) else {
+                        return error("syntax error");
+                    };
+                    // Like in Redis, a count of 0 or less is no count.
+                    count = Some(parse!(parse_integer(value))).filter(|count| *count > 0);
+                }
+                "block" => {
+                    let Some(value) = options.get(index + 1) else {
+                        return error("syntax error");
+                    };
+                    block = Some(parse!(parse_block(value)));
+                }
+                "streams" => {
+                    streams = Some(&options[index + 1..]);
+                    break;
+                }
+                _ => return error("syntax error"),
+            }
+            index += 2;
+        }
+        let Some(streams) = streams else {
+            return error("syntax error");
+        };
+        if streams.is_empty() || !streams.len().is_multiple_of(2) {
+            return error(
+                "Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.",
+            );
+        }
+        let (keys, ids) = streams.split_at(streams.len() / 2);
+        let storage = &client.server().storage;
+        let db = client.db();
+        let mut after = Vec::with_capacity(keys.len());
+        for (key, id) in keys.iter().zip(ids) {
+            // `$` only returns the entries added from now on.
+            let id = match id.0.as_str() {
+                "$" => match storage.stream_last_id(db, &key.0) {
+                    Ok(id) => id,
+                    Err(e) => return storage_error(e),
+                },
+                id => parse!(parse_id(id, 0)),
+            };
+            after.push((key.0.clone(), id));
+        }
+        let count = count.map(|count| count as usize);
+        let resp3 = client.resp() >= 3;
+        let Some(timeout) = block else {
+            return match storage.stream_read(db, &after, count) {
+                Ok(read) if read.is_empty() => Box::new(Value::Nulls(Nulls)),
+                Ok(read) => Box::new(streams_reply(resp3, read_reply(read))),
+                Err(e) => storage_error(e),
+            };
+        };
+        let keys = keys.iter().map(|key| key.0.clone()).collect();
+        block_on(
+            client,
+            keys,
+            timeout,
+            Box::new(move |storage| {
+                let read = storage.stream_read(db, &after, count)?;
+                Ok((!read.is_empty()).then(|| streams_reply(resp3, read_reply(read))))
+            }),
+        )
+    }
+}
+
+/// Parses the ID of `XGROUP CREATE` and `SETID`, `None` for `$`.
+fn parse_group_id(value: &BulkString) -> Result<Option<StreamId>, Box<dyn Serialize>> {
+    match value.0.as_str() {
+        "$" => Ok(None),
+        id => parse_id(id, 0).map(Some),
+    }
+}
+
+/// Parses `[MKSTREAM] [ENTRIESREAD n]`, only `XGROUP CREATE` accepting
+/// `MKSTREAM`. An entries read count of -1 is an unknown one.
+fn parse_group_options(
+    options: &[BulkString],
+    create: bool,
+) -> Result<(bool, Option<u64>), Box<dyn Serialize>> {
+    let (mut mkstream, mut entries_read) = (false, None);
+    let mut index = 0;
+    while let Some(BulkString(option)) = options.get(index) {
+        match option.to_lowercase().as_str() {
+            "mkstream" if create => {
+                mkstream = true;
+                index += 1;
+            }
+            "entriesread" => {
+                entries_read = match parse_integer(option_value(options, index)?)? {
+                    -1 => None,
+                    read if read < 0 => {
+                        return Err(error("value for ENTRIESREAD must be positive or -1"))
+                    }
+                    read => Some(read as u64),
+                };
+                index += 2;
+            }
+            _ => return Err(error("syntax error")),
+        }
+    }
+    Ok((mkstream, entries_read))
+}
+
+impl Execute for XGroupCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        let Some(BulkString(subcommand)) = options.first() else {
+            return wrong_number_of_arguments("xgroup");
+        };
+        let subcommand = subcommand.to_lowercase();
+        let args = &options[1..];
+        let storage = &client.server().storage;
+        let db = client.db();
+        let result = match subcommand.as_str() {
+            "create" if args.len() >= 3 => {
+                let id = parse!(parse_group_id(&args[2]));
+                let (create, entries_read) = parse!(parse_group_options(&args[3..], true));
+                storage
+                    .stream_create_group(db, &args[0].0, &args[1].0, id, entries_read, create)
+                    .map(|()| ok())
+            }
+            "setid" if args.len() >= 3 => {
+                let id = parse!(parse_group_id(&args[2]));
+                let (_, entries_read) = parse!(parse_group_options(&args[3..], false));
+                storage
+                    .stream_set_group_id(db, &args[0].0, &args[1].0, id, entries_read)
+                    .map(|()| ok())
+            }
+            "destroy" if args.len() == 2 => storage
+                .stream_destroy_group(db, &args[0].0, &args[1].0)
+                .map(|destroyed| integer(destroyed as i64)),
+            "createconsumer" if args.len() == 3 => storage
+                .stream_create_consumer(db, &args[0].0, &args[1].0, &args[2].0)
+                .map(|created| integer(created as i64)),
+            "delconsumer" if args.len() == 3 => storage
+                .stream_delete_consumer(db, &args[0].0, &args[1].0, &args[2].0)
+                .map(|pending| integer(pending as i64)),
+            "create" | "setid" | "destroy" | "createconsumer" | "delconsumer" => {
+                return wrong_number_of_arguments(&format!("xgroup|{}", subcommand))
+            }
+            _ => {
+                return error(&format!(
+                    "unknown subcommand '{}'. Try XGROUP HELP.",
+                    options[0].0
+                ))
+            }
+        };
+        match result {
+            Ok(reply) => reply,
+            Err(RedisError::KeyDoesNotExist) => error(
+                "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.",
+            ),
+            Err(RedisError::NoGroup { key, group }) => no_such_group(&key, &group),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+impl Execute for XReadGroupCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 6 {
+            return wrong_number_of_arguments("xreadgroup");
+        }
+        if !options[0].0.eq_ignore_ascii_case("group") {
+            return error("syntax error");
+        }
+        let (group, consumer) = (options[1].0.clone(), options[2].0.clone());
+        let (mut count, mut block, mut no_ack) = (None, None, false);
+        let mut streams = None;
+        let mut index = 3;
+        while let Some(BulkString(option)) = options.get(index) {
+            match option.to_lowercase().as_str() {
+                "count" => {
+                    let value = parse!(option_value(&options, index));
+                    count = Some(parse!(parse_integer(value))).filter(|count| *count > 0);
+                    index += 2;
+                }
+                "block" => {
+                    block = Some(parse!(parse_block(parse!(option_value(&options, index)))));
+                    index += 2;
+                }
+                "noack" => {
+                    no_ack = true;
+                    index += 1;
+                }
+                "streams" => {
+                    streams = Some(&options[index + 1..]);
+                    break;
+                }
+                _ => return error("syntax error"),
+            }
+        }
+        let Some(streams) = streams else {
+            return error("syntax error");
+        };
+        if streams.is_empty() || !streams.len().is_multiple_of(2) {
+            return error(
+                "Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must be specified.",
+            );
+        }
+        let (keys, ids) = streams.split_at(streams.len() / 2);
+        let mut after = Vec::with_capacity(keys.len());
+        for (key, id) in keys.iter().zip(ids) {
+            // `>` reads the entries never delivered to the group, any other
+            // ID the entries of the consumer still pending after it.
+            let id = match id.0.as_str() {
+                ">" => None,
+                id => Some(parse!(parse_id(id, 0))),
+            };
+            after.push((key.0.clone(), id));
+        }
+        let count = count.map(|count| count as usize);
+        let resp3 = client.resp() >= 3;
+        let db = client.db();
+        let attempt = move |storage: &Storage| {
+            match storage.stream_read_group(db, &group, &consumer, &after, count, no_ack) {
+                Ok(read) if read.is_empty() => Ok(None),
+                Ok(read) => {
+                    let read = read
+                        .into_iter()
+                        .map(|(key, entries)| (key, group_entries_reply(entries)))
+                        .collect();
+                    Ok(Some(streams_reply(resp3, read)))
+                }
+                // Also unblocks the client if the group goes away.
+                Err(RedisError::NoGroup { key, group }) => Ok(Some(nogroup(format!(
+                    "No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                    key, group
+                )))),
+                Err(e) => Err(e),
+            }
+        };
+        let storage = &client.server().storage;
+        let Some(timeout) = block else {
+            return match attempt(storage) {
+                Ok(Some(reply)) => Box::new(reply),
+                Ok(None) => Box::new(Value::Nulls(Nulls)),
+                Err(e) => storage_error(e),
+            };
+        };
+        let keys = keys.iter().map(|key| key.0.clone()).collect();
+        block_on(client, keys, timeout, Box::new(attempt))
+    }
+}
+
+fn parse_ids(ids: &[BulkString]) -> Result<Vec<StreamId>, Box<dyn Serialize>> {
+    ids.iter().map(|id| parse_id(&id.0, 0)).collect()
+}
+
+impl Execute for XAckCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 3 {
+            return wrong_number_of_arguments("xack");
+        }
+        let ids = parse!(parse_ids(&options[2..]));
+        let storage = &client.server().storage;
+        match storage.stream_ack(client.db(), &options[0].0, &options[1].0, &ids) {
+            Ok(acked) => integer(acked as i64),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// The summary form of `XPENDING`: the number of pending entries, the
+/// lowest and highest IDs, and the number of entries of each consumer.
+fn pending_summary(client: &Client, key: &str, group: &str) -> Box<dyn Serialize> {
+    let storage = &client.server().storage;
+    let summary = match storage.stream_pending_summary(client.db(), key, group) {
+        Ok(summary) => summary,
+        Err(e) => return storage_error(e),
+    };
+    let Some((first, last)) = summary.ids else {
+        let nulls = Value::Nulls(Nulls);
+        return Box::new(Value::Array(Array(vec![
+            int(0),
+            nulls.clone(),
+            nulls.clone(),
+            nulls,
+        ])));
+    };
+    let consumers = summary
+        .consumers
+        .into_iter()
+        .map(|(name, count)| Value::Array(Array(vec![bulk(name), bulk(count.to_string())])))
+        .collect();
+    Box::new(Value::Array(Array(vec![
+        int(summary.count as i64),
+        bulk(first.to_string()),
+        bulk(last.to_string()),
+        Value::Array(Array(consumers)),
+    ])))
+}
+
+impl Execute for XPendingCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 2 {
+            return wrong_number_of_arguments("xpending");
+        }
+        let (key, group) = (&options[0].0, &options[1].0);
+        if options.len() == 2 {
+            return pending_summary(client, key, group);
+        }
+        let mut args = &options[2..];
+        let mut min_idle = 0;
+        if args[0].0.eq_ignore_ascii_case("idle") {
+            let Some(idle) = args.get(1) else {
+                return error("syntax error");
+            };
+            min_idle = parse!(parse_integer(idle)).max(0) as u128;
+            args = &args[2..];
+        }
+        let (start, end, count, consumer) = match args {
+            [start, end, count] => (start, end, count, None),
+            [start, end, count, consumer] => (start, end, count, Some(consumer.0.clone())),
+            _ => return error("syntax error"),
+        };
+        let filter = PendingFilter {
+            ids: parse!(parse_range_bound(start, true))..=parse!(parse_range_bound(end, false)),
+            count: parse!(parse_integer(count)).max(0) as usize,
+            consumer,
+            min_idle,
+        };
+        let storage = &client.server().storage;
+        let now = storage.now_ms();
+        match storage.stream_pending(client.db(), key, group, &filter) {
+            Ok(pending) => Box::new(Value::Array(Array(
+                pending
+                    .into_iter()
+                    .map(|(id, entry)| {
+                        Value::Array(Array(vec![
+                            bulk(id.to_string()),
+                            bulk(entry.consumer),
+                            int(now.saturating_sub(entry.delivered_at) as i64),
+                            int(entry.deliveries as i64),
+                        ]))
+                    })
+                    .collect(),
+            ))),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+/// A claim for `consumer` of the entries idle for `min_idle`, with the
+/// defaults of `XCLAIM` and `XAUTOCLAIM`.
+fn new_claim(consumer: &str, min_idle: u128, now: u128) -> Claim {
+    Claim {
+        consumer: consumer.to_owned(),
+        now,
+        min_idle,
+        delivered_at: now,
+        deliveries: None,
+        force: false,
+        just_id: false,
+        last_id: None,
+    }
+}
+
+impl Execute for XClaimCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 5 {
+            return wrong_number_of_arguments("xclaim");
+        }
+        let min_idle = parse!(parse_ms(
+            &options[3],
+            "Invalid min-idle-time argument for XCLAIM"
+        ));
+        // Like in Redis, the IDs end at the first argument that is not one.
+        let mut index = 4;
+        let mut ids = Vec::new();
+        while let Some(Ok(id)) = options.get(index).map(|id| parse_id(&id.0, 0)) {
+            ids.push(id);
+            index += 1;
+        }
+        let storage = &client.server().storage;
+        let now = storage.now_ms();
+        let mut claim = new_claim(&options[2].0, min_idle, now);
+        while let Some(BulkString(option)) = options.get(index) {
+            let lowercase = option.to_lowercase();
+            match lowercase.as_str() {
+                "force" => claim.force = true,
+                "justid" => claim.just_id = true,
+                "idle" | "time" | "retrycount" | "lastid" => {
+                    let value = parse!(option_value(&options, index));
+                    match lowercase.as_str() {
+                        "idle" => {
+                            let idle =
+                                parse!(parse_ms(value, "Invalid IDLE option argument for XCLAIM"));
+                            claim.delivered_at = now.saturating_sub(idle);
+                        }
+                        "time" => {
+                            claim.delivered_at =
+                                parse!(parse_ms(value, "Invalid TIME option argument for XCLAIM"));
+                        }
+                        "retrycount" => {
+                            claim.deliveries = Some(parse!(parse_integer(value)).max(0) as u64);
+                        }
+                        _ => claim.last_id = Some(parse!(parse_id(&value.0, 0))),
+                    }
+                    index += 1;
+                }
+                _ => return error(&format!("Unrecognized XCLAIM option '{}'", option)),
+            }
+            index += 1;
+        }
+        match storage.stream_claim(client.db(), &options[0].0, &options[1].0, &ids, &claim) {
+            Ok(entries) if claim.just_id => {
+                Box::new(ids_reply(entries.iter().map(|entry| entry.id)))
+            }
+            Ok(entries) => Box::new(entries_reply(entries)),
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+impl Execute for XAutoClaimCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() < 5 {
+            return wrong_number_of_arguments("xautoclaim");
+        }
+        let min_idle = parse!(parse_ms(
+            &options[3],
+            "Invalid min-idle-time argument for XAUTOCLAIM"
+        ));
+        let start = parse!(parse_range_bound(&options[4], true));
+        let storage = &client.server().storage;
+        let mut claim = new_claim(&options[2].0, min_idle, storage.now_ms());
+        let mut count = 100;
+        let mut index = 5;
+        while let Some(BulkString(option)) = options.get(index) {
+            match option.to_lowercase().as_str() {
+                "count" => {
+                    // Bounded like in Redis, which scans up to 10 times more.
+                    count = match parse!(parse_integer(parse!(option_value(&options, index)))) {
+                        count if !(1..=i64::MAX / 10).contains(&count) => {
+                            return error("COUNT must be > 0")
+                        }
+                        count => count as usize,
+                    };
+                    index += 2;
+                }
+                "justid" => {
+                    claim.just_id = true;
+                    index += 1;
+                }
+                _ => return error("syntax error"),
+            }
+        }
+        let (key, group) = (&options[0].0, &options[1].0);
+        match storage.stream_auto_claim(client.db(), key, group, start, count, &claim) {
+            Ok((claimed, entries)) => {
+                let entries = match claim.just_id {
+                    true => ids_reply(claimed.claimed),
+                    false => entries_reply(entries),
+                };
+                Box::new(Value::Array(Array(vec![
+                    bulk(claimed.cursor.to_string()),
+                    entries,
+                    ids_reply(claimed.deleted),
+                ])))
+            }
+            Err(e) => storage_error(e),
+        }
+    }
+}
+
+fn info_reply(client: &Client, fields: Vec<(&str, Value)>) -> Value {
+    map_reply(
+        client,
+        fields
+            .into_iter()
+            .map(|(field, value)| (bulk(field.to_owned()), value))
+            .collect(),
+    )
+}
+
+fn stream_info(stream: &Stream) -> Vec<(&'static str, Value)> {
+    let entry =
+        |entry: Option<&StreamEntry>| entry.cloned().map_or(Value::Nulls(Nulls), entry_reply);
+    let first_id = stream.first_entry().map_or(StreamId::MIN, |entry| entry.id);
+    vec![
+        ("length", int(stream.len() as i64)),
+        ("radix-tree-keys", int(stream.node_count() as i64)),
+        ("radix-tree-nodes", int(stream.node_count() as i64)),
+        ("last-generated-id", bulk(stream.last_id().to_string())),
+        (
+            "max-deleted-entry-id",
+            bulk(stream.max_deleted_id().to_string()),
+        ),
+        ("entries-added", int(stream.entries_added() as i64)),
+        ("recorded-first-entry-id", bulk(first_id.to_string())),
+        ("groups", int(stream.groups().len() as i64)),
+        ("first-entry", entry(stream.first_entry())),
+        ("last-entry", entry(stream.last_entry())),
+    ]
+}
+
+fn group_info(stream: &Stream, name: &str, group: &ConsumerGroup) -> Vec<(&'static str, Value)> {
+    let or_null = |value: Option<u64>| value.map_or(Value::Nulls(Nulls), |value| int(value as i64));
+    vec![
+        ("name", bulk(name.to_owned())),
+        ("consumers", int(group.consumers().len() as i64)),
+        ("pending", int(group.pending().len() as i64)),
+        ("last-delivered-id", bulk(group.last_id.to_string())),
+        ("entries-read", or_null(group.entries_read)),
+        ("lag", or_null(stream.lag(group))),
+    ]
+}
+
+fn consumers_info(group: &ConsumerGroup, now: u128) -> Vec<Vec<(&'static str, Value)>> {
+    group
+        .consumers()
+        .iter()
+        .map(|(name, consumer)| {
+            let inactive = consumer
+                .active_at
+                .map_or(-1, |active_at| now.saturating_sub(active_at) as i64);
+            vec![
+                ("name", bulk(name.clone())),
+                ("pending", int(consumer.pending().len() as i64)),
+                ("idle", int(now.saturating_sub(consumer.seen_at) as i64)),
+                ("inactive", int(inactive)),
+            ]
+        })
+        .collect()
+}
+
+impl Execute for XInfoCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        let Some(BulkString(subcommand)) = options.first() else {
+            return wrong_number_of_arguments("xinfo");
+        };
+        let subcommand = subcommand.to_lowercase();
+        let args = &options[1..];
+        let storage = &client.server().storage;
+        let db = client.db();
+        let now = storage.now_ms();
+        // The groups or consumers, each as the fields describing it.
+        let info = match subcommand.as_str() {
+            "stream" if args.len() == 1 => {
+                return match storage.stream_info(db, &args[0].0, stream_info) {
+                    Ok(Some(info)) => Box::new(info_reply(client, info)),
+                    Ok(None) => error("no such key"),
+                    Err(e) => storage_error(e),
+                };
+            }
+            "groups" if args.len() == 1 => storage.stream_info(db, &args[0].0, |stream| {
+                let groups = stream.groups().iter();
+                Some(
+                    groups
+                        .map(|(name, group)| group_info(stream, name, group))
+                        .collect(),
+                )
+            }),
+            "consumers" if args.len() == 2 => storage.stream_info(db, &args[0].0, |stream| {
+                let group = stream.group(&args[1].0)?;
+                Some(consumers_info(group, now))
+            }),
+            "stream" | "groups" | "consumers" => {
+                return wrong_number_of_arguments(&format!("xinfo|{}", subcommand))
+            }
+            _ => {
+                return error(&format!(
+                    "unknown subcommand '{}'. Try XINFO HELP.",
+                    options[0].0
+                ))
+            }
+        };
+        match info {
+            Ok(Some(Some(info))) => Box::new(Value::Array(Array(
+                info.into_iter()
+                    .map(|fields| info_reply(client, fields))
+                    .collect(),
+            ))),
+            Ok(Some(None)) => no_such_group(&args[0].0, &args[1].0),
+            Ok(None) => error("no such key"),
+            Err(e) => storage_error(e),
+        }
+    }
+}
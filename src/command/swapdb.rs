@@ -0,0 +1,23 @@
+use crate::client::Client;
+use crate::command::select::parse_db_index;
+use crate::command::{ok, wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::serialize::Serialize;
+pub struct SwapDbCommand;
+impl Execute for SwapDbCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 2 {
+            return wrong_number_of_arguments("swapdb");
+        }
+        let first = match parse_db_index(client, &options[0].0, "invalid first DB index") {
+            Ok(db) => db,
+            Err(e) => return e,
+        };
+        let second = match parse_db_index(client, &options[1].0, "invalid second DB index") {
+            Ok(db) => db,
+            Err(e) => return e,
+        };
+        client.server().storage.swap(first, second);
+        ok()
+    }
+}
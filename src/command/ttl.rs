@@ -0,0 +1,70 @@
+use crate::client::Client;
+use crate::command::{wrong_number_of_arguments, Execute};
+use crate::value::bulk_string::BulkString;
+use crate::value::integer::Integer;
+use crate::value::serialize::Serialize;
+use crate::value::Value;
+pub struct TtlCommand;
+pub struct PTtlCommand;
+pub struct ExpireTimeCommand;
+pub struct PExpireTimeCommand;
+pub struct PersistCommand;
+
+/// Shared implementation of `TTL`, `PTTL`, `EXPIRETIME` and `PEXPIRETIME`:
+/// -2 if the key does not exist, -1 if it has no timeout, otherwise the time
+/// left (or the deadline if `absolute`) in units of `unit_ms` milliseconds.
+fn ttl(
+    name: &str,
+    options: &[BulkString],
+    client: &Client,
+    unit_ms: u128,
+    absolute: bool,
+) -> Box<dyn Serialize> {
+    if options.len() != 1 {
+        return wrong_number_of_arguments(name);
+    }
+    let storage = &client.server().storage;
+    let reply = match storage.expire_time(client.db(), &options[0].0) {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(deadline)) => {
+            let time = if absolute {
+                deadline
+            } else {
+                deadline.saturating_sub(storage.now_ms())
+            };
+            ((time + unit_ms / 2) / unit_ms) as i64
+        }
+    };
+    Box::new(Value::Integer(Integer(reply)))
+}
+
+impl Execute for TtlCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("ttl", &options, client, 1000, false)
+    }
+}
+impl Execute for PTtlCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("pttl", &options, client, 1, false)
+    }
+}
+impl Execute for ExpireTimeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("expiretime", &options, client, 1000, true)
+    }
+}
+impl Execute for PExpireTimeCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        ttl("pexpiretime", &options, client, 1, true)
+    }
+}
+impl Execute for PersistCommand {
+    fn execute(self, options: Vec<BulkString>, client: &Client) -> Box<dyn Serialize> {
+        if options.len() != 1 {
+            return wrong_number_of_arguments("persist");
+        }
+        let persisted = client.server().storage.persist(client.db(), &options[0].0);
+        Box::new(Value::Integer(Integer(persisted as i64)))
+    }
+}
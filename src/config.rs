@@ -0,0 +1,38 @@
+use crate::clock::{Clock, SystemClock};
+use crate::constants::{DATABASES, HZ, SHARDS, WATCH_CAPACITY};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Settings of a server instance, fixed when the server starts.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Number of logical databases available to `SELECT`.
+    pub databases: usize,
+    /// Number of independently locked shards each database is split in.
+    pub shards: usize,
+    /// Active expiration cycles per second, like the `hz` directive.
+    pub hz: usize,
+    /// Password of the default user, like the `requirepass` directive.
+    pub requirepass: Option<String>,
+    /// File used to load ACL users at startup and by `ACL LOAD` / `ACL SAVE`.
+    pub aclfile: Option<PathBuf>,
+    /// Number of keyspace events buffered for `Db::watch` subscribers before
+    /// the slowest ones start missing events.
+    pub watch_capacity: usize,
+    /// Time source of key expiry, replaced by a `MockClock` in tests.
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            databases: DATABASES,
+            shards: SHARDS,
+            hz: HZ,
+            requirepass: None,
+            aclfile: None,
+            watch_capacity: WATCH_CAPACITY,
+            clock: Arc::new(SystemClock::new()),
+        }
+    }
+}
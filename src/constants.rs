@@ -1,3 +1,7 @@
 pub const CRLF: &str = "\r\n";
 pub const PORT: &str = "6378";
 pub const IP: &str = "127.0.0.1";
+pub const DATABASES: usize = 16;
+pub const SHARDS: usize = 16;
+pub const HZ: usize = 10;
+pub const WATCH_CAPACITY: usize = 1024;
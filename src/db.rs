@@ -0,0 +1,501 @@
+use crate::config::Config;
+use crate::error::{RedisError, Result};
+use crate::object::RedisObject;
+use crate::server::Server;
+use crate::storage::{Expiry, ListEnd, Update};
+use crate::watch::Watch;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    /// Only set the key if it does not already exist.
+    NX,
+    /// Only set the key if it already exists.
+    XX,
+}
+
+/// Options of [`Db::set_with`], the same ones `SET` accepts.
+#[derive(Debug, Clone)]
+pub struct SetOptions {
+    pub condition: Option<SetCondition>,
+    pub expiry: Expiry,
+}
+
+impl Default for SetOptions {
+    fn default() -> Self {
+        SetOptions {
+            condition: None,
+            expiry: Expiry::INFINITE,
+        }
+    }
+}
+
+impl SetOptions {
+    pub fn nx(mut self) -> Self {
+        self.condition = Some(SetCondition::NX);
+        self
+    }
+    pub fn xx(mut self) -> Self {
+        self.condition = Some(SetCondition::XX);
+        self
+    }
+    /// Expire the key after `duration`, like `PX`.
+    pub fn expire_in(mut self, duration: Duration) -> Self {
+        self.expiry = Expiry::DURATION(duration.as_millis() as u64);
+        self
+    }
+    /// Expire the key at `time`, like `PXAT`.
+    pub fn expire_at(mut self, time: SystemTime) -> Self {
+        let epoch_ms = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        self.expiry = Expiry::EPOCH(epoch_ms);
+        self
+    }
+}
+
+/// Typed access to one logical database of a server, without going through
+/// RESP. It uses the same storage engine as the commands, so values written
+/// here are visible to network clients and the other way around.
+#[derive(Clone)]
+pub struct Db {
+    server: Arc<Server>,
+    index: usize,
+}
+
+impl Db {
+    /// Opens a server without a network listener and returns its database 0.
+    pub fn open(config: Config) -> Result<Self> {
+        Server::open(config)?.db(0)
+    }
+
+    pub(crate) fn new(server: Arc<Server>, index: usize) -> Self {
+        Db { server, index }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Another database of the same server.
+    pub fn select(&self, index: usize) -> Result<Self> {
+        self.server.db(index)
+    }
+
+    /// The string held by `key`. Fails with `WrongType` if the key holds
+    /// another type.
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.server.storage.get(self.index, key)
+    }
+
+    pub fn set(&self, key: &str, value: &str) {
+        self.set_with(key, value, SetOptions::default());
+    }
+
+    /// Sets the key with the `SET` options. Returns false if the NX/XX
+    /// condition was not met and nothing was written.
+    pub fn set_with(&self, key: &str, value: &str, options: SetOptions) -> bool {
+        let storage = &self.server.storage;
+        let value = value.to_owned();
+        match options.condition {
+            None => {
+                storage.insert(self.index, key.to_owned(), value, options.expiry);
+                true
+            }
+            Some(SetCondition::NX) => {
+                storage.insert_if_absent(self.index, key, value, options.expiry)
+            }
+            Some(SetCondition::XX) => {
+                storage.replace_if_present(self.index, key, value, options.expiry)
+            }
+        }
+    }
+
+    /// Like `SET ... GET`: sets the key with the options and returns its
+    /// previous value, whether or not the NX/XX condition was met. Fails
+    /// without writing if the key holds something else than a string.
+    pub fn set_get(&self, key: &str, value: &str, options: SetOptions) -> Result<Option<Vec<u8>>> {
+        self.update(key, |current| {
+            let previous = match current {
+                None => None,
+                Some(RedisObject::String(current)) => Some(current.clone()),
+                Some(_) => return (Update::Keep, Err(RedisError::WrongType)),
+            };
+            let write = match options.condition {
+                None => true,
+                Some(SetCondition::NX) => previous.is_none(),
+                Some(SetCondition::XX) => previous.is_some(),
+            };
+            if write {
+                let value = RedisObject::String(value.as_bytes().to_vec());
+                (Update::Set(value, options.expiry), Ok(previous))
+            } else {
+                (Update::Keep, Ok(previous))
+            }
+        })
+    }
+
+    /// Sets the key without a timeout and returns its previous value. Fails
+    /// without writing if the key holds something else than a string.
+    pub fn get_set(&self, key: &str, value: &str) -> Result<Option<Vec<u8>>> {
+        self.server
+            .storage
+            .get_and_set(self.index, key, value.to_owned(), Expiry::INFINITE)
+    }
+
+    /// Sets the key without a timeout if it holds the string `expected`,
+    /// `None` meaning the key must not exist. Returns whether it was written.
+    pub fn compare_and_swap(&self, key: &str, expected: Option<&str>, value: &str) -> bool {
+        self.server.storage.compare_and_swap(
+            self.index,
+            key,
+            expected,
+            value.to_owned(),
+            Expiry::INFINITE,
+        )
+    }
+
+    /// Reads the key and writes it back atomically: `f` gets the current
+    /// value and returns what to do with the key plus the result of the call.
+    pub fn update<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(Option<&mut RedisObject>) -> (Update, R),
+    ) -> R {
+        self.server.storage.update(self.index, key, f)
+    }
+
+    pub fn del(&self, keys: &[&str]) -> usize {
+        self.server
+            .storage
+            .delete(self.index, keys.iter().map(|key| key.to_string()).collect())
+    }
+
+    pub fn exists(&self, key: &str) -> bool {
+        self.server.storage.contains_key(self.index, key)
+    }
+
+    /// Name of the type of the value held by `key`, as `TYPE` reports it.
+    pub fn key_type(&self, key: &str) -> Option<&'static str> {
+        self.server.storage.type_of(self.index, key)
+    }
+
+    /// Sets a timeout on an existing key. Returns false if the key does not exist.
+    pub fn expire(&self, key: &str, duration: Duration) -> bool {
+        self.server.storage.expire(
+            self.index,
+            key,
+            Expiry::DURATION(duration.as_millis() as u64),
+        )
+    }
+
+    /// Removes the timeout of a key. Returns false if the key does not exist
+    /// or has no timeout.
+    pub fn persist(&self, key: &str) -> bool {
+        self.server.storage.persist(self.index, key)
+    }
+
+    /// Time left before the key expires, `None` if the key does not exist or
+    /// has no timeout.
+    pub fn ttl(&self, key: &str) -> Option<Duration> {
+        self.server.storage.ttl(self.index, key)
+    }
+
+    /// Pushes `elements` to the head of the list at `key`, creating it if
+    /// needed. Returns the new length.
+    pub fn lpush(&self, key: &str, elements: &[&str]) -> Result<usize> {
+        self.push(key, ListEnd::Left, elements)
+    }
+
+    /// Pushes `elements` to the tail of the list at `key`, creating it if
+    /// needed. Returns the new length.
+    pub fn rpush(&self, key: &str, elements: &[&str]) -> Result<usize> {
+        self.push(key, ListEnd::Right, elements)
+    }
+
+    fn push(&self, key: &str, end: ListEnd, elements: &[&str]) -> Result<usize> {
+        let elements = elements.iter().map(|element| element.to_string()).collect();
+        self.server
+            .storage
+            .list_push(self.index, key, end, elements, false)
+    }
+
+    /// Removes and returns the first element of the list at `key`.
+    pub fn lpop(&self, key: &str) -> Result<Option<String>> {
+        let popped = self
+            .server
+            .storage
+            .list_pop(self.index, key, ListEnd::Left, 1)?;
+        Ok(popped.and_then(|elements| elements.into_iter().next()))
+    }
+
+    /// Removes and returns the last element of the list at `key`.
+    pub fn rpop(&self, key: &str) -> Result<Option<String>> {
+        let popped = self
+            .server
+            .storage
+            .list_pop(self.index, key, ListEnd::Right, 1)?;
+        Ok(popped.and_then(|elements| elements.into_iter().next()))
+    }
+
+    /// Elements between `start` and `stop` included, negative indexes
+    /// counting from the tail.
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<String>> {
+        self.server.storage.list_range(self.index, key, start, stop)
+    }
+
+    pub fn llen(&self, key: &str) -> Result<usize> {
+        self.server.storage.list_len(self.index, key)
+    }
+
+    /// Sets fields of the hash at `key`, creating it if needed. Returns the
+    /// number of fields that were added.
+    pub fn hset(&self, key: &str, pairs: &[(&str, &str)]) -> Result<usize> {
+        let pairs = pairs
+            .iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect();
+        self.server.storage.hash_set(self.index, key, pairs)
+    }
+
+    pub fn hget(&self, key: &str, field: &str) -> Result<Option<String>> {
+        self.server.storage.hash_get(self.index, key, field)
+    }
+
+    /// All the fields of the hash at `key`, empty if the key does not exist.
+    pub fn hgetall(&self, key: &str) -> Result<HashMap<String, String>> {
+        let pairs = self.server.storage.hash_get_all(self.index, key)?;
+        Ok(pairs.into_iter().collect())
+    }
+
+    /// Removes fields of the hash at `key`. Returns the number of fields that
+    /// were removed.
+    pub fn hdel(&self, key: &str, fields: &[&str]) -> Result<usize> {
+        let fields: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+        self.server.storage.hash_delete(self.index, key, &fields)
+    }
+
+    /// Subscribes to the changes of the keys matching the glob `pattern`,
+    /// whether they come from this handle or from network clients. Events of
+    /// a mutation are published before it returns.
+    pub fn watch(&self, pattern: &str) -> Watch {
+        self.server.storage.watch(self.index, pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::watch::{KeyEvent, KeyEventKind, Lagged};
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn test_set_options() {
+        let db = Db::open(Config::default()).unwrap();
+        assert!(!db.set_with("key", "1", SetOptions::default().xx()));
+        assert_eq!(db.get("key").unwrap(), None);
+        assert!(db.set_with("key", "1", SetOptions::default().nx()));
+        assert!(!db.set_with("key", "2", SetOptions::default().nx()));
+        assert_eq!(db.get("key").unwrap(), Some(b"1".to_vec()));
+        assert!(db.set_with("key", "2", SetOptions::default().xx()));
+        assert_eq!(db.get("key").unwrap(), Some(b"2".to_vec()));
+
+        assert_eq!(db.select(1).unwrap().get("key").unwrap(), None);
+        let databases = Config::default().databases;
+        assert!(db.select(databases - 1).is_ok());
+        assert!(matches!(
+            db.select(databases),
+            Err(RedisError::DbIndexOutOfRange)
+        ));
+        assert_eq!(db.del(&["key", "missing"]), 1);
+        assert!(!db.exists("key"));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let db = Db::open(Config::default()).unwrap();
+        db.set("key", "value");
+        assert_eq!(db.ttl("key"), None);
+        assert!(db.expire("key", Duration::from_secs(100)));
+        assert!(db.ttl("key").unwrap() > Duration::from_secs(99));
+        assert!(!db.expire("missing", Duration::from_secs(100)));
+
+        db.set_with(
+            "past",
+            "value",
+            SetOptions::default().expire_at(UNIX_EPOCH + Duration::from_secs(1)),
+        );
+        assert_eq!(db.get("past").unwrap(), None);
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let config = Config {
+            clock: clock.clone(),
+            ..Config::default()
+        };
+        let db = Db::open(config).unwrap();
+        db.set_with(
+            "in",
+            "value",
+            SetOptions::default().expire_in(Duration::from_secs(10)),
+        );
+        db.set_with(
+            "at",
+            "value",
+            SetOptions::default().expire_at(UNIX_EPOCH + Duration::from_secs(1_005)),
+        );
+        assert_eq!(db.ttl("in"), Some(Duration::from_secs(10)));
+        assert_eq!(db.ttl("at"), Some(Duration::from_secs(5)));
+
+        clock.advance(Duration::from_secs(5));
+        assert!(db.exists("at"));
+        clock.advance(Duration::from_millis(1));
+        assert!(!db.exists("at"));
+        assert_eq!(db.ttl("in"), Some(Duration::from_millis(4_999)));
+
+        // Wall clock jumping backwards.
+        clock.set(0);
+        assert!(db.exists("in"));
+        clock.set(2_000_000);
+        assert!(!db.exists("in"));
+    }
+
+    fn event(
+        db: usize,
+        key: &str,
+        kind: KeyEventKind,
+    ) -> Option<std::result::Result<KeyEvent, Lagged>> {
+        Some(Ok(KeyEvent {
+            db,
+            key: key.to_owned(),
+            kind,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let db = Db::open(Config::default()).unwrap();
+        let mut watch = db.watch("user:*");
+        db.set("user:1", "a");
+        db.set("session:1", "b");
+        db.select(1).unwrap().set("user:2", "c");
+        db.expire("user:1", Duration::from_secs(100));
+        db.del(&["user:1", "session:1"]);
+        db.set_with(
+            "user:3",
+            "d",
+            SetOptions::default().expire_at(UNIX_EPOCH + Duration::from_secs(1)),
+        );
+        db.get("user:3").unwrap();
+
+        assert_eq!(watch.next().await, event(0, "user:1", KeyEventKind::Set));
+        assert_eq!(watch.next().await, event(0, "user:1", KeyEventKind::Expire));
+        assert_eq!(watch.next().await, event(0, "user:1", KeyEventKind::Del));
+        assert_eq!(watch.next().await, event(0, "user:3", KeyEventKind::Set));
+        assert_eq!(watch.next().await, event(0, "user:3", KeyEventKind::Evict));
+    }
+
+    #[tokio::test]
+    async fn test_watch_lag() {
+        let config = Config {
+            watch_capacity: 4,
+            ..Config::default()
+        };
+        let db = Db::open(config).unwrap();
+        let mut watch = db.watch("*");
+        for i in 0..10 {
+            db.set(&i.to_string(), "value");
+        }
+        assert_eq!(watch.next().await, Some(Err(Lagged { missed: 6 })));
+        for i in 6..10 {
+            assert_eq!(
+                watch.next().await,
+                event(0, &i.to_string(), KeyEventKind::Set)
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_conditional_sets() {
+        let db = Db::open(Config::default()).unwrap();
+        for round in 0..50 {
+            let key = format!("key:{}", round);
+            let tasks: Vec<_> = (0..32)
+                .map(|task| {
+                    let (db, key) = (db.clone(), key.clone());
+                    tokio::spawn(async move {
+                        if task % 2 == 0 {
+                            db.set_with(&key, &format!("nx:{}", task), SetOptions::default().nx())
+                        } else {
+                            db.set_with(&key, &format!("xx:{}", task), SetOptions::default().xx())
+                        }
+                    })
+                })
+                .collect();
+            let mut nx_written = 0;
+            let mut xx_written = 0;
+            for (task, handle) in tasks.into_iter().enumerate() {
+                match (task % 2 == 0, handle.await.unwrap()) {
+                    (true, true) => nx_written += 1,
+                    (false, true) => xx_written += 1,
+                    _ => {}
+                }
+            }
+            assert_eq!(nx_written, 1);
+            let value = db.get(&key).unwrap().unwrap();
+            assert_eq!(value.starts_with(b"xx:"), xx_written > 0);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_updates() {
+        let db = Db::open(Config::default()).unwrap();
+        let tasks: Vec<_> = (0..16)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    for _ in 0..200 {
+                        db.update("counter", |current| {
+                            let count = match current {
+                                Some(RedisObject::String(value)) => {
+                                    std::str::from_utf8(value).unwrap().parse::<u64>().unwrap()
+                                }
+                                _ => 0,
+                            };
+                            let value = RedisObject::String((count + 1).to_string().into_bytes());
+                            (Update::Set(value, Expiry::INFINITE), ())
+                        });
+                        loop {
+                            let current = db.get("cas").unwrap();
+                            let current = current
+                                .as_deref()
+                                .map(|value| std::str::from_utf8(value).unwrap());
+                            let next = current.map_or(0, |value| value.parse::<u64>().unwrap()) + 1;
+                            if db.compare_and_swap("cas", current, &next.to_string()) {
+                                break;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(db.get("counter").unwrap(), Some(b"3200".to_vec()));
+        assert_eq!(db.get("cas").unwrap(), Some(b"3200".to_vec()));
+        assert_eq!(db.get_set("counter", "0").unwrap(), Some(b"3200".to_vec()));
+        assert_eq!(
+            db.set_get("counter", "1", SetOptions::default().nx())
+                .unwrap(),
+            Some(b"0".to_vec())
+        );
+        assert_eq!(db.get("counter").unwrap(), Some(b"0".to_vec()));
+    }
+}
@@ -51,6 +51,54 @@ pub enum RedisError {
 
     #[error("Key has been expired")]
     ExpiredKey,
+
+    #[error("Operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    #[error("hash value is not an integer")]
+    HashValueNotInteger,
+
+    #[error("hash value is not a float")]
+    HashValueNotFloat,
+
+    #[error("increment or decrement would overflow")]
+    Overflow,
+
+    #[error("increment would produce NaN or Infinity")]
+    NaNOrInfinity,
+
+    #[error("resulting score is not a number (NaN)")]
+    ScoreNaN,
+
+    #[error("The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+
+    #[error("The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+
+    #[error("The stream has exhausted the last possible ID, unable to add more items")]
+    StreamExhausted,
+
+    #[error("No such key '{key}' or consumer group '{group}'")]
+    NoGroup { key: String, group: String },
+
+    #[error("Consumer Group name already exists")]
+    BusyGroup,
+
+    #[error("Key is not a valid HyperLogLog string value.")]
+    NotHyperLogLog,
+
+    #[error("Corrupted HLL object detected")]
+    CorruptedHll,
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("DB index is out of range")]
+    DbIndexOutOfRange,
+
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
 }
 
 pub type Result<T> = std::result::Result<T, RedisError>;
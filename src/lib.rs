@@ -1,18 +1,33 @@
+pub mod acl;
+pub mod client;
+pub mod clock;
 pub mod command;
+pub mod config;
 pub mod constants;
-mod error;
+pub mod db;
+pub mod error;
+pub mod object;
+pub mod pattern;
+pub mod server;
 pub mod storage;
 pub mod value;
+pub mod watch;
 
+use crate::acl::{Denied, LogReason, DEFAULT_USER};
+use crate::client::Client;
 use crate::command::Execute;
 use crate::error::{RedisError, Result};
+use crate::server::Server;
+use crate::storage::Unblocked;
 use crate::value::serialize::Serialize;
 use command::Command;
+use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use value::array::Array;
 use value::bulk_string::BulkString;
 use value::deserialize::Deserialize;
+use value::nulls::Nulls;
 use value::simple_error::{ErrorType, SimpleError};
 use value::Value;
 
@@ -43,7 +58,47 @@ fn check_crlf(value: &[u8], index: usize) -> Result<bool> {
     }
 }
 
-fn execute(request: Array) -> String {
+/// Checks that the client is authenticated and that its ACL user may run the command.
+fn check_permissions(
+    command: &Command,
+    subcommand: Option<&str>,
+    options: &[BulkString],
+    client: &Client,
+) -> std::result::Result<(), SimpleError> {
+    let acl = &client.server().acl;
+    let Some(user) = client.user().and_then(|name| acl.get_user(&name)) else {
+        return Err(SimpleError {
+            error_type: ErrorType::NOAUTH,
+            message: String::from("Authentication required."),
+        });
+    };
+    let spec = command.spec();
+    let Err(denied) = user.check(spec, subcommand, &spec.keys(options)) else {
+        return Ok(());
+    };
+    let (reason, object, message) = match denied {
+        Denied::Command(name) => (
+            LogReason::Command,
+            name.clone(),
+            format!(
+                "User {} has no permissions to run the '{}' command",
+                user.name, name
+            ),
+        ),
+        Denied::Key(key) => (
+            LogReason::Key,
+            key,
+            String::from("No permissions to access a key"),
+        ),
+    };
+    acl.log(reason, &object, &user.name, client.info().trim_end());
+    Err(SimpleError {
+        error_type: ErrorType::NOPERM,
+        message,
+    })
+}
+
+async fn execute(request: Array, client: &Client) -> Vec<u8> {
     let mut command: Option<Command> = None;
     let mut options: Vec<BulkString> = vec![];
     let mut requested_command: String = String::new();
@@ -74,18 +129,87 @@ fn execute(request: Array) -> String {
         })
         .serialize();
     }
-    let response = command.unwrap().execute(options);
-    response.serialize()
+    let command = command.unwrap();
+    let subcommand = if command.is_container() {
+        options
+            .first()
+            .map(|subcommand| subcommand.0.to_lowercase())
+    } else {
+        None
+    };
+    // HELLO authenticates the client itself when given AUTH.
+    let authenticating = matches!(command, Command::AUTH(_))
+        || matches!(command, Command::HELLO(_)) && client.user().is_none();
+    if !authenticating {
+        if let Err(e) = check_permissions(&command, subcommand.as_deref(), &options, client) {
+            return Value::SimpleError(e).serialize();
+        }
+    }
+    // CLIENT commands are never paused, otherwise nobody could unpause.
+    if !matches!(command, Command::CLIENT(_)) {
+        client
+            .server()
+            .clients
+            .wait_if_paused(command.spec().is_write())
+            .await;
+    }
+    let command_name = match &subcommand {
+        Some(subcommand) => format!("{}|{}", command.name(), subcommand),
+        None => command.name().to_owned(),
+    };
+    let mut response = command.execute(options, client).serialize();
+    if let Some((blocked, timeout)) = client.take_blocked() {
+        response = match blocked.wait(timeout).await {
+            Unblocked::Served(reply) => reply.serialize(),
+            Unblocked::Timeout => Value::Nulls(Nulls).serialize(),
+            Unblocked::Error => Value::SimpleError(SimpleError {
+                error_type: ErrorType::UNBLOCKED,
+                message: String::from("client unblocked via CLIENT UNBLOCK"),
+            })
+            .serialize(),
+        };
+    }
+    client.record_command(&command_name, response.len());
+    response
+}
+
+/// Resolves once the peer closed the connection. Never resolves if it sent
+/// more data, which is left in the socket for the next read.
+async fn disconnected(stream: &TcpStream) {
+    let mut buf = [0; 1];
+    match stream.peek(&mut buf).await {
+        Ok(0) | Err(_) => {}
+        Ok(_) => std::future::pending().await,
+    }
 }
 
-pub async fn handle_connection(mut stream: TcpStream) {
+pub async fn handle_connection(server: Arc<Server>, mut stream: TcpStream) {
     println!("Handling new connection");
+    let (Ok(addr), Ok(laddr)) = (stream.peer_addr(), stream.local_addr()) else {
+        return;
+    };
+    let client = server.clients.register(server.clone(), addr, laddr);
+    if server
+        .acl
+        .get_user(DEFAULT_USER)
+        .is_some_and(|user| user.is_enabled() && user.is_nopass())
+    {
+        client.authenticate(DEFAULT_USER);
+    }
     loop {
         let mut buf = vec![0; 512];
-        let bytes_read = stream.read(&mut buf).await.unwrap();
+        let bytes_read = tokio::select! {
+            biased;
+            _ = client.killed() => break,
+            bytes_read = stream.read(&mut buf) => match bytes_read {
+                Ok(bytes_read) => bytes_read,
+                Err(_) => break,
+            },
+        };
         if bytes_read == 0 {
             break;
         }
+        client.record_request(bytes_read);
         let value = Value::deserialize(&buf[..]);
         match value {
             Err(e) => {
@@ -93,9 +217,69 @@ pub async fn handle_connection(mut stream: TcpStream) {
             }
             Ok(val) => {
                 if let Value::Array(arr) = val.value {
-                    let _ = stream.write_all(execute(arr).as_bytes()).await;
+                    // A blocked command is cancelled when the connection goes away.
+                    let response = tokio::select! {
+                        response = execute(arr, &client) => response,
+                        _ = client.killed() => break,
+                        _ = disconnected(&stream) => break,
+                    };
+                    if client.consume_reply() {
+                        let _ = stream.write_all(&response).await;
+                    }
                 }
             }
         }
     }
+    server.clients.unregister(client.id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    async fn run(client: &Client, args: &[&str]) -> String {
+        let request = args
+            .iter()
+            .map(|arg| Value::BulkString(BulkString(arg.to_string())))
+            .collect();
+        String::from_utf8(execute(Array(request), client).await).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commands_are_checked_against_the_acl() {
+        let server = Server::open(Config::default()).unwrap();
+        let addr = "127.0.0.1:6378".parse().unwrap();
+        let client = server.clients.register(server.clone(), addr, addr);
+        assert_eq!(
+            run(&client, &["GET", "k"]).await,
+            "-NOAUTH Authentication required.\r\n"
+        );
+
+        let rules = ["on", "nopass", "+@read", "~cache:*", "%R~ro:*"];
+        server.acl.set_user("alice", &rules).unwrap();
+        assert_eq!(run(&client, &["AUTH", "alice", "any"]).await, "+OK\r\n");
+        assert_eq!(run(&client, &["GET", "cache:1"]).await, "_\r\n");
+        assert_eq!(
+            run(&client, &["SET", "cache:1", "v"]).await,
+            "-NOPERM User alice has no permissions to run the 'set' command\r\n"
+        );
+        assert_eq!(
+            run(&client, &["GET", "other"]).await,
+            "-NOPERM No permissions to access a key\r\n"
+        );
+        let denials: Vec<(LogReason, String)> = server
+            .acl
+            .log_entries(10)
+            .into_iter()
+            .map(|entry| (entry.reason, entry.object))
+            .collect();
+        assert_eq!(
+            denials,
+            [
+                (LogReason::Key, String::from("other")),
+                (LogReason::Command, String::from("set")),
+            ]
+        );
+    }
 }
@@ -1,16 +1,42 @@
-use my_redis::constants::{IP, PORT};
-use my_redis::handle_connection;
-use tokio::net::TcpListener;
+use my_redis::config::Config;
+use my_redis::server::Server;
 
 #[tokio::main]
 async fn main() {
-    let listener = TcpListener::bind(format!("{IP}:{PORT}"))
-        .await
-        .expect(format!("Cannot bind to {IP}:{PORT}").as_str());
-    loop {
-        let (stream, _) = listener.accept().await.unwrap();
-        tokio::spawn(async move {
-            handle_connection(stream).await;
-        });
+    let mut config = Config::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--databases" {
+            config.databases = args
+                .next()
+                .and_then(|count| count.parse::<usize>().ok())
+                .filter(|count| *count > 0)
+                .expect("--databases expects a positive integer");
+        } else if arg == "--shards" {
+            config.shards = args
+                .next()
+                .and_then(|count| count.parse::<usize>().ok())
+                .filter(|count| *count > 0)
+                .expect("--shards expects a positive integer");
+        } else if arg == "--hz" {
+            config.hz = args
+                .next()
+                .and_then(|hz| hz.parse::<usize>().ok())
+                .filter(|hz| (1..=500).contains(hz))
+                .expect("--hz expects an integer between 1 and 500");
+        } else if arg == "--requirepass" {
+            config.requirepass = Some(args.next().expect("--requirepass expects a password"));
+        } else if arg == "--aclfile" {
+            config.aclfile = Some(args.next().expect("--aclfile expects a path").into());
+        }
     }
+    let server = match Server::builder().config(config).start().await {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Aborting startup: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let _ = tokio::signal::ctrl_c().await;
+    server.shutdown().await;
 }
@@ -0,0 +1,488 @@
+use skiplist::SkipList;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::{Bound, Range};
+
+pub mod bitmap;
+mod consumer_group;
+pub mod hyperloglog;
+mod skiplist;
+mod stream;
+
+pub use consumer_group::{Consumer, ConsumerGroup, PendingEntry};
+pub use stream::{
+    AutoClaimed, Claim, Stream, StreamEntry, StreamId, StreamTrim, TrimStrategy, NODE_MAX_ENTRIES,
+};
+
+/// A value stored in the keyspace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedisObject {
+    /// Strings are binary safe: `SETBIT` or `PFADD` write arbitrary bytes.
+    String(Vec<u8>),
+    List(VecDeque<String>),
+    Hash(Hash),
+    Set(Set),
+    SortedSet(SortedSet),
+    Stream(Stream),
+}
+
+impl RedisObject {
+    /// Name of the type as reported by `TYPE`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RedisObject::String(_) => "string",
+            RedisObject::List(_) => "list",
+            RedisObject::Hash(_) => "hash",
+            RedisObject::Set(_) => "set",
+            RedisObject::SortedSet(_) => "zset",
+            RedisObject::Stream(_) => "stream",
+        }
+    }
+
+    /// Whether the value is a collection without elements. Such keys are
+    /// removed, like in Redis where an empty list or hash does not exist.
+    pub fn is_empty_collection(&self) -> bool {
+        match self {
+            RedisObject::String(_) => false,
+            RedisObject::List(list) => list.is_empty(),
+            RedisObject::Hash(hash) => hash.is_empty(),
+            RedisObject::Set(set) => set.is_empty(),
+            RedisObject::SortedSet(set) => set.is_empty(),
+            // Streams exist until deleted, even without entries.
+            RedisObject::Stream(_) => false,
+        }
+    }
+}
+
+/// Fields of a hash, each with an optional timeout. Deadlines are epoch
+/// milliseconds, like the ones of keys.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hash {
+    fields: HashMap<String, String>,
+    deadlines: HashMap<String, u128>,
+    /// The fields with a timeout ordered by deadline.
+    expires: BTreeSet<(u128, String)>,
+}
+
+impl Hash {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+    pub fn get(&self, field: &str) -> Option<&String> {
+        self.fields.get(field)
+    }
+    pub fn contains_key(&self, field: &str) -> bool {
+        self.fields.contains_key(field)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.fields.iter()
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.fields.keys()
+    }
+    pub fn values(&self) -> impl Iterator<Item = &String> {
+        self.fields.values()
+    }
+
+    /// Sets a field and clears its timeout, like `HSET`. Returns the previous value.
+    pub fn insert(&mut self, field: String, value: String) -> Option<String> {
+        self.set_deadline(&field, None);
+        self.fields.insert(field, value)
+    }
+
+    /// Sets a field and keeps its timeout, like `HINCRBY`.
+    pub fn update(&mut self, field: String, value: String) -> Option<String> {
+        self.fields.insert(field, value)
+    }
+
+    pub fn remove(&mut self, field: &str) -> Option<String> {
+        self.set_deadline(field, None);
+        self.fields.remove(field)
+    }
+
+    pub fn deadline(&self, field: &str) -> Option<u128> {
+        self.deadlines.get(field).copied()
+    }
+
+    /// Sets or clears the timeout of an existing field.
+    pub fn set_deadline(&mut self, field: &str, deadline: Option<u128>) {
+        if let Some(current) = self.deadlines.remove(field) {
+            self.expires.remove(&(current, field.to_owned()));
+        }
+        if let Some(deadline) = deadline.filter(|_| self.fields.contains_key(field)) {
+            self.deadlines.insert(field.to_owned(), deadline);
+            self.expires.insert((deadline, field.to_owned()));
+        }
+    }
+
+    /// The earliest deadline of the fields, `None` if no field has a timeout.
+    pub fn next_deadline(&self) -> Option<u128> {
+        self.expires.first().map(|(deadline, _)| *deadline)
+    }
+
+    /// Removes the fields whose deadline is before `now`. Returns how many.
+    pub fn remove_expired(&mut self, now: u128) -> usize {
+        let mut removed = 0;
+        while let Some((deadline, _)) = self.expires.first() {
+            if *deadline >= now {
+                break;
+            }
+            let (_, field) = self.expires.pop_first().expect("checked above");
+            self.deadlines.remove(&field);
+            self.fields.remove(&field);
+            removed += 1;
+        }
+        removed
+    }
+}
+
+impl FromIterator<(String, String)> for Hash {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Hash {
+            fields: iter.into_iter().collect(),
+            ..Hash::default()
+        }
+    }
+}
+
+/// Beyond this many members an integer set is converted to a hash table,
+/// like `set-max-intset-entries` in Redis.
+const INTSET_MAX_ENTRIES: usize = 512;
+
+/// Members of a set. A set holding only integers is stored compactly as a
+/// sorted vector, the intset encoding of Redis, until a member that is not
+/// an integer is added or it grows past `INTSET_MAX_ENTRIES`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Set {
+    Ints(Vec<i64>),
+    Strings(HashSet<String>),
+}
+
+impl Default for Set {
+    fn default() -> Self {
+        Set::Ints(Vec::new())
+    }
+}
+
+/// The integer a member stands for, if it is written the way Redis formats
+/// integers: "1" is one, "01" or "+1" are strings.
+fn as_int(member: &str) -> Option<i64> {
+    member
+        .parse()
+        .ok()
+        .filter(|int: &i64| int.to_string() == member)
+}
+
+impl Set {
+    /// Name of the encoding, as reported by `OBJECT ENCODING` in Redis.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Set::Ints(_) => "intset",
+            Set::Strings(_) => "hashtable",
+        }
+    }
+    pub fn len(&self) -> usize {
+        match self {
+            Set::Ints(ints) => ints.len(),
+            Set::Strings(strings) => strings.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn contains(&self, member: &str) -> bool {
+        match self {
+            Set::Ints(ints) => as_int(member).is_some_and(|int| ints.binary_search(&int).is_ok()),
+            Set::Strings(strings) => strings.contains(member),
+        }
+    }
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        match self {
+            Set::Ints(ints) => Box::new(ints.iter().map(|int| Cow::Owned(int.to_string()))),
+            Set::Strings(strings) => {
+                Box::new(strings.iter().map(|member| Cow::from(member.as_str())))
+            }
+        }
+    }
+    pub fn members(&self) -> Vec<String> {
+        self.iter().map(Cow::into_owned).collect()
+    }
+
+    /// Adds a member, returns false if it was already there.
+    pub fn insert(&mut self, member: String) -> bool {
+        if let Set::Ints(ints) = self {
+            if let Some(int) = as_int(&member) {
+                let Err(index) = ints.binary_search(&int) else {
+                    return false;
+                };
+                if ints.len() < INTSET_MAX_ENTRIES {
+                    ints.insert(index, int);
+                    return true;
+                }
+            }
+            *self = Set::Strings(ints.iter().map(i64::to_string).collect());
+        }
+        match self {
+            Set::Strings(strings) => strings.insert(member),
+            Set::Ints(_) => unreachable!("converted above"),
+        }
+    }
+
+    /// Removes a member, returns false if it was not there.
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self {
+            Set::Ints(ints) => match as_int(member).map(|int| ints.binary_search(&int)) {
+                Some(Ok(index)) => {
+                    ints.remove(index);
+                    true
+                }
+                _ => false,
+            },
+            Set::Strings(strings) => strings.remove(member),
+        }
+    }
+
+    /// `count` members picked at random, that may repeat if `repeat` is set.
+    /// Otherwise they are distinct, so at most the whole set. The members are
+    /// collected once and sampled by index, in O(len + count).
+    pub fn random(&self, count: usize, repeat: bool) -> Vec<String> {
+        let mut members: Vec<Cow<'_, str>> = self.iter().collect();
+        if members.is_empty() {
+            return Vec::new();
+        }
+        if repeat {
+            // Grown as members are picked rather than sized from `count` up
+            // front, which comes from the client.
+            let mut picked = Vec::new();
+            for _ in 0..count {
+                picked.push(members[fastrand::usize(..members.len())].to_string());
+            }
+            return picked;
+        }
+        // A partial Fisher-Yates shuffle: only the first `count` are drawn.
+        let len = members.len();
+        let count = count.min(len);
+        for index in 0..count {
+            members.swap(index, fastrand::usize(index..len));
+        }
+        members.truncate(count);
+        members.into_iter().map(Cow::into_owned).collect()
+    }
+}
+
+impl FromIterator<String> for Set {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        let mut set = Set::default();
+        for member in iter {
+            set.insert(member);
+        }
+        set
+    }
+}
+
+/// Members with a score, ordered by score then member. The scores are
+/// looked up in a hash table and the order is kept in a skiplist, which
+/// finds ranks and score or lexicographical ranges in O(log n).
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    list: SkipList,
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+/// Whether `value` is past the lower `bound` of a range.
+fn above<T: PartialOrd + ?Sized>(value: &T, bound: Bound<&T>) -> bool {
+    match bound {
+        Bound::Included(min) => value >= min,
+        Bound::Excluded(min) => value > min,
+        Bound::Unbounded => true,
+    }
+}
+
+/// Whether `value` is past the upper `bound` of a range.
+fn beyond<T: PartialOrd + ?Sized>(value: &T, bound: Bound<&T>) -> bool {
+    match bound {
+        Bound::Included(max) => value > max,
+        Bound::Excluded(max) => value >= max,
+        Bound::Unbounded => false,
+    }
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&String, f64)> {
+        self.scores.iter().map(|(member, score)| (member, *score))
+    }
+
+    /// Adds a member or updates its score. Returns the previous score.
+    pub fn insert(&mut self, member: String, score: f64) -> Option<f64> {
+        let previous = self.scores.insert(member.clone(), score);
+        if let Some(previous) = previous {
+            self.list.remove(previous, &member);
+        }
+        self.list.insert(score, member);
+        previous
+    }
+
+    /// Removes a member, returns its score.
+    pub fn remove(&mut self, member: &str) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.list.remove(score, member);
+        Some(score)
+    }
+
+    /// 0-based rank of a member in ascending order.
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.list.count_before(|other_score, other| {
+            other_score > score || (other_score == score && other >= member)
+        }))
+    }
+
+    /// Ranks of the members whose score is in the range.
+    pub fn score_ranks(&self, min: Bound<f64>, max: Bound<f64>) -> Range<usize> {
+        let start = self
+            .list
+            .count_before(|score, _| above(&score, min.as_ref()));
+        let end = self
+            .list
+            .count_before(|score, _| beyond(&score, max.as_ref()));
+        start..end.max(start)
+    }
+
+    /// Ranks of the members in the lexicographical range. Only meaningful
+    /// when all the members have the same score, like `ZRANGEBYLEX` assumes.
+    pub fn lex_ranks(&self, min: Bound<&str>, max: Bound<&str>) -> Range<usize> {
+        let start = self.list.count_before(|_, member| above(member, min));
+        let end = self.list.count_before(|_, member| beyond(member, max));
+        start..end.max(start)
+    }
+
+    /// Members and scores with a rank in `ranks`, in descending order if `rev`.
+    pub fn range(&self, ranks: Range<usize>, rev: bool) -> Vec<(String, f64)> {
+        self.list.range(ranks, rev)
+    }
+}
+
+impl FromIterator<(String, f64)> for SortedSet {
+    fn from_iter<I: IntoIterator<Item = (String, f64)>>(iter: I) -> Self {
+        let mut set = SortedSet::default();
+        for (member, score) in iter {
+            set.insert(member, score);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_field_deadlines() {
+        let mut hash: Hash = [("a", "1"), ("b", "2"), ("c", "3")]
+            .into_iter()
+            .map(|(field, value)| (field.to_owned(), value.to_owned()))
+            .collect();
+        hash.set_deadline("a", Some(300));
+        hash.set_deadline("b", Some(100));
+        hash.set_deadline("missing", Some(50));
+        assert_eq!(hash.next_deadline(), Some(100));
+
+        // Updating keeps the timeout, inserting clears it.
+        hash.update(String::from("b"), String::from("20"));
+        assert_eq!(hash.deadline("b"), Some(100));
+        hash.insert(String::from("a"), String::from("10"));
+        assert_eq!(hash.deadline("a"), None);
+
+        assert_eq!(hash.remove_expired(100), 0);
+        assert_eq!(hash.remove_expired(101), 1);
+        assert_eq!(hash.len(), 2);
+        assert_eq!(hash.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_set_encoding() {
+        let mut set: Set = ["3", "1", "2"].into_iter().map(String::from).collect();
+        assert_eq!(set.encoding(), "intset");
+        assert_eq!(set.members(), ["1", "2", "3"]);
+        assert!(!set.insert(String::from("2")));
+        // Not the canonical form of an integer.
+        assert!(!set.contains("02"));
+        assert!(set.remove("1"));
+        assert!(set.insert(String::from("02")));
+        assert_eq!(set.encoding(), "hashtable");
+        assert!(set.contains("2") && set.contains("02"));
+        assert_eq!(set.len(), 3);
+
+        let large: Set = (0..=INTSET_MAX_ENTRIES).map(|i| i.to_string()).collect();
+        assert_eq!(large.encoding(), "hashtable");
+        assert_eq!(large.len(), INTSET_MAX_ENTRIES + 1);
+
+        let mut picked = large.random(100, false);
+        picked.sort();
+        picked.dedup();
+        assert_eq!(picked.len(), 100);
+        assert!(picked.iter().all(|member| large.contains(member)));
+        assert_eq!(set.random(10, false).len(), 3);
+        let repeated = set.random(10, true);
+        assert_eq!(repeated.len(), 10);
+        assert!(repeated.iter().all(|member| set.contains(member)));
+        assert!(Set::default().random(10, true).is_empty());
+    }
+
+    #[test]
+    fn test_sorted_set_ranges() {
+        let mut set = SortedSet::default();
+        for (member, score) in [("a", 1.0), ("b", 2.0), ("c", 2.0), ("d", 3.0)] {
+            set.insert(member.to_owned(), score);
+        }
+        assert_eq!(set.insert(String::from("a"), 2.5), Some(1.0));
+        assert_eq!(set.rank("b"), Some(0));
+        assert_eq!(set.rank("a"), Some(2));
+        assert_eq!(
+            set.range(0..2, true),
+            [(String::from("c"), 2.0), (String::from("b"), 2.0)]
+        );
+        assert_eq!(
+            set.score_ranks(Bound::Included(2.0), Bound::Excluded(3.0)),
+            0..3
+        );
+        assert_eq!(
+            set.score_ranks(Bound::Excluded(2.0), Bound::Unbounded),
+            2..4
+        );
+        assert_eq!(
+            set.score_ranks(Bound::Included(5.0), Bound::Included(1.0)),
+            4..4
+        );
+        assert_eq!(set.remove("b"), Some(2.0));
+        assert_eq!(set.rank("d"), Some(2));
+
+        let mut set = SortedSet::default();
+        for member in ["a", "b", "c", "d"] {
+            set.insert(member.to_owned(), 0.0);
+        }
+        assert_eq!(
+            set.lex_ranks(Bound::Excluded("a"), Bound::Included("c")),
+            1..3
+        );
+        assert_eq!(set.lex_ranks(Bound::Unbounded, Bound::Excluded("b")), 0..1);
+    }
+}
@@ -0,0 +1,211 @@
+//! Bit operations on string values. Like in Redis, bit 0 is the most
+//! significant bit of the first byte, and bits past the end of the string
+//! read as zero.
+
+use std::ops::RangeInclusive;
+
+pub fn get_bit(bytes: &[u8], offset: u64) -> bool {
+    let byte = bytes.get((offset / 8) as usize).copied().unwrap_or(0);
+    byte >> (7 - offset % 8) & 1 == 1
+}
+
+/// Sets a bit, growing the string with zeros if needed. Returns the
+/// previous value of the bit.
+pub fn set_bit(bytes: &mut Vec<u8>, offset: u64, bit: bool) -> bool {
+    let index = (offset / 8) as usize;
+    if bytes.len() <= index {
+        bytes.resize(index + 1, 0);
+    }
+    let mask = 1 << (7 - offset % 8);
+    let previous = bytes[index] & mask != 0;
+    if bit {
+        bytes[index] |= mask;
+    } else {
+        bytes[index] &= !mask;
+    }
+    previous
+}
+
+/// Number of set bits in a range of bits within the string.
+pub fn count(bytes: &[u8], bits: RangeInclusive<u64>) -> u64 {
+    let (start, end) = bits.into_inner();
+    let (first, last) = ((start / 8) as usize, (end / 8) as usize);
+    let ones: u64 = bytes[first..=last]
+        .iter()
+        .map(|byte| byte.count_ones() as u64)
+        .sum();
+    // Minus the bits of the edge bytes out of the range.
+    let before = bytes[first] & !(0xff >> (start % 8));
+    let after = bytes[last] & 0xffu8.checked_shr(end as u32 % 8 + 1).unwrap_or(0);
+    ones - before.count_ones() as u64 - after.count_ones() as u64
+}
+
+/// Offset of the first bit set to `bit` in a range of bits.
+pub fn position(bytes: &[u8], bit: bool, bits: RangeInclusive<u64>) -> Option<u64> {
+    let (mut offset, end) = bits.into_inner();
+    let skipped = if bit { 0x00 } else { 0xff };
+    while offset <= end {
+        // Whole bytes without the bit are skipped at once.
+        if offset % 8 == 0 && offset + 7 <= end && bytes[(offset / 8) as usize] == skipped {
+            offset += 8;
+            continue;
+        }
+        if get_bit(bytes, offset) == bit {
+            return Some(offset);
+        }
+        offset += 1;
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// Combines strings byte by byte, the shorter ones padded with zeros. `Not`
+/// only uses the first one.
+pub fn bit_op(op: BitOp, sources: &[&[u8]]) -> Vec<u8> {
+    let len = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+    (0..len)
+        .map(|index| {
+            let mut bytes = sources
+                .iter()
+                .map(|source| source.get(index).copied().unwrap_or(0));
+            match op {
+                BitOp::And => bytes.fold(0xff, |result, byte| result & byte),
+                BitOp::Or => bytes.fold(0, |result, byte| result | byte),
+                BitOp::Xor => bytes.fold(0, |result, byte| result ^ byte),
+                BitOp::Not => !bytes.next().unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+/// What `BITFIELD` does with a value out of the range of a field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Keeps the low bits, like two's complement arithmetic.
+    #[default]
+    Wrap,
+    /// Saturates to the minimum or maximum.
+    Sat,
+    /// Leaves the field unchanged.
+    Fail,
+}
+
+/// An integer field of `BITFIELD`: up to 64 bits signed, 63 unsigned so
+/// that every value fits in an `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitField {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+impl BitField {
+    fn limits(self) -> (i128, i128) {
+        match self.signed {
+            true => (-(1 << (self.bits - 1)), (1 << (self.bits - 1)) - 1),
+            false => (0, (1 << self.bits) - 1),
+        }
+    }
+
+    /// Fits a value to the field, `None` if it is out of range with
+    /// [`Overflow::Fail`].
+    pub fn fit(self, value: i128, overflow: Overflow) -> Option<i64> {
+        let (min, max) = self.limits();
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+        match overflow {
+            Overflow::Wrap => {
+                let modulus = 1 << self.bits;
+                let value = value.rem_euclid(modulus);
+                Some((if value > max { value - modulus } else { value }) as i64)
+            }
+            Overflow::Sat => Some(if value > max { max } else { min } as i64),
+            Overflow::Fail => None,
+        }
+    }
+
+    /// Number of bytes the string needs to hold the field at `offset`.
+    pub fn required_len(self, offset: u64) -> usize {
+        ((offset + self.bits as u64 - 1) / 8 + 1) as usize
+    }
+
+    pub fn get(self, bytes: &[u8], offset: u64) -> i64 {
+        let raw = (offset..offset + self.bits as u64)
+            .fold(0u64, |raw, offset| raw << 1 | get_bit(bytes, offset) as u64);
+        let unused = 64 - self.bits;
+        match self.signed {
+            // Sign extended from the top bit of the field.
+            true => ((raw << unused) as i64) >> unused,
+            false => raw as i64,
+        }
+    }
+
+    /// Writes the low bits of `value`, growing the string if needed.
+    pub fn set(self, bytes: &mut Vec<u8>, offset: u64, value: i64) {
+        let value = value as u64;
+        for bit in 0..self.bits {
+            let set = value >> (self.bits - 1 - bit) & 1 == 1;
+            set_bit(bytes, offset + bit as u64, set);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits_and_ranges() {
+        let mut bytes = b"foobar".to_vec();
+        assert_eq!(count(&bytes, 0..=47), 26);
+        assert_eq!(count(&bytes, 8..=15), 6);
+        assert_eq!(count(&bytes, 5..=30), 17);
+        assert!(!set_bit(&mut bytes, 100, true));
+        assert_eq!(bytes.len(), 13);
+        assert!(get_bit(&bytes, 100));
+        assert!(!get_bit(&bytes, 1000));
+
+        let bytes = [0xff, 0xf0, 0x00];
+        assert_eq!(position(&bytes, false, 0..=23), Some(12));
+        assert_eq!(position(&bytes, true, 16..=23), None);
+        assert_eq!(position(&[0x00, 0x01], true, 0..=15), Some(15));
+        assert_eq!(bit_op(BitOp::And, &[b"ab", b"a"]), [b'a', 0]);
+        assert_eq!(bit_op(BitOp::Not, &[&[0x0f]]), [0xf0]);
+    }
+
+    #[test]
+    fn test_bitfields_overflow() {
+        let i8 = BitField {
+            signed: true,
+            bits: 8,
+        };
+        let u2 = BitField {
+            signed: false,
+            bits: 2,
+        };
+        assert_eq!(i8.fit(127 + 1, Overflow::Wrap), Some(-128));
+        assert_eq!(i8.fit(-200, Overflow::Sat), Some(-128));
+        assert_eq!(i8.fit(128, Overflow::Fail), None);
+        assert_eq!(u2.fit(5, Overflow::Wrap), Some(1));
+        assert_eq!(u2.fit(-1, Overflow::Sat), Some(0));
+
+        let mut bytes = Vec::new();
+        i8.set(&mut bytes, 4, -2);
+        assert_eq!(bytes, [0x0f, 0xe0]);
+        assert_eq!(i8.get(&bytes, 4), -2);
+        assert_eq!(u2.get(&bytes, 4), 3);
+        let i64 = BitField {
+            signed: true,
+            bits: 64,
+        };
+        i64.set(&mut bytes, 0, i64::MIN);
+        assert_eq!(i64.get(&bytes, 0), i64::MIN);
+    }
+}
@@ -0,0 +1,155 @@
+//! Consumer groups of a stream. Each group remembers the last entry it
+//! delivered and, until they are acknowledged, which consumer got each
+//! entry: the pending entries list (PEL) of the group, indexed per consumer.
+
+use super::StreamId;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An entry delivered to a consumer and not acknowledged yet. Times are
+/// epoch milliseconds, like the deadlines of keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEntry {
+    pub consumer: String,
+    pub delivered_at: u128,
+    pub deliveries: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Consumer {
+    /// Last time the consumer read or claimed entries, even if it got none.
+    pub seen_at: u128,
+    /// Last time the consumer got entries, `None` if it never did.
+    pub active_at: Option<u128>,
+    pending: BTreeSet<StreamId>,
+}
+
+impl Consumer {
+    /// IDs of the entries delivered to this consumer and not acknowledged.
+    pub fn pending(&self) -> &BTreeSet<StreamId> {
+        &self.pending
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsumerGroup {
+    /// ID of the last entry delivered to any consumer.
+    pub last_id: StreamId,
+    /// Number of entries of the stream read by the group, up to `last_id`.
+    /// `None` when it cannot be known, after deletions for instance.
+    pub entries_read: Option<u64>,
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<String, Consumer>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_id: StreamId, entries_read: Option<u64>) -> Self {
+        ConsumerGroup {
+            last_id,
+            entries_read,
+            ..ConsumerGroup::default()
+        }
+    }
+
+    pub fn pending(&self) -> &BTreeMap<StreamId, PendingEntry> {
+        &self.pending
+    }
+    pub fn consumers(&self) -> &BTreeMap<String, Consumer> {
+        &self.consumers
+    }
+
+    /// Adds a consumer, returns false if there already is one with this name.
+    pub fn create_consumer(&mut self, name: &str, now: u128) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+        let consumer = Consumer {
+            seen_at: now,
+            ..Consumer::default()
+        };
+        self.consumers.insert(name.to_owned(), consumer);
+        true
+    }
+
+    /// Records that a consumer, created if needed, interacted with the group.
+    pub fn see(&mut self, name: &str, now: u128) {
+        if !self.create_consumer(name, now) {
+            self.consumers.get_mut(name).expect("checked above").seen_at = now;
+        }
+    }
+
+    /// Records that a consumer got entries.
+    pub fn activate(&mut self, name: &str, now: u128) {
+        if let Some(consumer) = self.consumers.get_mut(name) {
+            consumer.active_at = Some(now);
+        }
+    }
+
+    /// Removes a consumer and its pending entries, returns how many it had.
+    pub fn delete_consumer(&mut self, name: &str) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    /// Makes `consumer`, created if needed, the owner of the pending entry
+    /// `id`, created if needed.
+    pub fn assign(&mut self, id: StreamId, consumer: &str, delivered_at: u128, deliveries: u64) {
+        let entry = PendingEntry {
+            consumer: consumer.to_owned(),
+            delivered_at,
+            deliveries,
+        };
+        if let Some(previous) = self.pending.insert(id, entry) {
+            if let Some(owner) = self.consumers.get_mut(&previous.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+        self.consumers
+            .entry(consumer.to_owned())
+            .or_insert_with(|| Consumer {
+                seen_at: delivered_at,
+                ..Consumer::default()
+            })
+            .pending
+            .insert(id);
+    }
+
+    /// Removes a pending entry, returns false if there is none with this ID.
+    pub fn ack(&mut self, id: StreamId) -> bool {
+        let Some(entry) = self.pending.remove(&id) else {
+            return false;
+        };
+        if let Some(owner) = self.consumers.get_mut(&entry.consumer) {
+            owner.pending.remove(&id);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_entries_follow_their_owner() {
+        let mut group = ConsumerGroup::new(StreamId::MIN, Some(0));
+        let (first, second) = (StreamId::new(1, 0), StreamId::new(2, 0));
+        group.see("alice", 10);
+        group.assign(first, "alice", 10, 1);
+        group.assign(second, "alice", 10, 1);
+        group.assign(second, "bob", 20, 2);
+        assert_eq!(group.consumers()["alice"].pending().len(), 1);
+        assert_eq!(group.consumers()["bob"].pending().len(), 1);
+        assert_eq!(group.pending()[&second].consumer, "bob");
+
+        assert!(group.ack(first));
+        assert!(!group.ack(first));
+        assert!(group.consumers()["alice"].pending().is_empty());
+        assert_eq!(group.delete_consumer("bob"), Some(1));
+        assert!(group.pending().is_empty());
+        assert!(!group.create_consumer("alice", 30));
+        assert!(group.create_consumer("bob", 30));
+    }
+}
@@ -0,0 +1,496 @@
+//! HyperLogLog cardinality estimation, stored in strings encoded exactly like
+//! Redis does: a 16 bytes header then 16384 registers of 6 bits, run-length
+//! encoded (sparse) while most are zero, packed (dense) afterwards. Updates
+//! follow the same steps as Redis, so the bytes match too.
+
+use crate::error::{RedisError, Result};
+
+/// Bits of the hash that select the register.
+const P: u32 = 14;
+/// Bits of the hash left to count the zeros of.
+const Q: u32 = 64 - P;
+pub const REGISTERS: usize = 1 << P;
+const BITS: usize = 6;
+const REGISTER_MAX: u16 = (1 << BITS) - 1;
+const HEADER_SIZE: usize = 16;
+const DENSE_SIZE: usize = HEADER_SIZE + (REGISTERS * BITS).div_ceil(8);
+const MAGIC: &[u8] = b"HYLL";
+const ENCODING: usize = 4;
+const DENSE: u8 = 0;
+const SPARSE: u8 = 1;
+/// The cached cardinality, little endian, invalid if its top bit is set.
+const CARDINALITY: std::ops::Range<usize> = 8..16;
+/// A sparse HyperLogLog grows up to this size, the default
+/// `hll-sparse-max-bytes` of Redis, then becomes dense.
+const SPARSE_MAX_BYTES: usize = 3000;
+const ZERO_MAX_LEN: usize = 64;
+const XZERO_MAX_LEN: usize = 16384;
+const VAL_MAX_VALUE: u8 = 32;
+const VAL_MAX_LEN: usize = 4;
+/// 0.5 / ln(2)
+const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const SEED: u64 = 0xadc8_3b19;
+
+/// An opcode of the sparse encoding, with the number of registers it covers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Opcode {
+    /// `00xxxxxx`: up to 64 zero registers.
+    Zero(usize),
+    /// `01xxxxxx yyyyyyyy`: up to 16384 zero registers.
+    XZero(usize),
+    /// `1vvvvvxx`: up to 4 registers set to a value up to 32.
+    Val(u8, usize),
+}
+
+impl Opcode {
+    fn decode(sparse: &[u8], at: usize) -> Opcode {
+        let byte = sparse[at];
+        if byte & 0x80 != 0 {
+            Opcode::Val(((byte >> 2) & 0x1f) + 1, (byte & 0x3) as usize + 1)
+        } else if byte & 0x40 != 0 {
+            // Redis reads the terminating null of a truncated opcode.
+            let low = sparse.get(at + 1).copied().unwrap_or(0);
+            Opcode::XZero(((((byte & 0x3f) as usize) << 8) | low as usize) + 1)
+        } else {
+            Opcode::Zero((byte & 0x3f) as usize + 1)
+        }
+    }
+
+    /// A zero opcode for `len` registers, the short one if possible.
+    fn zeros(len: usize) -> Opcode {
+        if len > ZERO_MAX_LEN {
+            Opcode::XZero(len)
+        } else {
+            Opcode::Zero(len)
+        }
+    }
+
+    fn span(self) -> usize {
+        match self {
+            Opcode::Zero(len) | Opcode::XZero(len) | Opcode::Val(_, len) => len,
+        }
+    }
+
+    /// Size of the opcode in bytes.
+    fn size(self) -> usize {
+        match self {
+            Opcode::XZero(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// The byte of a one byte opcode.
+    fn byte(self) -> u8 {
+        match self {
+            Opcode::Zero(len) => (len - 1) as u8,
+            Opcode::Val(value, len) => ((value - 1) << 2) | (len - 1) as u8 | 0x80,
+            Opcode::XZero(_) => unreachable!("XZERO takes two bytes"),
+        }
+    }
+
+    fn encode(self, out: &mut Vec<u8>) {
+        match self {
+            Opcode::XZero(len) => {
+                let len = len - 1;
+                out.extend([(len >> 8) as u8 | 0x40, len as u8]);
+            }
+            opcode => out.push(opcode.byte()),
+        }
+    }
+}
+
+/// MurmurHash64A, the hash function of the Redis HyperLogLog.
+fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("chunks of 8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+/// The register of `element` and the value it would raise it to: the
+/// position of the first set bit of the rest of the hash.
+fn pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmur_hash64a(element, SEED);
+    let index = (hash & (REGISTERS as u64 - 1)) as usize;
+    let hash = (hash >> P) | (1 << Q);
+    (index, hash.trailing_zeros() as u8 + 1)
+}
+
+fn dense_get(registers: &[u8], index: usize) -> u8 {
+    let (byte, shift) = (index * BITS / 8, index * BITS % 8);
+    let low = registers[byte] as u16 >> shift;
+    let high = (registers.get(byte + 1).copied().unwrap_or(0) as u16) << (8 - shift);
+    ((low | high) & REGISTER_MAX) as u8
+}
+
+fn dense_set(registers: &mut [u8], index: usize, value: u8) {
+    let (byte, shift) = (index * BITS / 8, index * BITS % 8);
+    let value = value as u16;
+    registers[byte] &= !(REGISTER_MAX << shift) as u8;
+    registers[byte] |= (value << shift) as u8;
+    // The last register does not reach the next byte.
+    if let Some(next) = registers.get_mut(byte + 1) {
+        *next &= !(REGISTER_MAX >> (8 - shift)) as u8;
+        *next |= (value >> (8 - shift)) as u8;
+    }
+}
+
+/// Raises a dense register to `count`, returns whether it changed.
+fn dense_raise(registers: &mut [u8], index: usize, count: u8) -> bool {
+    if dense_get(registers, index) >= count {
+        return false;
+    }
+    dense_set(registers, index, count);
+    true
+}
+
+/// Calls `f` with the first register, length and value of each run of a
+/// sparse encoding. Fails unless the runs cover exactly all the registers.
+fn sparse_runs(sparse: &[u8], mut f: impl FnMut(usize, usize, u8)) -> Result<()> {
+    let (mut index, mut at) = (0, 0);
+    while at < sparse.len() {
+        let opcode = Opcode::decode(sparse, at);
+        let value = match opcode {
+            Opcode::Val(_, len) if index + len > REGISTERS => break,
+            Opcode::Val(value, _) => value,
+            _ => 0,
+        };
+        f(index, opcode.span(), value);
+        index += opcode.span();
+        at += opcode.size();
+    }
+    match index {
+        REGISTERS => Ok(()),
+        _ => Err(RedisError::CorruptedHll),
+    }
+}
+
+/// An empty HyperLogLog: sparse, with a cached cardinality of 0.
+pub fn new() -> Vec<u8> {
+    let mut hll = MAGIC.to_vec();
+    hll.push(SPARSE);
+    hll.resize(HEADER_SIZE, 0);
+    Opcode::XZero(XZERO_MAX_LEN).encode(&mut hll);
+    hll
+}
+
+/// Checks the header of a string, like Redis before using it as a
+/// HyperLogLog.
+pub fn validate(hll: &[u8]) -> Result<()> {
+    let valid = hll.len() >= HEADER_SIZE
+        && hll.starts_with(MAGIC)
+        && hll[ENCODING] <= SPARSE
+        && (hll[ENCODING] != DENSE || hll.len() == DENSE_SIZE);
+    match valid {
+        true => Ok(()),
+        false => Err(RedisError::NotHyperLogLog),
+    }
+}
+
+pub fn cached_count(hll: &[u8]) -> Option<u64> {
+    let count = u64::from_le_bytes(hll[CARDINALITY].try_into().expect("8 bytes"));
+    (count >> 63 == 0).then_some(count)
+}
+
+pub fn set_cached_count(hll: &mut [u8], count: u64) {
+    hll[CARDINALITY].copy_from_slice(&count.to_le_bytes());
+}
+
+pub fn invalidate_cache(hll: &mut [u8]) {
+    hll[CARDINALITY.end - 1] |= 0x80;
+}
+
+/// Adds elements, returns whether a register changed. The cached
+/// cardinality is invalidated if so.
+pub fn add<'a>(hll: &mut Vec<u8>, elements: impl IntoIterator<Item = &'a [u8]>) -> Result<bool> {
+    let mut updated = false;
+    for element in elements {
+        let (index, count) = pattern(element);
+        updated |= raise(hll, index, count)?;
+    }
+    if updated {
+        invalidate_cache(hll);
+    }
+    Ok(updated)
+}
+
+/// Raises the registers of `hll` to `registers`, one byte per register, and
+/// invalidates the cached cardinality. Converts it to dense first if `dense`.
+pub fn merge(hll: &mut Vec<u8>, registers: &[u8], dense: bool) -> Result<()> {
+    if dense {
+        to_dense(hll)?;
+    }
+    for (index, count) in registers.iter().enumerate() {
+        if *count > 0 {
+            raise(hll, index, *count)?;
+        }
+    }
+    invalidate_cache(hll);
+    Ok(())
+}
+
+pub fn is_dense(hll: &[u8]) -> bool {
+    hll[ENCODING] == DENSE
+}
+
+/// Raises `registers`, one byte per register, to the registers of `hll`.
+pub fn registers_max(registers: &mut [u8], hll: &[u8]) -> Result<()> {
+    if is_dense(hll) {
+        for (index, register) in registers.iter_mut().enumerate() {
+            *register = (*register).max(dense_get(&hll[HEADER_SIZE..], index));
+        }
+        return Ok(());
+    }
+    sparse_runs(&hll[HEADER_SIZE..], |first, len, value| {
+        if value > 0 {
+            for register in &mut registers[first..first + len] {
+                *register = (*register).max(value);
+            }
+        }
+    })
+}
+
+/// Estimates the cardinality of `hll`, ignoring the cached one.
+pub fn count(hll: &[u8]) -> Result<u64> {
+    let mut histogram = [0; 64];
+    if is_dense(hll) {
+        for index in 0..REGISTERS {
+            histogram[dense_get(&hll[HEADER_SIZE..], index) as usize] += 1;
+        }
+    } else {
+        sparse_runs(&hll[HEADER_SIZE..], |_, len, value| {
+            histogram[value as usize] += len
+        })?;
+    }
+    Ok(estimate(&histogram))
+}
+
+/// Estimates the cardinality of `registers`, one byte per register.
+pub fn count_registers(registers: &[u8]) -> u64 {
+    let mut histogram = [0; 64];
+    for register in registers {
+        histogram[*register as usize] += 1;
+    }
+    estimate(&histogram)
+}
+
+/// The estimator of Otmar Ertl, "New cardinality estimation algorithms for
+/// HyperLogLog sketches", from the number of registers of each value.
+fn estimate(histogram: &[usize; 64]) -> u64 {
+    let m = REGISTERS as f64;
+    let q = Q as usize;
+    let mut z = m * tau((m - histogram[q + 1] as f64) / m);
+    for count in histogram[1..=q].iter().rev() {
+        z += *count as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histogram[0] as f64 / m);
+    (ALPHA_INF * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if z == previous {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z == previous {
+            return z / 3.0;
+        }
+    }
+}
+
+/// Raises register `index` to `count`, returns whether it changed.
+fn raise(hll: &mut Vec<u8>, index: usize, count: u8) -> Result<bool> {
+    if is_dense(hll) {
+        return Ok(dense_raise(&mut hll[HEADER_SIZE..], index, count));
+    }
+    sparse_raise(hll, index, count)
+}
+
+/// Converts a sparse HyperLogLog to dense, keeping its header.
+fn to_dense(hll: &mut Vec<u8>) -> Result<()> {
+    if is_dense(hll) {
+        return Ok(());
+    }
+    let mut dense = vec![0; DENSE_SIZE];
+    dense[..HEADER_SIZE].copy_from_slice(&hll[..HEADER_SIZE]);
+    dense[ENCODING] = DENSE;
+    let registers = &mut dense[HEADER_SIZE..];
+    sparse_runs(&hll[HEADER_SIZE..], |first, len, value| {
+        if value > 0 {
+            for index in first..first + len {
+                dense_set(registers, index, value);
+            }
+        }
+    })?;
+    *hll = dense;
+    Ok(())
+}
+
+/// Converts to dense to raise a register the sparse encoding cannot hold.
+fn promote(hll: &mut Vec<u8>, index: usize, count: u8) -> Result<bool> {
+    to_dense(hll)?;
+    Ok(dense_raise(&mut hll[HEADER_SIZE..], index, count))
+}
+
+/// Raises a register of a sparse HyperLogLog in place: the opcode covering
+/// it is split, then adjacent values merged, the way `hllSparseSet` does.
+fn sparse_raise(hll: &mut Vec<u8>, index: usize, count: u8) -> Result<bool> {
+    if count > VAL_MAX_VALUE {
+        return promote(hll, index, count);
+    }
+    let (mut at, mut first, mut previous) = (HEADER_SIZE, 0, None);
+    let opcode = loop {
+        if at >= hll.len() {
+            return Err(RedisError::CorruptedHll);
+        }
+        let opcode = Opcode::decode(hll, at);
+        if index < first + opcode.span() {
+            break opcode;
+        }
+        previous = Some(at);
+        at += opcode.size();
+        first += opcode.span();
+    };
+    match opcode {
+        Opcode::Val(value, _) if value >= count => return Ok(false),
+        Opcode::Val(_, 1) | Opcode::Zero(1) => hll[at] = Opcode::Val(count, 1).byte(),
+        _ => {
+            let last = first + opcode.span() - 1;
+            let run = |len| match opcode {
+                Opcode::Val(value, _) => Opcode::Val(value, len),
+                _ => Opcode::zeros(len),
+            };
+            let mut sequence = Vec::with_capacity(5);
+            if index != first {
+                run(index - first).encode(&mut sequence);
+            }
+            Opcode::Val(count, 1).encode(&mut sequence);
+            if index != last {
+                run(last - index).encode(&mut sequence);
+            }
+            if sequence.len() > opcode.size()
+                && hll.len() + sequence.len() - opcode.size() > SPARSE_MAX_BYTES
+            {
+                return promote(hll, index, count);
+            }
+            hll.splice(at..at + opcode.size(), sequence);
+        }
+    }
+    // Adjacent values may now be merged, within 5 opcodes of the previous.
+    let mut at = previous.unwrap_or(HEADER_SIZE);
+    let mut scan = 5;
+    while at < hll.len() && scan > 0 {
+        scan -= 1;
+        let opcode = Opcode::decode(hll, at);
+        if let (Opcode::Val(value, len), Some(next)) = (opcode, hll.get(at + 1)) {
+            if let Opcode::Val(next_value, next_len) = Opcode::decode(&[*next], 0) {
+                if value == next_value && len + next_len <= VAL_MAX_LEN {
+                    hll[at + 1] = Opcode::Val(value, len + next_len).byte();
+                    hll.remove(at);
+                    continue;
+                }
+            }
+        }
+        at += opcode.size();
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_encoding_matches_redis() {
+        let mut hll = new();
+        assert_eq!(hll.len(), HEADER_SIZE + 2);
+        assert_eq!(hll[HEADER_SIZE..], [0x7f, 0xff]);
+        assert_eq!(cached_count(&hll), Some(0));
+
+        // Register 0 to 1 splits the zeros into a value and the rest.
+        raise(&mut hll, 0, 1).unwrap();
+        assert_eq!(hll[HEADER_SIZE..], [0x80, 0x7f, 0xfe]);
+        raise(&mut hll, 1, 1).unwrap();
+        assert_eq!(hll[HEADER_SIZE..], [0x81, 0x7f, 0xfd]);
+        assert!(!raise(&mut hll, 1, 1).unwrap());
+        raise(&mut hll, 100, 3).unwrap();
+        assert_eq!(hll[HEADER_SIZE..], [0x81, 0x40, 0x61, 0x88, 0x7f, 0x9a]);
+
+        let mut registers = vec![0; REGISTERS];
+        registers_max(&mut registers, &hll).unwrap();
+        assert_eq!(registers[..2], [1, 1]);
+        assert_eq!(registers[100], 3);
+        assert_eq!(count_registers(&registers), count(&hll).unwrap());
+    }
+
+    #[test]
+    fn test_dense_after_large_values() {
+        let mut hll = new();
+        raise(&mut hll, 5, 40).unwrap();
+        assert!(is_dense(&hll));
+        assert_eq!(hll.len(), DENSE_SIZE);
+        assert_eq!(dense_get(&hll[HEADER_SIZE..], 5), 40);
+        dense_set(&mut hll[HEADER_SIZE..], REGISTERS - 1, 63);
+        assert_eq!(dense_get(&hll[HEADER_SIZE..], REGISTERS - 1), 63);
+        assert_eq!(dense_get(&hll[HEADER_SIZE..], REGISTERS - 2), 0);
+        assert!(validate(&hll).is_ok());
+        assert!(matches!(validate(b"HYLL"), Err(RedisError::NotHyperLogLog)));
+    }
+
+    #[test]
+    fn test_estimates_within_the_standard_error() {
+        let mut hll = new();
+        let elements: Vec<String> = (0..100_000).map(|i| format!("element:{}", i)).collect();
+        add(&mut hll, elements.iter().map(|element| element.as_bytes())).unwrap();
+        assert!(is_dense(&hll));
+        assert_eq!(cached_count(&hll), None);
+        let estimate = count(&hll).unwrap() as f64;
+        // 3 times the standard error of 0.81%.
+        assert!(
+            (estimate - 100_000.0).abs() < 100_000.0 * 0.0243,
+            "{}",
+            estimate
+        );
+    }
+}
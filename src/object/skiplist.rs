@@ -0,0 +1,300 @@
+//! The ordered half of a sorted set: a skiplist of `(score, member)` pairs,
+//! ordered by score then member, where every link records how many elements
+//! it skips so that ranks are found in O(log n) like in Redis.
+
+use std::ops::Range;
+
+const MAX_LEVEL: usize = 32;
+/// Probability for a node to have one more level.
+const P: f64 = 0.25;
+/// Index of the header node, which holds no element.
+const HEAD: usize = 0;
+
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    forward: Option<usize>,
+    /// Number of elements between the node and `forward`, `forward` included.
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    score: f64,
+    member: String,
+    backward: Option<usize>,
+    links: Vec<Link>,
+}
+
+/// Nodes live in a vector and link to each other by index. Removed nodes
+/// leave a free slot that the next insertion reuses.
+#[derive(Debug, Clone)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    /// Number of levels in use.
+    level: usize,
+    len: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            score: 0.0,
+            member: String::new(),
+            backward: None,
+            links: vec![
+                Link {
+                    forward: None,
+                    span: 0
+                };
+                MAX_LEVEL
+            ],
+        };
+        SkipList {
+            nodes: vec![head],
+            free: Vec::new(),
+            level: 1,
+            len: 0,
+        }
+    }
+}
+
+fn random_level() -> usize {
+    let mut level = 1;
+    while level < MAX_LEVEL && fastrand::f64() < P {
+        level += 1;
+    }
+    level
+}
+
+/// Whether `(score, member)` comes before `(other_score, other)`.
+fn precedes(score: f64, member: &str, other_score: f64, other: &str) -> bool {
+    score < other_score || (score == other_score && member < other)
+}
+
+impl SkipList {
+    fn forward(&self, node: usize, level: usize) -> Option<usize> {
+        self.nodes[node].links[level].forward
+    }
+
+    fn precedes(&self, node: usize, score: f64, member: &str) -> bool {
+        let node = &self.nodes[node];
+        precedes(node.score, &node.member, score, member)
+    }
+
+    /// Adds an element, which must not be in the list yet.
+    pub fn insert(&mut self, score: f64, member: String) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            rank[level] = if level == self.level - 1 {
+                0
+            } else {
+                rank[level + 1]
+            };
+            while let Some(next) = self.forward(node, level) {
+                if !self.precedes(next, score, &member) {
+                    break;
+                }
+                rank[level] += self.nodes[node].links[level].span;
+                node = next;
+            }
+            update[level] = node;
+        }
+        let new_level = random_level();
+        if new_level > self.level {
+            for level in self.level..new_level {
+                rank[level] = 0;
+                update[level] = HEAD;
+                self.nodes[HEAD].links[level].span = self.len;
+            }
+            self.level = new_level;
+        }
+        let new = Node {
+            score,
+            member,
+            backward: (update[0] != HEAD).then_some(update[0]),
+            links: vec![
+                Link {
+                    forward: None,
+                    span: 0
+                };
+                new_level
+            ],
+        };
+        let new = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = new;
+                slot
+            }
+            None => {
+                self.nodes.push(new);
+                self.nodes.len() - 1
+            }
+        };
+        for level in 0..new_level {
+            let previous = self.nodes[update[level]].links[level];
+            let skipped = rank[0] - rank[level];
+            self.nodes[new].links[level] = Link {
+                forward: previous.forward,
+                span: previous.span - skipped,
+            };
+            self.nodes[update[level]].links[level] = Link {
+                forward: Some(new),
+                span: skipped + 1,
+            };
+        }
+        for (level, &previous) in update.iter().enumerate().take(self.level).skip(new_level) {
+            self.nodes[previous].links[level].span += 1;
+        }
+        if let Some(next) = self.forward(new, 0) {
+            self.nodes[next].backward = Some(new);
+        }
+        self.len += 1;
+    }
+
+    /// Removes an element, returns false if it is not in the list.
+    pub fn remove(&mut self, score: f64, member: &str) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level) {
+                if !self.precedes(next, score, member) {
+                    break;
+                }
+                node = next;
+            }
+            update[level] = node;
+        }
+        let Some(removed) = self.forward(node, 0) else {
+            return false;
+        };
+        if self.nodes[removed].score != score || self.nodes[removed].member != member {
+            return false;
+        }
+        for (level, &previous) in update.iter().enumerate().take(self.level) {
+            if self.forward(previous, level) == Some(removed) {
+                let link = self.nodes[removed].links[level];
+                self.nodes[previous].links[level] = Link {
+                    forward: link.forward,
+                    span: self.nodes[previous].links[level].span + link.span - 1,
+                };
+            } else {
+                self.nodes[previous].links[level].span -= 1;
+            }
+        }
+        if let Some(next) = self.forward(removed, 0) {
+            self.nodes[next].backward = self.nodes[removed].backward;
+        }
+        while self.level > 1 && self.forward(HEAD, self.level - 1).is_none() {
+            self.level -= 1;
+        }
+        self.nodes[removed].member = String::new();
+        self.free.push(removed);
+        self.len -= 1;
+        true
+    }
+
+    /// Number of elements before the first one for which `reached` returns
+    /// true. `reached` must be false for the first elements of the list and
+    /// true for all the others, like a bound of a range.
+    pub fn count_before(&self, reached: impl Fn(f64, &str) -> bool) -> usize {
+        let mut node = HEAD;
+        let mut rank = 0;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level) {
+                if reached(self.nodes[next].score, &self.nodes[next].member) {
+                    break;
+                }
+                rank += self.nodes[node].links[level].span;
+                node = next;
+            }
+        }
+        rank
+    }
+
+    /// The node at 0-based `rank`.
+    fn node_at(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut node = HEAD;
+        let mut traversed = 0;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level) {
+                if traversed + self.nodes[node].links[level].span > target {
+                    break;
+                }
+                traversed += self.nodes[node].links[level].span;
+                node = next;
+            }
+            if traversed == target {
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// The elements with a rank in `ranks`, in descending order if `rev`.
+    pub fn range(&self, ranks: Range<usize>, rev: bool) -> Vec<(String, f64)> {
+        let ranks = ranks.start..ranks.end.min(self.len);
+        if ranks.is_empty() {
+            return Vec::new();
+        }
+        let start = if rev { ranks.end - 1 } else { ranks.start };
+        let mut node = self.node_at(start);
+        let mut elements = Vec::with_capacity(ranks.len());
+        while let Some(current) = node.filter(|_| elements.len() < ranks.len()) {
+            let current = &self.nodes[current];
+            elements.push((current.member.clone(), current.score));
+            node = if rev {
+                current.backward
+            } else {
+                current.links[0].forward
+            };
+        }
+        elements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(list: &SkipList, ranks: Range<usize>, rev: bool) -> Vec<String> {
+        list.range(ranks, rev)
+            .into_iter()
+            .map(|(member, _)| member)
+            .collect()
+    }
+
+    #[test]
+    fn test_skiplist_keeps_order_and_ranks() {
+        let mut list = SkipList::default();
+        // Enough elements to get several levels.
+        for i in (0..1000).rev() {
+            list.insert((i / 2) as f64, format!("{:04}", i));
+        }
+        assert_eq!(list.len, 1000);
+        assert_eq!(members(&list, 0..3, false), ["0000", "0001", "0002"]);
+        assert_eq!(members(&list, 997..1005, true), ["0999", "0998", "0997"]);
+        assert_eq!(list.count_before(|score, _| score >= 10.0), 20);
+
+        for i in (0..1000).step_by(2) {
+            assert!(list.remove((i / 2) as f64, &format!("{:04}", i)));
+        }
+        assert!(!list.remove(0.0, "0000"));
+        assert!(!list.remove(1.0, "0001"));
+        assert_eq!(list.len, 500);
+        assert_eq!(members(&list, 0..2, false), ["0001", "0003"]);
+        assert_eq!(members(&list, 250..251, false), ["0501"]);
+        assert_eq!(
+            list.count_before(|score, member| !precedes(score, member, 250.0, "0501")),
+            250
+        );
+
+        // Freed nodes are reused.
+        list.insert(-1.0, String::from("first"));
+        assert_eq!(list.nodes.len(), 1001);
+        assert_eq!(members(&list, 0..2, false), ["first", "0001"]);
+    }
+}
@@ -0,0 +1,572 @@
+//! An append-only log of entries identified by `ms-seq` IDs. Like the radix
+//! tree of listpacks of Redis, the entries are packed in nodes of up to
+//! [`NODE_MAX_ENTRIES`] consecutive entries, which are looked up by ID in an
+//! ordered map and dropped whole when the stream is trimmed.
+
+use super::ConsumerGroup;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Entries per node, like `stream-node-max-entries` in Redis.
+pub const NODE_MAX_ENTRIES: usize = 100;
+
+/// ID of a stream entry: a time in milliseconds and a sequence number for
+/// the entries added in the same millisecond.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    /// The smallest ID greater than this one.
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    /// The greatest ID less than this one.
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEntry {
+    pub id: StreamId,
+    pub fields: Vec<(String, String)>,
+}
+
+/// Which entries `XTRIM` and `XADD` remove from the start of a stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrimStrategy {
+    /// Keep at most this many entries.
+    MaxLen(usize),
+    /// Remove the entries with a lower ID.
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    /// `~`: only remove whole nodes, which may leave a few more entries.
+    pub approximate: bool,
+    /// Maximum number of entries removed, 0 for no limit. Only approximate
+    /// trimming has a limit.
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stream {
+    /// Nodes keyed by a lower bound of their IDs: the ID of their first entry
+    /// when they were created. Every entry of a node is below the key of the
+    /// next node.
+    nodes: BTreeMap<StreamId, Vec<StreamEntry>>,
+    len: usize,
+    last_id: StreamId,
+    /// Greatest ID removed by `XDEL`.
+    max_deleted_id: StreamId,
+    /// Number of entries ever added, deleted ones included.
+    entries_added: u64,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+/// How `XCLAIM` and `XAUTOCLAIM` transfer pending entries. Times are epoch
+/// milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    /// The consumer that becomes the owner of the entries, created if needed.
+    pub consumer: String,
+    pub now: u128,
+    /// Only claim the entries delivered at least this long ago.
+    pub min_idle: u128,
+    /// New delivery time of the claimed entries.
+    pub delivered_at: u128,
+    /// New delivery count, incremented by default unless `just_id`.
+    pub deliveries: Option<u64>,
+    /// Create the pending entries that do not exist, if the stream has them.
+    pub force: bool,
+    pub just_id: bool,
+    /// Raise the last delivered ID of the group to this one.
+    pub last_id: Option<StreamId>,
+}
+
+/// Outcome of [`Stream::auto_claim`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutoClaimed {
+    /// Where to resume scanning the pending entries, 0-0 once done.
+    pub cursor: StreamId,
+    pub claimed: Vec<StreamId>,
+    /// Pending entries dropped because they were deleted from the stream.
+    pub deleted: Vec<StreamId>,
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// ID of the last entry ever added, which may have been deleted since.
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+    pub fn first_entry(&self) -> Option<&StreamEntry> {
+        self.nodes.values().next()?.first()
+    }
+    pub fn last_entry(&self) -> Option<&StreamEntry> {
+        self.nodes.values().next_back()?.last()
+    }
+    /// Number of nodes the entries are packed in.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Appends an entry. Its ID must be greater than [`Stream::last_id`].
+    pub fn append(&mut self, id: StreamId, fields: Vec<(String, String)>) {
+        debug_assert!(id > self.last_id);
+        let entry = StreamEntry { id, fields };
+        match self.nodes.values_mut().next_back() {
+            Some(node) if node.len() < NODE_MAX_ENTRIES => node.push(entry),
+            _ => {
+                self.nodes.insert(id, vec![entry]);
+            }
+        }
+        self.len += 1;
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    /// Removes an entry, returns false if there is none with this ID.
+    pub fn remove(&mut self, id: StreamId) -> bool {
+        let Some((&key, node)) = self.nodes.range_mut(..=id).next_back() else {
+            return false;
+        };
+        let Ok(index) = node.binary_search_by_key(&id, |entry| entry.id) else {
+            return false;
+        };
+        node.remove(index);
+        if node.is_empty() {
+            self.nodes.remove(&key);
+        }
+        self.len -= 1;
+        self.max_deleted_id = self.max_deleted_id.max(id);
+        true
+    }
+
+    pub fn get(&self, id: StreamId) -> Option<&StreamEntry> {
+        let (_, node) = self.nodes.range(..=id).next_back()?;
+        let index = node.binary_search_by_key(&id, |entry| entry.id).ok()?;
+        Some(&node[index])
+    }
+
+    /// The entries with an ID in `ids`, at most `count` of them, from the
+    /// highest ID if `rev`.
+    pub fn range(
+        &self,
+        ids: RangeInclusive<StreamId>,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<StreamEntry> {
+        let (start, end) = (*ids.start(), *ids.end());
+        if start > end {
+            return Vec::new();
+        }
+        let count = count.unwrap_or(usize::MAX);
+        if rev {
+            self.nodes
+                .range(..=end)
+                .rev()
+                .flat_map(|(_, node)| node.iter().rev())
+                .skip_while(|entry| entry.id > end)
+                .take_while(|entry| entry.id >= start)
+                .take(count)
+                .cloned()
+                .collect()
+        } else {
+            // The node that may hold `start`, and the ones after it.
+            let first = self
+                .nodes
+                .range(..=start)
+                .next_back()
+                .map_or(StreamId::MIN, |(&key, _)| key);
+            self.nodes
+                .range(first..)
+                .flat_map(|(_, node)| node.iter())
+                .skip_while(|entry| entry.id < start)
+                .take_while(|entry| entry.id <= end)
+                .take(count)
+                .cloned()
+                .collect()
+        }
+    }
+
+    /// Removes entries from the start of the stream, returns how many.
+    pub fn trim(&mut self, trim: StreamTrim) -> usize {
+        let beyond_limit =
+            |removed: usize| trim.approximate && trim.limit > 0 && removed > trim.limit;
+        let mut removed = 0;
+        while let Some(mut node) = self.nodes.first_entry() {
+            let entries = node.get().len();
+            let whole = match trim.strategy {
+                TrimStrategy::MaxLen(max) => self.len - entries >= max,
+                TrimStrategy::MinId(min) => node.get().last().is_some_and(|last| last.id < min),
+            };
+            if whole {
+                if beyond_limit(removed + entries) {
+                    break;
+                }
+                node.remove();
+                self.len -= entries;
+                removed += entries;
+                continue;
+            }
+            if trim.approximate {
+                break;
+            }
+            let remove = match trim.strategy {
+                TrimStrategy::MaxLen(max) => self.len.saturating_sub(max),
+                TrimStrategy::MinId(min) => node.get().partition_point(|entry| entry.id < min),
+            };
+            node.get_mut().drain(..remove);
+            self.len -= remove;
+            removed += remove;
+            break;
+        }
+        removed
+    }
+}
+
+impl Stream {
+    pub fn groups(&self) -> &BTreeMap<String, ConsumerGroup> {
+        &self.groups
+    }
+    pub fn group(&self, name: &str) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    /// Adds a group, returns false if there already is one with this name.
+    pub fn create_group(&mut self, name: &str, group: ConsumerGroup) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups.insert(name.to_owned(), group);
+        true
+    }
+
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// Whether entries from `from` to the last one were deleted.
+    fn has_tombstones(&self, from: StreamId) -> bool {
+        !self.is_empty()
+            && self.max_deleted_id != StreamId::MIN
+            && (from..=self.last_id).contains(&self.max_deleted_id)
+    }
+
+    /// Number of entries added up to `id` included, when it can be told
+    /// without counting them: `id` is the last ID, or no entry was deleted
+    /// except from the start of the stream and `id` is before the first entry.
+    pub fn read_count(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 || (self.is_empty() && id <= self.last_id) {
+            return Some(self.entries_added);
+        }
+        if id >= self.last_id {
+            return (id == self.last_id).then_some(self.entries_added);
+        }
+        let first_id = self.first_entry().map_or(StreamId::MIN, |entry| entry.id);
+        let trimmed = (self.entries_added - self.len as u64) as i128;
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first_id {
+            if id < first_id {
+                return Some(trimmed as u64);
+            }
+            if id == first_id {
+                return Some(trimmed as u64 + 1);
+            }
+        }
+        None
+    }
+
+    /// Number of entries the group has yet to read, when it can be told.
+    pub fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        let read = match group.entries_read {
+            Some(read) if !self.has_tombstones(group.last_id) => Some(read),
+            _ => self.read_count(group.last_id),
+        };
+        read.map(|read| self.entries_added.saturating_sub(read))
+    }
+
+    /// Delivers to `consumer` at most `count` entries the group never
+    /// delivered, and adds them to its pending entries unless `no_ack`.
+    pub fn read_group(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        count: Option<usize>,
+        no_ack: bool,
+        now: u128,
+    ) -> Vec<StreamEntry> {
+        let Some(last_id) = self.groups.get(group).map(|group| group.last_id) else {
+            return Vec::new();
+        };
+        let entries = match last_id.next() {
+            Some(start) => self.range(start..=StreamId::MAX, count, false),
+            None => Vec::new(),
+        };
+        for entry in &entries {
+            let tombstones = self.has_tombstones(entry.id);
+            let read_count = self.read_count(entry.id);
+            let entries_added = self.entries_added;
+            let group = self.groups.get_mut(group).expect("checked above");
+            group.entries_read = match group.entries_read {
+                // Nothing was deleted past the entry, it is the next one.
+                Some(read) if !tombstones => Some(read + 1),
+                _ if entries_added > 0 => read_count,
+                read => read,
+            };
+            group.last_id = entry.id;
+            if !no_ack {
+                group.assign(entry.id, consumer, now, 1);
+            }
+        }
+        let group = self.groups.get_mut(group).expect("checked above");
+        group.see(consumer, now);
+        if !entries.is_empty() {
+            group.activate(consumer, now);
+        }
+        entries
+    }
+
+    /// At most `count` entries delivered to `consumer` after `after` and not
+    /// acknowledged yet. The ones deleted since are `None`.
+    pub fn read_group_history(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        after: StreamId,
+        count: Option<usize>,
+        now: u128,
+    ) -> Vec<(StreamId, Option<StreamEntry>)> {
+        let Some(group) = self.groups.get_mut(group) else {
+            return Vec::new();
+        };
+        group.see(consumer, now);
+        let Some(start) = after.next() else {
+            return Vec::new();
+        };
+        let ids: Vec<StreamId> = group.consumers()[consumer]
+            .pending()
+            .range(start..)
+            .take(count.unwrap_or(usize::MAX))
+            .copied()
+            .collect();
+        ids.into_iter()
+            .map(|id| (id, self.get(id).cloned()))
+            .collect()
+    }
+
+    /// `XCLAIM`: makes the consumer the owner of the pending entries `ids`
+    /// that are idle long enough. Pending entries deleted from the stream
+    /// are dropped. Returns the IDs of the claimed entries.
+    pub fn claim(&mut self, group: &str, ids: &[StreamId], claim: &Claim) -> Vec<StreamId> {
+        let consumer = claim.consumer.as_str();
+        let mut claimed = Vec::new();
+        for &id in ids {
+            let exists = self.get(id).is_some();
+            let Some(group) = self.groups.get_mut(group) else {
+                return claimed;
+            };
+            let (idle, deliveries) = match group.pending().get(&id) {
+                Some(entry) => (
+                    claim.now.saturating_sub(entry.delivered_at),
+                    entry.deliveries,
+                ),
+                // Forced entries were never delivered, they are idle forever.
+                None if claim.force && exists => (u128::MAX, 0),
+                None => continue,
+            };
+            if !exists {
+                group.ack(id);
+                continue;
+            }
+            if idle < claim.min_idle {
+                continue;
+            }
+            let deliveries = claim
+                .deliveries
+                .unwrap_or(deliveries + u64::from(!claim.just_id));
+            group.assign(id, consumer, claim.delivered_at, deliveries);
+            claimed.push(id);
+        }
+        if let Some(group) = self.groups.get_mut(group) {
+            if let Some(last_id) = claim.last_id.filter(|last_id| *last_id > group.last_id) {
+                group.last_id = last_id;
+            }
+            group.see(consumer, claim.now);
+            if !claimed.is_empty() {
+                group.activate(consumer, claim.now);
+            }
+        }
+        claimed
+    }
+
+    /// `XAUTOCLAIM`: claims up to `count` pending entries idle long enough,
+    /// scanning at most ten times as many from `start`.
+    pub fn auto_claim(
+        &mut self,
+        group: &str,
+        start: StreamId,
+        count: usize,
+        claim: &Claim,
+    ) -> AutoClaimed {
+        let Some(pending) = self.groups.get(group).map(ConsumerGroup::pending) else {
+            return AutoClaimed::default();
+        };
+        let attempts = count.saturating_mul(10);
+        let mut ids: Vec<StreamId> = pending
+            .range(start..)
+            .map(|(id, _)| *id)
+            .take(attempts.saturating_add(1))
+            .collect();
+        let cursor = if ids.len() > attempts {
+            ids.pop().expect("more than attempts")
+        } else {
+            StreamId::MIN
+        };
+        let mut result = AutoClaimed {
+            cursor,
+            ..AutoClaimed::default()
+        };
+        for (index, id) in ids.iter().enumerate() {
+            if result.claimed.len() == count {
+                result.cursor = *id;
+                break;
+            }
+            if self.get(*id).is_none() {
+                self.groups.get_mut(group).expect("found above").ack(*id);
+                result.deleted.push(*id);
+                continue;
+            }
+            let claimed = self.claim(group, &ids[index..=index], claim);
+            result.claimed.extend(claimed);
+        }
+        let group = self.groups.get_mut(group).expect("found above");
+        group.see(&claim.consumer, claim.now);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(len: u64) -> Stream {
+        let mut stream = Stream::default();
+        for ms in 1..=len {
+            stream.append(StreamId::new(ms, 0), vec![(ms.to_string(), String::new())]);
+        }
+        stream
+    }
+
+    fn ids(entries: Vec<StreamEntry>) -> Vec<u64> {
+        entries.into_iter().map(|entry| entry.id.ms).collect()
+    }
+
+    #[test]
+    fn test_stream_ranges_across_nodes() {
+        let mut stream = stream(250);
+        assert_eq!(stream.nodes.len(), 3);
+        let range = StreamId::new(99, 0)..=StreamId::new(102, 0);
+        assert_eq!(
+            ids(stream.range(range.clone(), None, false)),
+            [99, 100, 101, 102]
+        );
+        assert_eq!(ids(stream.range(range.clone(), Some(2), true)), [102, 101]);
+
+        assert!(stream.remove(StreamId::new(101, 0)));
+        assert!(!stream.remove(StreamId::new(101, 0)));
+        assert_eq!(ids(stream.range(range, None, false)), [99, 100, 102]);
+        assert_eq!(stream.len(), 249);
+        assert_eq!(stream.max_deleted_id(), StreamId::new(101, 0));
+        assert_eq!(stream.last_id(), StreamId::new(250, 0));
+        assert_eq!(StreamId::new(1, u64::MAX).next(), Some(StreamId::new(2, 0)));
+        assert_eq!(StreamId::new(2, 0).prev(), Some(StreamId::new(1, u64::MAX)));
+        assert_eq!(StreamId::MAX.next(), None);
+    }
+
+    #[test]
+    fn test_stream_trim() {
+        let approximate = |strategy| StreamTrim {
+            strategy,
+            approximate: true,
+            limit: 0,
+        };
+        let exact = |strategy| StreamTrim {
+            approximate: false,
+            ..approximate(strategy)
+        };
+
+        // Only whole nodes go with `~`.
+        let mut trimmed = stream(250);
+        assert_eq!(trimmed.trim(approximate(TrimStrategy::MaxLen(120))), 100);
+        assert_eq!(trimmed.len(), 150);
+        assert_eq!(trimmed.trim(exact(TrimStrategy::MaxLen(120))), 30);
+        assert_eq!(trimmed.first_entry().unwrap().id, StreamId::new(131, 0));
+
+        let mut trimmed = stream(250);
+        assert_eq!(
+            trimmed.trim(exact(TrimStrategy::MinId(StreamId::new(205, 0)))),
+            204
+        );
+        assert_eq!(
+            ids(trimmed.range(StreamId::MIN..=StreamId::MAX, Some(1), false)),
+            [205]
+        );
+
+        let mut trimmed = stream(250);
+        let limited = StreamTrim {
+            limit: 150,
+            ..approximate(TrimStrategy::MaxLen(0))
+        };
+        assert_eq!(trimmed.trim(limited), 100);
+        assert_eq!(trimmed.trim(exact(TrimStrategy::MaxLen(0))), 150);
+        assert!(trimmed.is_empty());
+        assert_eq!(trimmed.last_id(), StreamId::new(250, 0));
+    }
+}
@@ -0,0 +1,125 @@
+/// Glob-style matching with the same rules as Redis `stringmatchlen`:
+/// `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` to escape the next character.
+pub fn glob_match(pattern: &str, string: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let string: Vec<char> = string.chars().collect();
+    match_from(&pattern, &string)
+}
+
+fn match_from(pattern: &[char], string: &[char]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    while p < pattern.len() {
+        match pattern[p] {
+            '*' => {
+                while p + 1 < pattern.len() && pattern[p + 1] == '*' {
+                    p += 1;
+                }
+                if p + 1 == pattern.len() {
+                    return true;
+                }
+                return (s..=string.len())
+                    .any(|start| match_from(&pattern[p + 1..], &string[start..]));
+            }
+            '?' => {
+                if s >= string.len() {
+                    return false;
+                }
+                s += 1;
+            }
+            '[' => {
+                if s >= string.len() {
+                    return false;
+                }
+                let (matched, end) = match_class(pattern, p + 1, string[s]);
+                if !matched {
+                    return false;
+                }
+                p = end;
+                s += 1;
+            }
+            '\\' if p + 1 < pattern.len() => {
+                p += 1;
+                if s >= string.len() || pattern[p] != string[s] {
+                    return false;
+                }
+                s += 1;
+            }
+            c => {
+                if s >= string.len() || c != string[s] {
+                    return false;
+                }
+                s += 1;
+            }
+        }
+        p += 1;
+    }
+    s == string.len()
+}
+
+/// Matches `c` against the class starting right after `[`. Returns whether it
+/// matched and the index of the closing `]` (or the last pattern character).
+fn match_class(pattern: &[char], start: usize, c: char) -> (bool, usize) {
+    let mut p = start;
+    let negate = pattern.get(p) == Some(&'^');
+    if negate {
+        p += 1;
+    }
+    let mut matched = false;
+    while p < pattern.len() && pattern[p] != ']' {
+        if pattern[p] == '\\' && p + 1 < pattern.len() {
+            p += 1;
+            matched |= pattern[p] == c;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == '-' && pattern[p + 2] != ']' {
+            let (low, high) = if pattern[p] <= pattern[p + 2] {
+                (pattern[p], pattern[p + 2])
+            } else {
+                (pattern[p + 2], pattern[p])
+            };
+            matched |= low <= c && c <= high;
+            p += 2;
+        } else {
+            matched |= pattern[p] == c;
+        }
+        p += 1;
+    }
+    (matched != negate, p.min(pattern.len() - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let test_cases = vec![
+            ("*", "", true),
+            ("*", "anything", true),
+            ("cache:*", "cache:user:1", true),
+            ("cache:*", "session:1", false),
+            ("h?llo", "hello", true),
+            ("h?llo", "hllo", false),
+            ("h*llo", "heeeello", true),
+            ("h[ae]llo", "hallo", true),
+            ("h[ae]llo", "hillo", false),
+            ("h[^e]llo", "hallo", true),
+            ("h[^e]llo", "hello", false),
+            ("h[a-b]llo", "hbllo", true),
+            ("h[a-b]llo", "hcllo", false),
+            ("h\\*llo", "h*llo", true),
+            ("h\\*llo", "hello", false),
+            ("a*b*c", "a-b-c", true),
+            ("a*b*c", "a-c-b", false),
+            ("", "", true),
+            ("", "a", false),
+        ];
+        for (pattern, string, expected) in test_cases {
+            assert_eq!(
+                glob_match(pattern, string),
+                expected,
+                "pattern {:?} against {:?}",
+                pattern,
+                string
+            );
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use crate::acl::Acl;
+use crate::client::Clients;
+use crate::config::Config;
+use crate::constants::{IP, PORT};
+use crate::db::Db;
+use crate::error::{RedisError, Result};
+use crate::handle_connection;
+use crate::storage::Storage;
+use std::net::SocketAddr;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::{JoinHandle, JoinSet};
+
+/// State of one server instance. Everything a command can touch lives here,
+/// so several servers can run in the same process without sharing data.
+pub struct Server {
+    pub config: Config,
+    pub storage: Storage,
+    pub clients: Clients,
+    pub acl: Acl,
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder {
+            addr: format!("{IP}:{PORT}"),
+            config: Config::default(),
+        }
+    }
+
+    /// Creates a server instance without a network listener, for in-process use.
+    pub fn open(config: Config) -> Result<Arc<Self>> {
+        let server = Arc::new(Server::new(config)?);
+        start_active_expire(&server);
+        Ok(server)
+    }
+
+    /// Typed handle on the logical database `index`. Fails with
+    /// `DbIndexOutOfRange` past the configured number of databases.
+    pub fn db(self: &Arc<Self>, index: usize) -> Result<Db> {
+        if index >= self.storage.databases() {
+            return Err(RedisError::DbIndexOutOfRange);
+        }
+        Ok(Db::new(self.clone(), index))
+    }
+
+    fn new(config: Config) -> Result<Self> {
+        if config.databases == 0 {
+            return Err(RedisError::ConfigError(String::from(
+                "databases must be greater than 0",
+            )));
+        }
+        if config.shards == 0 {
+            return Err(RedisError::ConfigError(String::from(
+                "shards must be greater than 0",
+            )));
+        }
+        if config.hz == 0 {
+            return Err(RedisError::ConfigError(String::from(
+                "hz must be greater than 0",
+            )));
+        }
+        if config.watch_capacity == 0 {
+            return Err(RedisError::ConfigError(String::from(
+                "watch capacity must be greater than 0",
+            )));
+        }
+        let acl = Acl::default();
+        if let Some(password) = &config.requirepass {
+            acl.set_requirepass(password);
+        }
+        if let Some(path) = &config.aclfile {
+            acl.set_acl_file(path.clone());
+            acl.load().map_err(RedisError::ConfigError)?;
+        }
+        Ok(Server {
+            storage: Storage::new(
+                config.databases,
+                config.shards,
+                config.clock.clone(),
+                config.watch_capacity,
+            ),
+            clients: Clients::default(),
+            acl,
+            config,
+        })
+    }
+}
+
+pub struct ServerBuilder {
+    addr: String,
+    config: Config,
+}
+
+impl ServerBuilder {
+    /// Address to listen on. Use port 0 to let the OS pick a free port.
+    pub fn bind(mut self, addr: impl Into<String>) -> Self {
+        self.addr = addr.into();
+        self
+    }
+
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Binds the listener and starts accepting connections in the background.
+    pub async fn start(self) -> Result<ServerHandle> {
+        let server = Server::open(self.config)?;
+        let listener = TcpListener::bind(&self.addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (shutdown, shutdown_receiver) = oneshot::channel();
+        let task = tokio::spawn(serve(server.clone(), listener, shutdown_receiver));
+        Ok(ServerHandle {
+            local_addr,
+            server,
+            shutdown,
+            task,
+        })
+    }
+}
+
+/// A running server. Dropping the handle stops accepting new connections.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    server: Arc<Server>,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl ServerHandle {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn server(&self) -> &Arc<Server> {
+        &self.server
+    }
+
+    /// Typed handle on the logical database `index` of the running server.
+    pub fn db(&self, index: usize) -> Result<Db> {
+        self.server.db(index)
+    }
+
+    /// Stops accepting connections, closes the connected clients and waits
+    /// until every connection task has finished.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// Runs the active expiration cycle `hz` times per second on a dedicated
+/// thread, each cycle taking at most a quarter of its period. The thread
+/// stops once the server has been dropped.
+fn start_active_expire(server: &Arc<Server>) {
+    const CYCLE_CPU_PERCENT: u32 = 25;
+    let period = Duration::from_secs(1) / server.config.hz as u32;
+    let budget = period * CYCLE_CPU_PERCENT / 100;
+    let server: Weak<Server> = Arc::downgrade(server);
+    thread::spawn(move || loop {
+        thread::sleep(period);
+        match server.upgrade() {
+            Some(server) => server.storage.expire_cycle(budget),
+            None => break,
+        }
+    });
+}
+
+async fn serve(server: Arc<Server>, listener: TcpListener, mut shutdown: oneshot::Receiver<()>) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    connections.spawn(handle_connection(server.clone(), stream));
+                }
+            }
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+    drop(listener);
+    server.clients.unpause();
+    for client in server.clients.clients() {
+        client.kill();
+    }
+    while connections.join_next().await.is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn request(stream: &mut TcpStream, request: &str) -> String {
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut buf = vec![0; 512];
+        let bytes_read = stream.read(&mut buf).await.unwrap();
+        String::from_utf8(buf[..bytes_read].to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_servers_do_not_share_state() {
+        let first = Server::builder().bind("127.0.0.1:0").start().await.unwrap();
+        let second = Server::builder().bind("127.0.0.1:0").start().await.unwrap();
+        assert_ne!(first.local_addr(), second.local_addr());
+
+        let mut first_stream = TcpStream::connect(first.local_addr()).await.unwrap();
+        let mut second_stream = TcpStream::connect(second.local_addr()).await.unwrap();
+        let set = "*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+        let get = "*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n";
+        assert_eq!(request(&mut first_stream, set).await, "+Ok\r\n");
+        assert_eq!(request(&mut first_stream, get).await, "$5\r\nvalue\r\n");
+        assert_eq!(request(&mut second_stream, get).await, "_\r\n");
+
+        first.shutdown().await;
+        let mut buf = vec![0; 16];
+        assert_eq!(first_stream.read(&mut buf).await.unwrap(), 0);
+        second.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pop() {
+        let server = Server::builder().bind("127.0.0.1:0").start().await.unwrap();
+        let mut blocked = TcpStream::connect(server.local_addr()).await.unwrap();
+        let mut writer = TcpStream::connect(server.local_addr()).await.unwrap();
+        let blpop = "*3\r\n$5\r\nBLPOP\r\n$4\r\nlist\r\n$1\r\n0\r\n";
+        blocked.write_all(blpop.as_bytes()).await.unwrap();
+        while server.server().storage.blocked_clients() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let rpush = "*3\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n";
+        assert_eq!(request(&mut writer, rpush).await, ":1\r\n");
+        let mut buf = vec![0; 64];
+        let bytes_read = blocked.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..bytes_read], b"*2\r\n$4\r\nlist\r\n$1\r\na\r\n");
+
+        // A client that disconnects while blocked is no longer waiting.
+        blocked.write_all(blpop.as_bytes()).await.unwrap();
+        while server.server().storage.blocked_clients() == 0 {
+            tokio::task::yield_now().await;
+        }
+        drop(blocked);
+        while server.server().storage.blocked_clients() > 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(request(&mut writer, rpush).await, ":1\r\n");
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config() {
+        let config = Config {
+            databases: 0,
+            ..Config::default()
+        };
+        let result = Server::builder()
+            .bind("127.0.0.1:0")
+            .config(config)
+            .start()
+            .await;
+        assert!(matches!(result, Err(RedisError::ConfigError(_))));
+    }
+}
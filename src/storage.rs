@@ -1,8 +1,30 @@
+use crate::clock::Clock;
 use crate::error::*;
-use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::object::RedisObject;
+use crate::watch::{KeyEventKind, KeyEvents, Watch};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+mod bitmap;
+mod blocking;
+mod hash;
+mod hyperloglog;
+mod list;
+mod scan;
+mod set;
+mod sorted_set;
+mod stream;
+pub use bitmap::{BitFieldOp, BitRange};
+pub use blocking::{Attempt, BlockResult, Blocked, Unblocked};
+pub use hash::FieldExpire;
+pub use list::ListEnd;
+pub use set::SetOperation;
+pub use sorted_set::{Aggregate, RangeBy, ZAddOptions, ZCombine, ZRange};
+pub use stream::{GroupEntries, PendingFilter, PendingSummary, XAddId};
 type MilliSeconds = u64;
 type EpochMilliSeconds = u128;
 
@@ -14,77 +36,928 @@ pub enum Expiry {
 }
 #[derive(Debug, Clone)]
 struct DBEntry {
-    value: String,
-    created_time: SystemTime,
-    expiry: Expiry,
+    value: RedisObject,
+    /// Epoch milliseconds at which the entry expires, `None` if it never expires.
+    deadline: Option<EpochMilliSeconds>,
+}
+impl DBEntry {
+    /// Milliseconds left before the entry expires, `None` if it never expires.
+    fn ttl(&self, now: EpochMilliSeconds) -> Option<i128> {
+        self.deadline.map(|deadline| deadline as i128 - now as i128)
+    }
+    fn is_expired(&self, now: EpochMilliSeconds) -> bool {
+        self.deadline.is_some_and(|deadline| deadline < now)
+    }
+    /// Earliest deadline of the fields of a hash.
+    fn next_field_deadline(&self) -> Option<EpochMilliSeconds> {
+        match &self.value {
+            RedisObject::Hash(hash) => hash.next_deadline(),
+            _ => None,
+        }
+    }
+}
+/// What [`Storage::update`] does with the key once the closure has run.
+#[derive(Debug, Clone)]
+pub enum Update {
+    /// Leave the key as it is.
+    Keep,
+    /// Write a new value with the given expiry.
+    Set(RedisObject, Expiry),
+    /// The value was changed in place, its expiry is kept. A collection left
+    /// empty is removed.
+    Modified,
+    /// Remove the key.
+    Delete,
 }
-type Database = Arc<Mutex<HashMap<String, DBEntry>>>;
-
-static DB: Lazy<Database> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
-pub fn contains_key(key: &String) -> bool {
-    if DB.lock().unwrap().contains_key(key) {
-        return true;
-    } else {
-        return false;
+/// The keys of one shard, plus the keys with a timeout ordered by deadline
+/// so the active expiration cycle finds expired keys without a full scan.
+/// Hashes with field timeouts are indexed the same way by their earliest
+/// field deadline.
+#[derive(Default)]
+struct Shard {
+    entries: HashMap<String, DBEntry>,
+    expires: BTreeSet<(EpochMilliSeconds, String)>,
+    field_expires: BTreeSet<(EpochMilliSeconds, String)>,
+    field_deadlines: HashMap<String, EpochMilliSeconds>,
+}
+impl Shard {
+    fn get(&self, key: &str) -> Option<&DBEntry> {
+        self.entries.get(key)
+    }
+    /// Access to change the value. The deadline must go through `set_deadline`
+    /// to keep the index in sync, and `index_fields` must be called once the
+    /// field timeouts of a hash changed.
+    fn get_mut(&mut self, key: &str) -> Option<&mut DBEntry> {
+        self.entries.get_mut(key)
+    }
+    fn insert(&mut self, key: String, entry: DBEntry) {
+        self.remove(&key);
+        if let Some(deadline) = entry.deadline {
+            self.expires.insert((deadline, key.clone()));
+        }
+        if let Some(deadline) = entry.next_field_deadline() {
+            self.field_expires.insert((deadline, key.clone()));
+            self.field_deadlines.insert(key.clone(), deadline);
+        }
+        self.entries.insert(key, entry);
+    }
+    fn remove(&mut self, key: &str) -> Option<DBEntry> {
+        let entry = self.entries.remove(key)?;
+        if let Some(deadline) = entry.deadline {
+            self.expires.remove(&(deadline, key.to_owned()));
+        }
+        if let Some(deadline) = self.field_deadlines.remove(key) {
+            self.field_expires.remove(&(deadline, key.to_owned()));
+        }
+        Some(entry)
+    }
+    /// Brings the field timeout index up to date with the value of `key`.
+    fn index_fields(&mut self, key: &str) {
+        if let Some(deadline) = self.field_deadlines.remove(key) {
+            self.field_expires.remove(&(deadline, key.to_owned()));
+        }
+        if let Some(deadline) = self.get(key).and_then(DBEntry::next_field_deadline) {
+            self.field_expires.insert((deadline, key.to_owned()));
+            self.field_deadlines.insert(key.to_owned(), deadline);
+        }
+    }
+    fn set_deadline(&mut self, key: &str, deadline: Option<EpochMilliSeconds>) {
+        if let Some(mut entry) = self.remove(key) {
+            entry.deadline = deadline;
+            self.insert(key.to_owned(), entry);
+        }
+    }
+    /// Removes up to `limit` keys whose deadline is before `now`.
+    fn remove_expired(&mut self, now: EpochMilliSeconds, limit: usize) -> Vec<String> {
+        let mut removed = Vec::new();
+        while removed.len() < limit {
+            match self.expires.first() {
+                Some((deadline, _)) if *deadline < now => {
+                    let (_, key) = self.expires.pop_first().expect("checked above");
+                    // Through `remove` so the field timeouts go with the key.
+                    self.remove(&key);
+                    removed.push(key);
+                }
+                _ => break,
+            }
+        }
+        removed
+    }
+    /// Removes the fields of the hash at `key` whose deadline is before `now`,
+    /// and the key once its last field is gone. Returns how many fields were
+    /// removed and whether the key was. The index entry of a key that is no
+    /// longer a hash is dropped.
+    fn remove_expired_fields_of(&mut self, key: &str, now: EpochMilliSeconds) -> (usize, bool) {
+        let Some(DBEntry {
+            value: RedisObject::Hash(hash),
+            ..
+        }) = self.entries.get_mut(key)
+        else {
+            self.index_fields(key);
+            return (0, false);
+        };
+        let removed = hash.remove_expired(now);
+        if hash.is_empty() {
+            self.remove(key);
+            (removed, true)
+        } else {
+            self.index_fields(key);
+            (removed, false)
+        }
+    }
+    /// Removes the expired fields of up to `limit` hashes, see
+    /// `remove_expired_fields_of`.
+    fn remove_expired_fields(
+        &mut self,
+        now: EpochMilliSeconds,
+        limit: usize,
+    ) -> Vec<(String, usize, bool)> {
+        let mut expired = Vec::new();
+        for _ in 0..limit {
+            match self.field_expires.first() {
+                Some((deadline, _)) if *deadline < now => {
+                    // Reindexed by `remove_expired_fields_of` if the key is
+                    // still a hash with field timeouts.
+                    let (_, key) = self.field_expires.pop_first().expect("checked above");
+                    let (fields, deleted) = self.remove_expired_fields_of(&key, now);
+                    if fields > 0 || deleted {
+                        expired.push((key, fields, deleted));
+                    }
+                }
+                _ => break,
+            }
+        }
+        expired
+    }
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.expires.clear();
+        self.field_expires.clear();
+        self.field_deadlines.clear();
     }
 }
+/// One logical database, split in shards by key hash so that commands on
+/// different keys don't serialize on a single lock.
+type Database = Vec<Mutex<Shard>>;
 
-pub fn insert(key: String, value: String, expiry: Expiry) -> Result<()> {
-    let to_insert = DBEntry {
-        value,
-        created_time: SystemTime::now(),
-        expiry,
-    };
-    DB.lock().unwrap().insert(key, to_insert);
-    Ok(())
+/// The keyspace of one server instance: a fixed number of logical databases.
+/// Every mutation is published to the `Watch` subscribers.
+///
+/// When several shards are locked at once they are always locked in
+/// `(db, shard)` order, so multi-key operations can't deadlock.
+pub struct Storage {
+    dbs: Vec<Database>,
+    shards: usize,
+    hasher: RandomState,
+    clock: Arc<dyn Clock>,
+    events: KeyEvents,
+    blocked: Arc<blocking::BlockedClients>,
+    /// Next `(db, shard)` pair, flattened, for the active expiration cycle.
+    expire_cursor: AtomicUsize,
+    pub expire_stats: ExpireStats,
 }
-pub fn get(key: String) -> Result<String> {
-    let db = DB
-        .lock()
-        .expect("Could not lock DB while trying to get the value for a key");
-    let db_get_output = db.get(&key);
-    if db_get_output.is_none() {
-        return Err(RedisError::KeyDoesNotExist);
-    }
-    let db_entry = db_get_output.unwrap();
-    let db_entry_cloned = db_entry.clone();
-    let (value, created_time, expiry) = (
-        db_entry_cloned.value,
-        db_entry_cloned.created_time,
-        db_entry_cloned.expiry,
-    );
-    drop(db);
-    match expiry {
-        Expiry::DURATION(ms) => {
-            if created_time.elapsed().unwrap() > Duration::from_millis(ms) {
-                let _ = delete(vec![key.to_owned()]);
-                Err(RedisError::ExpiredKey)
-            } else {
-                Ok(value.to_owned())
+
+/// Counters of `INFO stats` about expiration.
+#[derive(Default)]
+pub struct ExpireStats {
+    /// Keys removed because their timeout elapsed, lazily or by the cycle.
+    pub expired_keys: AtomicU64,
+    /// Hash fields removed because their timeout elapsed.
+    pub expired_fields: AtomicU64,
+    /// Time spent in active expiration cycles, in microseconds.
+    pub cycle_time_us: AtomicU64,
+    /// Cycles that stopped because they ran out of time.
+    pub time_cap_reached: AtomicU64,
+}
+
+impl Storage {
+    pub fn new(
+        databases: usize,
+        shards: usize,
+        clock: Arc<dyn Clock>,
+        watch_capacity: usize,
+    ) -> Self {
+        Storage {
+            dbs: (0..databases)
+                .map(|_| (0..shards).map(|_| Mutex::new(Shard::default())).collect())
+                .collect(),
+            shards,
+            hasher: RandomState::new(),
+            clock,
+            events: KeyEvents::new(watch_capacity),
+            blocked: Arc::default(),
+            expire_cursor: AtomicUsize::new(0),
+            expire_stats: ExpireStats::default(),
+        }
+    }
+
+    /// Subscribes to the events of database `db` whose key matches `pattern`.
+    pub fn watch(&self, db: usize, pattern: &str) -> Watch {
+        self.events.subscribe(db, pattern)
+    }
+
+    /// Turns an expiry relative to now into an absolute deadline.
+    fn to_deadline(&self, expiry: Expiry) -> Option<EpochMilliSeconds> {
+        match expiry {
+            Expiry::DURATION(ms) => Some(self.clock.now_ms() + ms as u128),
+            Expiry::EPOCH(epoch_ms) => Some(epoch_ms),
+            Expiry::INFINITE => None,
+        }
+    }
+
+    pub fn databases(&self) -> usize {
+        self.dbs.len()
+    }
+
+    fn shard_index(&self, key: &str) -> usize {
+        (self.hasher.hash_one(key) % self.shards as u64) as usize
+    }
+
+    fn lock_shard(&self, db: usize, shard: usize) -> MutexGuard<'_, Shard> {
+        self.dbs[db][shard].lock().expect("Could not lock DB")
+    }
+
+    /// Locks the shard of database `db` holding `key`.
+    fn lock(&self, db: usize, key: &str) -> MutexGuard<'_, Shard> {
+        self.lock_shard(db, self.shard_index(key))
+    }
+
+    /// Locks every shard holding one of `keys`, in shard order.
+    fn lock_keys<K: AsRef<str>>(
+        &self,
+        db: usize,
+        keys: &[K],
+    ) -> BTreeMap<usize, MutexGuard<'_, Shard>> {
+        let mut indexes: Vec<usize> = keys
+            .iter()
+            .map(|key| self.shard_index(key.as_ref()))
+            .collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        indexes
+            .into_iter()
+            .map(|index| (index, self.lock_shard(db, index)))
+            .collect()
+    }
+
+    /// Locks all the shards of database `db`.
+    fn lock_all(&self, db: usize) -> Vec<MutexGuard<'_, Shard>> {
+        (0..self.shards)
+            .map(|shard| self.lock_shard(db, shard))
+            .collect()
+    }
+
+    /// The live entry of `key` in a locked shard of database `db`. An expired
+    /// entry is removed on the way, so that every read, write and existence
+    /// check sees it as absent. The same goes for expired hash fields.
+    fn entry<'a>(&self, shard: &'a mut Shard, db: usize, key: &str) -> Option<&'a mut DBEntry> {
+        let now = self.clock.now_ms();
+        if shard.get(key).is_some_and(|entry| entry.is_expired(now)) {
+            shard.remove(key);
+            self.expire_stats
+                .expired_keys
+                .fetch_add(1, Ordering::Relaxed);
+            self.events.publish(db, key, KeyEventKind::Evict);
+        } else if shard
+            .get(key)
+            .and_then(DBEntry::next_field_deadline)
+            .is_some_and(|deadline| deadline < now)
+        {
+            let (fields, deleted) = shard.remove_expired_fields_of(key, now);
+            self.fields_expired(db, key, fields, deleted);
+        }
+        shard.get_mut(key)
+    }
+
+    /// Accounts for hash fields removed because their timeout elapsed.
+    fn fields_expired(&self, db: usize, key: &str, fields: usize, deleted: bool) {
+        self.expire_stats
+            .expired_fields
+            .fetch_add(fields as u64, Ordering::Relaxed);
+        let kind = if deleted {
+            KeyEventKind::Evict
+        } else {
+            KeyEventKind::FieldEvict
+        };
+        self.events.publish(db, key, kind);
+    }
+
+    pub fn contains_key(&self, db: usize, key: &str) -> bool {
+        let mut db_lock = self.lock(db, key);
+        self.entry(&mut db_lock, db, key).is_some()
+    }
+
+    /// Sets the key to a string, whatever it held before.
+    pub fn insert(&self, db: usize, key: String, value: String, expiry: Expiry) {
+        let to_insert = DBEntry {
+            value: RedisObject::String(value.into_bytes()),
+            deadline: self.to_deadline(expiry),
+        };
+        let mut db_lock = self.lock(db, &key);
+        self.events.publish(db, &key, KeyEventKind::Set);
+        db_lock.insert(key, to_insert);
+    }
+
+    /// Runs `f` on the current value of `key` (`None` if it is absent or
+    /// expired) and applies the returned `Update`, all with the shard locked:
+    /// no other write can happen between the read and the write.
+    pub fn update<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(Option<&mut RedisObject>) -> (Update, R),
+    ) -> R {
+        let mut db_lock = self.lock(db, key);
+        let current = self.entry(&mut db_lock, db, key);
+        let (update, result) = f(current.map(|entry| &mut entry.value));
+        match update {
+            Update::Keep => {}
+            Update::Set(value, expiry) => {
+                let to_insert = DBEntry {
+                    value,
+                    deadline: self.to_deadline(expiry),
+                };
+                self.events.publish(db, key, KeyEventKind::Set);
+                self.blocked.signal(db, key);
+                db_lock.insert(key.to_owned(), to_insert);
             }
+            Update::Modified => {
+                if db_lock
+                    .get(key)
+                    .is_some_and(|entry| entry.value.is_empty_collection())
+                {
+                    db_lock.remove(key);
+                    self.events.publish(db, key, KeyEventKind::Del);
+                } else {
+                    db_lock.index_fields(key);
+                    self.events.publish(db, key, KeyEventKind::Set);
+                    self.blocked.signal(db, key);
+                }
+            }
+            Update::Delete => {
+                if db_lock.remove(key).is_some() {
+                    self.events.publish(db, key, KeyEventKind::Del);
+                }
+            }
+        }
+        drop(db_lock);
+        self.serve_blocked();
+        result
+    }
+
+    /// Locks `keys` of database `db` together, for commands that read or write
+    /// several keys atomically. Only the given keys can be accessed.
+    pub fn lock_many<K: AsRef<str>>(&self, db: usize, keys: &[K]) -> LockedKeys<'_> {
+        LockedKeys {
+            storage: self,
+            db,
+            shards: self.lock_keys(db, keys),
         }
-        Expiry::EPOCH(epoch_ms) => {
-            let current_epoch_ms = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards")
-                .as_millis();
-            if current_epoch_ms > epoch_ms {
-                delete(vec![key.to_owned()]);
-                Err(RedisError::ExpiredKey)
+    }
+
+    /// Runs `f` on the current value of `key`, `None` if it is absent or expired.
+    pub fn read<R>(&self, db: usize, key: &str, f: impl FnOnce(Option<&RedisObject>) -> R) -> R {
+        let mut db_lock = self.lock(db, key);
+        f(self.entry(&mut db_lock, db, key).map(|entry| &entry.value))
+    }
+
+    /// Sets the key to a string only if it does not exist. Returns whether it was written.
+    pub fn insert_if_absent(&self, db: usize, key: &str, value: String, expiry: Expiry) -> bool {
+        self.update(db, key, |current| match current {
+            None => (
+                Update::Set(RedisObject::String(value.into_bytes()), expiry),
+                true,
+            ),
+            Some(_) => (Update::Keep, false),
+        })
+    }
+
+    /// Sets the key to a string only if it already exists, whatever its type.
+    /// Returns whether it was written.
+    pub fn replace_if_present(&self, db: usize, key: &str, value: String, expiry: Expiry) -> bool {
+        self.update(db, key, |current| match current {
+            Some(_) => (
+                Update::Set(RedisObject::String(value.into_bytes()), expiry),
+                true,
+            ),
+            None => (Update::Keep, false),
+        })
+    }
+
+    /// Sets the key only if it holds the string `expected`, `None` meaning
+    /// the key must not exist. Returns whether it was written.
+    pub fn compare_and_swap(
+        &self,
+        db: usize,
+        key: &str,
+        expected: Option<&str>,
+        value: String,
+        expiry: Expiry,
+    ) -> bool {
+        self.update(db, key, |current| {
+            let matches = match (current, expected) {
+                (None, None) => true,
+                (Some(RedisObject::String(current)), Some(expected)) => {
+                    current == expected.as_bytes()
+                }
+                _ => false,
+            };
+            if matches {
+                (
+                    Update::Set(RedisObject::String(value.into_bytes()), expiry),
+                    true,
+                )
             } else {
-                Ok(value.to_owned())
+                (Update::Keep, false)
+            }
+        })
+    }
+
+    /// Sets the key and returns its previous value. Fails without writing if
+    /// the key holds something else than a string.
+    pub fn get_and_set(
+        &self,
+        db: usize,
+        key: &str,
+        value: String,
+        expiry: Expiry,
+    ) -> Result<Option<Vec<u8>>> {
+        self.update(db, key, |current| match current {
+            None => (
+                Update::Set(RedisObject::String(value.into_bytes()), expiry),
+                Ok(None),
+            ),
+            Some(RedisObject::String(current)) => {
+                let previous = Some(current.clone());
+                (
+                    Update::Set(RedisObject::String(value.into_bytes()), expiry),
+                    Ok(previous),
+                )
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// The string held by `key`, `None` if the key does not exist.
+    pub fn get(&self, db: usize, key: &str) -> Result<Option<Vec<u8>>> {
+        self.read(db, key, |value| match value {
+            None => Ok(None),
+            Some(RedisObject::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(RedisError::WrongType),
+        })
+    }
+
+    /// Name of the type of the value held by `key`, `None` if it does not exist.
+    pub fn type_of(&self, db: usize, key: &str) -> Option<&'static str> {
+        self.read(db, key, |value| value.map(RedisObject::type_name))
+    }
+
+    pub fn delete(&self, db: usize, keys: Vec<String>) -> usize {
+        let mut shards = self.lock_keys(db, &keys);
+        let mut deleted_keys_count = 0;
+        for key in keys {
+            let shard = shards
+                .get_mut(&self.shard_index(&key))
+                .expect("shard locked above");
+            if self.entry(shard, db, &key).is_some() {
+                shard.remove(&key);
+                self.events.publish(db, &key, KeyEventKind::Del);
+                deleted_keys_count += 1;
             }
         }
-        Expiry::INFINITE => Ok(value.to_owned()),
+        deleted_keys_count
+    }
+
+    /// Current time of the server's clock in epoch milliseconds.
+    pub fn now_ms(&self) -> EpochMilliSeconds {
+        self.clock.now_ms()
+    }
+
+    /// Replaces the expiry of an existing key, returns false if there is no such key.
+    pub fn expire(&self, db: usize, key: &str, expiry: Expiry) -> bool {
+        self.expire_if(db, key, expiry, |_, _| true)
+    }
+
+    /// Replaces the expiry of an existing key if `condition`, called with the
+    /// current and the new deadline, accepts it. A deadline that already
+    /// passed deletes the key. Returns false if there is no such key or the
+    /// condition was not met.
+    pub fn expire_if(
+        &self,
+        db: usize,
+        key: &str,
+        expiry: Expiry,
+        condition: impl FnOnce(Option<EpochMilliSeconds>, Option<EpochMilliSeconds>) -> bool,
+    ) -> bool {
+        let deadline = self.to_deadline(expiry);
+        let mut db_lock = self.lock(db, key);
+        let Some(entry) = self.entry(&mut db_lock, db, key) else {
+            return false;
+        };
+        if !condition(entry.deadline, deadline) {
+            return false;
+        }
+        if deadline.is_some_and(|deadline| deadline <= self.clock.now_ms()) {
+            db_lock.remove(key);
+            self.events.publish(db, key, KeyEventKind::Del);
+        } else {
+            db_lock.set_deadline(key, deadline);
+            let kind = match deadline {
+                Some(_) => KeyEventKind::Expire,
+                None => KeyEventKind::Persist,
+            };
+            self.events.publish(db, key, kind);
+        }
+        true
+    }
+
+    /// Removes the timeout of a key. Returns false if the key does not exist
+    /// or has no timeout.
+    pub fn persist(&self, db: usize, key: &str) -> bool {
+        self.expire_if(db, key, Expiry::INFINITE, |current, _| current.is_some())
+    }
+
+    /// Deadline of the key in epoch milliseconds: `None` if the key does not
+    /// exist, `Some(None)` if it never expires.
+    pub fn expire_time(&self, db: usize, key: &str) -> Option<Option<EpochMilliSeconds>> {
+        let mut db_lock = self.lock(db, key);
+        self.entry(&mut db_lock, db, key)
+            .map(|entry| entry.deadline)
+    }
+
+    /// Time left before the key expires, `None` if it does not exist or never expires.
+    pub fn ttl(&self, db: usize, key: &str) -> Option<Duration> {
+        let mut db_lock = self.lock(db, key);
+        let entry = self.entry(&mut db_lock, db, key)?;
+        entry
+            .ttl(self.clock.now_ms())
+            .map(|ttl| Duration::from_millis(ttl as u64))
+    }
+
+    /// Moves `key` from database `src` to `dst`. Returns false if the key does
+    /// not exist in `src` or already exists in `dst`.
+    pub fn move_key(&self, src: usize, dst: usize, key: &String) -> bool {
+        // The key lives in the same shard index in both databases.
+        let (mut source, mut destination);
+        if src < dst {
+            source = self.lock(src, key);
+            destination = self.lock(dst, key);
+        } else {
+            destination = self.lock(dst, key);
+            source = self.lock(src, key);
+        }
+        if self.entry(&mut source, src, key).is_none()
+            || self.entry(&mut destination, dst, key).is_some()
+        {
+            return false;
+        }
+        let entry = source.remove(key).expect("entry checked above");
+        destination.insert(key.to_owned(), entry);
+        self.events.publish(src, key, KeyEventKind::Del);
+        self.events.publish(dst, key, KeyEventKind::Set);
+        self.blocked.signal(dst, key);
+        drop((source, destination));
+        self.serve_blocked();
+        true
+    }
+
+    /// Swaps the content of two databases. Watchers are not notified, the
+    /// keys themselves do not change.
+    pub fn swap(&self, first: usize, second: usize) {
+        if first == second {
+            return;
+        }
+        let (low, high) = (first.min(second), first.max(second));
+        let mut low_shards = self.lock_all(low);
+        let mut high_shards = self.lock_all(high);
+        for (low_shard, high_shard) in low_shards.iter_mut().zip(high_shards.iter_mut()) {
+            std::mem::swap(&mut **low_shard, &mut **high_shard);
+        }
+        drop((low_shards, high_shards));
+        self.blocked.signal_db(first);
+        self.blocked.signal_db(second);
+        self.serve_blocked();
+    }
+
+    pub fn size(&self, db: usize) -> usize {
+        self.lock_all(db).iter().map(|shard| shard.len()).sum()
+    }
+
+    pub fn flush(&self, db: usize) {
+        for mut shard in self.lock_all(db) {
+            for key in shard.entries.keys() {
+                self.events.publish(db, key, KeyEventKind::Del);
+            }
+            shard.clear();
+        }
+    }
+
+    pub fn flush_all(&self) {
+        for db in 0..self.databases() {
+            self.flush(db);
+        }
+    }
+
+    /// One active expiration cycle: walks the shards from where the previous
+    /// cycle stopped and removes their expired keys, a few at a time so the
+    /// locks are held briefly, until every shard is clean or `budget` is spent.
+    pub fn expire_cycle(&self, budget: Duration) {
+        const KEYS_PER_LOCK: usize = 20;
+        let start = Instant::now();
+        let total_shards = self.dbs.len() * self.shards;
+        let mut clean_shards = 0;
+        let mut cursor = self.expire_cursor.load(Ordering::Relaxed);
+        while clean_shards < total_shards {
+            if start.elapsed() >= budget {
+                self.expire_stats
+                    .time_cap_reached
+                    .fetch_add(1, Ordering::Relaxed);
+                break;
+            }
+            let (db, shard) = (cursor / self.shards % self.dbs.len(), cursor % self.shards);
+            let now = self.clock.now_ms();
+            let mut shard_lock = self.lock_shard(db, shard);
+            let removed = shard_lock.remove_expired(now, KEYS_PER_LOCK);
+            for key in &removed {
+                self.events.publish(db, key, KeyEventKind::Evict);
+            }
+            let hashes = shard_lock.remove_expired_fields(now, KEYS_PER_LOCK);
+            for (key, fields, deleted) in &hashes {
+                self.fields_expired(db, key, *fields, *deleted);
+            }
+            drop(shard_lock);
+            self.expire_stats
+                .expired_keys
+                .fetch_add(removed.len() as u64, Ordering::Relaxed);
+            // Stay on the shard while it still has expired keys or fields.
+            if removed.len() < KEYS_PER_LOCK && hashes.len() < KEYS_PER_LOCK {
+                clean_shards += 1;
+                cursor = (cursor + 1) % total_shards;
+            }
+        }
+        self.expire_cursor.store(cursor, Ordering::Relaxed);
+        self.expire_stats
+            .cycle_time_us
+            .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Key counts for `INFO keyspace`, `None` for an empty database.
+    pub fn keyspace_stats(&self, db: usize) -> Option<KeyspaceStats> {
+        let now = self.clock.now_ms();
+        let shards = self.lock_all(db);
+        let keys: usize = shards.iter().map(|shard| shard.len()).sum();
+        if keys == 0 {
+            return None;
+        }
+        let ttls: Vec<i128> = shards
+            .iter()
+            .flat_map(|shard| shard.entries.values())
+            .filter_map(|entry| entry.ttl(now))
+            .map(|ttl| ttl.max(0))
+            .collect();
+        let avg_ttl = if ttls.is_empty() {
+            0
+        } else {
+            (ttls.iter().sum::<i128>() / ttls.len() as i128) as u64
+        };
+        Some(KeyspaceStats {
+            keys,
+            expires: ttls.len(),
+            avg_ttl,
+        })
     }
 }
-pub fn delete(keys: Vec<String>) -> usize {
-    let mut deleted_keys_count = 0;
-    for key in keys {
-        if let Some(_) = DB.lock().expect("Could not lock").remove(&key) {
-            deleted_keys_count += 1;
+
+/// Keys of one database locked together by [`Storage::lock_many`]. Writes
+/// are published to the watchers like the single-key operations.
+pub struct LockedKeys<'a> {
+    storage: &'a Storage,
+    db: usize,
+    shards: BTreeMap<usize, MutexGuard<'a, Shard>>,
+}
+
+impl LockedKeys<'_> {
+    fn shard(&mut self, key: &str) -> &mut Shard {
+        self.shards
+            .get_mut(&self.storage.shard_index(key))
+            .expect("key was not locked")
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<&RedisObject> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Mutable access to the value. Call `modified` once it was changed.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut RedisObject> {
+        let (storage, db) = (self.storage, self.db);
+        let shard = self.shard(key);
+        storage.entry(shard, db, key).map(|entry| &mut entry.value)
+    }
+
+    /// Records that the value of `key` was changed in place. A collection
+    /// left empty is removed.
+    pub fn modified(&mut self, key: &str) {
+        let (storage, db) = (self.storage, self.db);
+        let shard = self.shard(key);
+        if shard
+            .get(key)
+            .is_some_and(|entry| entry.value.is_empty_collection())
+        {
+            shard.remove(key);
+            storage.events.publish(db, key, KeyEventKind::Del);
+        } else {
+            shard.index_fields(key);
+            storage.events.publish(db, key, KeyEventKind::Set);
+            storage.blocked.signal(db, key);
+        }
+    }
+
+    /// Replaces the value of `key` and clears its timeout, like the `*STORE`
+    /// commands do. An empty collection deletes the key instead.
+    pub fn set(&mut self, key: &str, value: RedisObject) {
+        if value.is_empty_collection() {
+            self.delete(key);
+            return;
+        }
+        let (storage, db) = (self.storage, self.db);
+        storage.events.publish(db, key, KeyEventKind::Set);
+        storage.blocked.signal(db, key);
+        self.shard(key).insert(
+            key.to_owned(),
+            DBEntry {
+                value,
+                deadline: None,
+            },
+        );
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        if self.get_mut(key).is_none() {
+            return false;
         }
+        let (storage, db) = (self.storage, self.db);
+        self.shard(key).remove(key);
+        storage.events.publish(db, key, KeyEventKind::Del);
+        true
+    }
+}
+
+impl Drop for LockedKeys<'_> {
+    fn drop(&mut self) {
+        self.shards.clear();
+        self.storage.serve_blocked();
+    }
+}
+
+pub struct KeyspaceStats {
+    pub keys: usize,
+    pub expires: usize,
+    pub avg_ttl: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{MockClock, SystemClock};
+    use std::thread;
+
+    fn insert(storage: &Storage, db: usize, key: &str) {
+        storage.insert(db, key.to_owned(), String::from("value"), Expiry::INFINITE);
+    }
+
+    #[test]
+    fn test_keys_spread_over_shards() {
+        let storage = Storage::new(2, 8, Arc::new(SystemClock::new()), 16);
+        let keys: Vec<String> = (0..100).map(|i| format!("key:{}", i)).collect();
+        for key in &keys {
+            insert(&storage, 0, key);
+        }
+        assert_eq!(storage.size(0), 100);
+        assert!(storage.lock_keys(0, &keys).len() > 1);
+
+        storage.swap(0, 1);
+        assert_eq!(storage.size(0), 0);
+        assert_eq!(storage.keyspace_stats(1).unwrap().keys, 100);
+
+        let mut to_delete = keys[..50].to_vec();
+        to_delete.push(String::from("missing"));
+        assert_eq!(storage.delete(1, to_delete), 50);
+        assert!(storage.move_key(1, 0, &keys[50]));
+        assert_eq!(storage.size(1), 49);
+        storage.flush_all();
+        assert_eq!(storage.size(0) + storage.size(1), 0);
+    }
+
+    #[test]
+    fn test_multi_shard_operations_do_not_deadlock() {
+        let storage = Arc::new(Storage::new(2, 4, Arc::new(SystemClock::new()), 16));
+        let workers: Vec<_> = (0..8)
+            .map(|thread| {
+                let storage = storage.clone();
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        let keys: Vec<String> = (0..8).map(|k| format!("{}:{}", i, k)).collect();
+                        for key in &keys {
+                            insert(&storage, thread % 2, key);
+                        }
+                        match thread % 4 {
+                            0 => storage.swap(0, 1),
+                            1 => {
+                                storage.move_key(1, 0, &keys[0]);
+                            }
+                            _ => {
+                                storage.delete(thread % 2, keys);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_expire_cycle() {
+        let clock = Arc::new(MockClock::new(10_000));
+        let storage = Storage::new(2, 4, clock.clone(), 16);
+        for i in 0..100 {
+            let key = format!("expired:{}", i);
+            storage.insert(i % 2, key, String::from("value"), Expiry::EPOCH(1000));
+        }
+        for i in 0..10 {
+            let key = format!("volatile:{}", i);
+            storage.insert(0, key, String::from("value"), Expiry::DURATION(100_000));
+            insert(&storage, 1, &format!("persistent:{}", i));
+        }
+
+        storage.expire_cycle(Duration::ZERO);
+        assert_eq!(storage.size(0) + storage.size(1), 120);
+        assert_eq!(
+            storage
+                .expire_stats
+                .time_cap_reached
+                .load(Ordering::Relaxed),
+            1
+        );
+
+        storage.expire_cycle(Duration::from_secs(1));
+        assert_eq!(storage.size(0), 10);
+        assert_eq!(storage.size(1), 10);
+        assert_eq!(
+            storage.expire_stats.expired_keys.load(Ordering::Relaxed),
+            100
+        );
+        assert_eq!(storage.keyspace_stats(0).unwrap().expires, 10);
+
+        clock.advance(Duration::from_secs(101));
+        storage.expire_cycle(Duration::from_secs(1));
+        assert_eq!(storage.size(0), 0);
+        assert_eq!(storage.size(1), 10);
+    }
+
+    #[test]
+    fn test_expired_key_drops_its_field_timeouts() {
+        let clock = Arc::new(MockClock::new(10_000));
+        let storage = Storage::new(1, 1, clock.clone(), 16);
+        let pairs = vec![(String::from("f"), String::from("v"))];
+        storage.hash_set(0, "h", pairs).unwrap();
+        let fields = [String::from("f")];
+        storage
+            .hash_expire_fields(0, "h", &fields, 110_000, |_, _| true)
+            .unwrap();
+        storage.expire(0, "h", Expiry::EPOCH(10_001));
+
+        clock.advance(Duration::from_secs(1));
+        storage.expire_cycle(Duration::from_secs(1));
+        assert_eq!(storage.size(0), 0);
+        let shard = storage.lock_shard(0, 0);
+        assert!(shard.field_expires.is_empty());
+        assert!(shard.field_deadlines.is_empty());
+        drop(shard);
+
+        // An orphaned index entry is dropped without reporting evictions.
+        storage
+            .lock_shard(0, 0)
+            .field_expires
+            .insert((1, String::from("gone")));
+        storage.expire_cycle(Duration::from_secs(1));
+        assert!(storage.lock_shard(0, 0).field_expires.is_empty());
+        assert_eq!(
+            storage.expire_stats.expired_fields.load(Ordering::Relaxed),
+            0
+        );
+        assert_eq!(
+            storage
+                .expire_stats
+                .time_cap_reached
+                .load(Ordering::Relaxed),
+            0
+        );
     }
-    deleted_keys_count
 }
@@ -0,0 +1,228 @@
+use super::{Expiry, Storage, Update};
+use crate::error::{RedisError, Result};
+use crate::object::bitmap::{self, BitField, BitOp, Overflow};
+use crate::object::RedisObject;
+use std::ops::RangeInclusive;
+
+/// The range of `BITCOUNT` and `BITPOS`, in bytes or in bits. Negative
+/// indexes count from the end of the string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitRange {
+    pub start: i64,
+    /// `None` up to the end of the string.
+    pub end: Option<i64>,
+    pub bits: bool,
+}
+
+impl BitRange {
+    /// The offsets of the bits in the range, clamped to a string of `len`
+    /// bytes. `None` if the range is empty.
+    fn resolve(self, len: usize) -> Option<RangeInclusive<u64>> {
+        let total = if self.bits { len * 8 } else { len } as i64;
+        let index = |index: i64| {
+            if index < 0 {
+                (total + index).max(0)
+            } else {
+                index
+            }
+        };
+        let start = index(self.start);
+        let end = index(self.end.unwrap_or(-1)).min(total - 1);
+        if start > end {
+            return None;
+        }
+        let (start, end) = (start as u64, end as u64);
+        Some(match self.bits {
+            true => start..=end,
+            false => start * 8..=end * 8 + 7,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOp {
+    Get(BitField, u64),
+    Set(BitField, u64, i64, Overflow),
+    IncrBy(BitField, u64, i64, Overflow),
+}
+
+impl BitFieldOp {
+    /// Number of bytes the string needs for the write, `None` for `Get`.
+    fn required_len(self) -> Option<usize> {
+        match self {
+            BitFieldOp::Get(..) => None,
+            BitFieldOp::Set(field, offset, ..) | BitFieldOp::IncrBy(field, offset, ..) => {
+                Some(field.required_len(offset))
+            }
+        }
+    }
+
+    /// Runs the operation, returning the value to reply with: the value for
+    /// `GET`, the previous one for `SET` and the new one for `INCRBY`. `None`
+    /// when the write overflows with [`Overflow::Fail`].
+    fn apply(self, bytes: &mut Vec<u8>) -> Option<i64> {
+        match self {
+            BitFieldOp::Get(field, offset) => Some(field.get(bytes, offset)),
+            BitFieldOp::Set(field, offset, value, overflow) => {
+                let previous = field.get(bytes, offset);
+                // Like Redis, a negative value for an unsigned field is taken
+                // as its two's complement, so it saturates to the maximum.
+                let value = if field.signed {
+                    value as i128
+                } else {
+                    value as u64 as i128
+                };
+                let value = field.fit(value, overflow)?;
+                field.set(bytes, offset, value);
+                Some(previous)
+            }
+            BitFieldOp::IncrBy(field, offset, increment, overflow) => {
+                let value = field.get(bytes, offset) as i128 + increment as i128;
+                let value = field.fit(value, overflow)?;
+                field.set(bytes, offset, value);
+                Some(value)
+            }
+        }
+    }
+}
+
+impl Storage {
+    fn read_bytes<R>(&self, db: usize, key: &str, f: impl FnOnce(&[u8]) -> R) -> Result<Option<R>> {
+        self.read(db, key, |value| match value {
+            None => Ok(None),
+            Some(RedisObject::String(bytes)) => Ok(Some(f(bytes))),
+            Some(_) => Err(RedisError::WrongType),
+        })
+    }
+
+    /// `SETBIT`: sets a bit of the string at `key`, created if needed.
+    /// Returns the previous value of the bit.
+    pub fn set_bit(&self, db: usize, key: &str, offset: u64, bit: bool) -> Result<bool> {
+        self.update(db, key, |value| match value {
+            None => {
+                let mut bytes = Vec::new();
+                bitmap::set_bit(&mut bytes, offset, bit);
+                let value = RedisObject::String(bytes);
+                (Update::Set(value, Expiry::INFINITE), Ok(false))
+            }
+            Some(RedisObject::String(bytes)) => {
+                (Update::Modified, Ok(bitmap::set_bit(bytes, offset, bit)))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `GETBIT`: a bit of the string at `key`, zero past its end.
+    pub fn get_bit(&self, db: usize, key: &str, offset: u64) -> Result<bool> {
+        let bit = self.read_bytes(db, key, |bytes| bitmap::get_bit(bytes, offset))?;
+        Ok(bit.unwrap_or(false))
+    }
+
+    /// `BITCOUNT`: the number of set bits in the string, or in a range of it.
+    pub fn bit_count(&self, db: usize, key: &str, range: Option<BitRange>) -> Result<u64> {
+        let count = self.read_bytes(db, key, |bytes| {
+            let bits = match range {
+                None => 0..=(bytes.len() as u64 * 8).checked_sub(1)?,
+                // Redis special cases two negative indexes out of order,
+                // which would otherwise clamp to the first byte or bit.
+                Some(BitRange {
+                    start,
+                    end: Some(end),
+                    ..
+                }) if start < 0 && end < 0 && start > end => return None,
+                Some(range) => range.resolve(bytes.len())?,
+            };
+            Some(bitmap::count(bytes, bits))
+        })?;
+        Ok(count.flatten().unwrap_or(0))
+    }
+
+    /// `BITPOS`: the offset of the first bit set to `bit`, or -1. A missing
+    /// key is an infinite string of zeros, and so is the string past its end
+    /// when no end is given.
+    pub fn bit_pos(&self, db: usize, key: &str, bit: bool, range: Option<BitRange>) -> Result<i64> {
+        let range = range.unwrap_or(BitRange {
+            start: 0,
+            end: None,
+            bits: false,
+        });
+        let position = self.read_bytes(db, key, |bytes| {
+            let bits = range.resolve(bytes.len())?;
+            let end = *bits.end();
+            match bitmap::position(bytes, bit, bits) {
+                Some(position) => Some(position as i64),
+                None if !bit && range.end.is_none() => Some(end as i64 + 1),
+                None => None,
+            }
+        })?;
+        Ok(match position {
+            None if !bit => 0,
+            None => -1,
+            Some(position) => position.unwrap_or(-1),
+        })
+    }
+
+    /// `BITOP`: stores the combination of the strings at `destination`,
+    /// missing keys counting as empty strings. An empty result deletes it.
+    /// Returns the length of the result.
+    pub fn bit_op(
+        &self,
+        db: usize,
+        op: BitOp,
+        destination: &str,
+        sources: &[String],
+    ) -> Result<usize> {
+        let mut keys = vec![destination];
+        keys.extend(sources.iter().map(String::as_str));
+        let mut locked = self.lock_many(db, &keys);
+        let mut strings = Vec::with_capacity(sources.len());
+        for key in sources {
+            match locked.get(key) {
+                None => strings.push(Vec::new()),
+                Some(RedisObject::String(bytes)) => strings.push(bytes.clone()),
+                Some(_) => return Err(RedisError::WrongType),
+            }
+        }
+        let strings: Vec<&[u8]> = strings.iter().map(Vec::as_slice).collect();
+        let result = bitmap::bit_op(op, &strings);
+        let len = result.len();
+        if result.is_empty() {
+            locked.delete(destination);
+        } else {
+            locked.set(destination, RedisObject::String(result));
+        }
+        Ok(len)
+    }
+
+    /// `BITFIELD`: runs the operations in order on the string at `key`. With
+    /// any write the key is created and grown to fit all of them first, even
+    /// if some then fail, like in Redis.
+    pub fn bit_field(&self, db: usize, key: &str, ops: &[BitFieldOp]) -> Result<Vec<Option<i64>>> {
+        let Some(len) = ops.iter().filter_map(|op| op.required_len()).max() else {
+            let values = self.read_bytes(db, key, |bytes| {
+                let get = |op: &BitFieldOp| match *op {
+                    BitFieldOp::Get(field, offset) => Some(field.get(bytes, offset)),
+                    _ => None,
+                };
+                ops.iter().map(get).collect()
+            })?;
+            return Ok(values.unwrap_or_else(|| vec![Some(0); ops.len()]));
+        };
+        let apply = |bytes: &mut Vec<u8>| {
+            if bytes.len() < len {
+                bytes.resize(len, 0);
+            }
+            ops.iter().map(|op| op.apply(bytes)).collect()
+        };
+        self.update(db, key, |value| match value {
+            None => {
+                let mut bytes = Vec::new();
+                let values = apply(&mut bytes);
+                let value = RedisObject::String(bytes);
+                (Update::Set(value, Expiry::INFINITE), Ok(values))
+            }
+            Some(RedisObject::String(bytes)) => (Update::Modified, Ok(apply(bytes))),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+}
@@ -0,0 +1,346 @@
+use super::Storage;
+use crate::error::Result;
+use crate::value::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Tries to run a blocking command without blocking: `Ok(None)` while there
+/// is nothing to return yet.
+pub type Attempt = Box<dyn Fn(&Storage) -> Result<Option<Value>> + Send + Sync>;
+
+#[derive(Debug)]
+pub enum Unblocked {
+    /// A write made the command succeed, this is its reply.
+    Served(Value),
+    /// The timeout elapsed, or `CLIENT UNBLOCK` was called with `TIMEOUT`.
+    Timeout,
+    /// `CLIENT UNBLOCK` was called with `ERROR`.
+    Error,
+}
+
+/// Outcome of [`Storage::block`].
+pub enum BlockResult {
+    /// The command could run right away.
+    Ready(Value),
+    /// The client is parked until one of its keys is written.
+    Blocked(Blocked),
+}
+
+struct Waiter {
+    db: usize,
+    keys: Vec<String>,
+    attempt: Attempt,
+    reply: oneshot::Sender<Unblocked>,
+}
+
+#[derive(Default)]
+struct Waiters {
+    /// Ids of the clients blocked on each key, in the order they blocked.
+    queues: HashMap<(usize, String), VecDeque<u64>>,
+    clients: HashMap<u64, Waiter>,
+}
+
+impl Waiters {
+    fn remove(&mut self, client_id: u64) -> Option<Waiter> {
+        let waiter = self.clients.remove(&client_id)?;
+        for key in &waiter.keys {
+            let queue_key = (waiter.db, key.clone());
+            if let Some(queue) = self.queues.get_mut(&queue_key) {
+                queue.retain(|id| *id != client_id);
+                if queue.is_empty() {
+                    self.queues.remove(&queue_key);
+                }
+            }
+        }
+        Some(waiter)
+    }
+}
+
+/// The clients parked by the blocking commands, and the keys written since
+/// they were last served.
+///
+/// The waiters are locked before any shard, never after: a blocked command
+/// is attempted with the waiters locked, and writers only look at them once
+/// their shards are released.
+#[derive(Default)]
+pub(super) struct BlockedClients {
+    waiters: Mutex<Waiters>,
+    ready: Mutex<VecDeque<(usize, String)>>,
+    /// Number of blocked clients, so writes skip all of this when nobody waits.
+    count: AtomicUsize,
+}
+
+impl BlockedClients {
+    fn waiters(&self) -> MutexGuard<'_, Waiters> {
+        self.waiters.lock().expect("Could not lock blocked clients")
+    }
+
+    fn ready(&self) -> MutexGuard<'_, VecDeque<(usize, String)>> {
+        self.ready.lock().expect("Could not lock ready keys")
+    }
+
+    fn remove(&self, client_id: u64) -> Option<Waiter> {
+        let waiter = self.waiters().remove(client_id)?;
+        self.count.fetch_sub(1, Ordering::SeqCst);
+        Some(waiter)
+    }
+
+    /// Records that `key` was written, in case a client waits for it.
+    pub(super) fn signal(&self, db: usize, key: &str) {
+        if self.count.load(Ordering::SeqCst) > 0 {
+            self.ready().push_back((db, key.to_owned()));
+        }
+    }
+
+    /// Records that every key of `db` may have changed.
+    pub(super) fn signal_db(&self, db: usize) {
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let keys: Vec<(usize, String)> = self
+            .waiters()
+            .queues
+            .keys()
+            .filter(|(key_db, _)| *key_db == db)
+            .cloned()
+            .collect();
+        self.ready().extend(keys);
+    }
+}
+
+/// Handle of a blocked client, dropping it unblocks the client.
+pub struct Blocked {
+    client_id: u64,
+    clients: Arc<BlockedClients>,
+    reply: oneshot::Receiver<Unblocked>,
+}
+
+impl Blocked {
+    /// Waits until the command is served or the client unblocked, at most
+    /// `timeout` if given.
+    pub async fn wait(mut self, timeout: Option<Duration>) -> Unblocked {
+        let received = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, &mut self.reply).await.ok(),
+            None => Some((&mut self.reply).await),
+        };
+        match received {
+            Some(Ok(unblocked)) => unblocked,
+            // The client may have been served right before it was removed.
+            _ => match self.clients.remove(self.client_id) {
+                Some(_) => Unblocked::Timeout,
+                None => self.reply.try_recv().unwrap_or(Unblocked::Timeout),
+            },
+        }
+    }
+}
+
+impl Drop for Blocked {
+    fn drop(&mut self) {
+        self.clients.remove(self.client_id);
+    }
+}
+
+impl Storage {
+    /// Runs `attempt` and, if it has nothing to return, parks the client on
+    /// `keys` until a write to one of them makes it succeed. Clients blocked
+    /// on the same key are served in the order they blocked.
+    pub fn block(
+        &self,
+        client_id: u64,
+        db: usize,
+        keys: Vec<String>,
+        attempt: Attempt,
+    ) -> Result<BlockResult> {
+        let blocked = &self.blocked;
+        let mut waiters = blocked.waiters();
+        // Counted before the attempt: a write that comes after it sees a
+        // blocked client and signals its key.
+        blocked.count.fetch_add(1, Ordering::SeqCst);
+        match attempt(self) {
+            Ok(None) => {}
+            result => {
+                blocked.count.fetch_sub(1, Ordering::SeqCst);
+                return result.map(|value| BlockResult::Ready(value.expect("matched above")));
+            }
+        }
+        for key in &keys {
+            waiters
+                .queues
+                .entry((db, key.clone()))
+                .or_default()
+                .push_back(client_id);
+        }
+        let (sender, receiver) = oneshot::channel();
+        waiters.clients.insert(
+            client_id,
+            Waiter {
+                db,
+                keys,
+                attempt,
+                reply: sender,
+            },
+        );
+        drop(waiters);
+        // Keys signaled while the waiters were locked were left to us.
+        self.serve_blocked();
+        Ok(BlockResult::Blocked(Blocked {
+            client_id,
+            clients: blocked.clone(),
+            reply: receiver,
+        }))
+    }
+
+    /// Unblocks a blocked client as if its timeout elapsed, or with an error.
+    /// Returns false if the client is not blocked.
+    pub fn unblock(&self, client_id: u64, error: bool) -> bool {
+        let Some(waiter) = self.blocked.remove(client_id) else {
+            return false;
+        };
+        let _ = waiter.reply.send(if error {
+            Unblocked::Error
+        } else {
+            Unblocked::Timeout
+        });
+        true
+    }
+
+    pub fn is_blocked(&self, client_id: u64) -> bool {
+        self.blocked.count.load(Ordering::SeqCst) > 0
+            && self.blocked.waiters().clients.contains_key(&client_id)
+    }
+
+    pub fn blocked_clients(&self) -> usize {
+        self.blocked.count.load(Ordering::SeqCst)
+    }
+
+    /// Serves the clients blocked on the keys written since the last call.
+    /// Must not be called with a shard locked.
+    pub(super) fn serve_blocked(&self) {
+        let blocked = &self.blocked;
+        if blocked.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        while !blocked.ready().is_empty() {
+            // Whoever holds the waiters serves the keys signaled meanwhile,
+            // including the ones written by the commands it serves: it checks
+            // for ready keys again once it released them.
+            let Ok(mut waiters) = blocked.waiters.try_lock() else {
+                return;
+            };
+            loop {
+                let Some((db, key)) = blocked.ready().pop_front() else {
+                    break;
+                };
+                let Some(queue) = waiters.queues.get(&(db, key)) else {
+                    continue;
+                };
+                for client_id in queue.clone() {
+                    let Some(waiter) = waiters.clients.get(&client_id) else {
+                        continue;
+                    };
+                    // A key of the wrong type keeps the client blocked.
+                    if let Ok(Some(reply)) = (waiter.attempt)(self) {
+                        let waiter = waiters.remove(client_id).expect("waiter found above");
+                        blocked.count.fetch_sub(1, Ordering::SeqCst);
+                        let _ = waiter.reply.send(Unblocked::Served(reply));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::storage::ListEnd;
+    use crate::value::bulk_string::BulkString;
+
+    fn storage() -> Storage {
+        Storage::new(2, 4, Arc::new(SystemClock::new()), 16)
+    }
+
+    /// Blocks `client_id` on popping the head of `key`.
+    fn block_pop(storage: &Storage, client_id: u64, key: &str) -> Blocked {
+        let popped = key.to_owned();
+        let attempt: Attempt = Box::new(move |storage| {
+            let element = storage.list_pop(0, &popped, ListEnd::Left, 1)?;
+            Ok(element.map(|mut elements| Value::BulkString(BulkString(elements.remove(0)))))
+        });
+        match storage.block(client_id, 0, vec![key.to_owned()], attempt) {
+            Ok(BlockResult::Blocked(blocked)) => blocked,
+            _ => panic!("expected the client to block"),
+        }
+    }
+
+    fn push(storage: &Storage, key: &str, element: &str) {
+        let elements = vec![element.to_owned()];
+        storage
+            .list_push(0, key, ListEnd::Right, elements, false)
+            .unwrap();
+    }
+
+    fn served(element: &str) -> Value {
+        Value::BulkString(BulkString(element.to_owned()))
+    }
+
+    #[tokio::test]
+    async fn test_clients_are_served_in_order() {
+        let storage = storage();
+        let first = block_pop(&storage, 1, "list");
+        let second = block_pop(&storage, 2, "list");
+        assert_eq!(storage.blocked_clients(), 2);
+
+        push(&storage, "list", "a");
+        assert!(matches!(first.wait(None).await, Unblocked::Served(v) if v == served("a")));
+        assert!(storage.is_blocked(2));
+        push(&storage, "list", "b");
+        assert!(matches!(second.wait(None).await, Unblocked::Served(v) if v == served("b")));
+        assert_eq!(storage.blocked_clients(), 0);
+        assert!(!storage.contains_key(0, "list"));
+    }
+
+    #[tokio::test]
+    async fn test_served_commands_wake_other_clients() {
+        let storage = storage();
+        let moved: Attempt = Box::new(|storage| {
+            let element =
+                storage.list_move(0, "source", "destination", ListEnd::Left, ListEnd::Left)?;
+            Ok(element.map(|element| Value::BulkString(BulkString(element))))
+        });
+        let Ok(BlockResult::Blocked(mover)) =
+            storage.block(1, 0, vec![String::from("source")], moved)
+        else {
+            panic!("expected the client to block");
+        };
+        let popper = block_pop(&storage, 2, "destination");
+
+        push(&storage, "source", "a");
+        assert!(matches!(mover.wait(None).await, Unblocked::Served(v) if v == served("a")));
+        assert!(matches!(popper.wait(None).await, Unblocked::Served(v) if v == served("a")));
+    }
+
+    #[tokio::test]
+    async fn test_unblock() {
+        let storage = storage();
+        let blocked = block_pop(&storage, 1, "list");
+        assert!(storage.unblock(1, true));
+        assert!(!storage.unblock(1, true));
+        assert!(matches!(blocked.wait(None).await, Unblocked::Error));
+
+        let blocked = block_pop(&storage, 1, "list");
+        let unblocked = blocked.wait(Some(Duration::from_millis(10))).await;
+        assert!(matches!(unblocked, Unblocked::Timeout));
+        assert_eq!(storage.blocked_clients(), 0);
+
+        // Dropping the handle, like a disconnect does, unblocks the client.
+        drop(block_pop(&storage, 1, "list"));
+        assert_eq!(storage.blocked_clients(), 0);
+        push(&storage, "list", "a");
+        assert!(storage.contains_key(0, "list"));
+    }
+}
@@ -0,0 +1,408 @@
+use super::scan::scan;
+use super::{EpochMilliSeconds, Expiry, Storage, Update};
+use crate::db::SetCondition;
+use crate::error::{RedisError, Result};
+use crate::object::{Hash, RedisObject};
+
+/// Outcome of setting the timeout of one field, see [`Storage::hash_expire_fields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldExpire {
+    /// The key or the field does not exist.
+    NoField,
+    /// The NX/XX/GT/LT condition was not met.
+    NotSet,
+    Set,
+    /// The deadline already passed: the field was deleted.
+    Deleted,
+}
+
+impl Storage {
+    /// Runs `f` on the hash at `key`, `None` if the key does not exist.
+    fn read_hash<R>(&self, db: usize, key: &str, f: impl FnOnce(&Hash) -> R) -> Result<Option<R>> {
+        self.read(db, key, |value| match value {
+            None => Ok(None),
+            Some(RedisObject::Hash(hash)) => Ok(Some(f(hash))),
+            Some(_) => Err(RedisError::WrongType),
+        })
+    }
+
+    /// Runs `f` on the hash at `key`, created empty if the key does not exist,
+    /// and stores the result. A hash left empty is removed.
+    fn update_hash<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&mut Hash) -> Result<R>,
+    ) -> Result<R> {
+        self.update(db, key, |value| match value {
+            None => {
+                let mut hash = Hash::default();
+                match f(&mut hash) {
+                    Ok(result) if hash.is_empty() => (Update::Keep, Ok(result)),
+                    Ok(result) => (
+                        Update::Set(RedisObject::Hash(hash), Expiry::INFINITE),
+                        Ok(result),
+                    ),
+                    Err(e) => (Update::Keep, Err(e)),
+                }
+            }
+            Some(RedisObject::Hash(hash)) => (Update::Modified, f(hash)),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `HSET`: returns the number of fields that were added.
+    pub fn hash_set(&self, db: usize, key: &str, pairs: Vec<(String, String)>) -> Result<usize> {
+        self.update_hash(db, key, |hash| {
+            Ok(pairs
+                .into_iter()
+                .filter(|(field, value)| hash.insert(field.clone(), value.clone()).is_none())
+                .count())
+        })
+    }
+
+    /// `HSETNX`: returns false if the field already exists.
+    pub fn hash_set_if_absent(
+        &self,
+        db: usize,
+        key: &str,
+        field: &str,
+        value: String,
+    ) -> Result<bool> {
+        self.update_hash(db, key, |hash| {
+            if hash.contains_key(field) {
+                return Ok(false);
+            }
+            hash.insert(field.to_owned(), value);
+            Ok(true)
+        })
+    }
+
+    pub fn hash_get(&self, db: usize, key: &str, field: &str) -> Result<Option<String>> {
+        let value = self.read_hash(db, key, |hash| hash.get(field).cloned())?;
+        Ok(value.flatten())
+    }
+
+    pub fn hash_get_many(
+        &self,
+        db: usize,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<String>>> {
+        let values = self.read_hash(db, key, |hash| {
+            fields
+                .iter()
+                .map(|field| hash.get(field).cloned())
+                .collect()
+        })?;
+        Ok(values.unwrap_or_else(|| vec![None; fields.len()]))
+    }
+
+    pub fn hash_get_all(&self, db: usize, key: &str) -> Result<Vec<(String, String)>> {
+        let pairs = self.read_hash(db, key, |hash| {
+            hash.iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()
+        })?;
+        Ok(pairs.unwrap_or_default())
+    }
+
+    pub fn hash_fields(&self, db: usize, key: &str) -> Result<Vec<String>> {
+        let fields = self.read_hash(db, key, |hash| hash.keys().cloned().collect())?;
+        Ok(fields.unwrap_or_default())
+    }
+
+    pub fn hash_values(&self, db: usize, key: &str) -> Result<Vec<String>> {
+        let values = self.read_hash(db, key, |hash| hash.values().cloned().collect())?;
+        Ok(values.unwrap_or_default())
+    }
+
+    /// `HDEL`: returns the number of fields that were removed.
+    pub fn hash_delete(&self, db: usize, key: &str, fields: &[String]) -> Result<usize> {
+        self.update_hash(db, key, |hash| {
+            Ok(fields
+                .iter()
+                .filter(|field| hash.remove(field).is_some())
+                .count())
+        })
+    }
+
+    pub fn hash_len(&self, db: usize, key: &str) -> Result<usize> {
+        Ok(self.read_hash(db, key, |hash| hash.len())?.unwrap_or(0))
+    }
+
+    pub fn hash_exists(&self, db: usize, key: &str, field: &str) -> Result<bool> {
+        let exists = self.read_hash(db, key, |hash| hash.contains_key(field))?;
+        Ok(exists.unwrap_or(false))
+    }
+
+    /// `HSTRLEN`: length of the value of `field`, 0 if it does not exist.
+    pub fn hash_value_len(&self, db: usize, key: &str, field: &str) -> Result<usize> {
+        let len = self.read_hash(db, key, |hash| {
+            hash.get(field).map_or(0, |value| value.len())
+        })?;
+        Ok(len.unwrap_or(0))
+    }
+
+    /// `HINCRBY`: a missing field counts as 0.
+    pub fn hash_incr_by(&self, db: usize, key: &str, field: &str, increment: i64) -> Result<i64> {
+        self.update_hash(db, key, |hash| {
+            let current = match hash.get(field) {
+                Some(value) => value
+                    .parse::<i64>()
+                    .map_err(|_| RedisError::HashValueNotInteger)?,
+                None => 0,
+            };
+            let value = current.checked_add(increment).ok_or(RedisError::Overflow)?;
+            hash.update(field.to_owned(), value.to_string());
+            Ok(value)
+        })
+    }
+
+    /// `HINCRBYFLOAT`: a missing field counts as 0. Returns the new value as
+    /// it is stored.
+    pub fn hash_incr_by_float(
+        &self,
+        db: usize,
+        key: &str,
+        field: &str,
+        increment: f64,
+    ) -> Result<String> {
+        self.update_hash(db, key, |hash| {
+            let current = match hash.get(field) {
+                Some(value) => value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or(RedisError::HashValueNotFloat)?,
+                None => 0.0,
+            };
+            let value = current + increment;
+            if !value.is_finite() {
+                return Err(RedisError::NaNOrInfinity);
+            }
+            let value = value.to_string();
+            hash.update(field.to_owned(), value.clone());
+            Ok(value)
+        })
+    }
+
+    /// `HRANDFIELD`: up to `count` distinct fields with their values, or
+    /// exactly `-count` fields that may repeat if `count` is negative.
+    pub fn hash_random_fields(
+        &self,
+        db: usize,
+        key: &str,
+        count: i64,
+    ) -> Result<Vec<(String, String)>> {
+        let pairs = self.read_hash(db, key, |hash| {
+            let pairs: Vec<(&String, &String)> = hash.iter().collect();
+            let picked: Vec<(&String, &String)> = if count < 0 {
+                // Grown as fields are picked rather than sized from `count`.
+                let mut picked = Vec::new();
+                for _ in 0..count.unsigned_abs() {
+                    picked.push(pairs[fastrand::usize(..pairs.len())]);
+                }
+                picked
+            } else {
+                let mut pairs = pairs;
+                fastrand::shuffle(&mut pairs);
+                pairs.truncate(count as usize);
+                pairs
+            };
+            picked
+                .into_iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()
+        })?;
+        Ok(pairs.unwrap_or_default())
+    }
+
+    /// `HSCAN`: see [`scan`] for the cursor.
+    pub fn hash_scan(
+        &self,
+        db: usize,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, Vec<(String, String)>)> {
+        let scanned = self.read_hash(db, key, |hash| {
+            let pairs = hash
+                .iter()
+                .map(|(field, value)| (field.as_str(), (field.clone(), value.clone())));
+            scan(pairs, cursor, count, pattern)
+        })?;
+        Ok(scanned.unwrap_or((0, Vec::new())))
+    }
+
+    /// Applies `expiry` to the existing `fields` of a hash, `None` keeping
+    /// their timeouts. A deadline that already passed deletes them.
+    fn expire_hash_fields(&self, hash: &mut Hash, fields: &[String], expiry: Option<Expiry>) {
+        let Some(expiry) = expiry else {
+            return;
+        };
+        let deadline = self.to_deadline(expiry);
+        let now = self.now_ms();
+        for field in fields {
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                hash.remove(field);
+            } else {
+                hash.set_deadline(field, deadline);
+            }
+        }
+    }
+
+    /// `HEXPIRE` and co: sets the deadline of each field if `condition`,
+    /// called with the current and the new deadline, accepts it.
+    pub fn hash_expire_fields(
+        &self,
+        db: usize,
+        key: &str,
+        fields: &[String],
+        deadline: EpochMilliSeconds,
+        condition: impl Fn(Option<EpochMilliSeconds>, EpochMilliSeconds) -> bool,
+    ) -> Result<Vec<FieldExpire>> {
+        let now = self.now_ms();
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(vec![FieldExpire::NoField; fields.len()])),
+            Some(RedisObject::Hash(hash)) => {
+                let results: Vec<FieldExpire> = fields
+                    .iter()
+                    .map(|field| {
+                        if !hash.contains_key(field) {
+                            FieldExpire::NoField
+                        } else if !condition(hash.deadline(field), deadline) {
+                            FieldExpire::NotSet
+                        } else if deadline <= now {
+                            hash.remove(field);
+                            FieldExpire::Deleted
+                        } else {
+                            hash.set_deadline(field, Some(deadline));
+                            FieldExpire::Set
+                        }
+                    })
+                    .collect();
+                let update = if results
+                    .iter()
+                    .any(|result| matches!(result, FieldExpire::Set | FieldExpire::Deleted))
+                {
+                    Update::Modified
+                } else {
+                    Update::Keep
+                };
+                (update, Ok(results))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `HPERSIST`: removes the timeout of each field. `None` if the field
+    /// does not exist, false if it had no timeout.
+    pub fn hash_persist_fields(
+        &self,
+        db: usize,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<bool>>> {
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(vec![None; fields.len()])),
+            Some(RedisObject::Hash(hash)) => {
+                let results: Vec<Option<bool>> = fields
+                    .iter()
+                    .map(|field| {
+                        hash.contains_key(field).then(|| {
+                            let persisted = hash.deadline(field).is_some();
+                            hash.set_deadline(field, None);
+                            persisted
+                        })
+                    })
+                    .collect();
+                let update = if results.contains(&Some(true)) {
+                    Update::Modified
+                } else {
+                    Update::Keep
+                };
+                (update, Ok(results))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// Deadline of each field in epoch milliseconds: `None` if the field does
+    /// not exist, `Some(None)` if it never expires.
+    pub fn hash_field_deadlines(
+        &self,
+        db: usize,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<Option<EpochMilliSeconds>>>> {
+        let deadlines = self.read_hash(db, key, |hash| {
+            fields
+                .iter()
+                .map(|field| hash.contains_key(field).then(|| hash.deadline(field)))
+                .collect()
+        })?;
+        Ok(deadlines.unwrap_or_else(|| vec![None; fields.len()]))
+    }
+
+    /// `HGETEX`: the values of `fields`, then applies `expiry` to the ones
+    /// that exist. `None` keeps their timeouts.
+    pub fn hash_get_and_expire(
+        &self,
+        db: usize,
+        key: &str,
+        fields: &[String],
+        expiry: Option<Expiry>,
+    ) -> Result<Vec<Option<String>>> {
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(vec![None; fields.len()])),
+            Some(RedisObject::Hash(hash)) => {
+                let values: Vec<Option<String>> = fields
+                    .iter()
+                    .map(|field| hash.get(field).cloned())
+                    .collect();
+                let existing: Vec<String> = fields
+                    .iter()
+                    .filter(|field| hash.contains_key(field))
+                    .cloned()
+                    .collect();
+                if expiry.is_none() || existing.is_empty() {
+                    return (Update::Keep, Ok(values));
+                }
+                self.expire_hash_fields(hash, &existing, expiry);
+                (Update::Modified, Ok(values))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `HSETEX`: sets the fields and applies `expiry` to them, `None` keeping
+    /// their timeouts. With a condition, NX requires that none of the fields
+    /// exists and XX that all of them do. Returns false if nothing was set.
+    pub fn hash_set_and_expire(
+        &self,
+        db: usize,
+        key: &str,
+        pairs: Vec<(String, String)>,
+        condition: Option<SetCondition>,
+        expiry: Option<Expiry>,
+    ) -> Result<bool> {
+        self.update_hash(db, key, |hash| {
+            let set = match condition {
+                None => true,
+                Some(SetCondition::NX) => pairs.iter().all(|(field, _)| !hash.contains_key(field)),
+                Some(SetCondition::XX) => pairs.iter().all(|(field, _)| hash.contains_key(field)),
+            };
+            if !set {
+                return Ok(false);
+            }
+            let fields: Vec<String> = pairs.iter().map(|(field, _)| field.clone()).collect();
+            for (field, value) in pairs {
+                hash.update(field, value);
+            }
+            self.expire_hash_fields(hash, &fields, expiry);
+            Ok(true)
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use super::{Expiry, Storage, Update};
+use crate::error::{RedisError, Result};
+use crate::object::{hyperloglog, RedisObject};
+
+impl Storage {
+    /// `PFADD`: adds the elements to the HyperLogLog at `key`, created if
+    /// needed. Returns whether it was created or a register changed.
+    pub fn pf_add(&self, db: usize, key: &str, elements: &[String]) -> Result<bool> {
+        let elements = elements.iter().map(|element| element.as_bytes());
+        self.update(db, key, |value| match value {
+            None => {
+                let mut hll = hyperloglog::new();
+                if let Err(e) = hyperloglog::add(&mut hll, elements) {
+                    return (Update::Keep, Err(e));
+                }
+                hyperloglog::invalidate_cache(&mut hll);
+                let hll = RedisObject::String(hll);
+                (Update::Set(hll, Expiry::INFINITE), Ok(true))
+            }
+            Some(RedisObject::String(hll)) => {
+                match hyperloglog::validate(hll).and_then(|()| hyperloglog::add(hll, elements)) {
+                    Ok(true) => (Update::Modified, Ok(true)),
+                    Ok(false) => (Update::Keep, Ok(false)),
+                    Err(e) => (Update::Keep, Err(e)),
+                }
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `PFCOUNT`: the estimated cardinality of the union of the
+    /// HyperLogLogs, missing keys counting as empty ones. The cardinality of
+    /// a single key is cached in its header, like in Redis.
+    pub fn pf_count(&self, db: usize, keys: &[String]) -> Result<u64> {
+        if let [key] = keys {
+            return self.update(db, key, |value| match value {
+                None => (Update::Keep, Ok(0)),
+                Some(RedisObject::String(hll)) => {
+                    if let Err(e) = hyperloglog::validate(hll) {
+                        return (Update::Keep, Err(e));
+                    }
+                    if let Some(count) = hyperloglog::cached_count(hll) {
+                        return (Update::Keep, Ok(count));
+                    }
+                    match hyperloglog::count(hll) {
+                        Ok(count) => {
+                            hyperloglog::set_cached_count(hll, count);
+                            (Update::Modified, Ok(count))
+                        }
+                        Err(e) => (Update::Keep, Err(e)),
+                    }
+                }
+                Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+            });
+        }
+        let mut locked = self.lock_many(db, keys);
+        let mut registers = vec![0; hyperloglog::REGISTERS];
+        for key in keys {
+            match locked.get(key) {
+                None => {}
+                Some(RedisObject::String(hll)) => {
+                    hyperloglog::validate(hll)?;
+                    hyperloglog::registers_max(&mut registers, hll)?;
+                }
+                Some(_) => return Err(RedisError::WrongType),
+            }
+        }
+        Ok(hyperloglog::count_registers(&registers))
+    }
+
+    /// `PFMERGE`: merges the HyperLogLogs into `destination`, created if
+    /// needed and included in the union. It becomes dense if any of them is.
+    pub fn pf_merge(&self, db: usize, destination: &str, sources: &[String]) -> Result<()> {
+        let mut keys = vec![destination];
+        keys.extend(sources.iter().map(String::as_str));
+        let mut locked = self.lock_many(db, &keys);
+        let mut registers = vec![0; hyperloglog::REGISTERS];
+        let mut dense = false;
+        for key in &keys {
+            match locked.get(key) {
+                None => {}
+                Some(RedisObject::String(hll)) => {
+                    hyperloglog::validate(hll)?;
+                    dense |= hyperloglog::is_dense(hll);
+                    hyperloglog::registers_max(&mut registers, hll)?;
+                }
+                Some(_) => return Err(RedisError::WrongType),
+            }
+        }
+        match locked.get_mut(destination) {
+            Some(RedisObject::String(hll)) => {
+                hyperloglog::merge(hll, &registers, dense)?;
+                locked.modified(destination);
+            }
+            _ => {
+                let mut hll = hyperloglog::new();
+                hyperloglog::merge(&mut hll, &registers, dense)?;
+                locked.set(destination, RedisObject::String(hll));
+            }
+        }
+        Ok(())
+    }
+}
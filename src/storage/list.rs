@@ -0,0 +1,356 @@
+use super::{Expiry, Storage, Update};
+use crate::error::{RedisError, Result};
+use crate::object::RedisObject;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl TryFrom<&str> for ListEnd {
+    type Error = ();
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "left" => Ok(ListEnd::Left),
+            "right" => Ok(ListEnd::Right),
+            _ => Err(()),
+        }
+    }
+}
+
+fn push_to(list: &mut VecDeque<String>, end: ListEnd, element: String) {
+    match end {
+        ListEnd::Left => list.push_front(element),
+        ListEnd::Right => list.push_back(element),
+    }
+}
+
+fn pop_from(list: &mut VecDeque<String>, end: ListEnd, count: usize) -> Vec<String> {
+    let count = count.min(list.len());
+    match end {
+        ListEnd::Left => list.drain(..count).collect(),
+        ListEnd::Right => (0..count).filter_map(|_| list.pop_back()).collect(),
+    }
+}
+
+/// Resolves a `start`/`stop` pair of possibly negative indexes into an
+/// inclusive range, `None` if it selects nothing.
+pub(super) fn range_bounds(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+/// Resolves a possibly negative index, `None` if it is out of range.
+fn index_of(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+impl Storage {
+    /// Runs `f` on the list at `key`, `None` if the key does not exist.
+    fn read_list<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&VecDeque<String>) -> R,
+    ) -> Result<Option<R>> {
+        self.read(db, key, |value| match value {
+            None => Ok(None),
+            Some(RedisObject::List(list)) => Ok(Some(f(list))),
+            Some(_) => Err(RedisError::WrongType),
+        })
+    }
+
+    /// Runs `f` on the list at `key` and stores the result, `None` if the key
+    /// does not exist. A list left empty is removed.
+    fn update_list<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&mut VecDeque<String>) -> R,
+    ) -> Result<Option<R>> {
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(None)),
+            Some(RedisObject::List(list)) => (Update::Modified, Ok(Some(f(list)))),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `LPUSH`/`RPUSH`: pushes the elements one after the other and returns
+    /// the new length. With `only_if_exists` (`LPUSHX`/`RPUSHX`) a missing
+    /// key is left alone and 0 is returned.
+    pub fn list_push(
+        &self,
+        db: usize,
+        key: &str,
+        end: ListEnd,
+        elements: Vec<String>,
+        only_if_exists: bool,
+    ) -> Result<usize> {
+        self.update(db, key, |value| match value {
+            None if only_if_exists => (Update::Keep, Ok(0)),
+            None => {
+                let mut list = VecDeque::with_capacity(elements.len());
+                for element in elements {
+                    push_to(&mut list, end, element);
+                }
+                let len = list.len();
+                (
+                    Update::Set(RedisObject::List(list), Expiry::INFINITE),
+                    Ok(len),
+                )
+            }
+            Some(RedisObject::List(list)) => {
+                for element in elements {
+                    push_to(list, end, element);
+                }
+                (Update::Modified, Ok(list.len()))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `LPOP`/`RPOP`: removes up to `count` elements, `None` if the key does not exist.
+    pub fn list_pop(
+        &self,
+        db: usize,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<String>>> {
+        self.update_list(db, key, |list| pop_from(list, end, count))
+    }
+
+    pub fn list_len(&self, db: usize, key: &str) -> Result<usize> {
+        Ok(self.read_list(db, key, |list| list.len())?.unwrap_or(0))
+    }
+
+    pub fn list_range(&self, db: usize, key: &str, start: i64, stop: i64) -> Result<Vec<String>> {
+        let range = self.read_list(db, key, |list| {
+            match range_bounds(start, stop, list.len()) {
+                Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+                None => Vec::new(),
+            }
+        })?;
+        Ok(range.unwrap_or_default())
+    }
+
+    pub fn list_index(&self, db: usize, key: &str, index: i64) -> Result<Option<String>> {
+        let element = self.read_list(db, key, |list| {
+            index_of(index, list.len()).map(|index| list[index].clone())
+        })?;
+        Ok(element.flatten())
+    }
+
+    /// `LSET`: fails with `KeyDoesNotExist` or `IndexOutOfBoundError`.
+    pub fn list_set(&self, db: usize, key: &str, index: i64, element: String) -> Result<()> {
+        let set = self.update_list(db, key, |list| match index_of(index, list.len()) {
+            Some(index) => {
+                list[index] = element;
+                Ok(())
+            }
+            None => Err(RedisError::IndexOutOfBoundError),
+        })?;
+        set.unwrap_or(Err(RedisError::KeyDoesNotExist))
+    }
+
+    /// `LREM`: removes up to `count` occurrences of `element`, from the head
+    /// if `count` is positive, from the tail if negative, all of them if 0.
+    pub fn list_remove(&self, db: usize, key: &str, count: i64, element: &str) -> Result<usize> {
+        let removed = self.update_list(db, key, |list| {
+            let limit = if count == 0 {
+                usize::MAX
+            } else {
+                count.unsigned_abs() as usize
+            };
+            let mut removed = 0;
+            if count < 0 {
+                let mut index = list.len();
+                while index > 0 && removed < limit {
+                    index -= 1;
+                    if list[index] == element {
+                        list.remove(index);
+                        removed += 1;
+                    }
+                }
+            } else {
+                let mut index = 0;
+                while index < list.len() && removed < limit {
+                    if list[index] == element {
+                        list.remove(index);
+                        removed += 1;
+                    } else {
+                        index += 1;
+                    }
+                }
+            }
+            removed
+        })?;
+        Ok(removed.unwrap_or(0))
+    }
+
+    /// `LTRIM`: keeps only the elements between `start` and `stop`.
+    pub fn list_trim(&self, db: usize, key: &str, start: i64, stop: i64) -> Result<()> {
+        self.update_list(db, key, |list| {
+            match range_bounds(start, stop, list.len()) {
+                Some((start, stop)) => {
+                    list.truncate(stop + 1);
+                    list.drain(..start);
+                }
+                None => list.clear(),
+            }
+        })?;
+        Ok(())
+    }
+
+    /// `LINSERT`: returns the new length, -1 if `pivot` was not found and 0
+    /// if the key does not exist.
+    pub fn list_insert(
+        &self,
+        db: usize,
+        key: &str,
+        before: bool,
+        pivot: &str,
+        element: String,
+    ) -> Result<i64> {
+        let inserted = self.update_list(db, key, |list| {
+            match list.iter().position(|current| current == pivot) {
+                Some(index) => {
+                    list.insert(if before { index } else { index + 1 }, element);
+                    list.len() as i64
+                }
+                None => -1,
+            }
+        })?;
+        Ok(inserted.unwrap_or(0))
+    }
+
+    /// `LPOS`: indexes of the matches of `element`. `rank` selects the first
+    /// match to return (negative to search from the tail), `count` how many
+    /// matches to return (0 for all) and `max_len` how many elements to
+    /// compare (0 for all).
+    pub fn list_positions(
+        &self,
+        db: usize,
+        key: &str,
+        element: &str,
+        rank: i64,
+        count: usize,
+        max_len: usize,
+    ) -> Result<Vec<usize>> {
+        let positions = self.read_list(db, key, |list| {
+            let count = if count == 0 { usize::MAX } else { count };
+            let max_len = if max_len == 0 { list.len() } else { max_len };
+            let skip = rank.unsigned_abs() as usize - 1;
+            let indexes: Box<dyn Iterator<Item = usize>> = if rank < 0 {
+                Box::new((0..list.len()).rev().take(max_len))
+            } else {
+                Box::new((0..list.len()).take(max_len))
+            };
+            indexes
+                .filter(|index| list[*index] == element)
+                .skip(skip)
+                .take(count)
+                .collect()
+        })?;
+        Ok(positions.unwrap_or_default())
+    }
+
+    /// `LMOVE`: pops an element from `source` and pushes it to `destination`
+    /// atomically. Returns the element, `None` if `source` does not exist.
+    pub fn list_move(
+        &self,
+        db: usize,
+        source: &str,
+        destination: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<String>> {
+        let mut keys = self.lock_many(db, &[source, destination]);
+        let source_exists = match keys.get(source) {
+            None => false,
+            Some(RedisObject::List(_)) => true,
+            Some(_) => return Err(RedisError::WrongType),
+        };
+        match keys.get(destination) {
+            None | Some(RedisObject::List(_)) => {}
+            Some(_) => return Err(RedisError::WrongType),
+        }
+        if !source_exists {
+            return Ok(None);
+        }
+        let Some(RedisObject::List(list)) = keys.get_mut(source) else {
+            unreachable!("type checked above");
+        };
+        let element = pop_from(list, from, 1)
+            .pop()
+            .expect("lists are never empty");
+        keys.modified(source);
+        match keys.get_mut(destination) {
+            Some(RedisObject::List(list)) => {
+                push_to(list, to, element.clone());
+                keys.modified(destination);
+            }
+            _ => keys.set(
+                destination,
+                RedisObject::List(VecDeque::from([element.clone()])),
+            ),
+        }
+        Ok(Some(element))
+    }
+
+    /// `LMPOP`: pops up to `count` elements from the first non-empty list of
+    /// `keys`. Returns the key and the elements, `None` if no list exists.
+    pub fn list_multi_pop(
+        &self,
+        db: usize,
+        keys: &[String],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<(String, Vec<String>)>> {
+        let mut locked = self.lock_many(db, keys);
+        for key in keys {
+            match locked.get_mut(key) {
+                None => continue,
+                Some(RedisObject::List(list)) => {
+                    let elements = pop_from(list, end, count);
+                    locked.modified(key);
+                    return Ok(Some((key.clone(), elements)));
+                }
+                Some(_) => return Err(RedisError::WrongType),
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_bounds() {
+        assert_eq!(range_bounds(0, -1, 5), Some((0, 4)));
+        assert_eq!(range_bounds(-3, 2, 5), Some((2, 2)));
+        assert_eq!(range_bounds(-100, 100, 5), Some((0, 4)));
+        assert_eq!(range_bounds(3, 1, 5), None);
+        assert_eq!(range_bounds(5, 10, 5), None);
+        assert_eq!(range_bounds(0, -6, 5), None);
+        assert_eq!(range_bounds(0, 0, 0), None);
+        assert_eq!(index_of(-1, 3), Some(2));
+        assert_eq!(index_of(3, 3), None);
+        assert_eq!(index_of(-4, 3), None);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::pattern::glob_match;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Position of an element in the scan order: a hash with fixed keys, so it
+/// does not depend on the other elements or on the process.
+fn position(element: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    element.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// One step of an `HSCAN`/`SSCAN`/`ZSCAN` iteration over `elements`.
+///
+/// Elements are visited by increasing position, and the cursor is the
+/// position to resume from, so every element present during the whole
+/// iteration is returned whatever is added or removed meanwhile. Returns the
+/// next cursor, 0 once the iteration is complete, and the values of the
+/// visited elements whose name matches `pattern`. Like in Redis, `count`
+/// bounds the visited elements, not the matching ones.
+pub(super) fn scan<'a, T>(
+    elements: impl Iterator<Item = (&'a str, T)>,
+    cursor: u64,
+    count: usize,
+    pattern: Option<&str>,
+) -> (u64, Vec<T>) {
+    let mut remaining: Vec<(u64, &str, T)> = elements
+        .map(|(element, value)| (position(element), element, value))
+        .filter(|(position, _, _)| *position >= cursor)
+        .collect();
+    remaining.sort_unstable_by_key(|(position, _, _)| *position);
+    let mut visited = Vec::new();
+    let mut steps = 0;
+    let mut next_cursor = 0;
+    let mut remaining = remaining.into_iter().peekable();
+    while let Some((position, element, value)) = remaining.next() {
+        steps += 1;
+        if pattern.is_none_or(|pattern| glob_match(pattern, element)) {
+            visited.push(value);
+        }
+        match remaining.peek() {
+            // Elements at the same position can't be split between two calls.
+            Some((next, _, _)) if *next == position => {}
+            Some((next, _, _)) if steps >= count => {
+                next_cursor = *next;
+                break;
+            }
+            _ => {}
+        }
+    }
+    (next_cursor, visited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_returns_every_element_once() {
+        let elements: Vec<String> = (0..100).map(|i| format!("element:{}", i)).collect();
+        let mut cursor = 0;
+        let mut seen = Vec::new();
+        loop {
+            let iter = elements.iter().map(|e| (e.as_str(), e.clone()));
+            let (next, visited) = scan(iter, cursor, 7, None);
+            seen.extend(visited);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        let mut expected = elements.clone();
+        expected.sort();
+        assert_eq!(seen, expected);
+
+        let iter = elements.iter().map(|e| (e.as_str(), ()));
+        let (next, visited) = scan(iter, 0, 1000, Some("element:1?"));
+        assert_eq!((next, visited.len()), (0, 10));
+    }
+}
@@ -0,0 +1,242 @@
+use super::scan::scan;
+use super::{Expiry, Storage, Update};
+use crate::error::{RedisError, Result};
+use crate::object::{RedisObject, Set};
+use std::collections::HashSet;
+
+/// How the sets of `SINTER`, `SUNION` and `SDIFF` are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Inter,
+    Union,
+    /// The members of the first set that are in none of the others.
+    Diff,
+}
+
+impl Storage {
+    /// Runs `f` on the set at `key`, `None` if the key does not exist.
+    fn read_set<R>(&self, db: usize, key: &str, f: impl FnOnce(&Set) -> R) -> Result<Option<R>> {
+        self.read(db, key, |value| match value {
+            None => Ok(None),
+            Some(RedisObject::Set(set)) => Ok(Some(f(set))),
+            Some(_) => Err(RedisError::WrongType),
+        })
+    }
+
+    /// Runs `f` on the set at `key`, created empty if the key does not exist,
+    /// and stores the result. A set left empty is removed.
+    fn update_set<R>(&self, db: usize, key: &str, f: impl FnOnce(&mut Set) -> R) -> Result<R> {
+        self.update(db, key, |value| match value {
+            None => {
+                let mut set = Set::default();
+                let result = f(&mut set);
+                if set.is_empty() {
+                    (Update::Keep, Ok(result))
+                } else {
+                    (
+                        Update::Set(RedisObject::Set(set), Expiry::INFINITE),
+                        Ok(result),
+                    )
+                }
+            }
+            Some(RedisObject::Set(set)) => (Update::Modified, Ok(f(set))),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `SADD`: returns the number of members that were added.
+    pub fn set_add(&self, db: usize, key: &str, members: Vec<String>) -> Result<usize> {
+        self.update_set(db, key, |set| {
+            members
+                .into_iter()
+                .filter(|member| set.insert(member.clone()))
+                .count()
+        })
+    }
+
+    /// `SREM`: returns the number of members that were removed.
+    pub fn set_remove(&self, db: usize, key: &str, members: &[String]) -> Result<usize> {
+        self.update_set(db, key, |set| {
+            members.iter().filter(|member| set.remove(member)).count()
+        })
+    }
+
+    pub fn set_members(&self, db: usize, key: &str) -> Result<Vec<String>> {
+        Ok(self.read_set(db, key, Set::members)?.unwrap_or_default())
+    }
+
+    pub fn set_len(&self, db: usize, key: &str) -> Result<usize> {
+        Ok(self.read_set(db, key, Set::len)?.unwrap_or(0))
+    }
+
+    /// `SMISMEMBER`: whether each of `members` is in the set.
+    pub fn set_contains(&self, db: usize, key: &str, members: &[String]) -> Result<Vec<bool>> {
+        let found = self.read_set(db, key, |set| {
+            members.iter().map(|member| set.contains(member)).collect()
+        })?;
+        Ok(found.unwrap_or_else(|| vec![false; members.len()]))
+    }
+
+    /// `SPOP`: removes up to `count` random members. `None` if the key does
+    /// not exist.
+    pub fn set_pop(&self, db: usize, key: &str, count: usize) -> Result<Option<Vec<String>>> {
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(None)),
+            Some(RedisObject::Set(set)) => {
+                let popped = set.random(count, false);
+                for member in &popped {
+                    set.remove(member);
+                }
+                (Update::Modified, Ok(Some(popped)))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `SRANDMEMBER`: up to `count` distinct members, or exactly `-count`
+    /// members that may repeat if `count` is negative.
+    pub fn set_random_members(&self, db: usize, key: &str, count: i64) -> Result<Vec<String>> {
+        let members = self.read_set(db, key, |set| {
+            set.random(count.unsigned_abs() as usize, count < 0)
+        })?;
+        Ok(members.unwrap_or_default())
+    }
+
+    /// `SMOVE`: returns false if `member` is not in the source set.
+    pub fn set_move(
+        &self,
+        db: usize,
+        source: &str,
+        destination: &str,
+        member: &str,
+    ) -> Result<bool> {
+        let mut keys = self.lock_many(db, &[source, destination]);
+        let found = match keys.get(source) {
+            None => false,
+            Some(RedisObject::Set(set)) => set.contains(member),
+            Some(_) => return Err(RedisError::WrongType),
+        };
+        match keys.get(destination) {
+            None | Some(RedisObject::Set(_)) => {}
+            Some(_) => return Err(RedisError::WrongType),
+        }
+        if !found || source == destination {
+            return Ok(found);
+        }
+        if let Some(RedisObject::Set(set)) = keys.get_mut(source) {
+            set.remove(member);
+            keys.modified(source);
+        }
+        match keys.get_mut(destination) {
+            Some(RedisObject::Set(set)) => {
+                set.insert(member.to_owned());
+                keys.modified(destination);
+            }
+            _ => keys.set(
+                destination,
+                RedisObject::Set(Set::from_iter([member.to_owned()])),
+            ),
+        }
+        Ok(true)
+    }
+
+    /// `SINTER`, `SUNION` and `SDIFF`. Missing keys count as empty sets.
+    pub fn set_combine(
+        &self,
+        db: usize,
+        keys: &[String],
+        operation: SetOperation,
+    ) -> Result<Vec<String>> {
+        let mut locked = self.lock_many(db, keys);
+        let members = combine(keys, operation, |key| match locked.get(key) {
+            None => Ok(None),
+            Some(RedisObject::Set(set)) => Ok(Some(set.members())),
+            Some(_) => Err(RedisError::WrongType),
+        })?;
+        Ok(members.into_iter().collect())
+    }
+
+    /// `SINTERSTORE`, `SUNIONSTORE` and `SDIFFSTORE`: stores the result in
+    /// `destination`, deleted if it is empty. Returns the size of the result.
+    pub fn set_combine_store(
+        &self,
+        db: usize,
+        destination: &str,
+        keys: &[String],
+        operation: SetOperation,
+    ) -> Result<usize> {
+        let mut all_keys = keys.to_vec();
+        all_keys.push(destination.to_owned());
+        let mut locked = self.lock_many(db, &all_keys);
+        let members = combine(keys, operation, |key| match locked.get(key) {
+            None => Ok(None),
+            Some(RedisObject::Set(set)) => Ok(Some(set.members())),
+            Some(_) => Err(RedisError::WrongType),
+        })?;
+        let len = members.len();
+        locked.set(destination, RedisObject::Set(members.into_iter().collect()));
+        Ok(len)
+    }
+
+    /// `SINTERCARD`: size of the intersection, capped at `limit` unless it is 0.
+    pub fn set_intersection_len(&self, db: usize, keys: &[String], limit: usize) -> Result<usize> {
+        let members = self.set_combine(db, keys, SetOperation::Inter)?;
+        Ok(match limit {
+            0 => members.len(),
+            limit => members.len().min(limit),
+        })
+    }
+
+    /// `SSCAN`: see [`scan`] for the cursor.
+    pub fn set_scan(
+        &self,
+        db: usize,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, Vec<String>)> {
+        let scanned = self.read_set(db, key, |set| {
+            let members = set.members();
+            scan(
+                members
+                    .iter()
+                    .map(|member| (member.as_str(), member.clone())),
+                cursor,
+                count,
+                pattern,
+            )
+        })?;
+        Ok(scanned.unwrap_or((0, Vec::new())))
+    }
+}
+
+/// Combines the sets of `keys`, read with `members` which returns `None` for
+/// a missing key. Every key is read so that a wrong type is always reported.
+fn combine(
+    keys: &[String],
+    operation: SetOperation,
+    mut members: impl FnMut(&str) -> Result<Option<Vec<String>>>,
+) -> Result<HashSet<String>> {
+    let mut sets = Vec::with_capacity(keys.len());
+    for key in keys {
+        sets.push(members(key)?.unwrap_or_default());
+    }
+    let mut sets = sets.into_iter();
+    let mut result: HashSet<String> = sets.next().unwrap_or_default().into_iter().collect();
+    for set in sets {
+        match operation {
+            SetOperation::Inter => {
+                let set: HashSet<String> = set.into_iter().collect();
+                result.retain(|member| set.contains(member));
+            }
+            SetOperation::Union => result.extend(set),
+            SetOperation::Diff => {
+                for member in set {
+                    result.remove(&member);
+                }
+            }
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,549 @@
+use super::list::range_bounds;
+use super::scan::scan;
+use super::{Expiry, SetOperation, Storage, Update};
+use crate::db::SetCondition;
+use crate::error::{RedisError, Result};
+use crate::object::{RedisObject, SortedSet};
+use std::collections::HashMap;
+use std::ops::{Bound, Range};
+
+/// Members of a sorted set with their scores.
+type Scored = Vec<(String, f64)>;
+
+/// Options of `ZADD`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZAddOptions {
+    /// `NX` only adds new members, `XX` only updates existing ones.
+    pub condition: Option<SetCondition>,
+    /// Only update a score if the new one is greater.
+    pub gt: bool,
+    /// Only update a score if the new one is less.
+    pub lt: bool,
+    /// Count the updated members along with the added ones.
+    pub changed: bool,
+}
+
+/// Which members a range of a sorted set selects.
+#[derive(Debug, Clone)]
+pub enum RangeBy {
+    /// Ranks, possibly negative to count from the end, both inclusive.
+    Rank(i64, i64),
+    Score(Bound<f64>, Bound<f64>),
+    /// Members between two bounds, for sets whose members all have the same score.
+    Lex(Bound<String>, Bound<String>),
+}
+
+/// A range of `ZRANGE`.
+#[derive(Debug, Clone)]
+pub struct ZRange {
+    pub by: RangeBy,
+    /// Walk the set from the highest score. The bounds are still given as
+    /// min then max, and ranks count from the highest score.
+    pub rev: bool,
+    /// `LIMIT offset count`, a negative count returning all the members
+    /// after the offset.
+    pub limit: Option<(i64, i64)>,
+}
+
+impl ZRange {
+    pub fn new(by: RangeBy) -> Self {
+        ZRange {
+            by,
+            rev: false,
+            limit: None,
+        }
+    }
+
+    /// The members and scores of `set` in the range.
+    fn select(&self, set: &SortedSet) -> Vec<(String, f64)> {
+        let len = set.len();
+        let ranks = match &self.by {
+            RangeBy::Rank(start, stop) => match range_bounds(*start, *stop, len) {
+                Some((start, stop)) if self.rev => len - 1 - stop..len - start,
+                Some((start, stop)) => start..stop + 1,
+                None => 0..0,
+            },
+            RangeBy::Score(min, max) => set.score_ranks(*min, *max),
+            RangeBy::Lex(min, max) => set.lex_ranks(
+                min.as_ref().map(String::as_str),
+                max.as_ref().map(String::as_str),
+            ),
+        };
+        set.range(self.limit_ranks(ranks), self.rev)
+    }
+
+    /// Applies `LIMIT` to `ranks`, counting the offset from the end if `rev`.
+    fn limit_ranks(&self, ranks: Range<usize>) -> Range<usize> {
+        let Some((offset, count)) = self.limit else {
+            return ranks;
+        };
+        if offset < 0 || offset as usize >= ranks.len() {
+            return 0..0;
+        }
+        let available = ranks.len() - offset as usize;
+        let count = if count < 0 {
+            available
+        } else {
+            available.min(count as usize)
+        };
+        if self.rev {
+            let end = ranks.end - offset as usize;
+            end - count..end
+        } else {
+            let start = ranks.start + offset as usize;
+            start..start + count
+        }
+    }
+}
+
+/// How `ZUNION` and `ZINTER` combine the scores of a member found in
+/// several sets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(self, score: f64, other: f64) -> f64 {
+        match self {
+            // `inf + -inf` is 0 rather than NaN, like in Redis.
+            Aggregate::Sum => zero_if_nan(score + other),
+            Aggregate::Min => score.min(other),
+            Aggregate::Max => score.max(other),
+        }
+    }
+}
+
+/// How `ZUNION`, `ZINTER` and `ZDIFF` combine their input sets.
+#[derive(Debug, Clone)]
+pub struct ZCombine {
+    pub operation: SetOperation,
+    /// Factors of the scores of each input set, all 1 if empty.
+    pub weights: Vec<f64>,
+    pub aggregate: Aggregate,
+}
+
+impl ZCombine {
+    pub fn new(operation: SetOperation) -> Self {
+        ZCombine {
+            operation,
+            weights: Vec::new(),
+            aggregate: Aggregate::default(),
+        }
+    }
+
+    /// Combines the members of the input sets, in the order of the keys.
+    fn apply(&self, sets: Vec<Scored>) -> SortedSet {
+        let mut sets = sets.into_iter().enumerate().map(|(index, set)| {
+            let weight = self.weights.get(index).copied().unwrap_or(1.0);
+            set.into_iter()
+                .map(move |(member, score)| (member, zero_if_nan(score * weight)))
+        });
+        let mut result: HashMap<String, f64> = match sets.next() {
+            Some(first) => first.collect(),
+            None => HashMap::new(),
+        };
+        for set in sets {
+            match self.operation {
+                SetOperation::Union => {
+                    for (member, score) in set {
+                        result
+                            .entry(member)
+                            .and_modify(|current| *current = self.aggregate.apply(*current, score))
+                            .or_insert(score);
+                    }
+                }
+                SetOperation::Inter => {
+                    let set: HashMap<String, f64> = set.collect();
+                    result.retain(|member, current| match set.get(member) {
+                        Some(score) => {
+                            *current = self.aggregate.apply(*current, *score);
+                            true
+                        }
+                        None => false,
+                    });
+                }
+                SetOperation::Diff => {
+                    for (member, _) in set {
+                        result.remove(&member);
+                    }
+                }
+            }
+        }
+        result.into_iter().collect()
+    }
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+/// The members and scores of an input of `ZUNION` and co: a sorted set, or
+/// a set whose members all have a score of 1. Missing keys are empty.
+fn scored_members(value: Option<&RedisObject>) -> Result<Scored> {
+    match value {
+        None => Ok(Vec::new()),
+        Some(RedisObject::SortedSet(set)) => Ok(set
+            .iter()
+            .map(|(member, score)| (member.clone(), score))
+            .collect()),
+        Some(RedisObject::Set(set)) => Ok(set
+            .members()
+            .into_iter()
+            .map(|member| (member, 1.0))
+            .collect()),
+        Some(_) => Err(RedisError::WrongType),
+    }
+}
+
+impl Storage {
+    /// Runs `f` on the sorted set at `key`, `None` if the key does not exist.
+    fn read_sorted_set<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&SortedSet) -> R,
+    ) -> Result<Option<R>> {
+        self.read(db, key, |value| match value {
+            None => Ok(None),
+            Some(RedisObject::SortedSet(set)) => Ok(Some(f(set))),
+            Some(_) => Err(RedisError::WrongType),
+        })
+    }
+
+    /// Runs `f` on the sorted set at `key`, created empty if the key does not
+    /// exist, and stores the result. A sorted set left empty is removed.
+    fn update_sorted_set<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&mut SortedSet) -> Result<R>,
+    ) -> Result<R> {
+        self.update(db, key, |value| match value {
+            None => {
+                let mut set = SortedSet::default();
+                match f(&mut set) {
+                    Ok(result) if set.is_empty() => (Update::Keep, Ok(result)),
+                    Ok(result) => (
+                        Update::Set(RedisObject::SortedSet(set), Expiry::INFINITE),
+                        Ok(result),
+                    ),
+                    Err(e) => (Update::Keep, Err(e)),
+                }
+            }
+            Some(RedisObject::SortedSet(set)) => (Update::Modified, f(set)),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `ZADD`: returns the number of members added, plus the ones whose
+    /// score changed with `changed`.
+    pub fn sorted_set_add(
+        &self,
+        db: usize,
+        key: &str,
+        pairs: Vec<(f64, String)>,
+        options: ZAddOptions,
+    ) -> Result<usize> {
+        self.update_sorted_set(db, key, |set| {
+            let mut count = 0;
+            for (score, member) in pairs {
+                match add(set, member, score, options) {
+                    Some(None) => count += 1,
+                    Some(Some(previous)) if options.changed && previous != score => count += 1,
+                    _ => {}
+                }
+            }
+            Ok(count)
+        })
+    }
+
+    /// `ZINCRBY` and `ZADD ... INCR`: returns the new score, `None` if the
+    /// options prevented the update.
+    pub fn sorted_set_incr_by(
+        &self,
+        db: usize,
+        key: &str,
+        member: &str,
+        increment: f64,
+        options: ZAddOptions,
+    ) -> Result<Option<f64>> {
+        self.update_sorted_set(db, key, |set| {
+            let score = set.score(member).unwrap_or(0.0) + increment;
+            if score.is_nan() {
+                return Err(RedisError::ScoreNaN);
+            }
+            Ok(add(set, member.to_owned(), score, options).map(|_| score))
+        })
+    }
+
+    /// `ZREM`: returns the number of members that were removed.
+    pub fn sorted_set_remove(&self, db: usize, key: &str, members: &[String]) -> Result<usize> {
+        self.update_sorted_set(db, key, |set| {
+            Ok(members
+                .iter()
+                .filter(|member| set.remove(member).is_some())
+                .count())
+        })
+    }
+
+    /// `ZMSCORE`: the score of each member.
+    pub fn sorted_set_scores(
+        &self,
+        db: usize,
+        key: &str,
+        members: &[String],
+    ) -> Result<Vec<Option<f64>>> {
+        let scores = self.read_sorted_set(db, key, |set| {
+            members.iter().map(|member| set.score(member)).collect()
+        })?;
+        Ok(scores.unwrap_or_else(|| vec![None; members.len()]))
+    }
+
+    pub fn sorted_set_len(&self, db: usize, key: &str) -> Result<usize> {
+        Ok(self.read_sorted_set(db, key, SortedSet::len)?.unwrap_or(0))
+    }
+
+    /// `ZCOUNT`: the number of members with a score in the range.
+    pub fn sorted_set_count(
+        &self,
+        db: usize,
+        key: &str,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> Result<usize> {
+        let count = self.read_sorted_set(db, key, |set| set.score_ranks(min, max).len())?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// `ZRANK` and `ZREVRANK`: the rank of the member, counted from the
+    /// highest score if `rev`, with its score.
+    pub fn sorted_set_rank(
+        &self,
+        db: usize,
+        key: &str,
+        member: &str,
+        rev: bool,
+    ) -> Result<Option<(usize, f64)>> {
+        let rank = self.read_sorted_set(db, key, |set| {
+            let rank = set.rank(member)?;
+            let rank = if rev { set.len() - 1 - rank } else { rank };
+            Some((rank, set.score(member)?))
+        })?;
+        Ok(rank.flatten())
+    }
+
+    /// `ZRANGE`: the members and scores in the range.
+    pub fn sorted_set_range(
+        &self,
+        db: usize,
+        key: &str,
+        range: &ZRange,
+    ) -> Result<Vec<(String, f64)>> {
+        let members = self.read_sorted_set(db, key, |set| range.select(set))?;
+        Ok(members.unwrap_or_default())
+    }
+
+    /// `ZRANGESTORE`: stores the range of `source` in `destination`, deleted
+    /// if the range is empty. Returns the number of members stored.
+    pub fn sorted_set_range_store(
+        &self,
+        db: usize,
+        destination: &str,
+        source: &str,
+        range: &ZRange,
+    ) -> Result<usize> {
+        let mut keys = self.lock_many(db, &[source, destination]);
+        let members = match keys.get(source) {
+            None => Vec::new(),
+            Some(RedisObject::SortedSet(set)) => range.select(set),
+            Some(_) => return Err(RedisError::WrongType),
+        };
+        let len = members.len();
+        keys.set(
+            destination,
+            RedisObject::SortedSet(members.into_iter().collect()),
+        );
+        Ok(len)
+    }
+
+    /// `ZUNION`, `ZINTER` and `ZDIFF`: the members and scores of the
+    /// combination, ordered by score. Every key is read so that a wrong type
+    /// is always reported.
+    pub fn sorted_set_combine(
+        &self,
+        db: usize,
+        keys: &[String],
+        combine: &ZCombine,
+    ) -> Result<Vec<(String, f64)>> {
+        let mut locked = self.lock_many(db, keys);
+        let sets = keys
+            .iter()
+            .map(|key| scored_members(locked.get(key)))
+            .collect::<Result<_>>()?;
+        let set = combine.apply(sets);
+        Ok(set.range(0..set.len(), false))
+    }
+
+    /// `ZUNIONSTORE`, `ZINTERSTORE` and `ZDIFFSTORE`: stores the combination
+    /// in `destination`, deleted if it is empty. Returns its size.
+    pub fn sorted_set_combine_store(
+        &self,
+        db: usize,
+        destination: &str,
+        keys: &[String],
+        combine: &ZCombine,
+    ) -> Result<usize> {
+        let mut all_keys = keys.to_vec();
+        all_keys.push(destination.to_owned());
+        let mut locked = self.lock_many(db, &all_keys);
+        let sets = keys
+            .iter()
+            .map(|key| scored_members(locked.get(key)))
+            .collect::<Result<_>>()?;
+        let set = combine.apply(sets);
+        let len = set.len();
+        locked.set(destination, RedisObject::SortedSet(set));
+        Ok(len)
+    }
+
+    /// `ZINTERCARD`: size of the intersection, capped at `limit` unless it is 0.
+    pub fn sorted_set_intersection_len(
+        &self,
+        db: usize,
+        keys: &[String],
+        limit: usize,
+    ) -> Result<usize> {
+        let mut locked = self.lock_many(db, keys);
+        let sets = keys
+            .iter()
+            .map(|key| scored_members(locked.get(key)))
+            .collect::<Result<_>>()?;
+        let len = ZCombine::new(SetOperation::Inter).apply(sets).len();
+        Ok(match limit {
+            0 => len,
+            limit => len.min(limit),
+        })
+    }
+
+    /// `ZPOPMIN` and `ZPOPMAX`: removes up to `count` members with the lowest
+    /// or, if `max`, the highest scores. `None` if the key does not exist.
+    pub fn sorted_set_pop(
+        &self,
+        db: usize,
+        key: &str,
+        count: usize,
+        max: bool,
+    ) -> Result<Option<Vec<(String, f64)>>> {
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(None)),
+            Some(RedisObject::SortedSet(set)) => (Update::Modified, Ok(Some(pop(set, count, max)))),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// Pops from the first non-empty sorted set of `keys`, like
+    /// `BZPOPMIN` and `BZPOPMAX` do. Returns the key and the members.
+    pub fn sorted_set_multi_pop(
+        &self,
+        db: usize,
+        keys: &[String],
+        count: usize,
+        max: bool,
+    ) -> Result<Option<(String, Scored)>> {
+        let mut locked = self.lock_many(db, keys);
+        for key in keys {
+            match locked.get_mut(key) {
+                None => continue,
+                Some(RedisObject::SortedSet(set)) => {
+                    let members = pop(set, count, max);
+                    locked.modified(key);
+                    return Ok(Some((key.clone(), members)));
+                }
+                Some(_) => return Err(RedisError::WrongType),
+            }
+        }
+        Ok(None)
+    }
+
+    /// `ZREMRANGEBYRANK`, `ZREMRANGEBYSCORE` and `ZREMRANGEBYLEX`: returns
+    /// the number of members removed.
+    pub fn sorted_set_remove_range(&self, db: usize, key: &str, by: RangeBy) -> Result<usize> {
+        let range = ZRange::new(by);
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(0)),
+            Some(RedisObject::SortedSet(set)) => {
+                let members = range.select(set);
+                for (member, _) in &members {
+                    set.remove(member);
+                }
+                (Update::Modified, Ok(members.len()))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `ZSCAN`: see [`scan`] for the cursor.
+    pub fn sorted_set_scan(
+        &self,
+        db: usize,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, Vec<(String, f64)>)> {
+        let scanned = self.read_sorted_set(db, key, |set| {
+            let members = set
+                .iter()
+                .map(|(member, score)| (member.as_str(), (member.clone(), score)));
+            scan(members, cursor, count, pattern)
+        })?;
+        Ok(scanned.unwrap_or((0, Vec::new())))
+    }
+}
+
+/// Adds `member` or updates its score if `options` allow it. Returns `None`
+/// if they don't, otherwise the previous score if there was one.
+fn add(
+    set: &mut SortedSet,
+    member: String,
+    score: f64,
+    options: ZAddOptions,
+) -> Option<Option<f64>> {
+    let previous = set.score(&member);
+    let allowed = match previous {
+        None => options.condition != Some(SetCondition::XX),
+        Some(previous) => {
+            options.condition != Some(SetCondition::NX)
+                && (!options.gt || score > previous)
+                && (!options.lt || score < previous)
+        }
+    };
+    if !allowed {
+        return None;
+    }
+    set.insert(member, score);
+    Some(previous)
+}
+
+/// Removes up to `count` members from the lowest or, if `max`, the highest scores.
+fn pop(set: &mut SortedSet, count: usize, max: bool) -> Vec<(String, f64)> {
+    let len = set.len();
+    let ranks = if max {
+        len.saturating_sub(count)..len
+    } else {
+        0..count.min(len)
+    };
+    let members = set.range(ranks, max);
+    for (member, _) in &members {
+        set.remove(member);
+    }
+    members
+}
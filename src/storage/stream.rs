@@ -0,0 +1,521 @@
+use super::{Expiry, Storage, Update};
+use crate::error::{RedisError, Result};
+use crate::object::{
+    AutoClaimed, Claim, ConsumerGroup, PendingEntry, RedisObject, Stream, StreamEntry, StreamId,
+    StreamTrim,
+};
+use std::ops::RangeInclusive;
+
+/// Entries read by `XREADGROUP` from one stream. Entries of the history of a
+/// consumer that were deleted since are `None`.
+pub type GroupEntries = Vec<(StreamId, Option<StreamEntry>)>;
+
+/// The ID `XADD` gives to a new entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddId {
+    /// `*`: the current time, or right after the last ID if the clock is
+    /// behind it.
+    Auto,
+    /// `ms-*`: the next sequence number in this millisecond.
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// Summary form of `XPENDING`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendingSummary {
+    pub count: usize,
+    /// The lowest and highest pending IDs.
+    pub ids: Option<(StreamId, StreamId)>,
+    /// Number of pending entries of each consumer that has some.
+    pub consumers: Vec<(String, usize)>,
+}
+
+/// Which pending entries the extended form of `XPENDING` returns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingFilter {
+    pub ids: RangeInclusive<StreamId>,
+    pub count: usize,
+    pub consumer: Option<String>,
+    /// Only the entries delivered at least this many milliseconds ago.
+    pub min_idle: u128,
+}
+
+impl Storage {
+    /// Runs `f` on the stream at `key`, `None` if the key does not exist.
+    fn read_stream<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&Stream) -> R,
+    ) -> Result<Option<R>> {
+        self.read(db, key, |value| match value {
+            None => Ok(None),
+            Some(RedisObject::Stream(stream)) => Ok(Some(f(stream))),
+            Some(_) => Err(RedisError::WrongType),
+        })
+    }
+
+    /// `XADD`: appends an entry then trims the stream. Creates the stream
+    /// unless `no_create`, in which case `None` is returned for a missing key.
+    pub fn stream_add(
+        &self,
+        db: usize,
+        key: &str,
+        id: XAddId,
+        fields: Vec<(String, String)>,
+        no_create: bool,
+        trim: Option<StreamTrim>,
+    ) -> Result<Option<StreamId>> {
+        let now = self.now_ms() as u64;
+        let add = |stream: &mut Stream| {
+            let id = new_id(stream, id, now)?;
+            stream.append(id, fields);
+            if let Some(trim) = trim {
+                stream.trim(trim);
+            }
+            Ok(id)
+        };
+        self.update(db, key, |value| match value {
+            None if no_create => (Update::Keep, Ok(None)),
+            None => {
+                let mut stream = Stream::default();
+                match add(&mut stream) {
+                    Ok(id) => (
+                        Update::Set(RedisObject::Stream(stream), Expiry::INFINITE),
+                        Ok(Some(id)),
+                    ),
+                    Err(e) => (Update::Keep, Err(e)),
+                }
+            }
+            Some(RedisObject::Stream(stream)) => match add(stream) {
+                Ok(id) => (Update::Modified, Ok(Some(id))),
+                Err(e) => (Update::Keep, Err(e)),
+            },
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    pub fn stream_len(&self, db: usize, key: &str) -> Result<usize> {
+        Ok(self.read_stream(db, key, Stream::len)?.unwrap_or(0))
+    }
+
+    /// `XRANGE` and `XREVRANGE`: at most `count` entries with an ID in
+    /// `ids`, from the highest ID if `rev`.
+    pub fn stream_range(
+        &self,
+        db: usize,
+        key: &str,
+        ids: RangeInclusive<StreamId>,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Result<Vec<StreamEntry>> {
+        let entries = self.read_stream(db, key, |stream| stream.range(ids, count, rev))?;
+        Ok(entries.unwrap_or_default())
+    }
+
+    /// `XDEL`: returns the number of entries that were removed.
+    pub fn stream_delete(&self, db: usize, key: &str, ids: &[StreamId]) -> Result<usize> {
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(0)),
+            Some(RedisObject::Stream(stream)) => {
+                let removed = ids.iter().filter(|id| stream.remove(**id)).count();
+                let update = if removed > 0 {
+                    Update::Modified
+                } else {
+                    Update::Keep
+                };
+                (update, Ok(removed))
+            }
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `XTRIM`: returns the number of entries that were removed.
+    pub fn stream_trim(&self, db: usize, key: &str, trim: StreamTrim) -> Result<usize> {
+        self.update(db, key, |value| match value {
+            None => (Update::Keep, Ok(0)),
+            Some(RedisObject::Stream(stream)) => match stream.trim(trim) {
+                0 => (Update::Keep, Ok(0)),
+                removed => (Update::Modified, Ok(removed)),
+            },
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// The last ID of the stream at `key`, what `$` stands for in `XREAD`.
+    /// 0-0 if the key does not exist.
+    pub fn stream_last_id(&self, db: usize, key: &str) -> Result<StreamId> {
+        let last_id = self.read_stream(db, key, Stream::last_id)?;
+        Ok(last_id.unwrap_or(StreamId::MIN))
+    }
+
+    /// `XREAD`: at most `count` entries after each ID, for the streams that
+    /// have some.
+    pub fn stream_read(
+        &self,
+        db: usize,
+        streams: &[(String, StreamId)],
+        count: Option<usize>,
+    ) -> Result<Vec<(String, Vec<StreamEntry>)>> {
+        let keys: Vec<&str> = streams.iter().map(|(key, _)| key.as_str()).collect();
+        let mut locked = self.lock_many(db, &keys);
+        let mut read = Vec::new();
+        for (key, after) in streams {
+            let stream = match locked.get(key) {
+                None => continue,
+                Some(RedisObject::Stream(stream)) => stream,
+                Some(_) => return Err(RedisError::WrongType),
+            };
+            let Some(start) = after.next() else {
+                continue;
+            };
+            let entries = stream.range(start..=StreamId::MAX, count, false);
+            if !entries.is_empty() {
+                read.push((key.clone(), entries));
+            }
+        }
+        Ok(read)
+    }
+
+    /// Runs `f` on the stream at `key`, for `XINFO`.
+    pub fn stream_info<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&Stream) -> R,
+    ) -> Result<Option<R>> {
+        self.read_stream(db, key, f)
+    }
+
+    /// Runs `f` on the stream at `key` if it has the group, and stores the
+    /// result. A missing key or group is a [`RedisError::NoGroup`].
+    fn update_group<R>(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        f: impl FnOnce(&mut Stream) -> R,
+    ) -> Result<R> {
+        let no_group = || RedisError::NoGroup {
+            key: key.to_owned(),
+            group: group.to_owned(),
+        };
+        self.update(db, key, |value| match value {
+            Some(RedisObject::Stream(stream)) if stream.group(group).is_some() => {
+                (Update::Modified, Ok(f(stream)))
+            }
+            Some(RedisObject::Stream(_)) | None => (Update::Keep, Err(no_group())),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// Runs `f` on the existing stream at `key` and stores the result. A
+    /// missing key is a [`RedisError::KeyDoesNotExist`].
+    fn update_existing_stream<R>(
+        &self,
+        db: usize,
+        key: &str,
+        f: impl FnOnce(&mut Stream) -> Result<R>,
+    ) -> Result<R> {
+        self.update(db, key, |value| match value {
+            Some(RedisObject::Stream(stream)) => match f(stream) {
+                Ok(result) => (Update::Modified, Ok(result)),
+                Err(e) => (Update::Keep, Err(e)),
+            },
+            None => (Update::Keep, Err(RedisError::KeyDoesNotExist)),
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `XGROUP CREATE`: adds a group that starts reading after `id`, the last
+    /// ID of the stream if `None`. Creates an empty stream if `create`.
+    pub fn stream_create_group(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+        create: bool,
+    ) -> Result<()> {
+        let add = |stream: &mut Stream| {
+            let id = id.unwrap_or(stream.last_id());
+            match stream.create_group(group, ConsumerGroup::new(id, entries_read)) {
+                true => Ok(()),
+                false => Err(RedisError::BusyGroup),
+            }
+        };
+        self.update(db, key, |value| match value {
+            None if create => {
+                let mut stream = Stream::default();
+                let result = add(&mut stream);
+                (
+                    Update::Set(RedisObject::Stream(stream), Expiry::INFINITE),
+                    result,
+                )
+            }
+            None => (Update::Keep, Err(RedisError::KeyDoesNotExist)),
+            Some(RedisObject::Stream(stream)) => match add(stream) {
+                Ok(()) => (Update::Modified, Ok(())),
+                Err(e) => (Update::Keep, Err(e)),
+            },
+            Some(_) => (Update::Keep, Err(RedisError::WrongType)),
+        })
+    }
+
+    /// `XGROUP DESTROY`: returns false if there is no such group.
+    pub fn stream_destroy_group(&self, db: usize, key: &str, group: &str) -> Result<bool> {
+        self.update_existing_stream(db, key, |stream| Ok(stream.destroy_group(group)))
+    }
+
+    /// `XGROUP SETID`: the group goes on reading after `id`, the last ID of
+    /// the stream if `None`.
+    pub fn stream_set_group_id(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<()> {
+        self.update_existing_stream(db, key, |stream| {
+            let id = id.unwrap_or(stream.last_id());
+            let Some(group) = stream.group_mut(group) else {
+                return Err(RedisError::NoGroup {
+                    key: key.to_owned(),
+                    group: group.to_owned(),
+                });
+            };
+            group.last_id = id;
+            group.entries_read = entries_read;
+            Ok(())
+        })
+    }
+
+    /// `XGROUP CREATECONSUMER`: returns false if the consumer exists.
+    pub fn stream_create_consumer(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<bool> {
+        let now = self.now_ms();
+        self.update_group(db, key, group, |stream| {
+            let group = stream.group_mut(group).expect("checked by update_group");
+            group.create_consumer(consumer, now)
+        })
+    }
+
+    /// `XGROUP DELCONSUMER`: returns the number of pending entries the
+    /// consumer had.
+    pub fn stream_delete_consumer(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<usize> {
+        self.update_group(db, key, group, |stream| {
+            let group = stream.group_mut(group).expect("checked by update_group");
+            group.delete_consumer(consumer).unwrap_or(0)
+        })
+    }
+
+    /// `XREADGROUP`: for each stream, the entries never delivered to the
+    /// group if the ID is `None` (`>`), or the history of the consumer after
+    /// the ID. Streams without new entries are left out.
+    pub fn stream_read_group(
+        &self,
+        db: usize,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        no_ack: bool,
+    ) -> Result<Vec<(String, GroupEntries)>> {
+        let keys: Vec<&str> = streams.iter().map(|(key, _)| key.as_str()).collect();
+        let mut locked = self.lock_many(db, &keys);
+        // Nothing is read unless every stream has the group.
+        for key in &keys {
+            match locked.get(key) {
+                Some(RedisObject::Stream(stream)) if stream.group(group).is_some() => {}
+                Some(RedisObject::Stream(_)) | None => {
+                    return Err(RedisError::NoGroup {
+                        key: key.to_string(),
+                        group: group.to_owned(),
+                    })
+                }
+                Some(_) => return Err(RedisError::WrongType),
+            }
+        }
+        let now = self.now_ms();
+        let mut read = Vec::new();
+        for (key, after) in streams {
+            let Some(RedisObject::Stream(stream)) = locked.get_mut(key) else {
+                unreachable!("checked above");
+            };
+            let entries = match after {
+                None => stream
+                    .read_group(group, consumer, count, no_ack, now)
+                    .into_iter()
+                    .map(|entry| (entry.id, Some(entry)))
+                    .collect(),
+                Some(after) => stream.read_group_history(group, consumer, *after, count, now),
+            };
+            // Signaling the key when nothing was read would serve blocked
+            // readers again, including this one.
+            if !entries.is_empty() {
+                locked.modified(key);
+            }
+            if after.is_some() || !entries.is_empty() {
+                read.push((key.clone(), entries));
+            }
+        }
+        Ok(read)
+    }
+
+    /// `XACK`: returns the number of pending entries that were removed.
+    pub fn stream_ack(&self, db: usize, key: &str, group: &str, ids: &[StreamId]) -> Result<usize> {
+        let acked = self.update_group(db, key, group, |stream| {
+            let group = stream.group_mut(group).expect("checked by update_group");
+            ids.iter().filter(|id| group.ack(**id)).count()
+        });
+        match acked {
+            Err(RedisError::NoGroup { .. }) => Ok(0),
+            acked => acked,
+        }
+    }
+
+    /// The summary form of `XPENDING`.
+    pub fn stream_pending_summary(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+    ) -> Result<PendingSummary> {
+        self.read_group(db, key, group, |group| {
+            let pending = group.pending();
+            PendingSummary {
+                count: pending.len(),
+                ids: pending
+                    .keys()
+                    .next()
+                    .zip(pending.keys().next_back())
+                    .map(|(first, last)| (*first, *last)),
+                consumers: group
+                    .consumers()
+                    .iter()
+                    .filter(|(_, consumer)| !consumer.pending().is_empty())
+                    .map(|(name, consumer)| (name.clone(), consumer.pending().len()))
+                    .collect(),
+            }
+        })
+    }
+
+    /// The extended form of `XPENDING`: the pending entries that match
+    /// `filter`, in ID order.
+    pub fn stream_pending(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        filter: &PendingFilter,
+    ) -> Result<Vec<(StreamId, PendingEntry)>> {
+        let now = self.now_ms();
+        self.read_group(db, key, group, |group| {
+            group
+                .pending()
+                .range(filter.ids.clone())
+                .filter(|(_, entry)| {
+                    filter
+                        .consumer
+                        .as_ref()
+                        .is_none_or(|consumer| entry.consumer == *consumer)
+                        && now.saturating_sub(entry.delivered_at) >= filter.min_idle
+                })
+                .take(filter.count)
+                .map(|(id, entry)| (*id, entry.clone()))
+                .collect()
+        })
+    }
+
+    /// Runs `f` on a group of the stream at `key`.
+    fn read_group<R>(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        f: impl FnOnce(&ConsumerGroup) -> R,
+    ) -> Result<R> {
+        let result = self.read_stream(db, key, |stream| stream.group(group).map(f))?;
+        result.flatten().ok_or_else(|| RedisError::NoGroup {
+            key: key.to_owned(),
+            group: group.to_owned(),
+        })
+    }
+
+    /// `XCLAIM`: returns the claimed entries.
+    pub fn stream_claim(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        ids: &[StreamId],
+        claim: &Claim,
+    ) -> Result<Vec<StreamEntry>> {
+        self.update_group(db, key, group, |stream| {
+            let claimed = stream.claim(group, ids, claim);
+            claimed
+                .into_iter()
+                .filter_map(|id| stream.get(id).cloned())
+                .collect()
+        })
+    }
+
+    /// `XAUTOCLAIM`: claims up to `count` entries from `start`. Returns the
+    /// outcome and the claimed entries.
+    pub fn stream_auto_claim(
+        &self,
+        db: usize,
+        key: &str,
+        group: &str,
+        start: StreamId,
+        count: usize,
+        claim: &Claim,
+    ) -> Result<(AutoClaimed, Vec<StreamEntry>)> {
+        self.update_group(db, key, group, |stream| {
+            let claimed = stream.auto_claim(group, start, count, claim);
+            let entries = claimed
+                .claimed
+                .iter()
+                .filter_map(|id| stream.get(*id).cloned())
+                .collect();
+            (claimed, entries)
+        })
+    }
+}
+
+/// The ID of a new entry of `stream`, created at `now`.
+fn new_id(stream: &Stream, id: XAddId, now: u64) -> Result<StreamId> {
+    let last = stream.last_id();
+    let id = match id {
+        XAddId::Auto if now > last.ms => StreamId::new(now, 0),
+        XAddId::Auto => return last.next().ok_or(RedisError::StreamExhausted),
+        XAddId::AutoSeq(ms) if ms > last.ms => StreamId::new(ms, 0),
+        XAddId::AutoSeq(ms) if ms == last.ms => match last.seq.checked_add(1) {
+            Some(seq) => StreamId::new(ms, seq),
+            None => return Err(RedisError::StreamIdTooSmall),
+        },
+        XAddId::AutoSeq(_) => return Err(RedisError::StreamIdTooSmall),
+        XAddId::Explicit(id) => id,
+    };
+    if id == StreamId::MIN {
+        return Err(RedisError::StreamIdZero);
+    }
+    if id <= last {
+        return Err(RedisError::StreamIdTooSmall);
+    }
+    Ok(id)
+}
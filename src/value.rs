@@ -3,15 +3,17 @@ mod boolean;
 pub mod bulk_string;
 pub mod deserialize;
 pub mod integer;
+pub mod map;
 pub mod nulls;
 pub mod serialize;
 pub mod simple_error;
 pub mod simple_string;
 use array::Array;
 use boolean::Boolean;
-use bulk_string::BulkString;
+use bulk_string::{BulkBytes, BulkString};
 use deserialize::{Deserialize, WithIndex};
 use integer::Integer;
+use map::Map;
 use nulls::Nulls;
 use serialize::Serialize;
 use simple_error::SimpleError;
@@ -21,7 +23,7 @@ use crate::error::{ParseError, RedisError};
 
 use self::{
     array::ArrayWithIndex, boolean::BooleanWithIndex, bulk_string::BulkStringWithIndex,
-    integer::IntegerWithIndex, simple_error::SimpleErrorWithIndex,
+    integer::IntegerWithIndex, map::MapWithIndex, simple_error::SimpleErrorWithIndex,
     simple_string::SimpleStringWithIndex,
 };
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -30,7 +32,9 @@ pub enum Value {
     SimpleError(SimpleError),
     Integer(Integer),
     BulkString(BulkString),
+    BulkBytes(BulkBytes),
     Array(Array),
+    Map(Map),
     Boolean(Boolean),
     Nulls(Nulls),
 }
@@ -84,6 +88,14 @@ impl From<ArrayWithIndex> for ValueWithIndex {
         }
     }
 }
+impl From<MapWithIndex> for ValueWithIndex {
+    fn from(item: MapWithIndex) -> Self {
+        Self {
+            value: Value::Map(item.value),
+            index: item.index,
+        }
+    }
+}
 impl From<BooleanWithIndex> for ValueWithIndex {
     fn from(item: BooleanWithIndex) -> Self {
         Self {
@@ -98,13 +110,15 @@ impl WithIndex for ValueWithIndex {
     }
 }
 impl Serialize for Value {
-    fn serialize(&self) -> String {
+    fn serialize(&self) -> Vec<u8> {
         match self {
             Value::SimpleString(simple_string) => simple_string.serialize(),
             Value::SimpleError(simple_error) => simple_error.serialize(),
             Value::Integer(integer) => integer.serialize(),
             Value::BulkString(bulk_string) => bulk_string.serialize(),
+            Value::BulkBytes(bulk_bytes) => bulk_bytes.serialize(),
             Value::Array(array) => array.serialize(),
+            Value::Map(map) => map.serialize(),
             Value::Boolean(boolean) => boolean.serialize(),
             Value::Nulls(nulls) => nulls.serialize(),
         }
@@ -119,6 +133,7 @@ impl Deserialize for Value {
             Some(b':') => Ok(ValueWithIndex::from(Integer::deserialize(bytes)?)),
             Some(b'$') => Ok(ValueWithIndex::from(BulkString::deserialize(bytes)?)),
             Some(b'*') => Ok(ValueWithIndex::from(Array::deserialize(bytes)?)),
+            Some(b'%') => Ok(ValueWithIndex::from(Map::deserialize(bytes)?)),
             Some(b'#') => Ok(ValueWithIndex::from(Boolean::deserialize(bytes)?)),
             _ => Err(RedisError::ValueParseError(ParseError::InvalidFormat)),
         }
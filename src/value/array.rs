@@ -8,10 +8,10 @@ use crate::value::Value;
 pub struct Array(pub Vec<Value>);
 
 impl Serialize for Array {
-    fn serialize(&self) -> String {
-        let mut serialized_array = format!("*{}{CRLF}", self.0.len());
+    fn serialize(&self) -> Vec<u8> {
+        let mut serialized_array = format!("*{}{CRLF}", self.0.len()).into_bytes();
         for value in &self.0 {
-            serialized_array = format!("{serialized_array}{}", value.serialize())
+            serialized_array.extend(value.serialize())
         }
         serialized_array
     }
@@ -71,7 +71,7 @@ mod tests {
         Value,
     };
     fn serialize_and_assert(value: Value, expected: &str) {
-        assert_eq!(expected, value.serialize());
+        assert_eq!(expected.as_bytes(), value.serialize());
     }
 
     #[test]
@@ -7,11 +7,11 @@ use crate::error::*;
 pub struct Boolean(pub bool);
 
 impl Serialize for Boolean {
-    fn serialize(&self) -> String {
+    fn serialize(&self) -> Vec<u8> {
         if self.0 {
-            format!("#t{CRLF}")
+            format!("#t{CRLF}").into_bytes()
         } else {
-            format!("#f{CRLF}")
+            format!("#f{CRLF}").into_bytes()
         }
     }
 }
@@ -64,7 +64,7 @@ mod tests {
     use crate::value::Value;
 
     fn serialize_and_assert(value: Value, expected: &str) {
-        assert_eq!(expected, value.serialize());
+        assert_eq!(expected.as_bytes(), value.serialize());
     }
 
     #[test]
@@ -7,8 +7,21 @@ use crate::value::deserialize::{Deserialize, WithIndex};
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BulkString(pub String);
 impl Serialize for BulkString {
-    fn serialize(&self) -> String {
-        format!("${}{CRLF}{}{CRLF}", self.0.len(), self.0)
+    fn serialize(&self) -> Vec<u8> {
+        BulkBytes(self.0.as_bytes().to_vec()).serialize()
+    }
+}
+/// A bulk string reply of raw bytes, for the string values of the keyspace
+/// that need not be UTF-8 (HyperLogLogs, bitmaps). Requests still parse to
+/// [`BulkString`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkBytes(pub Vec<u8>);
+impl Serialize for BulkBytes {
+    fn serialize(&self) -> Vec<u8> {
+        let mut serialized = format!("${}{CRLF}", self.0.len()).into_bytes();
+        serialized.extend_from_slice(&self.0);
+        serialized.extend_from_slice(CRLF.as_bytes());
+        serialized
     }
 }
 pub struct BulkStringWithIndex {
@@ -72,7 +85,7 @@ mod tests {
     use crate::constants::CRLF;
     use crate::value::Value;
     fn serialize_and_assert(value: Value, expected: &str) {
-        assert_eq!(expected, Value::serialize(&value));
+        assert_eq!(expected.as_bytes(), Value::serialize(&value));
     }
 
     #[test]
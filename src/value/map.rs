@@ -0,0 +1,86 @@
+use super::deserialize::{Deserialize, WithIndex};
+use super::serialize::Serialize;
+use crate::constants::CRLF;
+use crate::error::*;
+use crate::read_until_crlf;
+use crate::value::Value;
+
+/// RESP3 map, only sent to clients that negotiated RESP3 with `HELLO`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Map(pub Vec<(Value, Value)>);
+
+impl Serialize for Map {
+    fn serialize(&self) -> Vec<u8> {
+        let mut serialized_map = format!("%{}{CRLF}", self.0.len()).into_bytes();
+        for (key, value) in &self.0 {
+            serialized_map.extend(key.serialize());
+            serialized_map.extend(value.serialize());
+        }
+        serialized_map
+    }
+}
+pub struct MapWithIndex {
+    pub value: Map,
+    pub index: (usize, usize),
+}
+impl WithIndex for MapWithIndex {
+    fn get_index(self) -> (usize, usize) {
+        self.index
+    }
+}
+
+impl Deserialize for Map {
+    type Value = MapWithIndex;
+    fn deserialize(bytes: &[u8]) -> Result<Self::Value> {
+        match bytes.first() {
+            Some(b'%') => {
+                let first_crlf_index = read_until_crlf(bytes, 1)?;
+                let count = std::str::from_utf8(&bytes[1..first_crlf_index])?.parse::<usize>()?;
+                let mut entries = Vec::new();
+                let mut upto_index = first_crlf_index + 1;
+                let next_value = |upto_index: &mut usize| -> Result<Value> {
+                    let value_with_index = Value::deserialize(&bytes[*upto_index + 1..])?;
+                    *upto_index += value_with_index.index.1 + 1;
+                    Ok(value_with_index.value)
+                };
+                for _ in 0..count {
+                    let key = next_value(&mut upto_index)?;
+                    let value = next_value(&mut upto_index)?;
+                    entries.push((key, value));
+                }
+                Ok(MapWithIndex {
+                    value: Map(entries),
+                    index: (0, upto_index),
+                })
+            }
+            _ => Err(RedisError::ValueParseError(ParseError::InvalidFormat)),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::{bulk_string::BulkString, integer::Integer};
+
+    #[test]
+    fn test_serialize_and_deserialize_map() {
+        let map = Value::Map(Map(vec![
+            (
+                Value::BulkString(BulkString("first".to_owned())),
+                Value::Integer(Integer(1)),
+            ),
+            (
+                Value::BulkString(BulkString("second".to_owned())),
+                Value::BulkString(BulkString("two".to_owned())),
+            ),
+        ]));
+        let serialized =
+            format!("%2{CRLF}$5{CRLF}first{CRLF}:1{CRLF}$6{CRLF}second{CRLF}$3{CRLF}two{CRLF}");
+        assert_eq!(map.serialize(), serialized.as_bytes());
+        assert_eq!(
+            Value::deserialize(serialized.as_bytes()).unwrap().value,
+            map
+        );
+        assert!(Value::deserialize(b"%1\r\n:1\r\n").is_err());
+    }
+}
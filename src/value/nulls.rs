@@ -8,8 +8,8 @@ use crate::error::*;
 pub struct Nulls;
 
 impl Serialize for Nulls {
-    fn serialize(&self) -> String {
-        format!("_{CRLF}")
+    fn serialize(&self) -> Vec<u8> {
+        format!("_{CRLF}").into_bytes()
     }
 }
 pub struct NullsWithIndex {
@@ -50,7 +50,7 @@ mod tests {
     use crate::constants::CRLF;
     use crate::value::Value;
     fn serialize_and_assert(value: Value, expected: &str) {
-        assert_eq!(expected, Value::serialize(&value));
+        assert_eq!(expected.as_bytes(), Value::serialize(&value));
     }
 
     #[test]
@@ -1,3 +1,4 @@
 pub trait Serialize {
-    fn serialize(&self) -> String;
+    /// The reply as sent on the wire. Bulk strings may carry any bytes.
+    fn serialize(&self) -> Vec<u8>;
 }
@@ -8,12 +8,28 @@ use crate::error::*;
 pub enum ErrorType {
     ERR,
     WRONGTYPE,
+    NOAUTH,
+    NOPERM,
+    WRONGPASS,
+    UNBLOCKED,
+    NOPROTO,
+    NOGROUP,
+    BUSYGROUP,
+    INVALIDOBJ,
 }
 impl ToString for ErrorType {
     fn to_string(&self) -> String {
         match self {
             ErrorType::ERR => "ERR".to_string(),
             ErrorType::WRONGTYPE => "WRONGTYPE".to_string(),
+            ErrorType::NOAUTH => "NOAUTH".to_string(),
+            ErrorType::NOPERM => "NOPERM".to_string(),
+            ErrorType::WRONGPASS => "WRONGPASS".to_string(),
+            ErrorType::UNBLOCKED => "UNBLOCKED".to_string(),
+            ErrorType::NOPROTO => "NOPROTO".to_string(),
+            ErrorType::NOGROUP => "NOGROUP".to_string(),
+            ErrorType::BUSYGROUP => "BUSYGROUP".to_string(),
+            ErrorType::INVALIDOBJ => "INVALIDOBJ".to_string(),
         }
     }
 }
@@ -24,6 +40,14 @@ impl TryFrom<&str> for ErrorType {
         match value.to_uppercase().as_str() {
             "ERR" => Result::Ok(ErrorType::ERR),
             "WRONGTYPE" => Result::Ok(ErrorType::WRONGTYPE),
+            "NOAUTH" => Result::Ok(ErrorType::NOAUTH),
+            "NOPERM" => Result::Ok(ErrorType::NOPERM),
+            "WRONGPASS" => Result::Ok(ErrorType::WRONGPASS),
+            "UNBLOCKED" => Result::Ok(ErrorType::UNBLOCKED),
+            "NOPROTO" => Result::Ok(ErrorType::NOPROTO),
+            "NOGROUP" => Result::Ok(ErrorType::NOGROUP),
+            "BUSYGROUP" => Result::Ok(ErrorType::BUSYGROUP),
+            "INVALIDOBJ" => Result::Ok(ErrorType::INVALIDOBJ),
             _ => Err(RedisError::SimpleErrorParseError(ParseError::UnknownValue)),
         }
     }
@@ -36,8 +60,8 @@ pub struct SimpleError {
 }
 
 impl Serialize for SimpleError {
-    fn serialize(&self) -> String {
-        format!("-{:?} {}{CRLF}", self.error_type, self.message)
+    fn serialize(&self) -> Vec<u8> {
+        format!("-{:?} {}{CRLF}", self.error_type, self.message).into_bytes()
     }
 }
 pub struct SimpleErrorWithIndex {
@@ -107,7 +131,7 @@ mod tests {
     use crate::value::Value;
 
     fn serialize_and_assert(value: Value, expected: &str) {
-        assert_eq!(expected, Value::serialize(&value));
+        assert_eq!(expected.as_bytes(), Value::serialize(&value));
     }
 
     #[test]
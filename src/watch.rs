@@ -0,0 +1,102 @@
+use crate::pattern::glob_match;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::Stream;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEventKind {
+    /// The key was written.
+    Set,
+    /// The key was removed by a command or moved to another database.
+    Del,
+    /// A timeout was set on the key.
+    Expire,
+    /// The timeout of the key was removed.
+    Persist,
+    /// The key was removed because its timeout elapsed.
+    Evict,
+    /// Fields of the hash were removed because their timeout elapsed. The
+    /// key itself is evicted instead when its last field goes.
+    FieldEvict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub db: usize,
+    pub key: String,
+    pub kind: KeyEventKind,
+}
+
+/// Returned by a [`Watch`] that fell behind: the channel is bounded, so the
+/// oldest events are dropped when a subscriber does not keep up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lagged {
+    /// Number of events dropped from the channel, including events of other
+    /// databases or keys the watch would have filtered out.
+    pub missed: u64,
+}
+
+/// Publishes the mutations of a keyspace to its subscribers.
+pub struct KeyEvents {
+    sender: broadcast::Sender<KeyEvent>,
+}
+
+impl KeyEvents {
+    pub fn new(capacity: usize) -> Self {
+        KeyEvents {
+            sender: broadcast::channel(capacity).0,
+        }
+    }
+
+    pub fn publish(&self, db: usize, key: &str, kind: KeyEventKind) {
+        // Don't pay for the event when nobody is watching.
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.sender.send(KeyEvent {
+            db,
+            key: key.to_owned(),
+            kind,
+        });
+    }
+
+    pub fn subscribe(&self, db: usize, pattern: &str) -> Watch {
+        Watch {
+            events: BroadcastStream::new(self.sender.subscribe()),
+            db,
+            pattern: pattern.to_owned(),
+        }
+    }
+}
+
+/// Stream of the events of one database whose key matches a glob pattern.
+/// Yields `Err(Lagged)` once for every gap, then resumes with newer events.
+pub struct Watch {
+    events: BroadcastStream<KeyEvent>,
+    db: usize,
+    pattern: String,
+}
+
+impl Stream for Watch {
+    type Item = Result<KeyEvent, Lagged>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.events).poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => {
+                    if event.db == self.db && glob_match(&self.pattern, &event.key) {
+                        return Poll::Ready(Some(Ok(event)));
+                    }
+                }
+                Poll::Ready(Some(Err(BroadcastStreamRecvError::Lagged(missed)))) => {
+                    return Poll::Ready(Some(Err(Lagged { missed })));
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}